@@ -4,8 +4,9 @@ use colored::Colorize;
 use heck::ToUpperCamelCase;
 use thiserror::Error;
 
-use super::{config::Config, env::Env, target::Target};
+use super::{config::Config, env::Env, jnilibs::JniLibs, target::Target};
 use crate::{
+    config::app::artifact_name,
     opts::{NoiseLevel, Profile},
     util::{
         cli::{Report, Reportable},
@@ -17,12 +18,15 @@ use crate::{
 pub enum AabError {
     #[error("Failed to build AAB: {0}")]
     BuildFailed(#[from] std::io::Error),
+    #[error(transparent)]
+    ArtifactNameFailed(artifact_name::Error),
 }
 
 impl Reportable for AabError {
     fn report(&self) -> Report {
         match self {
             Self::BuildFailed(err) => Report::error("Failed to build AAB", err),
+            Self::ArtifactNameFailed(err) => Report::error("Failed to name AAB", err),
         }
     }
 }
@@ -85,19 +89,27 @@ pub fn build(
             }
         })?
         .wait()?;
-
-    let mut outputs = Vec::new();
-    if split_per_abi {
-        outputs.extend(
-            targets
-                .iter()
-                .map(|t| dunce::simplified(&aab_path(config, profile, t.arch)).to_path_buf()),
-        );
-    } else {
-        outputs.push(dunce::simplified(&aab_path(config, profile, "universal")).to_path_buf());
+    if !split_per_abi {
+        JniLibs::warn_about_unbuilt_abi_filters(config);
     }
 
-    Ok(outputs)
+    let flavors = if split_per_abi {
+        targets.iter().map(|t| t.arch).collect()
+    } else {
+        vec!["universal"]
+    };
+    flavors
+        .into_iter()
+        .map(|flavor| {
+            let path = dunce::simplified(&aab_path(config, profile, flavor)).to_path_buf();
+            let vars = artifact_name::Vars {
+                platform: "android",
+                arch: flavor,
+                profile,
+            };
+            artifact_name::apply(config.app(), &path, vars).map_err(AabError::ArtifactNameFailed)
+        })
+        .collect()
 }
 
 pub fn aab_path(config: &Config, profile: Profile, flavor: &str) -> PathBuf {
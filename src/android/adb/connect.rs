@@ -0,0 +1,58 @@
+use super::adb;
+use crate::android::env::Env;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Failed to run `adb connect {endpoint}`: {cause}")]
+    CommandFailed {
+        endpoint: String,
+        cause: std::io::Error,
+    },
+    #[error("Couldn't connect to {endpoint}: {output}")]
+    ConnectFailed { endpoint: String, output: String },
+}
+
+// Older versions of `adb connect` exit successfully even when they couldn't
+// connect, so the output is all there is to go on.
+fn connected(output: &str) -> bool {
+    output
+        .lines()
+        .any(|line| line.starts_with("connected to") || line.starts_with("already connected to"))
+}
+
+/// Connects to a device over TCP, like a device farm's, which is a no-op if
+/// it's already connected.
+pub fn connect(env: &Env, endpoint: &str) -> Result<(), Error> {
+    let output = adb(env, ["connect", endpoint])
+        .stderr_to_stdout()
+        .unchecked()
+        .read()
+        .map_err(|cause| Error::CommandFailed {
+            endpoint: endpoint.to_owned(),
+            cause,
+        })?;
+    if connected(&output) {
+        Ok(())
+    } else {
+        Err(Error::ConnectFailed {
+            endpoint: endpoint.to_owned(),
+            output: output.trim().to_owned(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("connected to 10.0.0.2:5555\n", true)]
+    #[case("already connected to 10.0.0.2:5555\n", true)]
+    #[case("failed to connect to '10.0.0.2:5555': Connection refused\n", false)]
+    #[case("cannot connect to 10.0.0.2:5555: No route to host (113)\n", false)]
+    fn test_connected(#[case] output: &str, #[case] expected: bool) {
+        assert_eq!(connected(output), expected);
+    }
+}
@@ -39,7 +39,15 @@ impl Reportable for Error {
 
 const ADB_DEVICE_REGEX: &str = r"^([\S]{6,100})	device\b";
 
+/// Lists connected devices, after connecting to any of the env's remote
+/// devices that can be reached.
 pub fn device_list(env: &Env) -> Result<BTreeSet<Device<'static>>, Error> {
+    for endpoint in env.remote_devices() {
+        if let Err(err) = super::connect(env, endpoint) {
+            log::warn!("skipping remote device: {}", err);
+        }
+    }
+
     let mut cmd = Command::new(env.platform_tools_path().join("adb"));
     cmd.arg("devices").envs(env.explicit_env());
 
@@ -1,8 +1,13 @@
+pub mod connect;
 pub mod device_list;
 pub mod device_name;
 pub mod get_prop;
+pub mod package_info;
 
-pub use self::{device_list::device_list, device_name::device_name, get_prop::get_prop};
+pub use self::{
+    connect::connect, device_list::device_list, device_name::device_name, get_prop::get_prop,
+    package_info::package_info,
+};
 
 use super::env::Env;
 use crate::{env::ExplicitEnv as _, util::cli::Report, DuctExpressionExt};
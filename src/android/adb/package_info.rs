@@ -0,0 +1,80 @@
+use super::adb;
+use crate::{
+    android::env::Env,
+    device::AppInfo,
+    util::cli::{Report, Reportable},
+};
+use once_cell_regex::regex;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Failed to run `adb shell dumpsys package`: {0}")]
+    DumpsysFailed(#[source] super::RunCheckedError),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+impl Reportable for Error {
+    fn report(&self) -> Report {
+        match self {
+            Self::DumpsysFailed(err) => err.report("Failed to run `adb shell dumpsys package`"),
+            Self::Io(err) => Report::error("IO error", err),
+        }
+    }
+}
+
+fn parse(dumpsys: &str) -> Option<AppInfo> {
+    // If the package isn't installed, there won't be any version info at all.
+    let build = regex!(r"\bversionCode=(?P<code>\d+)").captures(dumpsys)?["code"].to_owned();
+    let version = regex!(r"\bversionName=(?P<name>\S+)")
+        .captures(dumpsys)
+        .map(|caps| caps["name"].to_owned())
+        .unwrap_or_default();
+    Some(AppInfo { version, build })
+}
+
+pub fn package_info(env: &Env, serial_no: &str, package: &str) -> Result<Option<AppInfo>, Error> {
+    let package = package.to_owned();
+    super::check_authorized(
+        &adb(env, ["-s", serial_no])
+            .before_spawn(move |cmd| {
+                cmd.args(["shell", "dumpsys", "package", &package]);
+                Ok(())
+            })
+            .stderr_capture()
+            .stdout_capture()
+            .unchecked()
+            .run()?,
+    )
+    .map(|stdout| parse(&stdout))
+    .map_err(Error::DumpsysFailed)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_installed_package() {
+        let dumpsys = "Packages:
+  Package [com.example.app] (3f2a1b):
+    userId=10190
+    pkg=Package{8c1d2e com.example.app}
+    versionCode=45 minSdk=24 targetSdk=34
+    versionName=1.2.3
+    splits=[base]";
+        assert_eq!(
+            parse(dumpsys),
+            Some(AppInfo {
+                version: "1.2.3".into(),
+                build: "45".into(),
+            })
+        );
+    }
+
+    #[test]
+    fn missing_package_is_none() {
+        assert_eq!(parse("Unable to find package: com.example.app"), None);
+    }
+}
@@ -7,6 +7,7 @@ use thiserror::Error;
 use super::{config::Config, env::Env, jnilibs, target::Target};
 use crate::{
     android::jnilibs::JniLibs,
+    config::app::artifact_name,
     opts::{NoiseLevel, Profile},
     util::{
         cli::{Report, Reportable},
@@ -20,6 +21,8 @@ pub enum ApkError {
     LibSymlinkCleaningFailed(jnilibs::RemoveBrokenLinksError),
     #[error("Failed to assemble APK: {0}")]
     AssembleFailed(#[from] std::io::Error),
+    #[error(transparent)]
+    ArtifactNameFailed(artifact_name::Error),
 }
 
 impl Reportable for ApkError {
@@ -27,6 +30,7 @@ impl Reportable for ApkError {
         match self {
             Self::LibSymlinkCleaningFailed(err) => err.report(),
             Self::AssembleFailed(err) => Report::error("Failed to assemble APK", err),
+            Self::ArtifactNameFailed(err) => Report::error("Failed to name APK", err),
         }
     }
 }
@@ -112,28 +116,30 @@ pub fn build(
             }
         })?
         .wait()?;
-
-    let mut outputs = Vec::new();
-    if split_per_abi {
-        let paths = targets
-            .iter()
-            .map(|t| {
-                apks_paths(config, profile, t.arch)
-                    .into_iter()
-                    .reduce(last_modified)
-                    .unwrap()
-            })
-            .collect::<Vec<_>>();
-        outputs.extend(paths);
-    } else {
-        let path = apks_paths(config, profile, "universal")
-            .into_iter()
-            .reduce(last_modified)
-            .unwrap();
-        outputs.push(path);
+    if !split_per_abi {
+        JniLibs::warn_about_unbuilt_abi_filters(config);
     }
 
-    Ok(outputs)
+    let flavors = if split_per_abi {
+        targets.iter().map(|t| t.arch).collect()
+    } else {
+        vec!["universal"]
+    };
+    flavors
+        .into_iter()
+        .map(|flavor| {
+            let path = apks_paths(config, profile, flavor)
+                .into_iter()
+                .reduce(last_modified)
+                .unwrap();
+            let vars = artifact_name::Vars {
+                platform: "android",
+                arch: flavor,
+                profile,
+            };
+            artifact_name::apply(config.app(), &path, vars).map_err(ApkError::ArtifactNameFailed)
+        })
+        .collect()
 }
 
 pub mod cli {
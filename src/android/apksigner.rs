@@ -0,0 +1,128 @@
+use super::{config::Config, env::Env};
+use crate::{
+    env::ExplicitEnv as _,
+    util::{
+        cli::{Report, Reportable},
+        VersionTriple,
+    },
+    DuctExpressionExt,
+};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum VerifyError {
+    #[error("No `apksigner` was found in {0}; install the Android SDK Build-Tools")]
+    ApksignerMissing(PathBuf),
+    #[error("`apksigner verify` failed: {0}")]
+    VerifyFailed(std::io::Error),
+    #[error("`apksigner` didn't report any signer certificates for {0}")]
+    CertsMissing(PathBuf),
+    #[error("{artifact} is signed with {}, but `android.signing-cert-sha256` isn't set", observed.join(", "))]
+    ExpectedMissing {
+        artifact: PathBuf,
+        observed: Vec<String>,
+    },
+    #[error("{artifact} is signed with {}, but `android.signing-cert-sha256` is {expected}", observed.join(", "))]
+    Mismatch {
+        artifact: PathBuf,
+        expected: String,
+        observed: Vec<String>,
+    },
+}
+
+impl Reportable for VerifyError {
+    fn report(&self) -> Report {
+        match self {
+            Self::ExpectedMissing { .. } => Report::action_request(
+                "Set `android.signing-cert-sha256` to the certificate you expect, then try again",
+                self,
+            ),
+            _ => Report::error("Failed to verify artifact signature", self),
+        }
+    }
+}
+
+// Uses the newest installed Build-Tools, since older ones can't verify newer
+// signature schemes.
+fn apksigner_path(env: &Env) -> Result<PathBuf, VerifyError> {
+    let build_tools = Path::new(env.android_home()).join("build-tools");
+    let bin = if cfg!(windows) {
+        "apksigner.bat"
+    } else {
+        "apksigner"
+    };
+    fs::read_dir(&build_tools)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let version = VersionTriple::from_str(entry.file_name().to_str()?).ok()?;
+            Some((version, entry.path().join(bin)))
+        })
+        .filter(|(_, path)| path.is_file())
+        .max_by_key(|(version, _)| *version)
+        .map(|(_, path)| path)
+        .ok_or(VerifyError::ApksignerMissing(build_tools))
+}
+
+fn parse_certs(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|line| line.split_once("certificate SHA-256 digest:"))
+        .map(|(_, digest)| digest.trim().to_ascii_lowercase())
+        .collect()
+}
+
+/// Checks that `artifact` has a valid signature from the certificate in
+/// `android.signing-cert-sha256`, returning the observed certificate digests.
+pub fn verify(config: &Config, env: &Env, artifact: &Path) -> Result<Vec<String>, VerifyError> {
+    let apksigner = apksigner_path(env)?;
+    let artifact_arg = artifact.to_owned();
+    let output = duct::cmd(apksigner, ["verify", "--print-certs"])
+        .vars(env.explicit_env())
+        .before_spawn(move |cmd| {
+            cmd.arg(&artifact_arg);
+            Ok(())
+        })
+        .stderr_capture()
+        .read()
+        .map_err(VerifyError::VerifyFailed)?;
+    let observed = parse_certs(&output);
+    if observed.is_empty() {
+        return Err(VerifyError::CertsMissing(artifact.to_owned()));
+    }
+    match config.signing_cert_sha256() {
+        None => Err(VerifyError::ExpectedMissing {
+            artifact: artifact.to_owned(),
+            observed,
+        }),
+        Some(expected) if observed.iter().any(|digest| digest == expected) => Ok(observed),
+        Some(expected) => Err(VerifyError::Mismatch {
+            artifact: artifact.to_owned(),
+            expected: expected.to_owned(),
+            observed,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_certs() {
+        let output = "Signer #1 certificate DN: CN=Android Debug, O=Android, C=US
+Signer #1 certificate SHA-256 digest: 3A1F0C9E2B7D4A6F8E1C3B5D7F9A2C4E6B8D0F1A3C5E7B9D2F4A6C8E0B1D3F5A
+Signer #1 certificate SHA-1 digest: 0123456789abcdef0123456789abcdef01234567
+";
+        assert_eq!(
+            parse_certs(output),
+            ["3a1f0c9e2b7d4a6f8e1c3b5d7f9a2c4e6b8d0f1a3c5e7b9d2f4a6c8e0b1d3f5a"]
+        );
+    }
+}
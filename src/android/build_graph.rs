@@ -0,0 +1,136 @@
+use crate::opts::Profile;
+use serde::Serialize;
+use std::{collections::BTreeMap, path::PathBuf};
+
+/// Bumped whenever a field is renamed or removed, or changes meaning, or a
+/// kind of step is added. Adding fields doesn't bump it, so consumers should
+/// ignore fields they don't know.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// What `cargo android build` would run, for build systems that want to run
+/// it themselves, printed by `cargo android export-build-graph`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct BuildGraph {
+    schema_version: u32,
+    profile: &'static str,
+    targets: Vec<TargetBuild>,
+}
+
+impl BuildGraph {
+    pub fn new(profile: Profile, targets: Vec<TargetBuild>) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            profile: profile.as_str(),
+            targets,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct TargetBuild {
+    pub name: String,
+    pub triple: String,
+    pub abi: String,
+    /// `.cargo/config.toml` there sets the linker, so `command` has to run
+    /// there too.
+    pub cwd: PathBuf,
+    /// Starts with `cargo`.
+    pub command: Vec<String>,
+    /// Set on top of the inherited env. Vars that aren't valid UTF-8 are
+    /// converted lossily.
+    pub env: BTreeMap<String, String>,
+    /// The lib `command` builds.
+    pub output: PathBuf,
+    /// Run in order once `command` succeeds.
+    pub post_build: Vec<PostBuildStep>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case", tag = "kind")]
+pub enum PostBuildStep {
+    /// Runs `command`, which modifies `output` in place.
+    Run { command: Vec<String> },
+    /// Symlinks (or copies, where symlinks aren't an option) `src` into the
+    /// `dest` dir, keeping its file name. When `if-output-needs` is set, this
+    /// only happens if `output` links against that lib, which can be checked
+    /// with `readelf -d`.
+    Symlink {
+        src: PathBuf,
+        dest: PathBuf,
+        #[serde(rename = "if-output-needs")]
+        if_output_needs: Option<String>,
+    },
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // The schema is documented in the README, so changing this means bumping
+    // `SCHEMA_VERSION` and updating the docs.
+    #[test]
+    fn schema_is_stable() {
+        let output = PathBuf::from("/app/target/aarch64-linux-android/release/libapp.so");
+        let jnilibs = PathBuf::from("/app/gen/android/app/src/main/jniLibs/arm64-v8a");
+        let graph = BuildGraph::new(
+            Profile::Release,
+            vec![TargetBuild {
+                name: "aarch64".into(),
+                triple: "aarch64-linux-android".into(),
+                abi: "arm64-v8a".into(),
+                cwd: "/app".into(),
+                command: vec!["cargo".into(), "build".into()],
+                env: BTreeMap::from([("TARGET_CC".into(), "clang".into())]),
+                output: output.clone(),
+                post_build: vec![
+                    PostBuildStep::Run {
+                        command: vec!["llvm-strip".into()],
+                    },
+                    PostBuildStep::Symlink {
+                        src: output,
+                        dest: jnilibs.clone(),
+                        if_output_needs: None,
+                    },
+                    PostBuildStep::Symlink {
+                        src: "/ndk/libc++_shared.so".into(),
+                        dest: jnilibs,
+                        if_output_needs: Some("libc++_shared.so".into()),
+                    },
+                ],
+            }],
+        );
+        assert_eq!(
+            serde_json::json!(graph),
+            serde_json::json!({
+                "schema-version": 1,
+                "profile": "release",
+                "targets": [{
+                    "name": "aarch64",
+                    "triple": "aarch64-linux-android",
+                    "abi": "arm64-v8a",
+                    "cwd": "/app",
+                    "command": ["cargo", "build"],
+                    "env": { "TARGET_CC": "clang" },
+                    "output": "/app/target/aarch64-linux-android/release/libapp.so",
+                    "post-build": [
+                        { "kind": "run", "command": ["llvm-strip"] },
+                        {
+                            "kind": "symlink",
+                            "src": "/app/target/aarch64-linux-android/release/libapp.so",
+                            "dest": "/app/gen/android/app/src/main/jniLibs/arm64-v8a",
+                            "if-output-needs": null
+                        },
+                        {
+                            "kind": "symlink",
+                            "src": "/ndk/libc++_shared.so",
+                            "dest": "/app/gen/android/app/src/main/jniLibs/arm64-v8a",
+                            "if-output-needs": "libc++_shared.so"
+                        }
+                    ]
+                }]
+            })
+        );
+    }
+}
@@ -1,6 +1,7 @@
 #[cfg(not(target_os = "macos"))]
 use crate::util;
 use crate::util::cli::{Report, Reportable};
+use std::ffi::OsString;
 #[cfg(not(target_os = "macos"))]
 use std::path::PathBuf;
 use thiserror::Error;
@@ -35,28 +36,32 @@ impl BundletoolJarInfo {
         )
     }
 
-    fn run_command(&self) -> duct::Expression {
-        let installation_path = self.installation_path();
-        duct::cmd("java", ["-jar"])
-            .dup_stdio()
-            .before_spawn(move |cmd| {
-                cmd.arg(&installation_path);
-                Ok(())
-            })
+    fn argv(&self) -> Vec<OsString> {
+        vec![
+            "java".into(),
+            "-jar".into(),
+            self.installation_path().into(),
+        ]
     }
 }
 
-pub fn command() -> duct::Expression {
+/// The program and args that run bundletool, before any subcommand.
+pub fn argv() -> Vec<OsString> {
     #[cfg(not(target_os = "macos"))]
     {
-        BUNDLE_TOOL_JAR_INFO.run_command()
+        BUNDLE_TOOL_JAR_INFO.argv()
     }
     #[cfg(target_os = "macos")]
     {
-        duct::cmd!("bundletool").dup_stdio()
+        vec!["bundletool".into()]
     }
 }
 
+pub fn command() -> duct::Expression {
+    let argv = argv();
+    duct::cmd(&argv[0], &argv[1..]).dup_stdio()
+}
+
 #[cfg(target_os = "macos")]
 #[derive(Debug, Error)]
 #[error(transparent)]
@@ -0,0 +1,35 @@
+use super::{config::Config, env::Env};
+use crate::{
+    opts::NoiseLevel,
+    util::{
+        cli::{Report, Reportable},
+        gradlew,
+    },
+};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+#[error("Failed to run `gradlew clean`: {0}")]
+pub struct CleanError(#[from] std::io::Error);
+
+impl Reportable for CleanError {
+    fn report(&self) -> Report {
+        Report::error("Failed to run `gradlew clean`", &self.0)
+    }
+}
+
+/// Runs Gradle's `clean` task, which clears the build outputs without touching
+/// the generated project.
+pub fn clean_native(config: &Config, env: &Env, noise_level: NoiseLevel) -> Result<(), CleanError> {
+    gradlew(config, env)
+        .before_spawn(move |cmd| {
+            cmd.arg("clean").arg(match noise_level {
+                NoiseLevel::Polite => "--warn",
+                NoiseLevel::LoudAndProud => "--info",
+                NoiseLevel::FranklyQuitePedantic => "--debug",
+            });
+            Ok(())
+        })
+        .run()?;
+    Ok(())
+}
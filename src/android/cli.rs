@@ -1,28 +1,36 @@
 use crate::{
     android::{
-        aab, adb, apk,
+        aab, adb, apk, apksigner,
+        build_graph::BuildGraph,
+        clean::{self, CleanError},
         config::{Config, Metadata},
-        device::{Device, RunError, StacktraceError},
+        device::{
+            Device, InstallOptions, InstallOptionsInvalid, PortMapping, RunError, StacktraceError,
+        },
         env::{Env, Error as EnvError},
-        target::{BuildError, CompileLibError, Target},
+        target::{BuildError, BuildGraphError, CargoPassthroughError, CompileLibError, Target},
         DEFAULT_ACTIVITY, NAME,
     },
+    build_info,
     config::{
         metadata::{self, Metadata as OmniMetadata},
         Config as OmniConfig, LoadOrGenError,
     },
     define_device_prompt,
-    device::PromptError,
-    os,
-    target::{call_for_targets_with_fallback, TargetInvalid, TargetTrait as _},
+    device::{self, PromptError},
+    env::ExportSyntax,
+    opts, os,
+    target::{
+        call_for_targets_with_fallback_keep_going, TargetFailures, TargetInvalid, TargetTrait as _,
+    },
     util::{
         cli::{
             self, Exec, GlobalFlags, Report, Reportable, TextWrapper, VERSION_LONG, VERSION_SHORT,
         },
-        prompt,
+        disk, prompt,
     },
 };
-use std::{ffi::OsString, path::PathBuf};
+use std::{ffi::OsString, path::PathBuf, process::ExitStatus};
 use structopt::StructOpt;
 
 #[derive(Debug, StructOpt)]
@@ -36,13 +44,19 @@ use structopt::StructOpt;
 pub struct Input {
     #[structopt(flatten)]
     flags: GlobalFlags,
+    #[structopt(flatten)]
+    config_flags: cli::ConfigFlags,
     #[structopt(subcommand)]
     command: Command,
 }
 
 impl Input {
-    pub fn new(flags: GlobalFlags, command: Command) -> Self {
-        Self { flags, command }
+    pub fn new(flags: GlobalFlags, config_flags: cli::ConfigFlags, command: Command) -> Self {
+        Self {
+            flags,
+            config_flags,
+            command,
+        }
     }
 }
 
@@ -50,17 +64,94 @@ impl Input {
 pub enum Command {
     #[structopt(name = "open", about = "Open project in Android Studio")]
     Open,
+    #[structopt(
+        name = "clean",
+        about = "Cleans build caches, leaving the generated project in place",
+        group = structopt::clap::ArgGroup::with_name("caches").required(true)
+    )]
+    Clean {
+        #[structopt(long = "native", help = "Run Gradle's `clean` task", group = "caches")]
+        native: bool,
+    },
     #[structopt(name = "check", about = "Checks if code compiles for target(s)")]
     Check {
-        #[structopt(name = "targets", default_value = Target::DEFAULT_KEY, possible_values = &Target::name_list())]
+        // The default is filled in by `requested_targets`, since
+        // `--device-abi-only` needs to know if these were passed.
+        #[structopt(name = "targets", possible_values = &Target::name_list())]
         targets: Vec<String>,
+        #[structopt(flatten)]
+        keep_going: cli::KeepGoing,
+        #[structopt(flatten)]
+        all_targets: cli::AllTargets,
+        #[structopt(flatten)]
+        cargo_jobs: cli::CargoJobs,
+        #[structopt(flatten)]
+        lockfile: cli::Lockfile,
+        #[structopt(flatten)]
+        deny_warnings: cli::DenyWarnings,
+        #[structopt(
+            long = "device-abi-only",
+            help = "Skip targets the connected device can't run, according to its ABI list"
+        )]
+        device_abi_only: bool,
     },
     #[structopt(name = "build", about = "Builds dynamic libraries for target(s)")]
     Build {
+        // The default is filled in by `requested_targets`, since
+        // `--device-abi-only` needs to know if these were passed.
+        #[structopt(name = "targets", possible_values = &Target::name_list())]
+        targets: Vec<String>,
+        #[structopt(flatten)]
+        profile: cli::Profile,
+        #[structopt(flatten)]
+        keep_going: cli::KeepGoing,
+        #[structopt(flatten)]
+        all_targets: cli::AllTargets,
+        #[structopt(flatten)]
+        if_changed: cli::IfChanged,
+        #[structopt(flatten)]
+        build_info: cli::BuildInfo,
+        #[structopt(flatten)]
+        cargo_jobs: cli::CargoJobs,
+        #[structopt(flatten)]
+        lockfile: cli::Lockfile,
+        #[structopt(flatten)]
+        deny_warnings: cli::DenyWarnings,
+        #[structopt(flatten)]
+        require_free: cli::RequireFree,
+        #[structopt(
+            long = "strip",
+            help = "Strip symbols from the built libs (ignored for debug builds)"
+        )]
+        strip: bool,
+        #[structopt(
+            long = "device-abi-only",
+            help = "Skip targets the connected device can't run, according to its ABI list"
+        )]
+        device_abi_only: bool,
+    },
+    #[structopt(
+        name = "export-build-graph",
+        about = "Prints what `build` would run for target(s) as JSON, without running it"
+    )]
+    ExportBuildGraph {
         #[structopt(name = "targets", default_value = Target::DEFAULT_KEY, possible_values = &Target::name_list())]
         targets: Vec<String>,
         #[structopt(flatten)]
         profile: cli::Profile,
+        #[structopt(flatten)]
+        all_targets: cli::AllTargets,
+        #[structopt(flatten)]
+        cargo_jobs: cli::CargoJobs,
+        #[structopt(flatten)]
+        lockfile: cli::Lockfile,
+        #[structopt(flatten)]
+        deny_warnings: cli::DenyWarnings,
+        #[structopt(
+            long = "strip",
+            help = "Include stripping symbols from the built libs (ignored for debug builds)"
+        )]
+        strip: bool,
     },
     #[structopt(name = "run", about = "Deploys APK to connected device")]
     Run {
@@ -70,6 +161,51 @@ pub enum Command {
         filter: cli::Filter,
         #[structopt(flatten)]
         reinstall_deps: cli::ReinstallDeps,
+        #[structopt(flatten)]
+        locale: cli::Locale,
+        #[structopt(flatten)]
+        last_device: cli::LastDevice,
+        #[structopt(flatten)]
+        after_run: cli::AfterRun,
+        #[structopt(flatten)]
+        dry_run: cli::DryRun,
+        #[structopt(flatten)]
+        explain: cli::Explain,
+        #[structopt(flatten)]
+        lockfile: cli::Lockfile,
+        #[structopt(flatten)]
+        deny_warnings: cli::DenyWarnings,
+        #[structopt(flatten)]
+        require_free: cli::RequireFree,
+        #[structopt(
+            long = "reverse",
+            value_name = "host-port:device-port",
+            help = "Forward a port on the device to one on this machine with `adb reverse` before launching; may be given more than once",
+            number_of_values = 1
+        )]
+        reverse: Vec<PortMapping>,
+        #[structopt(flatten)]
+        install: cli::Install,
+        #[structopt(
+            short = "a",
+            long = "activity",
+            help = "Specifies which activtiy to launch"
+        )]
+        activity: Option<String>,
+    },
+    #[structopt(
+        name = "deploy",
+        about = "Deploys a prebuilt APK to connected device, without building"
+    )]
+    Deploy {
+        #[structopt(long = "artifact", help = "Path to the APK to deploy")]
+        artifact: PathBuf,
+        #[structopt(flatten)]
+        filter: cli::Filter,
+        #[structopt(flatten)]
+        dry_run: cli::DryRun,
+        #[structopt(flatten)]
+        install: cli::Install,
         #[structopt(
             short = "a",
             long = "activity",
@@ -77,10 +213,57 @@ pub enum Command {
         )]
         activity: Option<String>,
     },
+    #[structopt(
+        name = "verify-artifact",
+        about = "Checks that an APK is signed with `android.signing-cert-sha256`"
+    )]
+    VerifyArtifact {
+        #[structopt(long = "artifact", help = "Path to the APK to verify")]
+        artifact: PathBuf,
+    },
     #[structopt(name = "st", about = "Displays a detailed stacktrace for a device")]
     Stacktrace,
     #[structopt(name = "list", about = "Lists connected devices")]
     List,
+    #[structopt(
+        name = "env",
+        about = "Prints the environment cargo-mobile2 builds with, for debugging"
+    )]
+    Env {
+        #[structopt(
+            long = "export",
+            help = "Print `export` lines for `eval` (or `set` lines for a batch file on Windows)"
+        )]
+        export: bool,
+    },
+    #[structopt(
+        name = "cargo",
+        about = "Runs cargo with the env builds for a target use, like `cargo android cargo -- clippy --target aarch64-linux-android`"
+    )]
+    Cargo {
+        #[structopt(name = "target", default_value = Target::DEFAULT_KEY, possible_values = &Target::name_list())]
+        target: String,
+        #[structopt(
+            name = "args",
+            last = true,
+            required = true,
+            help = "Arguments for cargo, which still need `--target <triple>` to build for the target"
+        )]
+        args: Vec<String>,
+    },
+    #[structopt(
+        name = "device-app-info",
+        about = "Shows the version of the app installed on a connected device"
+    )]
+    DeviceAppInfo {
+        #[structopt(long = "json", help = "Print the result as JSON")]
+        json: bool,
+        #[structopt(
+            long = "release",
+            help = "Look up the release build, if `app.debug-identifier-suffix` gives it a different identifier"
+        )]
+        release: bool,
+    },
     #[structopt(name = "apk", about = "Manage and build APKs")]
     Apk {
         #[structopt(subcommand)]
@@ -104,6 +287,10 @@ pub enum ApkSubcommand {
         profile: cli::Profile,
         #[structopt(long = "split-per-abi", help = "Whether to split the APKs per ABIs.")]
         split_per_abi: bool,
+        #[structopt(flatten)]
+        print_artifact: cli::PrintArtifact,
+        #[structopt(flatten)]
+        require_free: cli::RequireFree,
     },
 }
 #[derive(StructOpt, Clone, Debug)]
@@ -117,6 +304,10 @@ pub enum AabSubcommand {
         profile: cli::Profile,
         #[structopt(long = "split-per-abi", help = "Whether to split the AABs per ABIs.")]
         split_per_abi: bool,
+        #[structopt(flatten)]
+        print_artifact: cli::PrintArtifact,
+        #[structopt(flatten)]
+        require_free: cli::RequireFree,
     },
 }
 
@@ -124,19 +315,32 @@ pub enum AabSubcommand {
 pub enum Error {
     EnvInitFailed(EnvError),
     DevicePromptFailed(PromptError<adb::device_list::Error>),
+    AbiListFailed(adb::get_prop::Error),
+    NoDeviceAbiTargets { device: String, abis: Vec<String> },
     TargetInvalid(TargetInvalid),
+    TargetsFailed(TargetFailures<Error>),
     ConfigFailed(LoadOrGenError),
     MetadataFailed(metadata::Error),
     Unsupported,
     ProjectDirAbsent { project_dir: PathBuf },
     OpenFailed(os::OpenFileError),
+    CleanFailed(CleanError),
     CheckFailed(CompileLibError),
+    ChangeCheckFailed(std::io::Error),
+    BuildInfoFailed(build_info::Error),
     BuildFailed(BuildError),
+    BuildGraphFailed(BuildGraphError),
     RunFailed(RunError),
+    InstallOptionsInvalid(InstallOptionsInvalid),
+    VerifyFailed(apksigner::VerifyError),
     StacktraceFailed(StacktraceError),
     ListFailed(adb::device_list::Error),
+    AppInfoFailed(adb::package_info::Error),
     ApkError(apk::ApkError),
     AabError(aab::AabError),
+    FreeSpaceCheckFailed(disk::FreeSpaceError),
+    CargoPassthroughFailed(CargoPassthroughError),
+    CargoExited(ExitStatus),
 }
 
 impl Reportable for Error {
@@ -144,7 +348,20 @@ impl Reportable for Error {
         match self {
             Self::EnvInitFailed(err) => err.report(),
             Self::DevicePromptFailed(err) => err.report(),
+            Self::AbiListFailed(err) => err.report(),
+            Self::NoDeviceAbiTargets { device, abis } => Report::error(
+                "None of the requested targets can run on the connected device",
+                format!("{} only supports {}", device, abis.join(", ")),
+            ),
             Self::TargetInvalid(err) => Report::error("Specified target was invalid", err),
+            Self::TargetsFailed(failures) => Report::error(
+                format!(
+                    "{} of {} targets failed",
+                    failures.failed.len(),
+                    failures.failed.len() + failures.passed.len()
+                ),
+                failures,
+            ),
             Self::ConfigFailed(err) => err.report(),
             Self::MetadataFailed(err) => err.report(),
             Self::Unsupported => Report::error("Android is marked as unsupported in your Cargo.toml metadata", "If your project should support Android, modify your Cargo.toml, then run `cargo mobile init` and try again."),
@@ -156,13 +373,31 @@ impl Reportable for Error {
                 ),
             ),
             Self::OpenFailed(err) => Report::error("Failed to open project in Android Studio", err),
+            Self::CleanFailed(err) => err.report(),
             Self::CheckFailed(err) => err.report(),
+            Self::ChangeCheckFailed(err) => {
+                Report::error("Failed to check for changes with `git`", err)
+            }
+            Self::BuildInfoFailed(err) => err.report(),
             Self::BuildFailed(err) => err.report(),
+            Self::BuildGraphFailed(err) => err.report(),
             Self::RunFailed(err) => err.report(),
+            Self::InstallOptionsInvalid(err) => Report::error("Invalid install options", err),
+            Self::VerifyFailed(err) => err.report(),
             Self::StacktraceFailed(err) => err.report(),
             Self::ListFailed(err) => err.report(),
+            Self::AppInfoFailed(err) => err.report(),
             Self::ApkError(err) => err.report(),
             Self::AabError(err) => err.report(),
+            Self::FreeSpaceCheckFailed(err) => err.report(),
+            Self::CargoPassthroughFailed(err) => err.report(),
+            Self::CargoExited(status) => match status.code() {
+                Some(code) => {
+                    Report::error("cargo failed", format!("It exited with code {}", code))
+                        .with_exit_code(code)
+                }
+                None => Report::error("cargo failed", status),
+            },
         }
     }
 }
@@ -171,25 +406,53 @@ impl Exec for Input {
     type Report = Error;
 
     fn global_flags(&self) -> GlobalFlags {
-        self.flags
+        self.flags.clone()
     }
 
     fn exec(self, wrapper: &TextWrapper) -> Result<(), Self::Report> {
-        define_device_prompt!(adb::device_list, adb::device_list::Error, Android);
+        define_device_prompt!(
+            adb::device_list,
+            adb::device_list::Error,
+            Android,
+            serial_no
+        );
         fn detect_target_ok<'a>(env: &Env) -> Option<&'a Target<'a>> {
             device_prompt(env).map(|device| device.target()).ok()
         }
 
         fn with_config(
-            non_interactive: bool,
+            load_config: cli::LoadConfig,
             wrapper: &TextWrapper,
             f: impl FnOnce(&Config, &Metadata, &Env) -> Result<(), Error>,
         ) -> Result<(), Error> {
-            let (config, _origin) = OmniConfig::load_or_gen(".", non_interactive, wrapper)
-                .map_err(Error::ConfigFailed)?;
+            let cli::LoadConfig {
+                non_interactive,
+                flags: config_flags,
+            } = load_config;
+            let (config, _origin) = OmniConfig::load_or_gen(
+                ".",
+                non_interactive,
+                config_flags.force_config,
+                config_flags.app.as_deref(),
+                &config_flags.config_overrides,
+                config_flags.max_search_depth,
+                config_flags.env_file.as_deref(),
+                None,
+                wrapper,
+            )
+            .map_err(Error::ConfigFailed)?;
             let metadata =
                 OmniMetadata::load(config.app().root_dir()).map_err(Error::MetadataFailed)?;
             let mut env = Env::new().map_err(Error::EnvInitFailed)?;
+            env.base = env
+                .base
+                .explicit_env_vars(config.env_file_vars().clone())
+                .forward_vars(config.app().forward_env());
+            if config.app().use_sccache() {
+                env.base = env.base.with_sccache();
+            }
+            env.base = env.base.with_cargo_jobs(config.app().cargo_jobs());
+            env = env.with_remote_devices(config.android().remote_devices());
 
             if let Some(vars) = metadata.android().env_vars.as_ref() {
                 env.base = env.base.explicit_env_vars(
@@ -227,11 +490,103 @@ impl Exec for Input {
             }
         }
 
+        fn ensure_free_space(config: &Config, require_free: Option<f64>) -> Result<(), Error> {
+            match require_free.or(config.require_free()) {
+                Some(gib) => disk::ensure_free_space(
+                    [
+                        config.app().cargo_target_dir().as_path(),
+                        config.project_dir().as_path(),
+                    ],
+                    gib,
+                )
+                .map_err(Error::FreeSpaceCheckFailed),
+                None => Ok(()),
+            }
+        }
+
+        fn install_options(install: cli::Install) -> InstallOptions {
+            InstallOptions {
+                downgrade: install.downgrade,
+                grant_permissions: install.grant_permissions,
+                extra_args: install.install_args,
+            }
+        }
+
         fn open_in_android_studio(config: &Config, env: &Env) -> Result<(), Error> {
             os::open_file_with("Android Studio", config.project_dir(), &env.base)
                 .map_err(Error::OpenFailed)
         }
 
+        fn for_targets(
+            targets: &[String],
+            keep_going: bool,
+            wrapper: &TextWrapper,
+            env: &Env,
+            f: impl FnMut(&Target) -> Result<(), Error>,
+        ) -> Result<(), Error> {
+            call_for_targets_with_fallback_keep_going(
+                targets.iter(),
+                &detect_target_ok,
+                env,
+                keep_going,
+                wrapper,
+                Error::TargetsFailed,
+                f,
+            )
+            .map_err(Error::TargetInvalid)?
+        }
+
+        // Without `--device-abi-only`, this is the default target unless
+        // targets were passed. With it, passed targets are narrowed down to
+        // the device's ABIs, and otherwise the device's own target is used.
+        fn requested_targets(
+            env: &Env,
+            targets: Vec<String>,
+            all_targets: cli::AllTargets,
+            device_abi_only: bool,
+        ) -> Result<Vec<String>, Error> {
+            let targets = all_targets.expand::<Target>(targets);
+            if !device_abi_only {
+                return Ok(if targets.is_empty() {
+                    vec![Target::DEFAULT_KEY.to_owned()]
+                } else {
+                    targets
+                });
+            }
+            let device = device_prompt(env).map_err(Error::DevicePromptFailed)?;
+            if targets.is_empty() {
+                let triple = device.target().triple;
+                return Ok(Target::all()
+                    .iter()
+                    .filter(|(_, target)| target.triple == triple)
+                    .map(|(name, _)| (*name).to_owned())
+                    .collect());
+            }
+            let abis = device.supported_abis(env).map_err(Error::AbiListFailed)?;
+            // Invalid target names are kept, so that `for_targets` reports them.
+            // `is_none_or` would need Rust 1.82.
+            #[allow(clippy::unnecessary_map_or)]
+            let (supported, skipped): (Vec<_>, Vec<_>) = targets.into_iter().partition(|name| {
+                Target::for_name(name)
+                    .map_or(true, |target| abis.iter().any(|abi| abi == target.abi))
+            });
+            if supported.is_empty() {
+                return Err(Error::NoDeviceAbiTargets {
+                    device: device.to_string(),
+                    abis,
+                });
+            }
+            if !skipped.is_empty() {
+                println!(
+                    "Skipping {}, since {} only supports {}",
+                    skipped.join(", "),
+                    device,
+                    abis.join(", ")
+                );
+            }
+            Ok(supported)
+        }
+
         fn get_targets_or_all<'a>(targets: Vec<String>) -> Result<Vec<&'a Target<'a>>, Error> {
             if targets.is_empty() {
                 Ok(Target::all().iter().map(|t| t.1).collect())
@@ -255,58 +610,188 @@ impl Exec for Input {
                 GlobalFlags {
                     noise_level,
                     non_interactive,
+                    ..
                 },
+            config_flags,
             command,
         } = self;
+        let load_config = cli::LoadConfig {
+            non_interactive,
+            flags: &config_flags,
+        };
         match command {
-            Command::Open => with_config(non_interactive, wrapper, |config, _, env| {
+            Command::Open => with_config(load_config, wrapper, |config, _, env| {
                 ensure_init(config)?;
                 open_in_android_studio(config, env)
             }),
-            Command::Check { targets } => {
-                with_config(non_interactive, wrapper, |config, metadata, env| {
-                    let force_color = true;
-                    call_for_targets_with_fallback(
-                        targets.iter(),
-                        &detect_target_ok,
-                        env,
-                        |target: &Target| {
-                            target
-                                .check(config, metadata, env, noise_level, force_color)
-                                .map_err(Error::CheckFailed)
-                        },
-                    )
-                    .map_err(Error::TargetInvalid)?
+            Command::Clean { native } => with_config(load_config, wrapper, |config, _, env| {
+                ensure_init(config)?;
+                if native {
+                    clean::clean_native(config, env, noise_level).map_err(Error::CleanFailed)?;
+                }
+                Ok(())
+            }),
+            Command::Check {
+                targets,
+                keep_going: cli::KeepGoing { keep_going },
+                all_targets,
+                cargo_jobs: cli::CargoJobs { cargo_jobs },
+                lockfile,
+                deny_warnings: cli::DenyWarnings { deny_warnings },
+                device_abi_only,
+            } => with_config(load_config, wrapper, |config, metadata, env| {
+                let env = &env
+                    .clone()
+                    .with_cargo_jobs(cargo_jobs)
+                    .with_cargo_lock(lockfile.lock())
+                    .with_deny_warnings(deny_warnings);
+                let force_color = true;
+                let targets = requested_targets(env, targets, all_targets, device_abi_only)?;
+                for_targets(&targets, keep_going, wrapper, env, |target: &Target| {
+                    target
+                        .check(config, metadata, env, noise_level, force_color)
+                        .map_err(Error::CheckFailed)
                 })
-            }
+            }),
             Command::Build {
                 targets,
                 profile: cli::Profile { profile },
-            } => with_config(non_interactive, wrapper, |config, metadata, env| {
+                keep_going: cli::KeepGoing { keep_going },
+                all_targets,
+                if_changed: cli::IfChanged { if_changed },
+                build_info: cli::BuildInfo { with_build_info },
+                cargo_jobs: cli::CargoJobs { cargo_jobs },
+                lockfile,
+                deny_warnings: cli::DenyWarnings { deny_warnings },
+                require_free: cli::RequireFree { require_free },
+                strip,
+                device_abi_only,
+            } => with_config(load_config, wrapper, |config, metadata, env| {
+                let env = &env
+                    .clone()
+                    .with_cargo_jobs(cargo_jobs)
+                    .with_cargo_lock(lockfile.lock())
+                    .with_deny_warnings(deny_warnings);
+                if cli::unchanged(config.app(), if_changed.as_deref())
+                    .map_err(Error::ChangeCheckFailed)?
+                {
+                    return Ok(());
+                }
+                if with_build_info {
+                    build_info::generate(config.app()).map_err(Error::BuildInfoFailed)?;
+                }
                 ensure_init(config)?;
+                ensure_free_space(config, require_free)?;
                 let force_color = true;
-                call_for_targets_with_fallback(
-                    targets.iter(),
-                    &detect_target_ok,
-                    env,
-                    |target: &Target| {
+                let targets = requested_targets(env, targets, all_targets, device_abi_only)?;
+                for_targets(&targets, keep_going, wrapper, env, |target: &Target| {
+                    target
+                        .build(config, metadata, env, noise_level, force_color, profile)
+                        .map_err(Error::BuildFailed)?;
+                    // `build` already strips when it's enabled in the config.
+                    if strip && !config.strip_release() {
                         target
-                            .build(config, metadata, env, noise_level, force_color, profile)
-                            .map_err(Error::BuildFailed)
-                    },
-                )
-                .map_err(Error::TargetInvalid)?
+                            .strip_lib(config, &env.ndk, profile)
+                            .map_err(|err| Error::BuildFailed(BuildError::StripLibFailed(err)))?;
+                    }
+                    Ok(())
+                })
+            }),
+            Command::ExportBuildGraph {
+                targets,
+                profile: cli::Profile { profile },
+                all_targets,
+                cargo_jobs: cli::CargoJobs { cargo_jobs },
+                lockfile,
+                deny_warnings: cli::DenyWarnings { deny_warnings },
+                strip,
+            } => with_config(load_config, wrapper, |config, metadata, env| {
+                let env = &env
+                    .clone()
+                    .with_cargo_jobs(cargo_jobs)
+                    .with_cargo_lock(lockfile.lock())
+                    .with_deny_warnings(deny_warnings);
+                let strip = strip || config.strip_release();
+                let targets = get_targets_or_all(all_targets.expand::<Target>(targets))?
+                    .into_iter()
+                    .map(|target| {
+                        target.build_graph(config, metadata, env, noise_level, profile, strip)
+                    })
+                    .collect::<Result<_, _>>()
+                    .map_err(Error::BuildGraphFailed)?;
+                println!("{:#}", serde_json::json!(BuildGraph::new(profile, targets)));
+                Ok(())
             }),
             Command::Run {
                 profile: cli::Profile { profile },
                 filter: cli::Filter { filter },
                 reinstall_deps: cli::ReinstallDeps { reinstall_deps },
+                locale: cli::Locale { locale },
+                last_device: cli::LastDevice { last_device },
+                after_run: cli::AfterRun { after_run },
+                dry_run: cli::DryRun { dry_run },
+                explain: cli::Explain { explain },
+                lockfile,
+                deny_warnings: cli::DenyWarnings { deny_warnings },
+                require_free: cli::RequireFree { require_free },
+                reverse,
+                install,
                 activity,
-            } => with_config(non_interactive, wrapper, |config, metadata, env| {
+            } => with_config(load_config, wrapper, |config, metadata, env| {
+                let env = &env
+                    .clone()
+                    .with_cargo_lock(lockfile.lock())
+                    .with_deny_warnings(deny_warnings);
                 let build_app_bundle = metadata.asset_packs().is_some();
+                let install = install_options(install);
+                install
+                    .check(build_app_bundle)
+                    .map_err(Error::InstallOptionsInvalid)?;
                 ensure_init(config)?;
-                device_prompt(env)
-                    .map_err(Error::DevicePromptFailed)?
+                // Previews shouldn't change which device is used next time.
+                let remember = !dry_run && !explain;
+                let device =
+                    device_prompt_with(env, Some(config.app().root_dir()), last_device, remember)
+                        .map_err(Error::DevicePromptFailed)?;
+                let activity = activity.unwrap_or_else(|| {
+                    metadata
+                        .app_activity_name()
+                        .unwrap_or(DEFAULT_ACTIVITY)
+                        .to_string()
+                });
+                if dry_run {
+                    device
+                        .dry_run(
+                            config,
+                            env,
+                            profile,
+                            build_app_bundle,
+                            locale.as_ref(),
+                            &reverse,
+                            &install,
+                            &activity,
+                        )
+                        .map_err(Error::RunFailed)?
+                        .print();
+                    return Ok(());
+                }
+                if explain {
+                    device
+                        .explain(
+                            config,
+                            profile,
+                            build_app_bundle,
+                            locale.as_ref(),
+                            &reverse,
+                            &install,
+                            &activity,
+                            after_run.as_deref(),
+                        )
+                        .print();
+                    return Ok(());
+                }
+                ensure_free_space(config, require_free)?;
+                device
                     .run(
                         config,
                         env,
@@ -315,47 +800,146 @@ impl Exec for Input {
                         filter,
                         build_app_bundle,
                         reinstall_deps,
-                        activity.unwrap_or_else(|| {
-                            metadata
-                                .app_activity_name()
-                                .unwrap_or(DEFAULT_ACTIVITY)
-                                .to_string()
-                        }),
+                        locale.as_ref(),
+                        &reverse,
+                        &install,
+                        activity,
+                        after_run.as_deref(),
+                    )
+                    .and_then(|h| {
+                        // Once the after-run command is done, all that's
+                        // left is streaming logs.
+                        if after_run.is_some() {
+                            h.kill()
+                        } else {
+                            h.wait().map(|_| ())
+                        }
+                        .map_err(Into::into)
+                    })
+                    .map_err(Error::RunFailed)
+            }),
+            Command::Deploy {
+                artifact,
+                filter: cli::Filter { filter },
+                dry_run: cli::DryRun { dry_run },
+                install,
+                activity,
+            } => with_config(load_config, wrapper, |config, metadata, env| {
+                let install = install_options(install);
+                install.check(false).map_err(Error::InstallOptionsInvalid)?;
+                let device = device_prompt_for(env, config.app().root_dir())
+                    .map_err(Error::DevicePromptFailed)?;
+                let activity = activity.unwrap_or_else(|| {
+                    metadata
+                        .app_activity_name()
+                        .unwrap_or(DEFAULT_ACTIVITY)
+                        .to_string()
+                });
+                if dry_run {
+                    device
+                        .deploy_dry_run(config, env, &artifact, &install, &activity)
+                        .map_err(Error::RunFailed)?
+                        .print();
+                    return Ok(());
+                }
+                device
+                    .deploy(
+                        config,
+                        env,
+                        &artifact,
+                        &install,
+                        noise_level,
+                        filter,
+                        activity,
                     )
                     .and_then(|h| h.wait().map(|_| ()).map_err(Into::into))
                     .map_err(Error::RunFailed)
             }),
-            Command::Stacktrace => with_config(non_interactive, wrapper, |config, _, env| {
+            Command::VerifyArtifact { artifact } => {
+                with_config(load_config, wrapper, |config, _, env| {
+                    let observed =
+                        apksigner::verify(config, env, &artifact).map_err(Error::VerifyFailed)?;
+                    println!(
+                        "{:?} is signed with the expected certificate ({})",
+                        artifact,
+                        observed.join(", ")
+                    );
+                    Ok(())
+                })
+            }
+            Command::Stacktrace => with_config(load_config, wrapper, |config, _, env| {
                 ensure_init(config)?;
-                device_prompt(env)
+                device_prompt_for(env, config.app().root_dir())
                     .map_err(Error::DevicePromptFailed)?
                     .stacktrace(config, env)
                     .map_err(Error::StacktraceFailed)
             }),
-            Command::List => with_config(non_interactive, wrapper, |_, _, env| {
+            Command::List => with_config(load_config, wrapper, |_, _, env| {
                 adb::device_list(env)
                     .map_err(Error::ListFailed)
                     .map(|device_list| {
                         prompt::list_display_only(device_list.iter(), device_list.len());
                     })
             }),
+            Command::Env { export } => with_config(load_config, wrapper, |_, _, env| {
+                crate::env::print(env, export.then(ExportSyntax::host));
+                Ok(())
+            }),
+            Command::Cargo { target, args } => {
+                with_config(load_config, wrapper, |config, _, env| {
+                    let target = Target::for_name(&target).unwrap_or_else(Target::default_ref);
+                    let status = target
+                        .cargo_passthrough(config, env, &args)
+                        .map_err(Error::CargoPassthroughFailed)?;
+                    if status.success() {
+                        Ok(())
+                    } else {
+                        Err(Error::CargoExited(status))
+                    }
+                })
+            }
+            Command::DeviceAppInfo { json, release } => {
+                with_config(load_config, wrapper, |config, _, env| {
+                    let device = device_prompt_for(env, config.app().root_dir())
+                        .map_err(Error::DevicePromptFailed)?;
+                    let profile = opts::Profile::from_flag(release);
+                    let info = device
+                        .app_info(config, env, profile)
+                        .map_err(Error::AppInfoFailed)?;
+                    device::print_app_info(
+                        device.name(),
+                        &config.application_id_for(profile),
+                        info.as_ref(),
+                        json,
+                    );
+                    Ok(())
+                })
+            }
             Command::Apk { cmd } => match cmd {
                 ApkSubcommand::Build {
                     targets,
                     profile: cli::Profile { profile },
                     split_per_abi,
-                } => with_config(non_interactive, wrapper, |config, _, env| {
+                    print_artifact: cli::PrintArtifact { print_artifact },
+                    require_free: cli::RequireFree { require_free },
+                } => with_config(load_config, wrapper, |config, _, env| {
                     ensure_init(config)?;
+                    ensure_free_space(config, require_free)?;
 
-                    apk::cli::build(
-                        config,
-                        env,
-                        noise_level,
-                        profile,
-                        get_targets_or_all(targets)?,
-                        split_per_abi,
-                    )
-                    .map_err(Error::ApkError)
+                    let targets = get_targets_or_all(targets)?;
+                    if print_artifact {
+                        cli::reserve_stdout();
+                        for path in
+                            apk::build(config, env, noise_level, profile, targets, split_per_abi)
+                                .map_err(Error::ApkError)?
+                        {
+                            println!("{}", path.display());
+                        }
+                        Ok(())
+                    } else {
+                        apk::cli::build(config, env, noise_level, profile, targets, split_per_abi)
+                            .map_err(Error::ApkError)
+                    }
                 }),
             },
             Command::Aab { cmd } => match cmd {
@@ -363,17 +947,25 @@ impl Exec for Input {
                     targets,
                     profile: cli::Profile { profile },
                     split_per_abi,
-                } => with_config(non_interactive, wrapper, |config, _, env| {
+                    print_artifact: cli::PrintArtifact { print_artifact },
+                    require_free: cli::RequireFree { require_free },
+                } => with_config(load_config, wrapper, |config, _, env| {
                     ensure_init(config)?;
-                    aab::cli::build(
-                        config,
-                        env,
-                        noise_level,
-                        profile,
-                        get_targets_or_all(targets)?,
-                        split_per_abi,
-                    )
-                    .map_err(Error::AabError)
+                    ensure_free_space(config, require_free)?;
+                    let targets = get_targets_or_all(targets)?;
+                    if print_artifact {
+                        cli::reserve_stdout();
+                        for path in
+                            aab::build(config, env, noise_level, profile, targets, split_per_abi)
+                                .map_err(Error::AabError)?
+                        {
+                            println!("{}", path.display());
+                        }
+                        Ok(())
+                    } else {
+                        aab::cli::build(config, env, noise_level, profile, targets, split_per_abi)
+                            .map_err(Error::AabError)
+                    }
                 }),
             },
         }
@@ -1,16 +1,26 @@
+use super::target::Target;
 use crate::{
-    config::app::App,
-    util::{self, cli::Report},
+    config::{
+        app::{identifier::IdentifierError, App},
+        extra_file::{self, ExtraFile},
+        target_env::{TargetEnv, UnknownTriple},
+        toolchain::TargetToolchain,
+    },
+    opts::Profile,
+    target::TargetTrait as _,
+    util::{self, cli::Report, VersionTriple, VersionTripleError},
 };
 use serde::{Deserialize, Serialize};
 use std::{
     collections::HashMap,
     fmt::{self, Display},
     path::PathBuf,
+    str::FromStr,
 };
 use thiserror::Error;
 
 const DEFAULT_MIN_SDK_VERSION: u32 = 24;
+static DEFAULT_GRADLE_VERSION: &str = "8.10.2";
 pub const DEFAULT_VULKAN_VALIDATION: bool = true;
 static DEFAULT_PROJECT_DIR: &str = "gen/android";
 
@@ -152,7 +162,7 @@ impl Display for ProjectDirInvalid {
                 root_dir,
             } => write!(
                 f,
-                "{:?} is outside of the app root {:?}",
+                "{:?} is outside of the app root {:?}; set `android.allow-external-project-dir = true` if this is intentional",
                 project_dir, root_dir,
             ),
             Self::ContainsSpaces { project_dir } => write!(
@@ -170,6 +180,49 @@ pub enum Error {
     ProjectDirInvalid(ProjectDirInvalid),
     #[error("Identifier cannot contain hyphens on Android")]
     IdentifierCannotContainHyphens,
+    #[error("android.gradle-dependencies contains {0:?}, which isn't of the form `group:artifact:version`")]
+    GradleDependencyInvalid(String),
+    #[error("android.gradle-version invalid: {0}")]
+    GradleVersionInvalid(VersionTripleError),
+    #[error("android.gradle-args contains {0:?}, which cargo-mobile2 already passes to Gradle")]
+    GradleArgReserved(String),
+    #[error("android.application-id {application_id:?} invalid: {cause}")]
+    ApplicationIdInvalid {
+        application_id: String,
+        cause: IdentifierError,
+    },
+    #[error("android.application-id {0:?} invalid: Application IDs need at least two segments, e.g. `com.example`")]
+    ApplicationIdTooShort(String),
+    #[error("android.application-id {0:?} invalid: Application IDs can't contain hyphens")]
+    ApplicationIdCannotContainHyphens(String),
+    #[error("android.extra-files invalid: {0}")]
+    ExtraFileInvalid(extra_file::Error),
+    #[error("android.kotlin-package {kotlin_package:?} invalid: {cause}")]
+    KotlinPackageInvalid {
+        kotlin_package: String,
+        cause: IdentifierError,
+    },
+    #[error("android.kotlin-package {0:?} invalid: Packages need at least two segments, e.g. `com.example`")]
+    KotlinPackageTooShort(String),
+    #[error("android.kotlin-package {0:?} invalid: Packages can't contain hyphens")]
+    KotlinPackageCannotContainHyphens(String),
+    #[error("android.kotlin-package {kotlin_package:?} invalid: {keyword:?} is a Kotlin keyword")]
+    KotlinPackageKeyword {
+        kotlin_package: String,
+        keyword: String,
+    },
+    #[error("android.signing-cert-sha256 {0:?} invalid: It should be 32 bytes of hex, optionally separated by colons")]
+    SigningCertSha256Invalid(String),
+    #[error("android.remote-devices contains {0:?}, which isn't of the form `host:port`")]
+    RemoteDeviceInvalid(String),
+    #[error("android.target-env invalid: {0}")]
+    TargetEnvInvalid(UnknownTriple),
+    #[error("android.toolchain invalid: {0}")]
+    ToolchainInvalid(UnknownTriple),
+    #[error("android.require-free {0} invalid: It should be a positive number of GiB")]
+    RequireFreeInvalid(f64),
+    #[error("android.abi-filters contains {0:?}, which isn't an ABI; expected one of arm64-v8a, armeabi-v7a, x86, x86_64")]
+    AbiFilterInvalid(String),
 }
 
 impl Error {
@@ -187,6 +240,27 @@ pub struct Raw {
     pub features: Option<Vec<String>>,
     #[serde(default)]
     pub logcat_filter_specs: Vec<String>,
+    #[serde(default)]
+    pub gradle_dependencies: Vec<String>,
+    pub allow_external_project_dir: Option<bool>,
+    pub gradle_version: Option<String>,
+    pub strip_release: Option<bool>,
+    #[serde(default)]
+    pub gradle_args: Vec<String>,
+    pub application_id: Option<String>,
+    #[serde(default)]
+    pub extra_files: Vec<ExtraFile>,
+    pub signing_cert_sha256: Option<String>,
+    pub kotlin_package: Option<String>,
+    #[serde(default)]
+    pub remote_devices: Vec<String>,
+    #[serde(default, skip_serializing_if = "TargetEnv::is_empty")]
+    pub target_env: TargetEnv,
+    #[serde(default, skip_serializing_if = "TargetToolchain::is_empty")]
+    pub toolchain: TargetToolchain,
+    pub require_free: Option<f64>,
+    #[serde(default)]
+    pub abi_filters: Vec<String>,
 }
 
 #[derive(Clone, Debug, Serialize)]
@@ -197,13 +271,173 @@ pub struct Config {
     min_sdk_version: u32,
     project_dir: PathBuf,
     logcat_filter_specs: Vec<String>,
+    gradle_dependencies: Vec<String>,
+    gradle_version: String,
+    strip_release: bool,
+    gradle_args: Vec<String>,
+    application_id: String,
+    extra_files: Vec<ExtraFile>,
+    signing_cert_sha256: Option<String>,
+    kotlin_package: String,
+    remote_devices: Vec<String>,
+    target_env: TargetEnv,
+    toolchain: TargetToolchain,
+    require_free: Option<f64>,
+    abi_filters: Vec<String>,
+}
+
+fn gradle_version(raw: Option<String>) -> Result<String, Error> {
+    match raw {
+        // Gradle versions are usually `major.minor`, but patch releases are
+        // `major.minor.patch`; `VersionTriple` accepts both.
+        Some(version) => VersionTriple::from_str(&version)
+            .map(|_| version)
+            .map_err(Error::GradleVersionInvalid),
+        None => Ok(DEFAULT_GRADLE_VERSION.to_owned()),
+    }
+}
+
+fn gradle_dependencies(raw: Vec<String>) -> Result<Vec<String>, Error> {
+    let mut deps = Vec::with_capacity(raw.len());
+    for dep in raw {
+        let parts = dep.split(':').collect::<Vec<_>>();
+        // `group:artifact:version`, optionally followed by a classifier
+        if !(3..=4).contains(&parts.len()) || parts.iter().any(|part| part.trim().is_empty()) {
+            return Err(Error::GradleDependencyInvalid(dep));
+        }
+        if deps.contains(&dep) {
+            log::warn!(
+                "`{}.gradle-dependencies` contains {:?} more than once; ignoring the duplicate",
+                super::NAME,
+                dep
+            );
+        } else {
+            deps.push(dep);
+        }
+    }
+    Ok(deps)
+}
+
+fn remote_devices(raw: Vec<String>) -> Result<Vec<String>, Error> {
+    match raw.iter().find(|endpoint| {
+        !endpoint
+            .rsplit_once(':')
+            .is_some_and(|(host, port)| !host.is_empty() && port.parse::<u16>().is_ok())
+    }) {
+        Some(endpoint) => Err(Error::RemoteDeviceInvalid(endpoint.clone())),
+        None => Ok(raw),
+    }
+}
+
+fn abi_filters(raw: Vec<String>) -> Result<Vec<String>, Error> {
+    match raw.iter().find(|abi| Target::for_abi(abi).is_none()) {
+        Some(abi) => Err(Error::AbiFilterInvalid(abi.clone())),
+        None => Ok(raw),
+    }
+}
+
+fn gradle_args(raw: Vec<String>) -> Result<Vec<String>, Error> {
+    match raw
+        .iter()
+        .find(|arg| *arg == "-p" || *arg == "--project-dir" || arg.starts_with("--project-dir="))
+    {
+        Some(arg) => Err(Error::GradleArgReserved(arg.clone())),
+        None => Ok(raw),
+    }
+}
+
+// The Play Store identifies apps by their application ID, which only needs to
+// match `app.identifier` when the latter is used as a Kotlin/Java package.
+fn application_id(identifier: &str, raw: Option<String>) -> Result<String, Error> {
+    let Some(application_id) = raw else {
+        return Ok(identifier.to_owned());
+    };
+    crate::config::app::identifier::check_identifier_syntax(&application_id).map_err(|cause| {
+        Error::ApplicationIdInvalid {
+            application_id: application_id.clone(),
+            cause,
+        }
+    })?;
+    if !application_id.contains('.') {
+        return Err(Error::ApplicationIdTooShort(application_id));
+    }
+    if application_id.contains('-') {
+        return Err(Error::ApplicationIdCannotContainHyphens(application_id));
+    }
+    if application_id == identifier {
+        log::warn!(
+            "`{}.application-id` is the same as `app.identifier`; you can remove it from your config",
+            super::NAME
+        );
+    } else {
+        log::warn!(
+            "`{}.application-id` {:?} differs from `app.identifier` {:?}, so the app will be published as {:?} while its code stays in the {:?} package",
+            super::NAME,
+            application_id,
+            identifier,
+            application_id,
+            identifier
+        );
+    }
+    Ok(application_id)
+}
+
+// The package the generated `MainActivity` is in, which only needs to differ
+// from `app.identifier` when integrating with existing Kotlin code.
+fn kotlin_package(identifier: &str, raw: Option<String>) -> Result<String, Error> {
+    let Some(kotlin_package) = raw else {
+        return Ok(identifier.to_owned());
+    };
+    crate::config::app::identifier::check_identifier_syntax(&kotlin_package).map_err(|cause| {
+        Error::KotlinPackageInvalid {
+            kotlin_package: kotlin_package.clone(),
+            cause,
+        }
+    })?;
+    if !kotlin_package.contains('.') {
+        return Err(Error::KotlinPackageTooShort(kotlin_package));
+    }
+    if kotlin_package.contains('-') {
+        return Err(Error::KotlinPackageCannotContainHyphens(kotlin_package));
+    }
+    if let Some(keyword) = kotlin_package
+        .split('.')
+        .find(|segment| crate::reserved_names::KOTLIN_ONLY_KEYWORDS.contains(segment))
+    {
+        return Err(Error::KotlinPackageKeyword {
+            keyword: keyword.to_owned(),
+            kotlin_package,
+        });
+    }
+    if kotlin_package == identifier {
+        log::warn!(
+            "`{}.kotlin-package` is the same as `app.identifier`; you can remove it from your config",
+            super::NAME
+        );
+    }
+    Ok(kotlin_package)
+}
+
+// `keytool` prints fingerprints as colon-separated uppercase hex, while
+// `apksigner` prints them as lowercase hex; either is accepted.
+fn signing_cert_sha256(raw: Option<String>) -> Result<Option<String>, Error> {
+    raw.map(|raw| {
+        let digest = raw.replace(':', "").trim().to_ascii_lowercase();
+        if digest.len() == 64 && digest.chars().all(|c| c.is_ascii_hexdigit()) {
+            Ok(digest)
+        } else {
+            Err(Error::SigningCertSha256Invalid(raw))
+        }
+    })
+    .transpose()
 }
 
 impl Config {
     pub fn from_raw(app: App, raw: Option<Raw>) -> Result<Self, Error> {
         let raw = raw.unwrap_or_default();
 
-        if app.identifier().contains('-') {
+        // Covers `app.debug-identifier-suffix` too.
+        if app.identifier_for(Profile::Debug).contains('-') {
             return Err(Error::IdentifierCannotContainHyphens);
         }
 
@@ -216,18 +450,30 @@ impl Config {
                     super::NAME
                 );
             }
-            if util::under_root(&project_dir, app.root_dir()).map_err(|cause| {
+            let under_root = util::under_root(&project_dir, app.root_dir()).map_err(|cause| {
                 Error::ProjectDirInvalid(ProjectDirInvalid::NormalizationFailed {
                     project_dir: project_dir.clone(),
                     cause,
                 })
-            })? {
-                if !project_dir.contains(' ') {
-                    Ok(project_dir.into())
-                } else {
+            })?;
+            if under_root || raw.allow_external_project_dir.unwrap_or_default() {
+                if project_dir.contains(' ') {
                     Err(Error::ProjectDirInvalid(
                         ProjectDirInvalid::ContainsSpaces { project_dir },
                     ))
+                } else if under_root {
+                    Ok(project_dir.into())
+                } else {
+                    // Store it as an absolute path, since it can't be
+                    // meaningfully expressed relative to the app root.
+                    util::normalize_path(app.root_dir().join(&project_dir))
+                        .map(|path| dunce::simplified(&path).to_owned())
+                        .map_err(|cause| {
+                            Error::ProjectDirInvalid(ProjectDirInvalid::NormalizationFailed {
+                                project_dir,
+                                cause,
+                            })
+                        })
                 }
             } else {
                 Err(Error::ProjectDirInvalid(
@@ -241,11 +487,48 @@ impl Config {
             Ok(DEFAULT_PROJECT_DIR.into())
         }?;
 
+        let gradle_dependencies = gradle_dependencies(raw.gradle_dependencies)?;
+        let gradle_version = gradle_version(raw.gradle_version)?;
+        let gradle_args = gradle_args(raw.gradle_args)?;
+        let application_id = application_id(app.identifier(), raw.application_id)?;
+        let extra_files = extra_file::validate(app.root_dir(), raw.extra_files)
+            .map_err(Error::ExtraFileInvalid)?;
+        let signing_cert_sha256 = signing_cert_sha256(raw.signing_cert_sha256)?;
+        let kotlin_package = kotlin_package(app.identifier(), raw.kotlin_package)?;
+        let remote_devices = remote_devices(raw.remote_devices)?;
+        let abi_filters = abi_filters(raw.abi_filters)?;
+        let target_env = raw
+            .target_env
+            .validate(Target::all().values().map(|target| target.triple()))
+            .map_err(Error::TargetEnvInvalid)?;
+        let toolchain = raw
+            .toolchain
+            .validate(Target::all().values().map(|target| target.triple()))
+            .map_err(Error::ToolchainInvalid)?;
+
+        let require_free = match raw.require_free {
+            Some(gib) if !util::disk::gib_valid(gib) => Err(Error::RequireFreeInvalid(gib)),
+            require_free => Ok(require_free),
+        }?;
+
         Ok(Self {
             app,
             min_sdk_version,
             project_dir,
             logcat_filter_specs: raw.logcat_filter_specs,
+            gradle_dependencies,
+            gradle_version,
+            strip_release: raw.strip_release.unwrap_or_default(),
+            gradle_args,
+            application_id,
+            extra_files,
+            signing_cert_sha256,
+            kotlin_package,
+            remote_devices,
+            target_env,
+            toolchain,
+            require_free,
+            abi_filters,
         })
     }
 
@@ -257,6 +540,80 @@ impl Config {
         &self.logcat_filter_specs
     }
 
+    pub fn gradle_dependencies(&self) -> &[String] {
+        &self.gradle_dependencies
+    }
+
+    pub fn gradle_version(&self) -> &str {
+        &self.gradle_version
+    }
+
+    /// The ABIs universal APKs and AABs are limited to, whichever targets were
+    /// built. Empty if they aren't limited.
+    pub fn abi_filters(&self) -> &[String] {
+        &self.abi_filters
+    }
+
+    /// Extra args passed to every Gradle invocation, e.g. `--offline`.
+    pub fn gradle_args(&self) -> &[String] {
+        &self.gradle_args
+    }
+
+    /// The `applicationId` the app is installed and published under, which
+    /// defaults to `app.identifier`.
+    pub fn application_id(&self) -> &str {
+        &self.application_id
+    }
+
+    /// The `applicationId` builds with `profile` end up with, after Gradle
+    /// applies the debug `applicationIdSuffix`.
+    pub fn application_id_for(&self, profile: Profile) -> String {
+        self.app.suffixed_for(&self.application_id, profile)
+    }
+
+    /// Files copied into the generated project. Anything under
+    /// `app/src/main/assets` is bundled into the APK by Gradle.
+    pub fn extra_files(&self) -> &[ExtraFile] {
+        &self.extra_files
+    }
+
+    /// The package the generated Kotlin sources are in, which defaults to
+    /// `app.identifier`.
+    pub fn kotlin_package(&self) -> &str {
+        &self.kotlin_package
+    }
+
+    /// The SHA-256 digest of the certificate release artifacts are expected to
+    /// be signed with, as lowercase hex.
+    pub fn signing_cert_sha256(&self) -> Option<&str> {
+        self.signing_cert_sha256.as_deref()
+    }
+
+    /// `host:port` endpoints that are `adb connect`ed to before listing
+    /// devices, e.g. for a device farm.
+    pub fn remote_devices(&self) -> &[String] {
+        &self.remote_devices
+    }
+
+    pub fn target_env(&self) -> &TargetEnv {
+        &self.target_env
+    }
+
+    pub fn toolchain(&self) -> &TargetToolchain {
+        &self.toolchain
+    }
+
+    /// How many GiB must be free on the disks builds write to before they
+    /// start, unless `--require-free` is passed.
+    pub fn require_free(&self) -> Option<f64> {
+        self.require_free
+    }
+
+    /// Whether release libraries should have their symbols stripped.
+    pub fn strip_release(&self) -> bool {
+        self.strip_release
+    }
+
     pub fn so_name(&self) -> String {
         format!("lib{}.so", self.app().lib_name())
     }
@@ -273,3 +630,50 @@ impl Config {
         self.project_dir().is_dir()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(None, Some("com.example.fun_app"))]
+    #[case(Some("com.example.legacy"), Some("com.example.legacy"))]
+    #[case(Some("legacy"), None)]
+    #[case(Some("com.example.fun-app"), None)]
+    #[case(Some("com.example.new"), None)]
+    fn test_application_id(#[case] raw: Option<&str>, #[case] expected: Option<&str>) {
+        assert_eq!(
+            application_id("com.example.fun_app", raw.map(ToOwned::to_owned)).ok(),
+            expected.map(ToOwned::to_owned)
+        );
+    }
+
+    #[rstest]
+    #[case(None, Some("com.example.fun_app"))]
+    #[case(Some("org.example.legacy"), Some("org.example.legacy"))]
+    #[case(Some("legacy"), None)]
+    #[case(Some("org.example.fun"), None)]
+    #[case(Some("org.example.class"), None)]
+    fn test_kotlin_package(#[case] raw: Option<&str>, #[case] expected: Option<&str>) {
+        assert_eq!(
+            kotlin_package("com.example.fun_app", raw.map(ToOwned::to_owned)).ok(),
+            expected.map(ToOwned::to_owned)
+        );
+    }
+
+    #[rstest]
+    #[case(
+        &["androidx.core:core-ktx:1.12.0", "androidx.core:core-ktx:1.12.0", "org.example:lib:1.0:sources"],
+        Some(&["androidx.core:core-ktx:1.12.0", "org.example:lib:1.0:sources"][..])
+    )]
+    #[case(&["androidx.core:core-ktx"], None)]
+    #[case(&["org.example::1.0"], None)]
+    #[case(&["org.example:lib:1.0:sources:extra"], None)]
+    fn test_gradle_dependencies(#[case] raw: &[&str], #[case] expected: Option<&[&str]>) {
+        assert_eq!(
+            gradle_dependencies(raw.iter().map(|&dep| dep.to_owned()).collect()).ok(),
+            expected.map(|deps| deps.iter().map(|&dep| dep.to_owned()).collect())
+        );
+    }
+}
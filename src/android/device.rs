@@ -1,6 +1,7 @@
 use super::{aab, adb, bundletool, config::Config, env::Env, jnilibs, target::Target};
 use crate::{
     android::apk,
+    device::{self, AfterRunError, AppInfo, DryRun, Explanation, Locale},
     env::ExplicitEnv as _,
     opts::{FilterLevel, NoiseLevel, Profile},
     os::consts,
@@ -11,14 +12,93 @@ use crate::{
     },
     DuctExpressionExt,
 };
+use heck::ToUpperCamelCase as _;
 use std::{
+    ffi::OsString,
     fmt::{self, Display},
-    path::PathBuf,
+    path::{Path, PathBuf},
+    str::FromStr,
     thread::sleep,
     time::Duration,
 };
 use thiserror::Error;
 
+// `ro.product.cpu.abilist` is comma-separated, in order of preference.
+fn parse_abi_list(list: &str) -> Vec<String> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|abi| !abi.is_empty())
+        .map(ToOwned::to_owned)
+        .collect()
+}
+
+// Uses `aapt2` from the newest installed build-tools.
+fn apk_package_name(env: &Env, apk: &Path) -> Option<String> {
+    let aapt2 = std::fs::read_dir(Path::new(env.android_home()).join("build-tools"))
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| {
+            entry
+                .path()
+                .join(format!("aapt2{}", std::env::consts::EXE_SUFFIX))
+        })
+        .filter(|path| path.is_file())
+        .max()?;
+    duct::cmd(aapt2, ["dump", "packagename"])
+        .before_spawn({
+            let apk = apk.to_owned();
+            move |cmd| {
+                cmd.arg(&apk);
+                Ok(())
+            }
+        })
+        .vars(env.explicit_env())
+        .stderr_capture()
+        .read()
+        .ok()
+        .map(|package| package.trim().to_owned())
+}
+
+// APKs built elsewhere could be from either profile, so this goes by the
+// package name in the APK if it's one of this project's.
+fn deployed_application_id(config: &Config, env: &Env, apk: &Path) -> String {
+    let package = apk_package_name(env, apk);
+    if package.is_none() {
+        log::warn!(
+            "couldn't check the package name of {:?}, since `aapt2` wasn't found",
+            apk
+        );
+    }
+    pick_application_id(
+        apk,
+        package,
+        config.application_id_for(Profile::Debug),
+        config.application_id_for(Profile::Release),
+    )
+}
+
+fn pick_application_id(
+    apk: &Path,
+    package: Option<String>,
+    debug_id: String,
+    release_id: String,
+) -> String {
+    match package {
+        Some(package) if package == debug_id => debug_id,
+        Some(package) if package != release_id => {
+            log::warn!(
+                "{:?} has the package name {:?}, but this project's application ID is {:?} ({:?} for debug builds)",
+                apk,
+                package,
+                release_id,
+                debug_id
+            );
+            release_id
+        }
+        _ => release_id,
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum AabBuildError {
     #[error("Failed to build AAB: {0}")]
@@ -67,6 +147,117 @@ impl Reportable for ApkInstallError {
     }
 }
 
+#[derive(Debug, Error)]
+#[error("{0:?} isn't a port mapping of the form `host-port:device-port` or `port`")]
+pub struct PortMappingInvalid(String);
+
+/// A port on the device to forward to a port on the host with `adb reverse`,
+/// e.g. so the app can reach a dev server at `localhost`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct PortMapping {
+    pub host: u16,
+    pub device: u16,
+}
+
+impl FromStr for PortMapping {
+    type Err = PortMappingInvalid;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || PortMappingInvalid(s.to_owned());
+        let (host, device) = s.split_once(':').unwrap_or((s, s));
+        Ok(Self {
+            host: host.parse().map_err(|_| invalid())?,
+            device: device.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+impl Display for PortMapping {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.host, self.device)
+    }
+}
+
+// Pairs of `adb install` flags that can't be used together.
+static CONFLICTING_INSTALL_ARGS: &[(&str, &str)] = &[
+    ("--instant", "--full"),
+    ("--streaming", "--no-streaming"),
+    ("--incremental", "--no-incremental"),
+    ("-f", "-s"),
+];
+
+#[derive(Debug, Error)]
+pub enum InstallOptionsInvalid {
+    #[error("`{0}` and `{1}` can't be passed to `adb install` together")]
+    Conflict(String, String),
+    #[error("`bundletool install-apks` can't grant permissions, which is how apps with asset packs are installed")]
+    GrantPermissionsUnsupported,
+}
+
+/// Options for installing the app, on top of replacing an existing install.
+/// `extra_args` are passed to `bundletool install-apks` instead of
+/// `adb install` for apps with asset packs.
+#[derive(Clone, Debug, Default)]
+pub struct InstallOptions {
+    pub downgrade: bool,
+    pub grant_permissions: bool,
+    pub extra_args: Vec<String>,
+}
+
+impl InstallOptions {
+    pub fn check(&self, build_app_bundle: bool) -> Result<(), InstallOptionsInvalid> {
+        if build_app_bundle {
+            if self.grant_permissions {
+                return Err(InstallOptionsInvalid::GrantPermissionsUnsupported);
+            }
+            return Ok(());
+        }
+        let has = |arg: &str| self.extra_args.iter().any(|extra| extra == arg);
+        match CONFLICTING_INSTALL_ARGS
+            .iter()
+            .find(|(a, b)| has(a) && has(b))
+        {
+            Some((a, b)) => Err(InstallOptionsInvalid::Conflict(
+                (*a).to_owned(),
+                (*b).to_owned(),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    fn adb_args(&self) -> impl Iterator<Item = OsString> + '_ {
+        self.downgrade
+            .then_some("-d")
+            .into_iter()
+            .chain(self.grant_permissions.then_some("-g"))
+            .map(Into::into)
+            .chain(self.extra_args.iter().map(Into::into))
+    }
+
+    fn bundletool_args(&self) -> impl Iterator<Item = OsString> + '_ {
+        self.downgrade
+            .then_some("--allow-downgrade".into())
+            .into_iter()
+            .chain(self.extra_args.iter().map(Into::into))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum RunStep<'a> {
+    InstallBundletool,
+    BuildAab,
+    BuildApksFromAab,
+    BuildApk,
+    WaitForBoot,
+    InstallApks,
+    InstallApk,
+    Reverse(PortMapping),
+    SetLocale(&'a Locale),
+    Launch,
+    AfterRun(&'a str),
+    FollowLogs,
+}
+
 #[derive(Debug, Error)]
 pub enum RunError {
     #[error(transparent)]
@@ -83,6 +274,17 @@ pub enum RunError {
     AabBuildFailed(AabBuildError),
     #[error(transparent)]
     ApksFromAabBuildFailed(ApksBuildError),
+    #[error("{0} isn't an APK")]
+    ArtifactInvalid(PathBuf),
+    #[error("No artifact paths are known for the {0} profile")]
+    ArtifactPathsMissing(&'static str),
+    #[error("Failed to reverse device port {} to host port {}: {cause}", .mapping.device, .mapping.host)]
+    ReverseFailed {
+        mapping: PortMapping,
+        cause: std::io::Error,
+    },
+    #[error(transparent)]
+    AfterRunFailed(AfterRunError),
     #[error(transparent)]
     Io(#[from] std::io::Error),
 }
@@ -97,6 +299,10 @@ impl Reportable for RunError {
             Self::BundletoolInstallFailed(err) => err.report(),
             Self::AabBuildFailed(err) => err.report(),
             Self::ApksFromAabBuildFailed(err) => err.report(),
+            Self::ArtifactInvalid(_) => Report::error("Failed to deploy artifact", self),
+            Self::ArtifactPathsMissing(_) => Report::error("Failed to locate artifact", self),
+            Self::ReverseFailed { .. } => Report::error("Failed to set up `adb reverse`", self),
+            Self::AfterRunFailed(err) => err.report(),
             Self::Io(err) => Report::error("IO error", err),
         }
     }
@@ -165,10 +371,92 @@ impl<'a> Device<'a> {
         &self.serial_no
     }
 
+    /// Every ABI the device can run, which is just the primary one on devices
+    /// too old to report `ro.product.cpu.abilist`.
+    pub fn supported_abis(&self, env: &Env) -> Result<Vec<String>, adb::get_prop::Error> {
+        let abis = parse_abi_list(&adb::get_prop(
+            env,
+            &self.serial_no,
+            "ro.product.cpu.abilist",
+        )?);
+        Ok(if abis.is_empty() {
+            vec![self.target.abi.to_owned()]
+        } else {
+            abis
+        })
+    }
+
     fn adb(&self, env: &Env) -> duct::Expression {
         adb::adb(env, ["-s", &self.serial_no])
     }
 
+    // The full command line for `--dry-run`, matching what `adb` runs.
+    fn adb_argv(&self, env: &Env, args: Vec<OsString>) -> Vec<OsString> {
+        [
+            env.platform_tools_path().join("adb").into(),
+            "-s".into(),
+            self.serial_no.clone().into(),
+        ]
+        .into_iter()
+        .chain(args)
+        .collect()
+    }
+
+    fn install_apk_args(apk_path: &Path, options: &InstallOptions) -> Vec<OsString> {
+        ["install".into(), "-r".into()]
+            .into_iter()
+            .chain(options.adb_args())
+            .chain(std::iter::once(apk_path.into()))
+            .collect()
+    }
+
+    fn install_apks_args(apks_path: &Path, options: &InstallOptions) -> Vec<OsString> {
+        [
+            "install-apks".into(),
+            format!("--apks={}", apks_path.to_str().unwrap()).into(),
+        ]
+        .into_iter()
+        .chain(options.bundletool_args())
+        .collect()
+    }
+
+    fn start_activity_args(application_id: &str, activity: &str) -> Vec<OsString> {
+        ["shell", "am", "start", "-n"]
+            .into_iter()
+            .map(Into::into)
+            .chain(std::iter::once(
+                format!("{}/{}", application_id, activity).into(),
+            ))
+            .collect()
+    }
+
+    fn reverse_args(mapping: PortMapping) -> Vec<OsString> {
+        vec![
+            "reverse".into(),
+            format!("tcp:{}", mapping.device).into(),
+            format!("tcp:{}", mapping.host).into(),
+        ]
+    }
+
+    fn set_app_locale_args(application_id: &str, locale: &Locale) -> Vec<OsString> {
+        ["shell", "cmd", "locale", "set-app-locales", application_id]
+            .into_iter()
+            .chain(["--locales", locale.tag()])
+            .map(Into::into)
+            .collect()
+    }
+
+    /// Looks up the version of the `profile` build of the app that's
+    /// installed on this device, if any.
+    pub fn app_info(
+        &self,
+        config: &Config,
+        env: &Env,
+        profile: Profile,
+    ) -> Result<Option<AppInfo>, adb::package_info::Error> {
+        adb::package_info(env, &self.serial_no, &config.application_id_for(profile))
+    }
+
     pub fn all_apks_paths(config: &Config, profile: Profile, flavor: &str) -> Vec<PathBuf> {
         profile
             .suffixes()
@@ -232,17 +520,26 @@ impl<'a> Device<'a> {
         config: &Config,
         env: &Env,
         profile: Profile,
+        options: &InstallOptions,
     ) -> Result<(), ApkInstallError> {
         let flavor = self.target.arch;
         let apk_path = apk::apks_paths(config, profile, flavor)
             .into_iter()
             .reduce(last_modified)
             .unwrap();
+        self.install_apk_at(env, apk_path, options)
+    }
 
+    fn install_apk_at(
+        &self,
+        env: &Env,
+        apk_path: PathBuf,
+        options: &InstallOptions,
+    ) -> Result<(), ApkInstallError> {
+        let args = Self::install_apk_args(&apk_path, options);
         self.adb(env)
             .before_spawn(move |cmd| {
-                cmd.args(["install", "-r"]);
-                cmd.arg(&apk_path);
+                cmd.args(&args);
                 Ok(())
             })
             .dup_stdio()
@@ -299,19 +596,17 @@ impl<'a> Device<'a> {
         &self,
         config: &Config,
         profile: Profile,
+        options: &InstallOptions,
     ) -> Result<(), ApkInstallError> {
         let flavor = self.target.arch;
         let apks_path = Self::all_apks_paths(config, profile, flavor)
             .into_iter()
             .reduce(last_modified)
             .unwrap();
+        let args = Self::install_apks_args(&apks_path, options);
         bundletool::command()
             .before_spawn(move |cmd| {
-                cmd.args([
-                    "install-apks",
-                    &format!("--apks={}", apks_path.to_str().unwrap()),
-                ]);
-
+                cmd.args(&args);
                 Ok(())
             })
             .run()
@@ -331,6 +626,39 @@ impl<'a> Device<'a> {
         Ok(())
     }
 
+    // What `run` does, in order. `dry_run` and `explain` are derived from this
+    // too, so that they can't drift from what actually happens.
+    fn run_steps<'s>(
+        &self,
+        build_app_bundle: bool,
+        locale: Option<&'s Locale>,
+        reverse: &[PortMapping],
+        after_run: Option<&'s str>,
+    ) -> Vec<RunStep<'s>> {
+        let mut steps = if build_app_bundle {
+            vec![
+                RunStep::InstallBundletool,
+                RunStep::BuildAab,
+                RunStep::BuildApksFromAab,
+            ]
+        } else {
+            vec![RunStep::BuildApk]
+        };
+        if self.serial_no.starts_with("emulator") {
+            steps.push(RunStep::WaitForBoot);
+        }
+        steps.push(if build_app_bundle {
+            RunStep::InstallApks
+        } else {
+            RunStep::InstallApk
+        });
+        steps.extend(reverse.iter().copied().map(RunStep::Reverse));
+        steps.extend(locale.map(RunStep::SetLocale));
+        steps.push(RunStep::Launch);
+        steps.push(after_run.map_or(RunStep::FollowLogs, RunStep::AfterRun));
+        steps
+    }
+
     #[allow(clippy::too_many_arguments)]
     pub fn run(
         &self,
@@ -341,32 +669,333 @@ impl<'a> Device<'a> {
         filter_level: Option<FilterLevel>,
         build_app_bundle: bool,
         reinstall_deps: bool,
+        locale: Option<&Locale>,
+        reverse: &[PortMapping],
+        install: &InstallOptions,
         activity: String,
+        after_run: Option<&str>,
     ) -> Result<duct::Handle, RunError> {
-        if build_app_bundle {
-            bundletool::install(reinstall_deps).map_err(RunError::BundletoolInstallFailed)?;
-            self.build_aab(config, env, noise_level, profile)
-                .map_err(RunError::AabError)?;
-            self.build_apks_from_aab(config, profile)
-                .map_err(RunError::ApksFromAabBuildFailed)?;
-            if self.serial_no.starts_with("emulator") {
-                self.wait_device_boot(env);
+        let application_id = config.application_id_for(profile);
+        let mut logcat = None;
+        for step in self.run_steps(build_app_bundle, locale, reverse, after_run) {
+            match step {
+                RunStep::InstallBundletool => bundletool::install(reinstall_deps)
+                    .map_err(RunError::BundletoolInstallFailed)?,
+                RunStep::BuildAab => self
+                    .build_aab(config, env, noise_level, profile)
+                    .map_err(RunError::AabError)?,
+                RunStep::BuildApksFromAab => self
+                    .build_apks_from_aab(config, profile)
+                    .map_err(RunError::ApksFromAabBuildFailed)?,
+                RunStep::BuildApk => self
+                    .build_apk(config, env, noise_level, profile)
+                    .map_err(RunError::ApkError)?,
+                RunStep::WaitForBoot => self.wait_device_boot(env),
+                RunStep::InstallApks => self
+                    .install_apk_from_aab(config, profile, install)
+                    .map_err(RunError::ApkInstallFailed)?,
+                RunStep::InstallApk => self
+                    .install_apk(config, env, profile, install)
+                    .map_err(RunError::ApkInstallFailed)?,
+                RunStep::Reverse(mapping) => self
+                    .reverse(env, mapping.host, mapping.device)
+                    .map_err(|cause| RunError::ReverseFailed { mapping, cause })?,
+                RunStep::SetLocale(locale) => self.set_app_locale(env, &application_id, locale),
+                RunStep::Launch => {
+                    if !reverse.is_empty() {
+                        println!("Port reversals last until the device disconnects or the adb server restarts; run `adb reverse --remove-all` to clear them sooner.");
+                    }
+                    logcat = Some(self.launch(
+                        config,
+                        env,
+                        &application_id,
+                        noise_level,
+                        filter_level,
+                        &activity,
+                    )?);
+                }
+                RunStep::AfterRun(command) => {
+                    if let Err(err) =
+                        device::after_run(env, command, &self.serial_no, &application_id)
+                    {
+                        if let Some(logcat) = &logcat {
+                            let _ = logcat.kill();
+                        }
+                        return Err(RunError::AfterRunFailed(err));
+                    }
+                }
+                // That's left to the caller, which gets the `logcat` handle.
+                RunStep::FollowLogs => (),
             }
-            self.install_apk_from_aab(config, profile)
-                .map_err(RunError::ApkInstallFailed)?;
+        }
+        Ok(logcat.expect("developer error: `run_steps` didn't include launching"))
+    }
+
+    /// The artifact and commands `run` would use to install and launch the
+    /// app, without building anything.
+    #[allow(clippy::too_many_arguments)]
+    pub fn dry_run(
+        &self,
+        config: &Config,
+        env: &Env,
+        profile: Profile,
+        build_app_bundle: bool,
+        locale: Option<&Locale>,
+        reverse: &[PortMapping],
+        install: &InstallOptions,
+        activity: &str,
+    ) -> Result<DryRun, RunError> {
+        let flavor = self.target.arch;
+        // With an app bundle, what's deployed is the APK set built from it.
+        let (artifact, installed) = if build_app_bundle {
+            let apks_path = Self::all_apks_paths(config, profile, flavor)
+                .into_iter()
+                .reduce(last_modified)
+                .ok_or(RunError::ArtifactPathsMissing(profile.as_str()))?;
+            (aab::aab_path(config, profile, flavor), apks_path)
         } else {
-            self.build_apk(config, env, noise_level, profile)
-                .map_err(RunError::ApkError)?;
-            if self.serial_no.starts_with("emulator") {
-                self.wait_device_boot(env);
-            }
-            self.install_apk(config, env, profile)
-                .map_err(RunError::ApkInstallFailed)?;
+            let apk_path = apk::apks_paths(config, profile, flavor)
+                .into_iter()
+                .reduce(last_modified)
+                .ok_or(RunError::ArtifactPathsMissing(profile.as_str()))?;
+            (apk_path.clone(), apk_path)
+        };
+        let application_id = config.application_id_for(profile);
+        let commands = self
+            .run_steps(build_app_bundle, locale, reverse, None)
+            .into_iter()
+            .filter_map(|step| match step {
+                RunStep::InstallApks => Some(
+                    bundletool::argv()
+                        .into_iter()
+                        .chain(Self::install_apks_args(&installed, install))
+                        .collect(),
+                ),
+                RunStep::InstallApk => {
+                    Some(self.adb_argv(env, Self::install_apk_args(&installed, install)))
+                }
+                RunStep::Reverse(mapping) => Some(self.adb_argv(env, Self::reverse_args(mapping))),
+                RunStep::SetLocale(locale) => {
+                    Some(self.adb_argv(env, Self::set_app_locale_args(&application_id, locale)))
+                }
+                RunStep::Launch => {
+                    Some(self.adb_argv(env, Self::start_activity_args(&application_id, activity)))
+                }
+                _ => None,
+            })
+            .collect();
+        Ok(DryRun {
+            device: self.to_string(),
+            artifact,
+            commands,
+        })
+    }
+
+    /// The steps `run` would take with the same arguments.
+    #[allow(clippy::too_many_arguments)]
+    pub fn explain(
+        &self,
+        config: &Config,
+        profile: Profile,
+        build_app_bundle: bool,
+        locale: Option<&Locale>,
+        reverse: &[PortMapping],
+        install: &InstallOptions,
+        activity: &str,
+        after_run: Option<&str>,
+    ) -> Explanation {
+        self.explanation(
+            &config.application_id_for(profile),
+            profile,
+            self.run_steps(build_app_bundle, locale, reverse, after_run),
+            install,
+            activity,
+        )
+    }
+
+    fn explanation(
+        &self,
+        application_id: &str,
+        profile: Profile,
+        steps: Vec<RunStep<'_>>,
+        install: &InstallOptions,
+        activity: &str,
+    ) -> Explanation {
+        let build_ty = profile.as_str().to_upper_camel_case();
+        let args = |args: Vec<OsString>| {
+            args.iter()
+                .map(|arg| format!(" {}", arg.to_string_lossy()))
+                .collect::<String>()
+        };
+        let steps = steps
+            .into_iter()
+            .map(|step| match step {
+                RunStep::InstallBundletool => "Install `bundletool`, if it isn't already".to_owned(),
+                RunStep::BuildAab => format!(
+                    "Build an AAB with Gradle's `bundleUniversal{}` task, which builds the Rust library for `{}`",
+                    build_ty, self.target.triple
+                ),
+                RunStep::BuildApksFromAab => {
+                    "Convert the AAB to APKs with `bundletool build-apks`".to_owned()
+                }
+                RunStep::BuildApk => format!(
+                    "Build an APK with Gradle's `assemble{}{}` task, which builds the Rust library for `{}`",
+                    self.target.arch_upper_camel_case(),
+                    build_ty,
+                    self.target.triple
+                ),
+                RunStep::WaitForBoot => "Wait for the emulator to finish booting".to_owned(),
+                RunStep::InstallApks => format!(
+                    "Install the APKs with `bundletool install-apks{}`",
+                    args(install.bundletool_args().collect())
+                ),
+                RunStep::InstallApk => format!(
+                    "Install the APK with `adb install{}`",
+                    args(install.adb_args().collect())
+                ),
+                RunStep::Reverse(mapping) => format!(
+                    "Forward port {} on the device to port {} on this machine with `adb reverse`",
+                    mapping.device, mapping.host
+                ),
+                RunStep::SetLocale(locale) => format!(
+                    "Set the app's locale to {} with `adb shell cmd locale set-app-locales`",
+                    locale
+                ),
+                RunStep::Launch => format!(
+                    "Launch `{}` in `{}` with `adb shell am start`",
+                    activity, application_id
+                ),
+                RunStep::AfterRun(command) => format!("Run `{}`, then stop", command),
+                RunStep::FollowLogs => {
+                    "Follow the app's logs with `adb logcat` until interrupted".to_owned()
+                }
+            })
+            .collect();
+        Explanation {
+            device: self.to_string(),
+            steps,
+        }
+    }
+
+    /// Like [`Device::dry_run`], but for [`Device::deploy`].
+    pub fn deploy_dry_run(
+        &self,
+        config: &Config,
+        env: &Env,
+        artifact: &Path,
+        install: &InstallOptions,
+        activity: &str,
+    ) -> Result<DryRun, RunError> {
+        if !artifact.is_file() || artifact.extension() != Some("apk".as_ref()) {
+            return Err(RunError::ArtifactInvalid(artifact.to_owned()));
         }
-        let activity = format!("{}/{}", config.app().identifier(), activity);
+        let application_id = deployed_application_id(config, env, artifact);
+        Ok(DryRun {
+            device: self.to_string(),
+            artifact: artifact.to_owned(),
+            commands: vec![
+                self.adb_argv(env, Self::install_apk_args(artifact, install)),
+                self.adb_argv(env, Self::start_activity_args(&application_id, activity)),
+            ],
+        })
+    }
+
+    /// Makes connections to `device_port` on the device reach `host_port` on
+    /// this machine, via `adb reverse`.
+    pub fn reverse(&self, env: &Env, host_port: u16, device_port: u16) -> std::io::Result<()> {
+        println!(
+            "Reversing device port {} to host port {}...",
+            device_port, host_port
+        );
+        let args = Self::reverse_args(PortMapping {
+            host: host_port,
+            device: device_port,
+        });
         self.adb(env)
             .before_spawn(move |cmd| {
-                cmd.args(["shell", "am", "start", "-n", &activity]);
+                cmd.args(&args);
+                Ok(())
+            })
+            .stdout_null()
+            .stderr_capture()
+            .run()
+            .map(|_| ())
+    }
+
+    /// Installs and launches an APK that was built elsewhere, without
+    /// building anything.
+    #[allow(clippy::too_many_arguments)]
+    pub fn deploy(
+        &self,
+        config: &Config,
+        env: &Env,
+        artifact: &Path,
+        install: &InstallOptions,
+        noise_level: NoiseLevel,
+        filter_level: Option<FilterLevel>,
+        activity: String,
+    ) -> Result<duct::Handle, RunError> {
+        if !artifact.is_file() || artifact.extension() != Some("apk".as_ref()) {
+            return Err(RunError::ArtifactInvalid(artifact.to_owned()));
+        }
+        let application_id = deployed_application_id(config, env, artifact);
+        if self.serial_no.starts_with("emulator") {
+            self.wait_device_boot(env);
+        }
+        self.install_apk_at(env, artifact.to_owned(), install)
+            .map_err(RunError::ApkInstallFailed)?;
+        self.launch(
+            config,
+            env,
+            &application_id,
+            noise_level,
+            filter_level,
+            &activity,
+        )
+    }
+
+    // Per-app locales were added in Android 13, so older devices can't do this.
+    fn set_app_locale(&self, env: &Env, package: &str, locale: &Locale) {
+        let args = Self::set_app_locale_args(package, locale);
+        let result = self
+            .adb(env)
+            .before_spawn(move |cmd| {
+                cmd.args(&args);
+                Ok(())
+            })
+            .stderr_capture()
+            .stdout_capture()
+            .run();
+        match result {
+            Ok(output) if output.stdout.is_empty() && output.stderr.is_empty() => (),
+            Ok(output) => log::warn!(
+                "couldn't set the app locale to {}: {}",
+                locale,
+                String::from_utf8_lossy(if output.stderr.is_empty() {
+                    &output.stdout
+                } else {
+                    &output.stderr
+                })
+                .trim()
+            ),
+            Err(err) => log::warn!("couldn't set the app locale to {}: {}", locale, err),
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn launch(
+        &self,
+        config: &Config,
+        env: &Env,
+        application_id: &str,
+        noise_level: NoiseLevel,
+        filter_level: Option<FilterLevel>,
+        activity: &str,
+    ) -> Result<duct::Handle, RunError> {
+        let args = Self::start_activity_args(application_id, activity);
+        self.adb(env)
+            .before_spawn(move |cmd| {
+                cmd.args(&args);
                 Ok(())
             })
             .dup_stdio()
@@ -390,7 +1019,7 @@ impl<'a> Device<'a> {
         let stdout = loop {
             let cmd = duct::cmd(
                 env.platform_tools_path().join("adb"),
-                ["shell", "pidof", "-s", config.app().identifier()],
+                ["shell", "pidof", "-s", application_id],
             )
             .vars(env.explicit_env())
             .stderr_capture()
@@ -423,14 +1052,17 @@ impl<'a> Device<'a> {
     }
 
     pub fn stacktrace(&self, config: &Config, env: &Env) -> Result<(), StacktraceError> {
+        let jnilib_path = jnilibs::path(config, *self.target);
         let jnilib_path = config
             .app()
             // ndk-stack can't seem to handle spaces in args, no matter
             // how I try to quote or escape them... so, instead of
             // mandating that the entire path not contain spaces, we'll
             // just use a relative path!
-            .unprefix_path(jnilibs::path(config, *self.target))
-            .expect("developer error: jnilibs subdir not prefixed");
+            .unprefix_path(&jnilib_path)
+            // The project dir may live outside of the app root if
+            // `allow-external-project-dir` is set.
+            .unwrap_or_else(|_| util::relativize_path(&jnilib_path, config.app().root_dir()));
         // -d = print and exit
         let logcat_command = adb::adb(env, ["-s", &self.serial_no])
             .before_spawn(move |cmd| {
@@ -455,3 +1087,146 @@ impl<'a> Device<'a> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::target::TargetTrait as _;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(Some("com.example.app.debug"), "com.example.app.debug")]
+    #[case(Some("com.example.app"), "com.example.app")]
+    #[case(Some("com.example.other"), "com.example.app")]
+    #[case(None, "com.example.app")]
+    fn deployed_apks_use_their_profiles_application_id(
+        #[case] package: Option<&str>,
+        #[case] expected: &str,
+    ) {
+        assert_eq!(
+            pick_application_id(
+                Path::new("app.apk"),
+                package.map(ToOwned::to_owned),
+                "com.example.app.debug".to_owned(),
+                "com.example.app".to_owned(),
+            ),
+            expected
+        );
+    }
+
+    fn device(serial_no: &str) -> Device<'static> {
+        Device {
+            serial_no: serial_no.to_owned(),
+            name: "Pixel 8".to_owned(),
+            model: "Pixel 8".to_owned(),
+            target: &Target::all()["aarch64"],
+        }
+    }
+
+    #[test]
+    fn run_steps_depend_on_the_device_and_options() {
+        let locale = "fr-CA".parse::<Locale>().unwrap();
+        let mapping = PortMapping {
+            host: 3000,
+            device: 8081,
+        };
+        assert_eq!(
+            device("emulator-5554").run_steps(false, Some(&locale), &[mapping], Some("./check")),
+            [
+                RunStep::BuildApk,
+                RunStep::WaitForBoot,
+                RunStep::InstallApk,
+                RunStep::Reverse(mapping),
+                RunStep::SetLocale(&locale),
+                RunStep::Launch,
+                RunStep::AfterRun("./check"),
+            ]
+        );
+        assert_eq!(
+            device("R5CT1234").run_steps(true, None, &[], None),
+            [
+                RunStep::InstallBundletool,
+                RunStep::BuildAab,
+                RunStep::BuildApksFromAab,
+                RunStep::InstallApks,
+                RunStep::Launch,
+                RunStep::FollowLogs,
+            ]
+        );
+    }
+
+    #[test]
+    fn explanation_narrates_each_run_step() {
+        let device = device("emulator-5554");
+        let locale = "fr-CA".parse::<Locale>().unwrap();
+        let install = InstallOptions {
+            downgrade: true,
+            ..Default::default()
+        };
+        let explanation = device.explanation(
+            "com.example.app",
+            Profile::Debug,
+            device.run_steps(false, Some(&locale), &[], None),
+            &install,
+            ".MainActivity",
+        );
+        assert_eq!(
+            explanation.steps,
+            [
+                "Build an APK with Gradle's `assembleArm64Debug` task, which builds the Rust library for `aarch64-linux-android`",
+                "Wait for the emulator to finish booting",
+                "Install the APK with `adb install -d`",
+                "Set the app's locale to fr-CA with `adb shell cmd locale set-app-locales`",
+                "Launch `.MainActivity` in `com.example.app` with `adb shell am start`",
+                "Follow the app's logs with `adb logcat` until interrupted",
+            ]
+        );
+    }
+
+    #[rstest]
+    #[case("8080", Some((8080, 8080)))]
+    #[case("3000:8081", Some((3000, 8081)))]
+    #[case("8080:", None)]
+    #[case("http", None)]
+    #[case("70000", None)]
+    fn test_port_mapping_from_str(#[case] input: &str, #[case] expected: Option<(u16, u16)>) {
+        assert_eq!(
+            input.parse::<PortMapping>().ok(),
+            expected.map(|(host, device)| PortMapping { host, device })
+        );
+    }
+
+    #[rstest]
+    #[case("arm64-v8a,armeabi-v7a,armeabi\n", &["arm64-v8a", "armeabi-v7a", "armeabi"])]
+    #[case("x86_64, x86", &["x86_64", "x86"])]
+    #[case("", &[])]
+    fn test_parse_abi_list(#[case] input: &str, #[case] expected: &[&str]) {
+        assert_eq!(parse_abi_list(input), expected);
+    }
+
+    #[test]
+    fn install_options_are_passed_along() {
+        let options = InstallOptions {
+            downgrade: true,
+            grant_permissions: true,
+            extra_args: vec!["-t".to_owned()],
+        };
+        assert!(options.check(false).is_ok());
+        assert_eq!(
+            Device::install_apk_args("app.apk".as_ref(), &options),
+            ["install", "-r", "-d", "-g", "-t", "app.apk"]
+        );
+        assert!(matches!(
+            options.check(true),
+            Err(InstallOptionsInvalid::GrantPermissionsUnsupported)
+        ));
+        let conflicting = InstallOptions {
+            extra_args: vec!["--full".to_owned(), "--instant".to_owned()],
+            ..Default::default()
+        };
+        assert!(matches!(
+            conflicting.check(false),
+            Err(InstallOptionsInvalid::Conflict(..))
+        ));
+    }
+}
@@ -41,6 +41,13 @@ impl Emulator {
         self.command(env).start()
     }
 
+    /// Like [`Self::start`], but the emulator gets its own process group, so
+    /// that it and the processes it spawns can be signalled together. On Unix,
+    /// the group id is the pid given by [`Handle::pids`].
+    pub fn start_in_process_group(&self, env: &Env) -> Result<Handle, std::io::Error> {
+        self.command(env).new_process_group().start()
+    }
+
     pub fn start_detached(&self, env: &Env) -> Result<(), std::io::Error> {
         self.command(env).run_and_detach()?;
         Ok(())
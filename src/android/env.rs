@@ -5,9 +5,12 @@ use super::{
 use crate::{
     env::{Error as CoreError, ExplicitEnv},
     os::Env as CoreEnv,
-    util::cli::{Report, Reportable},
+    util::{
+        cli::{Report, Reportable},
+        CargoLock,
+    },
 };
-use std::{collections::HashMap, ffi::OsString, path::PathBuf};
+use std::{collections::HashMap, ffi::OsString, num::NonZeroU32, path::PathBuf};
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -44,6 +47,7 @@ pub struct Env {
     pub base: CoreEnv,
     android_home: PathBuf,
     pub ndk: ndk::Env,
+    remote_devices: Vec<String>,
 }
 
 impl Env {
@@ -90,9 +94,36 @@ impl Env {
             base,
             android_home,
             ndk: ndk::Env::new()?,
+            remote_devices: Vec::new(),
         })
     }
 
+    pub fn with_cargo_jobs(mut self, jobs: Option<NonZeroU32>) -> Self {
+        self.base = self.base.with_cargo_jobs(jobs);
+        self
+    }
+
+    pub fn with_cargo_lock(mut self, lock: Option<CargoLock>) -> Self {
+        self.base = self.base.with_cargo_lock(lock);
+        self
+    }
+
+    pub fn with_deny_warnings(mut self, deny_warnings: bool) -> Self {
+        self.base = self.base.with_deny_warnings(deny_warnings);
+        self
+    }
+
+    /// Makes device detection `adb connect` to these `host:port` endpoints
+    /// first, so that remote devices show up alongside local ones.
+    pub fn with_remote_devices(mut self, remote_devices: &[String]) -> Self {
+        self.remote_devices = remote_devices.to_vec();
+        self
+    }
+
+    pub fn remote_devices(&self) -> &[String] {
+        &self.remote_devices
+    }
+
     pub fn path(&self) -> &OsString {
         self.base.path()
     }
@@ -80,6 +80,29 @@ impl JniLibs {
         std::fs::create_dir_all(&path).map(|()| Self { path })
     }
 
+    /// Warns about each of `android.abi-filters` that has no library to
+    /// package, since Gradle silently leaves those out.
+    pub fn warn_about_unbuilt_abi_filters(config: &Config) {
+        for abi in config.abi_filters() {
+            let built = Target::for_abi(abi)
+                .and_then(|target| std::fs::read_dir(path(config, *target)).ok())
+                .is_some_and(|mut entries| {
+                    entries.any(|entry| {
+                        entry.is_ok_and(|entry| {
+                            entry.path().extension().is_some_and(|ext| ext == "so")
+                                && entry.path().exists()
+                        })
+                    })
+                });
+            if !built {
+                log::warn!(
+                    "`android.abi-filters` contains {:?}, but no library was built for it, so it won't be packaged",
+                    abi
+                );
+            }
+        }
+    }
+
     pub fn remove_broken_links(config: &Config) -> Result<(), RemoveBrokenLinksError> {
         for abi_dir in Target::all()
             .values()
@@ -1,7 +1,10 @@
 pub mod aab;
 pub mod adb;
 pub mod apk;
+pub mod apksigner;
+pub mod build_graph;
 mod bundletool;
+pub mod clean;
 #[cfg(feature = "cli")]
 pub mod cli;
 pub mod config;
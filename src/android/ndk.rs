@@ -14,6 +14,7 @@ use std::{
     collections::HashSet,
     fmt::{self, Display},
     path::{Path, PathBuf},
+    sync::OnceLock,
 };
 use thiserror::Error;
 
@@ -68,7 +69,7 @@ impl Binutil {
     }
 }
 
-#[derive(Debug, Error)]
+#[derive(Clone, Debug, Error)]
 #[error("Missing tool `{name}`; tried at {tried_path:?}.")]
 pub struct MissingToolError {
     name: &'static str,
@@ -166,9 +167,13 @@ impl Reportable for RequiredLibsError {
     }
 }
 
+// The version and toolchain paths are looked up once and reused, since every
+// target needs them several times per build.
 #[derive(Debug, Clone)]
 pub struct Env {
     ndk_home: PathBuf,
+    version: source_props::Revision,
+    tool_dir: OnceLock<Result<PathBuf, MissingToolError>>,
 }
 
 impl Env {
@@ -183,16 +188,23 @@ impl Env {
                     Err(Error::NdkHomeNotADir)
                 }
             })?;
-        let env = Self { ndk_home };
-        let version = env
-            .version()
-            .map(NdkVersion::from)
+        Self::from_home(ndk_home)
+    }
+
+    fn from_home(ndk_home: PathBuf) -> Result<Self, Error> {
+        let version = SourceProps::from_path(ndk_home.join("source.properties"))
+            .map(|props| props.pkg.revision)
             .map_err(Error::VersionLookupFailed)?;
-        if version >= MIN_NDK_VERSION {
-            Ok(env)
+        let ndk_version = NdkVersion::from(version.clone());
+        if ndk_version >= MIN_NDK_VERSION {
+            Ok(Self {
+                ndk_home,
+                version,
+                tool_dir: OnceLock::new(),
+            })
         } else {
             Err(Error::VersionTooLow {
-                you_have: version,
+                you_have: ndk_version,
                 you_need: MIN_NDK_VERSION,
             })
         }
@@ -202,9 +214,8 @@ impl Env {
         &self.ndk_home
     }
 
-    pub fn version(&self) -> Result<source_props::Revision, source_props::Error> {
-        SourceProps::from_path(self.ndk_home.join("source.properties"))
-            .map(|props| props.pkg.revision)
+    pub fn version(&self) -> &source_props::Revision {
+        &self.version
     }
 
     pub fn prebuilt_dir(&self) -> Result<PathBuf, MissingToolError> {
@@ -217,7 +228,9 @@ impl Env {
     }
 
     pub fn tool_dir(&self) -> Result<PathBuf, MissingToolError> {
-        MissingToolError::check_dir(self.prebuilt_dir()?.join("bin"), "tools")
+        self.tool_dir
+            .get_or_init(|| MissingToolError::check_dir(self.prebuilt_dir()?.join("bin"), "tools"))
+            .clone()
     }
 
     pub fn compiler_path(
@@ -247,7 +260,7 @@ impl Env {
 
     pub fn libcxx_shared_path(&self, target: Target<'_>) -> Result<PathBuf, MissingToolError> {
         static LIB: &str = "libc++_shared.so";
-        let ndk_ver = self.version().unwrap_or_default();
+        let ndk_ver = self.version();
         let so_path = if ndk_ver.triple.major >= 22 {
             let ndk_triple = if target.triple == "armv7-linux-androideabi" {
                 "arm-linux-androideabi"
@@ -266,7 +279,7 @@ impl Env {
     }
 
     pub fn ar_path(&self, triple: &str) -> Result<PathBuf, MissingToolError> {
-        let ndk_ver = self.version().unwrap_or_default();
+        let ndk_ver = self.version();
         let bin_path = if ndk_ver.triple.major >= 23 {
             format!("llvm-{}", consts::AR)
         } else {
@@ -276,7 +289,7 @@ impl Env {
     }
 
     fn readelf_path(&self, triple: &str) -> Result<PathBuf, MissingToolError> {
-        let ndk_ver = self.version().unwrap_or_default();
+        let ndk_ver = self.version();
         let bin_path = if ndk_ver.triple.major >= 23 {
             format!("llvm-{}", consts::READELF)
         } else {
@@ -285,6 +298,16 @@ impl Env {
         MissingToolError::check_file(self.tool_dir()?.join(bin_path), "readelf")
     }
 
+    pub fn strip_path(&self, triple: &str) -> Result<PathBuf, MissingToolError> {
+        let ndk_ver = self.version();
+        let bin_path = if ndk_ver.triple.major >= 23 {
+            format!("llvm-{}", consts::STRIP)
+        } else {
+            format!("{}-{}", triple, consts::STRIP)
+        };
+        MissingToolError::check_file(self.tool_dir()?.join(bin_path), "strip")
+    }
+
     pub fn required_libs(
         &self,
         elf: &Path,
@@ -313,3 +336,58 @@ impl Env {
             .collect())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+    use std::fs;
+
+    #[test]
+    fn detection_is_cached() {
+        let tmp = tempfile::tempdir().unwrap();
+        let ndk_home = tmp.path();
+        let tool_dir = ndk_home.join(format!("toolchains/llvm/prebuilt/{}/bin", host_tag()));
+        fs::create_dir_all(&tool_dir).unwrap();
+        fs::write(
+            ndk_home.join("source.properties"),
+            "Pkg.Revision = 25.2.9519653\n",
+        )
+        .unwrap();
+        let env = Env::from_home(ndk_home.to_owned()).unwrap();
+        assert_eq!(env.tool_dir().unwrap(), tool_dir);
+
+        // Neither the version nor the toolchain should be looked up again.
+        fs::remove_dir_all(ndk_home.join("toolchains")).unwrap();
+        fs::remove_file(ndk_home.join("source.properties")).unwrap();
+        assert_eq!(env.version().triple.major, 25);
+        assert_eq!(env.tool_dir().unwrap(), tool_dir);
+    }
+
+    #[rstest]
+    #[case("25.2.9519653", format!("llvm-{}", consts::STRIP))]
+    #[case("22.1.7171670", format!("aarch64-linux-android-{}", consts::STRIP))]
+    fn strip_depends_on_the_ndk_version(#[case] revision: &str, #[case] bin: String) {
+        let tmp = tempfile::tempdir().unwrap();
+        let ndk_home = tmp.path();
+        let tool_dir = ndk_home.join(format!("toolchains/llvm/prebuilt/{}/bin", host_tag()));
+        fs::create_dir_all(&tool_dir).unwrap();
+        fs::write(
+            ndk_home.join("source.properties"),
+            format!("Pkg.Revision = {}\n", revision),
+        )
+        .unwrap();
+        let env = Env::from_home(ndk_home.to_owned()).unwrap();
+        assert_eq!(
+            env.strip_path("aarch64-linux-android")
+                .unwrap_err()
+                .tried_path,
+            tool_dir.join(&bin)
+        );
+        fs::write(tool_dir.join(&bin), "").unwrap();
+        assert_eq!(
+            env.strip_path("aarch64-linux-android").unwrap(),
+            tool_dir.join(&bin)
+        );
+    }
+}
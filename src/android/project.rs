@@ -6,7 +6,9 @@ use super::{
 };
 use crate::{
     android::{config::DEFAULT_VULKAN_VALIDATION, DEFAULT_ACTIVITY, DEFAULT_THEME_PARENT},
-    bicycle, dot_cargo,
+    bicycle,
+    config::{app::localization, extra_file},
+    dot_cargo,
     os::{self, replace_path_separator},
     target::TargetTrait as _,
     templating::{self, Pack},
@@ -24,6 +26,7 @@ use std::{
 
 pub static TEMPLATE_PACK: &str = "android-studio";
 pub static ASSET_PACK_TEMPLATE_PACK: &str = "android-studio-asset-pack";
+pub static STRINGS_TEMPLATE_PACK: &str = "android-studio-strings";
 
 #[derive(Debug)]
 pub enum Error {
@@ -50,6 +53,8 @@ pub enum Error {
         cause: std::io::Error,
     },
     AssetSourceInvalid(PathBuf),
+    ExtraFileCopyFailed(bicycle::ProcessingError),
+    LocalizationFailed(localization::Error),
 }
 
 impl Reportable for Error {
@@ -85,6 +90,13 @@ impl Reportable for Error {
                 format!("Asset source at {:?} invalid", src),
                 "Asset sources must be either a directory or a file",
             ),
+            Self::ExtraFileCopyFailed(err) => Report::error(
+                "Failed to copy `android.extra-files` into Android project",
+                err,
+            ),
+            Self::LocalizationFailed(err) => {
+                Report::error("Failed to generate Android localized strings", err)
+            }
         }
     }
 }
@@ -111,7 +123,13 @@ pub fn gen(
     let dest = config.project_dir();
 
     let asset_packs = metadata.asset_packs().unwrap_or_default();
-    bike.filter_and_process(
+    let strings = config
+        .app()
+        .localization()
+        .map(|localization| localization.load(config.app().root_dir()))
+        .transpose()
+        .map_err(Error::LocalizationFailed)?;
+    bike.filter_and_process_changed(
         src,
         &dest,
         |map| {
@@ -131,6 +149,7 @@ pub fn gen(
                     .collect::<Vec<_>>(),
             );
             map.insert("target-list", Target::all().keys().collect::<Vec<_>>());
+            map.insert("abi-filters", config.abi_filters());
             map.insert(
                 "arch-list",
                 Target::all()
@@ -151,6 +170,7 @@ pub fn gen(
                 "android-app-dependencies-platform",
                 metadata.app_dependencies_platform().unwrap_or_default(),
             );
+            map.insert("android-gradle-dependencies", config.gradle_dependencies());
             map.insert(
                 "has-code",
                 metadata.project_dependencies().is_some()
@@ -178,9 +198,16 @@ pub fn gen(
                 .collect::<Vec<_>>();
             map.insert("has-asset-packs", !asset_packs.is_empty());
             map.insert("asset-packs", asset_packs);
+            map.insert(
+                "localized-app-name",
+                strings
+                    .as_ref()
+                    .is_some_and(|strings| strings.contains_base_key("app_name")),
+            );
             map.insert("windows", cfg!(windows));
         },
         filter.fun(),
+        &templating::snapshot_path(&dest),
     )
     .map_err(Error::TemplateProcessingFailed)?;
     if !asset_packs.is_empty() {
@@ -206,6 +233,34 @@ pub fn gen(
         .map_err(Error::TemplateProcessingFailed)?;
     }
 
+    if let Some(strings) = &strings {
+        let strings_src = Pack::lookup_platform(STRINGS_TEMPLATE_PACK)
+            .map_err(Error::MissingPack)?
+            .expect_local();
+        for (locale, entries) in strings.iter() {
+            // The base locale's strings are the ones used when nothing more
+            // specific matches.
+            let values_dir = if locale == strings.base_locale() {
+                "values".to_owned()
+            } else {
+                localization::android_values_dir(locale)
+            };
+            bike.filter_and_process(
+                &strings_src,
+                &dest,
+                |map| {
+                    map.insert("values-dir", &values_dir);
+                    map.insert(
+                        "strings",
+                        localization::entries(entries, localization::android_escape),
+                    );
+                },
+                filter.fun(),
+            )
+            .map_err(Error::TemplateProcessingFailed)?;
+        }
+    }
+
     let source_dest = dest.join("app");
     for source in metadata.app_sources() {
         let source_src = config.app().root_dir().join(source);
@@ -229,6 +284,14 @@ pub fn gen(
     os::ln::force_symlink_relative(config.app().asset_dir(), dest, ln::TargetStyle::Directory)
         .map_err(Error::AssetDirSymlinkFailed)?;
 
+    extra_file::copy_all(
+        bike,
+        config.app().root_dir(),
+        config.extra_files(),
+        &config.project_dir(),
+    )
+    .map_err(Error::ExtraFileCopyFailed)?;
+
     {
         for target in Target::all().values() {
             dot_cargo.insert_target(
@@ -242,3 +305,47 @@ pub fn gen(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{config::app::url_scheme, templating};
+
+    #[test]
+    fn url_schemes_get_intent_filters() {
+        let url_schemes = [("myapp", None), ("https", Some("example.com"))]
+            .into_iter()
+            .map(|(scheme, host)| {
+                url_scheme::UrlScheme::from_raw(url_scheme::Raw::WithHost {
+                    scheme: scheme.to_owned(),
+                    host: host.map(ToOwned::to_owned),
+                })
+                .unwrap()
+            })
+            .collect::<Vec<_>>();
+        let rendered = templating::init(None)
+            .render(
+                include_str!(
+                    "../../templates/platforms/android-studio/app/src/main/AndroidManifest.xml.hbs"
+                ),
+                |map| {
+                    map.insert(
+                        "app",
+                        serde_json::json!({ "name": "fun-app", "url-schemes": url_schemes }),
+                    );
+                    map.insert("android-app-permissions", Vec::<String>::new());
+                    map.insert("has-code", false);
+                    map.insert("android-app-activity-name", "android.app.NativeActivity");
+                },
+            )
+            .unwrap();
+        assert!(rendered.contains(r#"<data android:scheme="myapp" />"#));
+        assert!(rendered.contains(r#"<intent-filter android:autoVerify="true">"#));
+        assert!(rendered.contains(r#"<data android:scheme="https" android:host="example.com" />"#));
+        assert_eq!(
+            rendered
+                .matches("android.intent.category.BROWSABLE")
+                .count(),
+            2
+        );
+    }
+}
@@ -1,21 +1,25 @@
 use super::{
+    build_graph::{PostBuildStep, TargetBuild},
     config::{Config, Metadata},
     env::Env,
     jnilibs::{self, JniLibs},
     ndk,
 };
 use crate::{
+    config::toolchain,
     dot_cargo::DotCargoTarget,
     opts::{NoiseLevel, Profile},
     target::TargetTrait,
     util::{
+        self,
         cli::{Report, Reportable},
         CargoCommand,
     },
+    DuctExpressionExt,
 };
 use once_cell_regex::exports::once_cell::sync::OnceCell;
 use serde::Serialize;
-use std::{collections::BTreeMap, fmt, io, path::PathBuf, str};
+use std::{collections::BTreeMap, ffi::OsString, fmt, io, path::PathBuf, process::ExitStatus, str};
 use thiserror::Error;
 
 #[derive(Clone, Copy, Debug)]
@@ -46,6 +50,8 @@ impl CargoMode {
 pub enum CompileLibError {
     #[error("Failed to locate required build tool: {0}")]
     MissingTool(ndk::MissingToolError),
+    #[error(transparent)]
+    ToolchainFailed(toolchain::Error),
     #[error("`Failed to run `cargo {mode}`: {cause}")]
     CargoFailed {
         mode: CargoMode,
@@ -57,7 +63,29 @@ pub enum CompileLibError {
 
 impl Reportable for CompileLibError {
     fn report(&self) -> Report {
-        Report::error("Failed to compile lib", self)
+        match self {
+            Self::ToolchainFailed(err) => err.report(),
+            _ => Report::error("Failed to compile lib", self),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum CargoPassthroughError {
+    #[error("Failed to locate required build tool: {0}")]
+    MissingTool(ndk::MissingToolError),
+    #[error(transparent)]
+    ToolchainFailed(toolchain::Error),
+    #[error("Failed to run `cargo`: {0}")]
+    CargoFailed(io::Error),
+}
+
+impl Reportable for CargoPassthroughError {
+    fn report(&self) -> Report {
+        match self {
+            Self::ToolchainFailed(err) => err.report(),
+            _ => Report::error("Failed to run cargo", self),
+        }
     }
 }
 
@@ -81,11 +109,46 @@ impl Reportable for SymlinkLibsError {
     }
 }
 
+#[derive(Debug, Error)]
+pub enum StripLibError {
+    #[error("Failed to locate `strip`: {0}")]
+    MissingTool(ndk::MissingToolError),
+    #[error("Failed to get size of {path}: {cause}")]
+    SizeFailed { path: PathBuf, cause: io::Error },
+    #[error("Failed to run `strip` on {path}: {cause}")]
+    StripFailed { path: PathBuf, cause: io::Error },
+}
+
+impl Reportable for StripLibError {
+    fn report(&self) -> Report {
+        Report::error("Failed to strip lib", self)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum BuildGraphError {
+    #[error("Failed to locate required build tool: {0}")]
+    MissingTool(ndk::MissingToolError),
+    #[error(transparent)]
+    ToolchainFailed(toolchain::Error),
+}
+
+impl Reportable for BuildGraphError {
+    fn report(&self) -> Report {
+        match self {
+            Self::ToolchainFailed(err) => err.report(),
+            _ => Report::error("Failed to resolve build graph", self),
+        }
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum BuildError {
     #[error(transparent)]
     BuildFailed(CompileLibError),
     #[error(transparent)]
+    StripLibFailed(StripLibError),
+    #[error(transparent)]
     SymlinkLibsFailed(SymlinkLibsError),
 }
 
@@ -93,11 +156,16 @@ impl Reportable for BuildError {
     fn report(&self) -> Report {
         match self {
             Self::BuildFailed(err) => err.report(),
+            Self::StripLibFailed(err) => err.report(),
             Self::SymlinkLibsFailed(err) => err.report(),
         }
     }
 }
 
+fn format_size(bytes: u64) -> String {
+    format!("{:.2} MiB", bytes as f64 / (1024.0 * 1024.0))
+}
+
 #[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd, Serialize)]
 pub struct Target<'a> {
     pub triple: &'a str,
@@ -216,9 +284,29 @@ impl<'a> Target<'a> {
                 "-Clink-arg=-llog".to_owned(),
                 "-Clink-arg=-lOpenSLES".to_owned(),
             ],
+            ..Default::default()
         })
     }
 
+    fn cargo_command<'c>(
+        &'c self,
+        config: &'c Config,
+        metadata: &'c Metadata,
+        noise_level: NoiseLevel,
+        profile: Profile,
+        mode: CargoMode,
+    ) -> CargoCommand<'c> {
+        CargoCommand::new(mode.as_str())
+            .with_verbose(noise_level.pedantic())
+            .with_package(Some(config.app().name()))
+            .with_manifest_path(Some(config.app().manifest_path()))
+            .with_target(Some(self.triple))
+            .with_no_default_features(metadata.no_default_features())
+            .with_args(metadata.cargo_args())
+            .with_features(metadata.features())
+            .with_release(profile.release())
+    }
+
     #[allow(clippy::too_many_arguments)]
     fn compile_lib(
         &self,
@@ -230,40 +318,22 @@ impl<'a> Target<'a> {
         profile: Profile,
         mode: CargoMode,
     ) -> Result<(), CompileLibError> {
-        let min_sdk_version = config.min_sdk_version();
-
         // Force color, since gradle would otherwise give us uncolored output
         // (which Android Studio makes red, which is extra gross!)
         let color = if force_color { "always" } else { "auto" };
-        CargoCommand::new(mode.as_str())
-            .with_verbose(noise_level.pedantic())
-            .with_package(Some(config.app().name()))
-            .with_manifest_path(Some(config.app().manifest_path()))
-            .with_target(Some(self.triple))
-            .with_no_default_features(metadata.no_default_features())
-            .with_args(metadata.cargo_args())
-            .with_features(metadata.features())
-            .with_release(profile.release())
+        self.cargo_command(config, metadata, noise_level, profile, mode)
             .build(env)
-            .env("ANDROID_NATIVE_API_LEVEL", min_sdk_version.to_string())
-            .env(
-                "TARGET_AR",
-                env.ndk
-                    .ar_path(self.triple)
+            .vars(
+                self.ndk_vars(config, &env.ndk)
                     .map_err(CompileLibError::MissingTool)?,
             )
-            .env(
-                "TARGET_CC",
-                env.ndk
-                    .compiler_path(ndk::Compiler::Clang, self.clang_triple(), min_sdk_version)
-                    .map_err(CompileLibError::MissingTool)?,
-            )
-            .env(
-                "TARGET_CXX",
-                env.ndk
-                    .compiler_path(ndk::Compiler::Clangxx, self.clang_triple(), min_sdk_version)
-                    .map_err(CompileLibError::MissingTool)?,
+            .vars(
+                config
+                    .toolchain()
+                    .vars(self.triple)
+                    .map_err(CompileLibError::ToolchainFailed)?,
             )
+            .vars(config.target_env().vars(self.triple))
             .before_spawn(move |cmd| {
                 cmd.args(["--color", color]);
                 Ok(())
@@ -273,6 +343,58 @@ impl<'a> Target<'a> {
         Ok(())
     }
 
+    // The NDK tools for C/C++ deps built by `cc` and friends.
+    fn ndk_vars(
+        &self,
+        config: &Config,
+        ndk: &ndk::Env,
+    ) -> Result<[(&'static str, OsString); 4], ndk::MissingToolError> {
+        let min_sdk_version = config.min_sdk_version();
+        Ok([
+            (
+                "ANDROID_NATIVE_API_LEVEL",
+                min_sdk_version.to_string().into(),
+            ),
+            ("TARGET_AR", ndk.ar_path(self.triple)?.into()),
+            (
+                "TARGET_CC",
+                ndk.compiler_path(ndk::Compiler::Clang, self.clang_triple(), min_sdk_version)?
+                    .into(),
+            ),
+            (
+                "TARGET_CXX",
+                ndk.compiler_path(ndk::Compiler::Clangxx, self.clang_triple(), min_sdk_version)?
+                    .into(),
+            ),
+        ])
+    }
+
+    /// Runs `cargo` with `args` as given, in the env builds for this target
+    /// use. `args` still needs `--target` to actually build for it.
+    pub fn cargo_passthrough(
+        &self,
+        config: &Config,
+        env: &Env,
+        args: &[String],
+    ) -> Result<ExitStatus, CargoPassthroughError> {
+        util::cargo_passthrough(args, env)
+            .vars(
+                self.ndk_vars(config, &env.ndk)
+                    .map_err(CargoPassthroughError::MissingTool)?,
+            )
+            .vars(
+                config
+                    .toolchain()
+                    .vars(self.triple)
+                    .map_err(CargoPassthroughError::ToolchainFailed)?,
+            )
+            .vars(config.target_env().vars(self.triple))
+            .unchecked()
+            .run()
+            .map(|output| output.status)
+            .map_err(CargoPassthroughError::CargoFailed)
+    }
+
     pub fn check(
         &self,
         config: &Config,
@@ -350,7 +472,154 @@ impl<'a> Target<'a> {
             CargoMode::Build,
         )
         .map_err(BuildError::BuildFailed)?;
+        if config.strip_release() {
+            self.strip_lib(config, &env.ndk, profile)
+                .map_err(BuildError::StripLibFailed)?;
+        }
         self.symlink_libs(config, &env.ndk, profile)
             .map_err(BuildError::SymlinkLibsFailed)
     }
+
+    /// What [`Self::build`] would run, without running anything. `strip` is
+    /// whether release builds get stripped, which `build` leaves up to
+    /// `android.strip-release`.
+    pub fn build_graph(
+        &self,
+        config: &Config,
+        metadata: &Metadata,
+        env: &Env,
+        noise_level: NoiseLevel,
+        profile: Profile,
+        strip: bool,
+    ) -> Result<TargetBuild, BuildGraphError> {
+        let (args, mut vars) = self
+            .cargo_command(config, metadata, noise_level, profile, CargoMode::Build)
+            .resolve(env);
+        // `compile_lib` sets these outside of what `CargoCommand` sets, so
+        // they only apply where it doesn't set them itself.
+        let ndk_vars = self
+            .ndk_vars(config, &env.ndk)
+            .map_err(BuildGraphError::MissingTool)?
+            .into_iter()
+            .map(|(key, value)| (key.to_owned(), value));
+        let toolchain_vars = config
+            .toolchain()
+            .vars(self.triple)
+            .map_err(BuildGraphError::ToolchainFailed)?
+            .map(|(key, value)| (key.to_owned(), value.into()));
+        let target_env_vars = config
+            .target_env()
+            .vars(self.triple)
+            .map(|(key, value)| (key.clone(), value.into()));
+        for (key, value) in ndk_vars.chain(toolchain_vars).chain(target_env_vars) {
+            vars.entry(key).or_insert(value);
+        }
+
+        let output = config
+            .app()
+            .target_dir(self.triple, profile)
+            .join(config.so_name());
+        let mut post_build = Vec::new();
+        if strip && profile.release() {
+            let strip_path = env
+                .ndk
+                .strip_path(self.binutils_triple())
+                .map_err(BuildGraphError::MissingTool)?;
+            post_build.push(PostBuildStep::Run {
+                command: vec![
+                    strip_path.display().to_string(),
+                    "--strip-unneeded".to_owned(),
+                    output.display().to_string(),
+                ],
+            });
+        }
+        let jnilibs = jnilibs::path(config, *self);
+        post_build.push(PostBuildStep::Symlink {
+            src: output.clone(),
+            dest: jnilibs.clone(),
+            if_output_needs: None,
+        });
+        post_build.push(PostBuildStep::Symlink {
+            src: env
+                .ndk
+                .libcxx_shared_path(*self)
+                .map_err(BuildGraphError::MissingTool)?,
+            dest: jnilibs,
+            if_output_needs: Some("libc++_shared.so".to_owned()),
+        });
+
+        Ok(TargetBuild {
+            name: Self::all()
+                .iter()
+                .find(|(_, target)| *target == self)
+                .map(|(name, _)| name.to_string())
+                .unwrap_or_default(),
+            triple: self.triple.to_owned(),
+            abi: self.abi.to_owned(),
+            cwd: config.app().root_dir().to_owned(),
+            command: std::iter::once("cargo".to_owned()).chain(args).collect(),
+            env: vars
+                .into_iter()
+                .map(|(key, value)| (key, value.to_string_lossy().into_owned()))
+                .collect(),
+            output,
+            post_build,
+        })
+    }
+
+    /// Strips symbols from the built lib using the NDK's `llvm-strip`. This
+    /// is a no-op for debug builds, since you'd want the symbols there.
+    pub fn strip_lib(
+        &self,
+        config: &Config,
+        ndk: &ndk::Env,
+        profile: Profile,
+    ) -> Result<(), StripLibError> {
+        if !profile.release() {
+            log::info!(
+                "not stripping lib for {}, since this is a debug build",
+                self.triple
+            );
+            return Ok(());
+        }
+        let path = config
+            .app()
+            .target_dir(self.triple, profile)
+            .join(config.so_name());
+        let size = |path: &PathBuf| {
+            path.metadata()
+                .map(|metadata| metadata.len())
+                .map_err(|cause| StripLibError::SizeFailed {
+                    path: path.clone(),
+                    cause,
+                })
+        };
+        let before = size(&path)?;
+        duct::cmd(
+            ndk.strip_path(self.binutils_triple())
+                .map_err(StripLibError::MissingTool)?,
+            ["--strip-unneeded"],
+        )
+        .before_spawn({
+            let path = path.clone();
+            move |cmd| {
+                cmd.arg(&path);
+                Ok(())
+            }
+        })
+        .run()
+        .map_err(|cause| StripLibError::StripFailed {
+            path: path.clone(),
+            cause,
+        })?;
+        let after = size(&path)?;
+        println!(
+            "Stripped {} for {}: {} -> {}",
+            config.so_name(),
+            self.triple,
+            format_size(before),
+            format_size(after)
+        );
+        Ok(())
+    }
 }
@@ -0,0 +1,138 @@
+use super::config::Config;
+use crate::{
+    env::{Env, ExplicitEnv as _},
+    opts::NoiseLevel,
+    util::cli::{Report, Reportable},
+    DuctExpressionExt,
+};
+use std::{
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum CleanError {
+    #[error("Failed to run `xcodebuild clean`: {0}")]
+    CleanFailed(std::io::Error),
+    #[error("Failed to get build settings from `xcodebuild`: {0}")]
+    BuildSettingsFailed(std::io::Error),
+    #[error("Failed to remove DerivedData at {path}: {cause}")]
+    RemoveFailed {
+        path: PathBuf,
+        cause: std::io::Error,
+    },
+}
+
+impl Reportable for CleanError {
+    fn report(&self) -> Report {
+        match self {
+            Self::CleanFailed(err) => Report::error("Failed to run `xcodebuild clean`", err),
+            Self::BuildSettingsFailed(err) => {
+                Report::error("Failed to get build settings from `xcodebuild`", err)
+            }
+            Self::RemoveFailed { path, cause } => {
+                Report::error(format!("Failed to remove DerivedData at {:?}", path), cause)
+            }
+        }
+    }
+}
+
+fn xcodebuild(config: &Config, env: &Env, action: &'static str) -> duct::Expression {
+    let scheme = config.scheme();
+    let workspace_path = config.workspace_path();
+    let args: Vec<OsString> = vec![];
+    duct::cmd("xcodebuild", args)
+        .full_env(env.explicit_env())
+        .before_spawn(move |cmd| {
+            cmd.args(["-scheme", &scheme])
+                .arg("-workspace")
+                .arg(&workspace_path)
+                .arg(action);
+            Ok(())
+        })
+}
+
+// `BUILD_ROOT` is `<DerivedData>/<project>-<hash>/Build/Products`, wherever the
+// user has DerivedData configured to live.
+fn parse_derived_data_dir(build_settings: &str) -> Option<PathBuf> {
+    build_settings
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("BUILD_ROOT = "))
+        .and_then(|build_root| Path::new(build_root).ancestors().nth(2))
+        // Refuse to delete anything that doesn't look like DerivedData.
+        .filter(|dir| {
+            dir.parent()
+                .and_then(Path::file_name)
+                .is_some_and(|name| name == "DerivedData")
+        })
+        .map(ToOwned::to_owned)
+}
+
+/// The project's DerivedData, according to `xcodebuild`, if it could be found.
+pub fn derived_data_dir(config: &Config, env: &Env) -> Result<Option<PathBuf>, CleanError> {
+    xcodebuild(config, env, "-showBuildSettings")
+        .stdout_capture()
+        .read()
+        .map(|build_settings| parse_derived_data_dir(&build_settings))
+        .map_err(CleanError::BuildSettingsFailed)
+}
+
+/// Runs `xcodebuild clean`, and optionally removes the project's DerivedData,
+/// without touching the generated project.
+pub fn clean_native(
+    config: &Config,
+    env: &Env,
+    noise_level: NoiseLevel,
+    derived_data: bool,
+) -> Result<(), CleanError> {
+    // Look this up first, since it's needed after `clean`.
+    let derived_data_dir = if derived_data {
+        derived_data_dir(config, env)?
+    } else {
+        None
+    };
+    xcodebuild(config, env, "clean")
+        .before_spawn(move |cmd| {
+            if !noise_level.pedantic() {
+                cmd.arg("-quiet");
+            }
+            Ok(())
+        })
+        .dup_stdio()
+        .run()
+        .map_err(CleanError::CleanFailed)?;
+    if derived_data {
+        match derived_data_dir {
+            Some(dir) if dir.exists() => {
+                println!("Removing {}", dir.display());
+                fs::remove_dir_all(&dir)
+                    .map_err(|cause| CleanError::RemoveFailed { path: dir, cause })?;
+            }
+            Some(_) => (),
+            None => log::warn!("couldn't find this project's DerivedData, so it wasn't removed"),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn finds_derived_data_from_build_root() {
+        let settings = "Build settings for action build and target fun_iOS:\n    BUILD_DIR = /Users/me/Library/Developer/Xcode/DerivedData/fun-abc123/Build/Products\n    BUILD_ROOT = /Users/me/Library/Developer/Xcode/DerivedData/fun-abc123/Build/Products\n";
+        assert_eq!(
+            parse_derived_data_dir(settings),
+            Some(PathBuf::from(
+                "/Users/me/Library/Developer/Xcode/DerivedData/fun-abc123"
+            ))
+        );
+        assert_eq!(
+            parse_derived_data_dir("    BUILD_ROOT = /Users/me/fun/build/Build/Products\n"),
+            None
+        );
+    }
+}
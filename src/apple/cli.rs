@@ -1,32 +1,38 @@
 use crate::{
     apple::{
-        config::{Config, Metadata},
+        clean::{self, CleanError},
+        codesign,
+        config::{ApiKeyError, Config, Error as AppleConfigError, Metadata},
         device::{self, Device, RunError},
-        rust_version_check,
+        project, rust_version_check,
         target::{
-            ArchiveConfig, ArchiveError, BuildConfig, BuildError, CheckError, CompileLibError,
-            ExportError, Target,
+            ArchiveConfig, ArchiveError, BuildConfig, BuildError, CargoPassthroughError,
+            CheckError, CompileLibError, DsymUploadError, ExportConfig, ExportError, Target,
         },
-        NAME,
+        xcode_script, xcresult, NAME,
     },
+    build_info,
     config::{
+        app::artifact_name,
         metadata::{self, Metadata as OmniMetadata},
         Config as OmniConfig, LoadOrGenError,
     },
     define_device_prompt,
     device::PromptError,
-    env::{Env, Error as EnvError},
+    env::{Env, Error as EnvError, ExportSyntax},
     opts, os,
-    target::{call_for_targets_with_fallback, TargetInvalid, TargetTrait as _},
+    target::{
+        call_for_targets_with_fallback_keep_going, TargetFailures, TargetInvalid, TargetTrait as _,
+    },
     util::{
         self,
         cli::{
             self, Exec, GlobalFlags, Report, Reportable, TextWrapper, VERSION_LONG, VERSION_SHORT,
         },
-        prompt,
+        disk, prompt,
     },
 };
-use std::{collections::HashMap, ffi::OsStr, path::PathBuf};
+use std::{collections::HashMap, ffi::OsStr, path::PathBuf, process::ExitStatus, time::Duration};
 use structopt::{clap::AppSettings, StructOpt};
 
 #[derive(Debug, StructOpt)]
@@ -40,13 +46,19 @@ use structopt::{clap::AppSettings, StructOpt};
 pub struct Input {
     #[structopt(flatten)]
     flags: GlobalFlags,
+    #[structopt(flatten)]
+    config_flags: cli::ConfigFlags,
     #[structopt(subcommand)]
     command: Command,
 }
 
 impl Input {
-    pub fn new(flags: GlobalFlags, command: Command) -> Self {
-        Self { flags, command }
+    pub fn new(flags: GlobalFlags, config_flags: cli::ConfigFlags, command: Command) -> Self {
+        Self {
+            flags,
+            config_flags,
+            command,
+        }
     }
 }
 
@@ -66,10 +78,35 @@ fn profile_from_configuration(configuration: &str) -> opts::Profile {
 pub enum Command {
     #[structopt(name = "open", about = "Open project in Xcode")]
     Open,
+    #[structopt(
+        name = "clean",
+        about = "Cleans build caches, leaving the generated project in place",
+        group = structopt::clap::ArgGroup::with_name("caches").required(true)
+    )]
+    Clean {
+        #[structopt(long = "native", help = "Run `xcodebuild clean`", group = "caches")]
+        native: bool,
+        #[structopt(
+            long = "derived-data",
+            help = "Also remove the project's DerivedData",
+            requires = "native"
+        )]
+        derived_data: bool,
+    },
     #[structopt(name = "check", about = "Checks if code compiles for target(s)")]
     Check {
         #[structopt(name = "targets", default_value = Target::DEFAULT_KEY, possible_values = &Target::name_list())]
         targets: Vec<String>,
+        #[structopt(flatten)]
+        keep_going: cli::KeepGoing,
+        #[structopt(flatten)]
+        all_targets: cli::AllTargets,
+        #[structopt(flatten)]
+        cargo_jobs: cli::CargoJobs,
+        #[structopt(flatten)]
+        lockfile: cli::Lockfile,
+        #[structopt(flatten)]
+        deny_warnings: cli::DenyWarnings,
     },
     #[structopt(name = "build", about = "Builds static libraries for target(s)")]
     Build {
@@ -77,6 +114,47 @@ pub enum Command {
         targets: Vec<String>,
         #[structopt(flatten)]
         profile: cli::Profile,
+        #[structopt(flatten)]
+        keep_going: cli::KeepGoing,
+        #[structopt(flatten)]
+        all_targets: cli::AllTargets,
+        #[structopt(flatten)]
+        if_changed: cli::IfChanged,
+        #[structopt(flatten)]
+        build_info: cli::BuildInfo,
+        #[structopt(flatten)]
+        cargo_jobs: cli::CargoJobs,
+        #[structopt(flatten)]
+        lockfile: cli::Lockfile,
+        #[structopt(flatten)]
+        deny_warnings: cli::DenyWarnings,
+        #[structopt(flatten)]
+        require_free: cli::RequireFree,
+        #[structopt(flatten)]
+        skip_signing: cli::SkipSigning,
+        #[structopt(
+            long = "print-artifact",
+            help = "Also archive and export an IPA, then print only its path(s) to stdout, sending everything else to stderr"
+        )]
+        print_artifact: bool,
+        #[structopt(
+            long = "simulator",
+            help = "Build for the iOS Simulator on this Mac, instead of for `targets`",
+            conflicts_with_all = &["all-targets", "print-artifact"]
+        )]
+        simulator: bool,
+        #[structopt(
+            long = "export",
+            requires = "simulator",
+            help = "Export the simulator `.app` and print its path; it can't be installed on physical devices"
+        )]
+        export: bool,
+        #[structopt(
+            long = "universal",
+            requires = "export",
+            help = "Export a simulator `.app` that runs on both Intel and Apple silicon Macs"
+        )]
+        universal: bool,
     },
     #[structopt(name = "archive", about = "Builds and archives for targets(s)")]
     Archive {
@@ -86,14 +164,219 @@ pub enum Command {
         targets: Vec<String>,
         #[structopt(flatten)]
         profile: cli::Profile,
+        #[structopt(flatten)]
+        keep_going: cli::KeepGoing,
+        #[structopt(flatten)]
+        require_free: cli::RequireFree,
+        #[structopt(flatten)]
+        skip_signing: cli::SkipSigning,
     },
     #[structopt(name = "run", about = "Deploys IPA to connected device")]
     Run {
         #[structopt(flatten)]
         profile: cli::Profile,
+        #[structopt(flatten)]
+        locale: cli::Locale,
+        #[structopt(flatten)]
+        last_device: cli::LastDevice,
+        #[structopt(flatten)]
+        after_run: cli::AfterRun,
+        #[structopt(flatten)]
+        dry_run: cli::DryRun,
+        #[structopt(flatten)]
+        explain: cli::Explain,
+        #[structopt(flatten)]
+        lockfile: cli::Lockfile,
+        #[structopt(flatten)]
+        deny_warnings: cli::DenyWarnings,
+        #[structopt(flatten)]
+        require_free: cli::RequireFree,
+        #[structopt(
+            long = "wait",
+            help = "Wait for the app to exit, then exit with its exit code (not supported by devices that use `devicectl`)",
+            conflicts_with = "after-run"
+        )]
+        wait: bool,
+        // Only here to explain why it isn't supported.
+        #[structopt(long = "skip-signing", hidden = true)]
+        skip_signing: bool,
+    },
+    #[structopt(
+        name = "test",
+        about = "Runs the scheme's tests on a simulator and summarizes the results"
+    )]
+    Test {
+        #[structopt(
+            long = "simulator",
+            value_name = "name-or-udid",
+            help = "Simulator to test on (prompts if there's more than one)"
+        )]
+        simulator: Option<String>,
+        #[structopt(
+            long = "output",
+            help = "How to print the summary",
+            default_value = "text",
+            possible_values = &xcresult::OutputFormat::VARIANTS
+        )]
+        output: xcresult::OutputFormat,
+        #[structopt(flatten)]
+        profile: cli::Profile,
+    },
+    #[structopt(
+        name = "deploy",
+        about = "Deploys a prebuilt IPA or app to connected device, without building"
+    )]
+    Deploy {
+        #[structopt(long = "artifact", help = "Path to the `.ipa` or `.app` to deploy")]
+        artifact: PathBuf,
+        #[structopt(flatten)]
+        dry_run: cli::DryRun,
+        // Only here to explain why it isn't supported.
+        #[structopt(long = "skip-signing", hidden = true)]
+        skip_signing: bool,
+    },
+    #[structopt(
+        name = "verify-artifact",
+        about = "Checks that an `.ipa` or `.app` is signed by `apple.development-team`"
+    )]
+    VerifyArtifact {
+        #[structopt(long = "artifact", help = "Path to the `.ipa` or `.app` to verify")]
+        artifact: PathBuf,
     },
     #[structopt(name = "list", about = "Lists connected devices")]
     List,
+    #[structopt(
+        name = "env",
+        about = "Prints the environment cargo-mobile2 builds with, for debugging"
+    )]
+    Env {
+        #[structopt(
+            long = "export",
+            help = "Print `export` lines for `eval` (or `set` lines for a batch file on Windows)"
+        )]
+        export: bool,
+    },
+    #[structopt(
+        name = "cargo",
+        about = "Runs cargo with the env builds for a target use, like `cargo apple cargo -- clippy --target aarch64-apple-ios`"
+    )]
+    Cargo {
+        #[structopt(name = "target", default_value = Target::DEFAULT_KEY, possible_values = &Target::name_list())]
+        target: String,
+        #[structopt(
+            name = "args",
+            last = true,
+            required = true,
+            help = "Arguments for cargo, which still need `--target <triple>` to build for the target"
+        )]
+        args: Vec<String>,
+    },
+    #[structopt(
+        name = "device-app-info",
+        about = "Shows the version of the app installed on a connected device"
+    )]
+    DeviceAppInfo {
+        #[structopt(long = "json", help = "Print the result as JSON")]
+        json: bool,
+        #[structopt(
+            long = "release",
+            help = "Look up the release build, if `app.debug-identifier-suffix` gives it a different identifier"
+        )]
+        release: bool,
+    },
+    #[structopt(
+        name = "container",
+        about = "Prints the path to the app's container on a simulator, or opens it in Finder"
+    )]
+    Container {
+        #[structopt(
+            long = "type",
+            help = "Which container to locate",
+            default_value = "data",
+            possible_values = &device::SimulatorContainerKind::VARIANTS
+        )]
+        kind: device::SimulatorContainerKind,
+        #[structopt(
+            long = "simulator",
+            value_name = "name-or-udid",
+            help = "Simulator to look on (prompts if there's more than one)"
+        )]
+        simulator: Option<String>,
+        #[structopt(long = "open", help = "Open the container in Finder")]
+        open: bool,
+        #[structopt(
+            long = "release",
+            help = "Look up the release build, if `app.debug-identifier-suffix` gives it a different identifier"
+        )]
+        release: bool,
+    },
+    #[structopt(name = "pair", about = "Pairs with a connected iOS device")]
+    Pair,
+    #[structopt(
+        name = "create-simulator",
+        about = "Creates an iOS simulator, or lists the available device types and runtimes"
+    )]
+    CreateSimulator {
+        #[structopt(
+            long = "device-type",
+            requires = "runtime",
+            help = "Device type name or identifier, i.e. \"iPhone 15\""
+        )]
+        device_type: Option<String>,
+        #[structopt(
+            long = "runtime",
+            requires = "device-type",
+            help = "Runtime name or identifier, i.e. \"iOS 17.0\""
+        )]
+        runtime: Option<String>,
+        #[structopt(
+            name = "name",
+            help = "Name of the new simulator (defaults to the device type)"
+        )]
+        name: Option<String>,
+    },
+    #[structopt(
+        name = "screenshot-matrix",
+        about = "Captures a screenshot of the app on each simulator in each locale"
+    )]
+    ScreenshotMatrix {
+        #[structopt(
+            long = "locale",
+            value_name = "tag",
+            help = "Locale to capture (a BCP-47 tag like `en-US`); may be given more than once",
+            required = true,
+            number_of_values = 1
+        )]
+        locales: Vec<crate::device::Locale>,
+        #[structopt(
+            long = "simulator",
+            value_name = "udid",
+            help = "UDID of a simulator to capture on; may be given more than once",
+            required = true,
+            number_of_values = 1
+        )]
+        simulators: Vec<String>,
+        #[structopt(
+            long = "out-dir",
+            help = "Directory to save screenshots to, as `<locale>/<udid>.png`",
+            default_value = "screenshots"
+        )]
+        out_dir: PathBuf,
+        #[structopt(
+            long = "settle",
+            value_name = "secs",
+            help = "How long to wait after launching before capturing",
+            default_value = "3"
+        )]
+        settle: u64,
+        #[structopt(flatten)]
+        profile: cli::Profile,
+    },
+    #[structopt(
+        name = "pod-install",
+        about = "Regenerates the Podfile from your pods config and runs `pod install`"
+    )]
+    PodInstall,
     #[structopt(name = "pod", about = "Runs `pod <args>`")]
     Pod {
         #[structopt(
@@ -104,6 +387,19 @@ pub enum Command {
         )]
         arguments: Vec<String>,
     },
+    #[structopt(
+        name = "gen-xcode-script",
+        about = "Prints a Run Script build phase for building the Rust library from another Xcode project"
+    )]
+    GenXcodeScript {
+        #[structopt(
+            long = "xcode-env",
+            value_name = "path",
+            help = "Write the env the script needs to this file (e.g. `.xcode.env` next to your Xcode project), and source it from the script instead",
+            parse(from_os_str)
+        )]
+        xcode_env: Option<PathBuf>,
+    },
     #[structopt(
         name = "xcode-script",
         about = "Compiles static lib (should only be called by Xcode!)",
@@ -157,17 +453,41 @@ pub enum Error {
     RustVersionCheckFailed(util::RustVersionError),
     DevicePromptFailed(PromptError<String>),
     TargetInvalid(TargetInvalid),
+    TargetsFailed(TargetFailures<Error>),
     ConfigFailed(LoadOrGenError),
+    ApiKeyInvalid(ApiKeyError),
+    XcodePathInvalid(AppleConfigError),
     MetadataFailed(metadata::Error),
     Unsupported,
+    SkipSigningUnsupported,
+    SkipSigningExportUnsupported,
     ProjectDirAbsent { project_dir: PathBuf },
     OpenFailed(os::OpenFileError),
+    CleanFailed(CleanError),
     CheckFailed(CheckError),
+    ChangeCheckFailed(std::io::Error),
+    BuildInfoFailed(build_info::Error),
     BuildFailed(BuildError),
     ArchiveFailed(ArchiveError),
+    DsymUploadFailed(DsymUploadError),
     ExportFailed(ExportError),
+    IpaMissing { old: PathBuf, new: PathBuf },
+    ArtifactNameFailed(artifact_name::Error),
+    ExportSimulatorAppFailed(device::ExportSimulatorAppError),
     RunFailed(RunError),
+    AppFailed(std::process::ExitStatus),
+    RunTestsFailed(device::RunTestsError),
+    TestsFailed(xcresult::Summary),
+    VerifyFailed(codesign::VerifyError),
+    XcodeScriptGenFailed(xcode_script::Error),
     ListFailed(String),
+    AppInfoFailed(device::AppInfoError),
+    PairFailed(device::PairError),
+    CreateSimulatorFailed(device::CreateSimulatorError),
+    ScreenshotMatrixFailed(device::ScreenshotMatrixError),
+    ScreenshotsFailed(device::ScreenshotFailures),
+    AppContainerFailed(device::AppContainerError),
+    OpenContainerFailed(os::OpenFileError),
     NoHomeDir(util::NoHomeDir),
     CargoEnvFailed(std::io::Error),
     SdkRootInvalid { sdk_root: PathBuf },
@@ -176,8 +496,12 @@ pub enum Error {
     ArchInvalid { arch: String },
     CompileLibFailed(CompileLibError),
     PodCommandFailed(std::io::Error),
+    PodInstallFailed(project::Error),
     CopyLibraryFailed(std::io::Error),
     LibNotFound { path: PathBuf },
+    FreeSpaceCheckFailed(disk::FreeSpaceError),
+    CargoPassthroughFailed(CargoPassthroughError),
+    CargoExited(ExitStatus),
 }
 
 impl Reportable for Error {
@@ -187,20 +511,82 @@ impl Reportable for Error {
             Self::RustVersionCheckFailed(err) => err.report(),
             Self::DevicePromptFailed(err) => err.report(),
             Self::TargetInvalid(err) => Report::error("Specified target was invalid", err),
+            Self::TargetsFailed(failures) => Report::error(
+                format!(
+                    "{} of {} targets failed",
+                    failures.failed.len(),
+                    failures.failed.len() + failures.passed.len()
+                ),
+                failures,
+            ),
             Self::ConfigFailed(err) => err.report(),
+            Self::ApiKeyInvalid(err) => err.report(),
+            Self::XcodePathInvalid(err) => Report::error("Failed to select Xcode", err),
             Self::MetadataFailed(err) => err.report(),
             Self::Unsupported => Report::error("iOS is marked as unsupported in your Cargo.toml metadata", "If your project should support Android, modify your Cargo.toml, then run `cargo mobile init` and try again."),
+            Self::SkipSigningUnsupported => Report::error(
+                "`--skip-signing` can't be used when deploying",
+                "Devices only install signed apps, so unsigned builds are only made by `cargo apple build --skip-signing` and `cargo apple archive --skip-signing`, for inspecting.",
+            ),
+            Self::SkipSigningExportUnsupported => Report::error(
+                "`--skip-signing` can't be used with `--print-artifact`",
+                "Exporting an IPA needs a signed archive; drop `--print-artifact` to build unsigned, or `--skip-signing` to export an IPA.",
+            ),
             Self::ProjectDirAbsent { project_dir } => Report::action_request(
                 "Please run `cargo mobile init` and try again!",
                 format!("Xcode project directory {:?} doesn't exist.", project_dir),
             ),
             Self::OpenFailed(err) => Report::error("Failed to open project in Xcode", err),
+            Self::CleanFailed(err) => err.report(),
             Self::CheckFailed(err) => err.report(),
+            Self::ChangeCheckFailed(err) => {
+                Report::error("Failed to check for changes with `git`", err)
+            }
+            Self::BuildInfoFailed(err) => err.report(),
             Self::BuildFailed(err) => err.report(),
             Self::ArchiveFailed(err) => err.report(),
+            Self::DsymUploadFailed(err) => err.report(),
             Self::ExportFailed(err) => err.report(),
+            Self::IpaMissing { old, new } => Report::error(
+                "IPA appears to be missing",
+                format!("Not found at either {:?} or {:?}", old, new),
+            ),
+            Self::ArtifactNameFailed(err) => Report::error("Failed to name IPA", err),
+            Self::ExportSimulatorAppFailed(err) => err.report(),
             Self::RunFailed(err) => err.report(),
+            Self::AppFailed(status) => match status.code() {
+                Some(code) => {
+                    Report::error("App failed", format!("It exited with code {}", code))
+                        .with_exit_code(code)
+                }
+                None => Report::error("App failed", status),
+            },
+            Self::RunTestsFailed(err) => err.report(),
+            Self::TestsFailed(summary) => Report::error(
+                if summary.failed > 0 {
+                    format!("{} of {} tests failed", summary.failed, summary.total)
+                } else {
+                    "Testing failed".to_owned()
+                },
+                "See the summary above for details.",
+            ),
+            Self::VerifyFailed(err) => err.report(),
+            Self::XcodeScriptGenFailed(err) => err.report(),
             Self::ListFailed(err) => Report::error("Failed to list devices", err),
+            Self::AppInfoFailed(err) => err.report(),
+            Self::PairFailed(err) => err.report(),
+            Self::CreateSimulatorFailed(err) => err.report(),
+            Self::ScreenshotMatrixFailed(err) => err.report(),
+            Self::ScreenshotsFailed(failures) => Report::error(
+                format!(
+                    "{} of {} screenshots failed",
+                    failures.failed.len(),
+                    failures.total
+                ),
+                failures,
+            ),
+            Self::AppContainerFailed(err) => err.report(),
+            Self::OpenContainerFailed(err) => Report::error("Failed to open container in Finder", err),
             Self::NoHomeDir(err) => Report::error("Failed to load cargo env profile", err),
             Self::CargoEnvFailed(err) => Report::error("Failed to load cargo env profile", err),
             Self::SdkRootInvalid { sdk_root } => Report::error(
@@ -221,8 +607,18 @@ impl Reportable for Error {
             ),
             Self::CompileLibFailed(err) => err.report(),
             Self::PodCommandFailed(err) => Report::error("pod command failed", err),
+            Self::PodInstallFailed(err) => err.report(),
             Self::CopyLibraryFailed(err) => Report::error("Failed to copy static library to Xcode Project", err),
             Self::LibNotFound { path } => Report::error("Library artifact not found", format!("Library not found at {}. Make sure your Cargo.toml file has a [lib] block with `crate-type = [\"staticlib\", \"cdylib\", \"rlib\"]`", path.display())),
+            Self::FreeSpaceCheckFailed(err) => err.report(),
+            Self::CargoPassthroughFailed(err) => err.report(),
+            Self::CargoExited(status) => match status.code() {
+                Some(code) => {
+                    Report::error("cargo failed", format!("It exited with code {}", code))
+                        .with_exit_code(code)
+                }
+                None => Report::error("cargo failed", status),
+            },
         }
     }
 }
@@ -231,31 +627,68 @@ impl Exec for Input {
     type Report = Error;
 
     fn global_flags(&self) -> GlobalFlags {
-        self.flags
+        self.flags.clone()
     }
 
     fn exec(self, wrapper: &TextWrapper) -> Result<(), Self::Report> {
-        define_device_prompt!(crate::apple::device::list_devices, String, iOS);
+        define_device_prompt!(crate::apple::device::list_devices, String, iOS, id);
         fn detect_target_ok<'a>(env: &Env) -> Option<&'a Target<'a>> {
             device_prompt(env).map(|device| device.target()).ok()
         }
 
         fn with_config(
-            non_interactive: bool,
+            load_config: cli::LoadConfig,
             wrapper: &TextWrapper,
-            f: impl FnOnce(&Config, &Metadata) -> Result<(), Error>,
+            env: &Env,
+            f: impl FnOnce(&Config, &Metadata, &Env) -> Result<(), Error>,
         ) -> Result<(), Error> {
-            let (config, _origin) = OmniConfig::load_or_gen(".", non_interactive, wrapper)
-                .map_err(Error::ConfigFailed)?;
+            let cli::LoadConfig {
+                non_interactive,
+                flags: config_flags,
+            } = load_config;
+            let (config, _origin) = OmniConfig::load_or_gen(
+                ".",
+                non_interactive,
+                config_flags.force_config,
+                config_flags.app.as_deref(),
+                &config_flags.config_overrides,
+                config_flags.max_search_depth,
+                config_flags.env_file.as_deref(),
+                None,
+                wrapper,
+            )
+            .map_err(Error::ConfigFailed)?;
             let metadata =
                 OmniMetadata::load(config.app().root_dir()).map_err(Error::MetadataFailed)?;
+            let mut env = env
+                .clone()
+                .explicit_env_vars(config.env_file_vars().clone())
+                .forward_vars(config.app().forward_env());
+            if config.app().use_sccache() {
+                env = env.with_sccache();
+            }
+            env = env.with_cargo_jobs(config.app().cargo_jobs());
+            if let Some(developer_dir) = config
+                .apple()
+                .developer_dir()
+                .map_err(Error::XcodePathInvalid)?
+            {
+                log::info!("using Xcode developer dir {:?}", developer_dir);
+                env.insert_env_var("DEVELOPER_DIR".into(), developer_dir.into());
+            }
             if metadata.apple().supported() {
-                f(config.apple(), metadata.apple())
+                f(config.apple(), metadata.apple(), &env)
             } else {
                 Err(Error::Unsupported)
             }
         }
 
+        fn warn_unsigned() {
+            log::warn!(
+                "code signing is skipped, so this build can't be installed on a normal device; it's only good for inspecting"
+            );
+        }
+
         fn ensure_init(config: &Config) -> Result<(), Error> {
             if !config.project_dir_exists() {
                 Err(Error::ProjectDirAbsent {
@@ -266,10 +699,43 @@ impl Exec for Input {
             }
         }
 
+        fn ensure_free_space(config: &Config, require_free: Option<f64>) -> Result<(), Error> {
+            match require_free.or(config.require_free()) {
+                Some(gib) => disk::ensure_free_space(
+                    [
+                        config.app().cargo_target_dir().as_path(),
+                        config.project_dir().as_path(),
+                    ],
+                    gib,
+                )
+                .map_err(Error::FreeSpaceCheckFailed),
+                None => Ok(()),
+            }
+        }
+
         fn open_in_xcode(config: &Config) -> Result<(), Error> {
             os::open_in_xcode(config.project_dir()).map_err(Error::OpenFailed)
         }
 
+        fn for_targets(
+            targets: &[String],
+            keep_going: bool,
+            wrapper: &TextWrapper,
+            env: &Env,
+            f: impl FnMut(&Target) -> Result<(), Error>,
+        ) -> Result<(), Error> {
+            call_for_targets_with_fallback_keep_going(
+                targets.iter(),
+                &detect_target_ok,
+                env,
+                keep_going,
+                wrapper,
+                Error::TargetsFailed,
+                f,
+            )
+            .map_err(Error::TargetInvalid)?
+        }
+
         let version_check = || rust_version_check(wrapper).map_err(Error::RustVersionCheckFailed);
 
         let Self {
@@ -277,106 +743,357 @@ impl Exec for Input {
                 GlobalFlags {
                     noise_level,
                     non_interactive,
+                    ..
                 },
+            config_flags,
             command,
         } = self;
+        let load_config = cli::LoadConfig {
+            non_interactive,
+            flags: &config_flags,
+        };
         let env = Env::new().map_err(Error::EnvInitFailed)?;
         match command {
             Command::Open => {
                 version_check()?;
-                with_config(non_interactive, wrapper, |config, _| {
+                with_config(load_config, wrapper, &env, |config, _, _| {
                     ensure_init(config)?;
                     open_in_xcode(config)
                 })
             }
-            Command::Check { targets } => {
+            Command::Clean {
+                native,
+                derived_data,
+            } => {
                 version_check()?;
-                with_config(non_interactive, wrapper, |config, metadata| {
-                    call_for_targets_with_fallback(
-                        targets.iter(),
-                        &detect_target_ok,
-                        &env,
-                        |target: &Target| {
-                            target
-                                .check(config, metadata, &env, noise_level)
-                                .map_err(Error::CheckFailed)
-                        },
-                    )
-                    .map_err(Error::TargetInvalid)?
+                with_config(load_config, wrapper, &env, |config, _, env| {
+                    ensure_init(config)?;
+                    if native {
+                        clean::clean_native(config, env, noise_level, derived_data)
+                            .map_err(Error::CleanFailed)?;
+                    }
+                    Ok(())
                 })
             }
+            Command::Check {
+                targets,
+                keep_going: cli::KeepGoing { keep_going },
+                all_targets,
+                cargo_jobs: cli::CargoJobs { cargo_jobs },
+                lockfile,
+                deny_warnings: cli::DenyWarnings { deny_warnings },
+            } => {
+                version_check()?;
+                with_config(load_config, wrapper, &env, |config, metadata, env| {
+                    let env = &env
+                        .clone()
+                        .with_cargo_jobs(cargo_jobs)
+                        .with_cargo_lock(lockfile.lock())
+                        .with_deny_warnings(deny_warnings);
+                    let targets = all_targets.expand::<Target>(targets);
+                    for_targets(&targets, keep_going, wrapper, env, |target: &Target| {
+                        target
+                            .check(config, metadata, env, noise_level)
+                            .map_err(Error::CheckFailed)
+                    })
+                })
+            }
+            Command::Build {
+                skip_signing: cli::SkipSigning { skip_signing: true },
+                print_artifact: true,
+                ..
+            } => Err(Error::SkipSigningExportUnsupported),
             Command::Build {
                 targets,
                 profile: cli::Profile { profile },
-            } => with_config(non_interactive, wrapper, |config, _| {
+                keep_going: cli::KeepGoing { keep_going },
+                all_targets,
+                if_changed: cli::IfChanged { if_changed },
+                build_info: cli::BuildInfo { with_build_info },
+                cargo_jobs: cli::CargoJobs { cargo_jobs },
+                lockfile,
+                deny_warnings: cli::DenyWarnings { deny_warnings },
+                require_free: cli::RequireFree { require_free },
+                skip_signing: cli::SkipSigning { skip_signing },
+                print_artifact,
+                simulator,
+                export,
+                universal,
+            } => with_config(load_config, wrapper, &env, |config, metadata, env| {
+                if print_artifact {
+                    cli::reserve_stdout();
+                }
+                let env = &env
+                    .clone()
+                    .with_cargo_jobs(cargo_jobs)
+                    .with_cargo_lock(lockfile.lock())
+                    .with_deny_warnings(deny_warnings);
+                if cli::unchanged(config.app(), if_changed.as_deref())
+                    .map_err(Error::ChangeCheckFailed)?
+                {
+                    return Ok(());
+                }
+                if with_build_info {
+                    build_info::generate(config.app()).map_err(Error::BuildInfoFailed)?;
+                }
                 version_check()?;
                 ensure_init(config)?;
-                call_for_targets_with_fallback(
-                    targets.iter(),
-                    &detect_target_ok,
-                    &env,
-                    |target: &Target| {
-                        target
-                            .build(
-                                config,
-                                &env,
-                                noise_level,
-                                profile,
-                                BuildConfig::default().allow_provisioning_updates(),
-                            )
-                            .map_err(Error::BuildFailed)
-                    },
-                )
-                .map_err(Error::TargetInvalid)?
+                ensure_free_space(config, require_free)?;
+                if export {
+                    let export = if universal {
+                        device::export_universal_simulator_app
+                    } else {
+                        device::export_simulator_app
+                    };
+                    let path = export(config, metadata, env, noise_level, profile)
+                        .map_err(Error::ExportSimulatorAppFailed)?;
+                    println!("{}", path.display());
+                    return Ok(());
+                }
+                let credentials = config.auth_credentials().map_err(Error::ApiKeyInvalid)?;
+                let targets = if simulator {
+                    vec![device::simulator_target_key().to_owned()]
+                } else {
+                    all_targets.expand::<Target>(targets)
+                };
+                if skip_signing {
+                    warn_unsigned();
+                }
+                // Targets export to the same IPA, so there's only more than
+                // one path if the artifact name template tells them apart.
+                let mut artifacts = Vec::new();
+                for_targets(&targets, keep_going, wrapper, env, |target: &Target| {
+                    let mut build_config = BuildConfig::default()
+                        .allow_provisioning_updates()
+                        .valid_archs(metadata.ios().valid_archs());
+                    if let Some(credentials) = credentials.clone() {
+                        build_config = build_config.authentication_credentials(credentials);
+                    }
+                    if skip_signing {
+                        build_config = build_config.skip_codesign();
+                    }
+                    target
+                        .build(config, env, noise_level, profile, build_config)
+                        .map_err(Error::BuildFailed)?;
+                    if !print_artifact {
+                        return Ok(());
+                    }
+                    // `build` leaves the app in DerivedData, so an IPA
+                    // takes archiving and exporting too.
+                    let mut archive_config = ArchiveConfig::new()
+                        .allow_provisioning_updates()
+                        .valid_archs(metadata.ios().valid_archs());
+                    let mut export_config = ExportConfig::new().allow_provisioning_updates();
+                    if let Some(credentials) = credentials.clone() {
+                        archive_config =
+                            archive_config.authentication_credentials(credentials.clone());
+                        export_config = export_config.authentication_credentials(credentials);
+                    }
+                    target
+                        .archive(config, env, noise_level, profile, None, archive_config)
+                        .map_err(Error::ArchiveFailed)?;
+                    target
+                        .export(config, env, noise_level, export_config)
+                        .map_err(Error::ExportFailed)?;
+                    let ipa = config
+                        .ipa_path()
+                        .map_err(|(old, new)| Error::IpaMissing { old, new })?;
+                    let vars = artifact_name::Vars {
+                        platform: "ios",
+                        arch: target.arch,
+                        profile,
+                    };
+                    let ipa = artifact_name::apply(config.app(), &ipa, vars)
+                        .map_err(Error::ArtifactNameFailed)?;
+                    if !artifacts.contains(&ipa) {
+                        artifacts.push(ipa);
+                    }
+                    Ok(())
+                })?;
+                for artifact in artifacts {
+                    println!("{}", artifact.display());
+                }
+                Ok(())
             }),
             Command::Archive {
                 targets,
                 build_number,
                 profile: cli::Profile { profile },
-            } => with_config(non_interactive, wrapper, |config, _| {
+                keep_going: cli::KeepGoing { keep_going },
+                require_free: cli::RequireFree { require_free },
+                skip_signing: cli::SkipSigning { skip_signing },
+            } => with_config(load_config, wrapper, &env, |config, metadata, env| {
                 version_check()?;
                 ensure_init(config)?;
-                call_for_targets_with_fallback(
-                    targets.iter(),
-                    &detect_target_ok,
-                    &env,
-                    |target: &Target| {
-                        let mut app_version = config.bundle_version().clone();
-                        if let Some(build_number) = build_number {
-                            app_version.push_extra(build_number);
-                        }
+                ensure_free_space(config, require_free)?;
+                let credentials = config.auth_credentials().map_err(Error::ApiKeyInvalid)?;
+                if skip_signing {
+                    warn_unsigned();
+                }
+                for_targets(&targets, keep_going, wrapper, env, |target: &Target| {
+                    let mut app_version = config.bundle_version().clone();
+                    if let Some(build_number) = build_number {
+                        app_version.push_extra(build_number);
+                    }
 
+                    let mut build_config = BuildConfig::new()
+                        .allow_provisioning_updates()
+                        .valid_archs(metadata.ios().valid_archs());
+                    let mut archive_config = ArchiveConfig::new()
+                        .allow_provisioning_updates()
+                        .valid_archs(metadata.ios().valid_archs());
+                    if let Some(credentials) = credentials.clone() {
+                        build_config = build_config.authentication_credentials(credentials.clone());
+                        archive_config = archive_config.authentication_credentials(credentials);
+                    }
+                    if skip_signing {
+                        build_config = build_config.skip_codesign();
+                        archive_config = archive_config.skip_codesign();
+                    }
+                    target
+                        .build(config, env, noise_level, profile, build_config)
+                        .map_err(Error::BuildFailed)?;
+                    let dsyms = target
+                        .archive(
+                            config,
+                            env,
+                            noise_level,
+                            profile,
+                            Some(app_version),
+                            archive_config,
+                        )
+                        .map_err(Error::ArchiveFailed)?;
+                    if config.dsym_upload_script().is_some() {
                         target
-                            .build(
-                                config,
-                                &env,
-                                noise_level,
-                                profile,
-                                BuildConfig::new().allow_provisioning_updates(),
-                            )
-                            .map_err(Error::BuildFailed)?;
-                        target
-                            .archive(
-                                config,
-                                &env,
-                                noise_level,
-                                profile,
-                                Some(app_version),
-                                ArchiveConfig::new().allow_provisioning_updates(),
-                            )
-                            .map_err(Error::ArchiveFailed)
-                    },
-                )
-                .map_err(Error::TargetInvalid)?
+                            .upload_dsyms(config, env, &dsyms)
+                            .map_err(Error::DsymUploadFailed)
+                    } else {
+                        for dsym in dsyms {
+                            println!("dSYM: {}", dsym.display());
+                        }
+                        Ok(())
+                    }
+                })
             }),
             Command::Run {
+                skip_signing: true, ..
+            }
+            | Command::Deploy {
+                skip_signing: true, ..
+            } => Err(Error::SkipSigningUnsupported),
+            Command::Run {
+                profile: cli::Profile { profile },
+                locale: cli::Locale { locale },
+                last_device: cli::LastDevice { last_device },
+                after_run: cli::AfterRun { after_run },
+                dry_run: cli::DryRun { dry_run },
+                explain: cli::Explain { explain },
+                lockfile,
+                deny_warnings: cli::DenyWarnings { deny_warnings },
+                require_free: cli::RequireFree { require_free },
+                wait,
+                skip_signing: _,
+            } => with_config(load_config, wrapper, &env, |config, metadata, env| {
+                let env = &env
+                    .clone()
+                    .with_cargo_lock(lockfile.lock())
+                    .with_deny_warnings(deny_warnings);
+                version_check()?;
+                ensure_init(config)?;
+                // Previews shouldn't change which device is used next time.
+                let remember = !dry_run && !explain;
+                let device =
+                    device_prompt_with(env, Some(config.app().root_dir()), last_device, remember)
+                        .map_err(Error::DevicePromptFailed)?;
+                if dry_run {
+                    device
+                        .dry_run(config, profile, non_interactive || wait, locale.as_ref())
+                        .print();
+                    return Ok(());
+                }
+                if explain {
+                    device
+                        .explain(
+                            config,
+                            non_interactive,
+                            locale.as_ref(),
+                            after_run.as_deref(),
+                            wait,
+                        )
+                        .print();
+                    return Ok(());
+                }
+                ensure_free_space(config, require_free)?;
+                let status = device
+                    .run(
+                        config,
+                        metadata,
+                        env,
+                        noise_level,
+                        non_interactive,
+                        profile,
+                        locale.as_ref(),
+                        after_run.as_deref(),
+                        wait,
+                    )
+                    .and_then(|h| {
+                        // Once the after-run command is done, all that's
+                        // left is streaming logs.
+                        if after_run.is_some() {
+                            h.kill().map(|_| None)
+                        } else {
+                            h.wait().map(|output| Some(output.status))
+                        }
+                        .map_err(|e| RunError::DeployFailed(e.to_string()))
+                    })
+                    .map_err(Error::RunFailed)?;
+                match status {
+                    Some(status) if wait && !status.success() => Err(Error::AppFailed(status)),
+                    _ => Ok(()),
+                }
+            }),
+            Command::Test {
+                simulator,
+                output,
                 profile: cli::Profile { profile },
-            } => with_config(non_interactive, wrapper, |config, _| {
+            } => with_config(load_config, wrapper, &env, |config, metadata, env| {
+                if output == xcresult::OutputFormat::Json {
+                    cli::reserve_stdout();
+                }
                 version_check()?;
                 ensure_init(config)?;
-                device_prompt(&env)
-                    .map_err(Error::DevicePromptFailed)?
-                    .run(config, &env, noise_level, non_interactive, profile)
+                let summary = device::run_tests(
+                    config,
+                    metadata,
+                    env,
+                    noise_level,
+                    profile,
+                    simulator.as_deref(),
+                )
+                .map_err(Error::RunTestsFailed)?;
+                summary.print(output);
+                if summary.succeeded() {
+                    Ok(())
+                } else {
+                    Err(Error::TestsFailed(summary))
+                }
+            }),
+            Command::Deploy {
+                artifact,
+                dry_run: cli::DryRun { dry_run },
+                skip_signing: _,
+            } => with_config(load_config, wrapper, &env, |config, _, env| {
+                let device = device_prompt_for(env, config.app().root_dir())
+                    .map_err(Error::DevicePromptFailed)?;
+                if dry_run {
+                    device
+                        .deploy_dry_run(config, &artifact, non_interactive)
+                        .map_err(Error::RunFailed)?
+                        .print();
+                    return Ok(());
+                }
+                device
+                    .deploy(config, env, &artifact, noise_level, non_interactive)
                     .and_then(|h| {
                         h.wait()
                             .map(|_| ())
@@ -384,6 +1101,14 @@ impl Exec for Input {
                     })
                     .map_err(Error::RunFailed)
             }),
+            Command::VerifyArtifact { artifact } => {
+                with_config(load_config, wrapper, &env, |config, _, env| {
+                    let observed = codesign::verify(config, env, noise_level, &artifact)
+                        .map_err(Error::VerifyFailed)?;
+                    println!("{:?} is signed by the expected {}", artifact, observed);
+                    Ok(())
+                })
+            }
             Command::List => {
                 device::list_devices(&env)
                     .map_err(Error::ListFailed)
@@ -391,16 +1116,144 @@ impl Exec for Input {
                         prompt::list_display_only(device_list.iter(), device_list.len());
                     })
             }
-            Command::Pod { mut arguments } => with_config(non_interactive, wrapper, |config, _| {
-                arguments.push(format!(
-                    "--project-directory={}",
-                    config.project_dir().display()
-                ));
-                duct::cmd("pod", arguments)
-                    .run()
-                    .map_err(Error::PodCommandFailed)?;
+            Command::Env { export } => with_config(load_config, wrapper, &env, |_, _, env| {
+                crate::env::print(env, export.then(ExportSyntax::host));
                 Ok(())
             }),
+            Command::Cargo { target, args } => {
+                with_config(load_config, wrapper, &env, |config, _, env| {
+                    let target = Target::for_name(&target).unwrap_or_else(Target::default_ref);
+                    let status = target
+                        .cargo_passthrough(config, env, &args)
+                        .map_err(Error::CargoPassthroughFailed)?;
+                    if status.success() {
+                        Ok(())
+                    } else {
+                        Err(Error::CargoExited(status))
+                    }
+                })
+            }
+            Command::DeviceAppInfo { json, release } => {
+                with_config(load_config, wrapper, &env, |config, _, env| {
+                    let device = device_prompt_for(env, config.app().root_dir())
+                        .map_err(Error::DevicePromptFailed)?;
+                    let profile = opts::Profile::from_flag(release);
+                    let info = device
+                        .app_info(config, env, profile)
+                        .map_err(Error::AppInfoFailed)?;
+                    crate::device::print_app_info(
+                        device.name(),
+                        &config.app().identifier_for(profile),
+                        info.as_ref(),
+                        json,
+                    );
+                    Ok(())
+                })
+            }
+            Command::Container {
+                kind,
+                simulator,
+                open,
+                release,
+            } => with_config(load_config, wrapper, &env, |config, _, env| {
+                let bundle_id = config
+                    .app()
+                    .identifier_for(opts::Profile::from_flag(release));
+                let containers = device::app_container(env, simulator.as_deref(), &bundle_id, kind)
+                    .map_err(Error::AppContainerFailed)?;
+                for container in containers {
+                    if open {
+                        os::open_file_with("Finder", &container.path, env)
+                            .map_err(Error::OpenContainerFailed)?;
+                    } else if let Some(group) = container.group {
+                        println!("{}\t{}", group, container.path.display());
+                    } else {
+                        println!("{}", container.path.display());
+                    }
+                }
+                Ok(())
+            }),
+            Command::Pair => {
+                let device = device_prompt(&env).map_err(Error::DevicePromptFailed)?;
+                if device.kind() != device::DeviceKind::DeviceCtlDevice {
+                    println!("{} doesn't need to be paired", device);
+                } else if device.is_paired() {
+                    println!("{} is already paired", device);
+                } else {
+                    device.pair(&env).map_err(Error::PairFailed)?;
+                    println!("Paired with {}", device);
+                }
+                Ok(())
+            }
+            Command::CreateSimulator {
+                device_type: Some(device_type),
+                runtime: Some(runtime),
+                name,
+            } => {
+                let name = name.as_deref().unwrap_or(&device_type);
+                let udid = device::create_simulator(&env, name, &device_type, &runtime)
+                    .map_err(Error::CreateSimulatorFailed)?;
+                println!("Created simulator {:?} ({})", name, udid);
+                Ok(())
+            }
+            Command::CreateSimulator { .. } => {
+                let (device_types, runtimes) = device::simulator_device_types_and_runtimes(&env)
+                    .map_err(Error::CreateSimulatorFailed)?;
+                println!("Device types:");
+                prompt::list_display_only(device_types.iter(), device_types.len());
+                println!("Runtimes:");
+                prompt::list_display_only(runtimes.iter(), runtimes.len());
+                println!("Pass `--device-type` and `--runtime` to create a simulator.");
+                Ok(())
+            }
+            Command::ScreenshotMatrix {
+                locales,
+                simulators,
+                out_dir,
+                settle,
+                profile: cli::Profile { profile },
+            } => with_config(load_config, wrapper, &env, |config, metadata, env| {
+                version_check()?;
+                ensure_init(config)?;
+                device::screenshot_matrix(
+                    config,
+                    metadata,
+                    env,
+                    noise_level,
+                    profile,
+                    &locales,
+                    &simulators,
+                    &out_dir,
+                    Duration::from_secs(settle),
+                )
+                .map_err(Error::ScreenshotMatrixFailed)?
+                .map_err(Error::ScreenshotsFailed)
+            }),
+            Command::PodInstall => {
+                with_config(load_config, wrapper, &env, |config, metadata, env| {
+                    project::install_pods(config, metadata, env).map_err(Error::PodInstallFailed)
+                })
+            }
+            Command::Pod { mut arguments } => {
+                with_config(load_config, wrapper, &env, |config, _, _| {
+                    arguments.push(format!(
+                        "--project-directory={}",
+                        config.project_dir().display()
+                    ));
+                    duct::cmd("pod", arguments)
+                        .run()
+                        .map_err(Error::PodCommandFailed)?;
+                    Ok(())
+                })
+            }
+            Command::GenXcodeScript { xcode_env } => {
+                with_config(load_config, wrapper, &env, |config, _, env| {
+                    let script = xcode_script::gen(config, env, xcode_env.as_deref())
+                        .map_err(Error::XcodeScriptGenFailed)?;
+                    print!("{}", script);
+                    Ok(())
+                })
+            }
             Command::XcodeScript {
                 macos,
                 sdk_root,
@@ -410,11 +1263,11 @@ impl Exec for Input {
                 profile,
                 force_color,
                 arches,
-            } => with_config(non_interactive, wrapper, |config, metadata| {
+            } => with_config(load_config, wrapper, &env, |config, metadata, env| {
                 // The `PATH` env var Xcode gives us is missing any additions
                 // made by the user's profile, so we'll manually add cargo's
                 // `PATH`.
-                let env = env.prepend_to_path(
+                let env = env.clone().prepend_to_path(
                     util::home_dir()
                         .map_err(Error::NoHomeDir)?
                         .join(".cargo/bin"),
@@ -515,7 +1368,10 @@ impl Exec for Input {
                         profile.as_str(),
                         config.app().lib_name()
                     );
-                    let lib_path = PathBuf::from(format!("../../target/{lib_location}"));
+                    let lib_path = config
+                        .app()
+                        .target_dir(rust_triple, profile)
+                        .join(format!("lib{}.a", config.app().lib_name()));
 
                     if !lib_path.exists() {
                         return Err(Error::LibNotFound { path: lib_path });
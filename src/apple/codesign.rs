@@ -0,0 +1,141 @@
+use super::{
+    config::Config,
+    device::{self, RunError},
+};
+use crate::{
+    env::{Env, ExplicitEnv as _},
+    opts,
+    util::cli::{Report, Reportable},
+    DuctExpressionExt,
+};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum VerifyError {
+    #[error(transparent)]
+    ArtifactInvalid(RunError),
+    #[error("`codesign -dv` failed: {0}")]
+    CodesignFailed(std::io::Error),
+    #[error("{0} isn't signed by a team")]
+    TeamMissing(PathBuf),
+    #[error("{artifact} is signed by {observed}, but `apple.development-team` isn't set")]
+    ExpectedMissing {
+        artifact: PathBuf,
+        observed: Identity,
+    },
+    #[error("{artifact} is signed by {observed}, but `apple.development-team` is {expected}")]
+    Mismatch {
+        artifact: PathBuf,
+        expected: String,
+        observed: Identity,
+    },
+}
+
+impl Reportable for VerifyError {
+    fn report(&self) -> Report {
+        match self {
+            Self::ExpectedMissing { .. } => Report::action_request(
+                "Set `apple.development-team` to the team you expect, then try again",
+                self,
+            ),
+            _ => Report::error("Failed to verify artifact signature", self),
+        }
+    }
+}
+
+/// Who an app was signed by, according to `codesign`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Identity {
+    pub team: String,
+    /// The leaf certificate's common name, e.g. `Apple Distribution: Example
+    /// Inc (ABCDE12345)`.
+    pub authority: Option<String>,
+}
+
+impl std::fmt::Display for Identity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.authority {
+            Some(authority) => write!(f, "team {} ({})", self.team, authority),
+            None => write!(f, "team {}", self.team),
+        }
+    }
+}
+
+fn parse_identity(output: &str) -> Option<Identity> {
+    let field = |name: &str| {
+        output
+            .lines()
+            .find_map(|line| line.strip_prefix(name)?.strip_prefix('='))
+            .map(|value| value.trim().to_owned())
+    };
+    let team = field("TeamIdentifier").filter(|team| team != "not set")?;
+    Some(Identity {
+        team,
+        authority: field("Authority"),
+    })
+}
+
+/// Checks that the app in `artifact` is signed by `apple.development-team`,
+/// returning the observed identity.
+pub fn verify(
+    config: &Config,
+    env: &Env,
+    noise_level: opts::NoiseLevel,
+    artifact: &Path,
+) -> Result<Identity, VerifyError> {
+    let app_path = device::app_in_artifact(
+        env,
+        noise_level,
+        artifact,
+        &config.export_dir().join("verify"),
+    )
+    .map_err(VerifyError::ArtifactInvalid)?;
+    // `codesign` prints the details to stderr.
+    let output = duct::cmd("codesign", ["-dv"])
+        .vars(env.explicit_env())
+        .before_spawn(move |cmd| {
+            cmd.arg(&app_path);
+            Ok(())
+        })
+        .stderr_to_stdout()
+        .read()
+        .map_err(VerifyError::CodesignFailed)?;
+    let observed =
+        parse_identity(&output).ok_or_else(|| VerifyError::TeamMissing(artifact.to_owned()))?;
+    match config.development_team() {
+        None => Err(VerifyError::ExpectedMissing {
+            artifact: artifact.to_owned(),
+            observed,
+        }),
+        Some(expected) if expected == observed.team => Ok(observed),
+        Some(expected) => Err(VerifyError::Mismatch {
+            artifact: artifact.to_owned(),
+            expected: expected.to_owned(),
+            observed,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_identity() {
+        let output = "Executable=/tmp/Example.app/Example
+Identifier=com.example.app
+Authority=Apple Distribution: Example Inc (ABCDE12345)
+Authority=Apple Worldwide Developer Relations Certification Authority
+TeamIdentifier=ABCDE12345
+";
+        assert_eq!(
+            parse_identity(output),
+            Some(Identity {
+                team: "ABCDE12345".to_owned(),
+                authority: Some("Apple Distribution: Example Inc (ABCDE12345)".to_owned()),
+            })
+        );
+        assert_eq!(parse_identity("TeamIdentifier=not set\n"), None);
+    }
+}
@@ -2,17 +2,30 @@ mod raw;
 
 pub use self::raw::*;
 
-use super::version_number::{VersionNumber, VersionNumberError};
+use super::{
+    target::Target,
+    version_number::{VersionNumber, VersionNumberError},
+    AuthCredentials,
+};
 use crate::{
-    config::app::App,
+    config::{
+        app::App,
+        extra_file::{self, ExtraFile},
+        target_env::{TargetEnv, UnknownTriple},
+        toolchain::TargetToolchain,
+    },
+    opts::Profile,
+    target::TargetTrait as _,
     util::{
-        self, cli::Report, Pod, VersionDouble, VersionDoubleError, VersionTriple,
-        VersionTripleError,
+        self,
+        cli::{Report, Reportable},
+        Pod, VersionDouble, VersionDoubleError, VersionTriple, VersionTripleError,
     },
 };
 use serde::{Deserialize, Serialize};
 use std::{
     fmt::{self, Display},
+    io,
     path::{Path, PathBuf},
     str::FromStr,
 };
@@ -20,8 +33,8 @@ use thiserror::Error;
 
 static DEFAULT_PROJECT_DIR: &str = "gen/apple";
 const DEFAULT_BUNDLE_VERSION: VersionNumber = VersionNumber::new(VersionTriple::new(1, 0, 0), None);
-const DEFAULT_IOS_VERSION: VersionDouble = VersionDouble::new(13, 0);
-const DEFAULT_MACOS_VERSION: VersionDouble = VersionDouble::new(11, 0);
+pub(crate) const DEFAULT_IOS_VERSION: VersionDouble = VersionDouble::new(13, 0);
+pub(crate) const DEFAULT_MACOS_VERSION: VersionDouble = VersionDouble::new(11, 0);
 
 #[derive(Debug, Default, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
@@ -55,6 +68,7 @@ pub struct BuildScript {
 #[derive(Debug, Default, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct Platform {
+    pub supported: Option<bool>,
     #[serde(default)]
     pub no_default_features: bool,
     pub cargo_args: Option<Vec<String>>,
@@ -75,6 +89,12 @@ pub struct Platform {
 }
 
 impl Platform {
+    /// Whether the generated Xcode project should have a target for this
+    /// platform. Defaults to `true`.
+    pub fn supported(&self) -> bool {
+        self.supported.unwrap_or(true)
+    }
+
     pub fn no_default_features(&self) -> bool {
         self.no_default_features
     }
@@ -203,7 +223,7 @@ impl Display for ProjectDirInvalid {
                 root_dir,
             } => write!(
                 f,
-                "Xcode project dir {:?} is outside of the app root dir {:?}",
+                "Xcode project dir {:?} is outside of the app root dir {:?}; set `apple.allow-external-project-dir = true` if this is intentional",
                 project_dir, root_dir,
             ),
         }
@@ -232,6 +252,24 @@ pub enum Error {
     InvalidVersionConfiguration,
     #[error("Identifier cannot contain underscores on iOS")]
     IdentifierCannotContainUnderscores,
+    #[error("`apple.extra-files` invalid: {0}")]
+    ExtraFileInvalid(extra_file::Error),
+    #[error("`apple.xcode-path` invalid: {0} doesn't contain `Contents/Developer`")]
+    XcodePathInvalid(PathBuf),
+    #[error("`apple.swift-module` {0:?} invalid: Module names can only contain ASCII letters, numbers, and underscores, and can't start with a number")]
+    SwiftModuleInvalid(String),
+    #[error("`apple.target-env` invalid: {0}")]
+    TargetEnvInvalid(UnknownTriple),
+    #[error("`apple.toolchain` invalid: {0}")]
+    ToolchainInvalid(UnknownTriple),
+    #[error("`apple.require-free` {0} invalid: It should be a positive number of GiB")]
+    RequireFreeInvalid(f64),
+    #[error("`apple.plist-pairs` sets the `Info.plist` key {0:?} more than once")]
+    PlistKeyDuplicated(String),
+    #[error(
+        "`apple.plist-pairs` sets the `Info.plist` key {key:?}, which is already set by {set_by}"
+    )]
+    PlistKeyConflict { key: String, set_by: &'static str },
 }
 
 impl Error {
@@ -240,6 +278,31 @@ impl Error {
     }
 }
 
+#[derive(Debug, Error)]
+pub enum ApiKeyError {
+    #[error("App Store Connect API key {0} doesn't exist")]
+    Missing(PathBuf),
+    #[error("Failed to read App Store Connect API key {path}: {cause}")]
+    Unreadable { path: PathBuf, cause: io::Error },
+    #[error(
+        "`apple.api-key.key-id` must be specified, since it can't be inferred from the name of {0}"
+    )]
+    KeyIdMissing(PathBuf),
+}
+
+impl Reportable for ApiKeyError {
+    fn report(&self) -> Report {
+        Report::error("App Store Connect API key invalid", self)
+    }
+}
+
+#[derive(Clone, Debug)]
+struct ApiKey {
+    path: PathBuf,
+    key_id: Option<String>,
+    issuer_id: String,
+}
+
 #[derive(Debug)]
 pub(crate) struct VersionInfo {
     pub version_number: Option<VersionNumber>,
@@ -292,12 +355,94 @@ pub struct Config {
     use_legacy_build_system: bool,
     plist_pairs: Vec<PListPair>,
     enable_bitcode: bool,
+    strip_release: bool,
+    dsym_upload_script: Option<String>,
+    #[serde(skip_serializing)]
+    api_key: Option<ApiKey>,
     export_options_plist_path: PathBuf,
+    extra_files: Vec<ExtraFile>,
+    #[serde(skip_serializing)]
+    xcode_path: Option<PathBuf>,
+    swift_module: Option<String>,
+    target_env: TargetEnv,
+    toolchain: TargetToolchain,
+    require_free: Option<f64>,
+}
+
+/// Resolves `apple.xcode-path` against the app root to the developer dir
+/// inside of it, which is what `DEVELOPER_DIR` expects.
+pub fn developer_dir(root_dir: &Path, xcode_path: &str) -> Result<PathBuf, Error> {
+    validate_xcode_path(root_dir.join(xcode_path))
+}
+
+fn validate_xcode_path(xcode_path: PathBuf) -> Result<PathBuf, Error> {
+    let developer_dir = xcode_path.join("Contents/Developer");
+    if developer_dir.is_dir() {
+        Ok(developer_dir)
+    } else {
+        Err(Error::XcodePathInvalid(xcode_path))
+    }
+}
+
+// The `Info.plist` keys that `project.yml` sets on its own, and what sets them.
+// `plist-pairs` can't set these too, since `xcodegen` would only keep one.
+static TEMPLATE_PLIST_KEYS: &[(&str, &str)] = &[
+    ("LSRequiresIPhoneOS", "the project template"),
+    ("UILaunchStoryboardName", "the project template"),
+    ("UIRequiredDeviceCapabilities", "the project template"),
+    ("UISupportedInterfaceOrientations", "the project template"),
+    (
+        "UISupportedInterfaceOrientations~ipad",
+        "the project template",
+    ),
+    ("CFBundleDisplayName", "`app.display-name`"),
+    ("CFBundleShortVersionString", "`apple.bundle-version-short`"),
+    ("CFBundleVersion", "`apple.bundle-version`"),
+];
+
+fn check_plist_pairs(pairs: &[PListPair], url_types: bool) -> Result<(), Error> {
+    for (i, pair) in pairs.iter().enumerate() {
+        let key = pair.key();
+        if pairs[..i].iter().any(|earlier| earlier.key() == key) {
+            return Err(Error::PlistKeyDuplicated(key.to_owned()));
+        }
+        let set_by = TEMPLATE_PLIST_KEYS
+            .iter()
+            .find(|(template_key, _)| *template_key == key)
+            .map(|(_, set_by)| *set_by)
+            .or_else(|| (url_types && key == "CFBundleURLTypes").then_some("`app.url-schemes`"));
+        if let Some(set_by) = set_by {
+            return Err(Error::PlistKeyConflict {
+                key: key.to_owned(),
+                set_by,
+            });
+        }
+    }
+    Ok(())
+}
+
+fn swift_module(raw: Option<String>) -> Result<Option<String>, Error> {
+    raw.map(|module| {
+        let valid = module
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && module
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if valid {
+            Ok(module)
+        } else {
+            Err(Error::SwiftModuleInvalid(module))
+        }
+    })
+    .transpose()
 }
 
 impl Config {
     pub fn from_raw(app: App, raw: Option<Raw>) -> Result<Self, Error> {
-        if app.identifier().contains('_') {
+        // Covers `app.debug-identifier-suffix` too.
+        if app.identifier_for(Profile::Debug).contains('_') {
             return Err(Error::IdentifierCannotContainUnderscores);
         }
 
@@ -325,6 +470,15 @@ impl Config {
                     }))?
                 {
                     Ok(project_dir)
+                } else if raw.allow_external_project_dir.unwrap_or_default() {
+                    // Store it as an absolute path, since it can't be
+                    // meaningfully expressed relative to the app root.
+                    util::normalize_path(app.root_dir().join(&project_dir))
+                        .map(|path| dunce::simplified(&path).to_string_lossy().into_owned())
+                        .map_err(|cause| Error::ProjectDirInvalid(ProjectDirInvalid::NormalizationFailed {
+                            project_dir,
+                            cause,
+                        }))
                 } else {
                     Err(Error::ProjectDirInvalid(ProjectDirInvalid::OutsideOfAppRoot {
                         project_dir,
@@ -353,6 +507,44 @@ impl Config {
             .map(PathBuf::from)
             .unwrap_or_else(|| "ExportOptions.plist".into());
 
+        let extra_files = extra_file::validate(app.root_dir(), raw.extra_files)
+            .map_err(Error::ExtraFileInvalid)?;
+
+        // This is only checked once an Apple command needs it, so that a
+        // missing Xcode doesn't get in the way of anything else.
+        let xcode_path = raw
+            .xcode_path
+            .map(|xcode_path| app.root_dir().join(xcode_path));
+
+        let swift_module = swift_module(raw.swift_module)?;
+
+        let target_env = raw
+            .target_env
+            .validate(Target::all().values().map(|target| target.triple))
+            .map_err(Error::TargetEnvInvalid)?;
+
+        let toolchain = raw
+            .toolchain
+            .validate(Target::all().values().map(|target| target.triple))
+            .map_err(Error::ToolchainInvalid)?;
+
+        let require_free = match raw.require_free {
+            Some(gib) if !util::disk::gib_valid(gib) => Err(Error::RequireFreeInvalid(gib)),
+            require_free => Ok(require_free),
+        }?;
+
+        let plist_pairs = raw.plist_pairs.unwrap_or_default();
+        check_plist_pairs(
+            &plist_pairs,
+            app.url_schemes().iter().any(|scheme| !scheme.is_web()),
+        )?;
+
+        let api_key = raw.api_key.map(|api_key| ApiKey {
+            path: app.root_dir().join(api_key.path),
+            key_id: api_key.key_id,
+            issuer_id: api_key.issuer_id,
+        });
+
         Ok(Self {
             app,
             development_team: raw.development_team,
@@ -372,9 +564,18 @@ impl Config {
                 .map_err(Error::IosVersionInvalid)?
                 .unwrap_or(DEFAULT_MACOS_VERSION),
             use_legacy_build_system: raw.use_legacy_build_system.unwrap_or(true),
-            plist_pairs: raw.plist_pairs.unwrap_or_default(),
+            plist_pairs,
             enable_bitcode: raw.enable_bitcode.unwrap_or(false),
+            strip_release: raw.strip_release.unwrap_or(false),
+            dsym_upload_script: raw.dsym_upload_script,
+            api_key,
             export_options_plist_path,
+            extra_files,
+            xcode_path,
+            swift_module,
+            target_env,
+            toolchain,
+            require_free,
         })
     }
 
@@ -412,6 +613,42 @@ impl Config {
         self.project_dir().join("build")
     }
 
+    /// The path of the `.xcarchive` produced by `Target::archive`.
+    pub fn archive_path(&self) -> PathBuf {
+        self.archive_dir()
+            .join(format!("{}.xcarchive", self.scheme()))
+    }
+
+    /// The `.xcresult` bundle written by `Target::test`.
+    pub fn test_results_path(&self) -> PathBuf {
+        self.archive_dir()
+            .join(format!("{}.xcresult", self.scheme()))
+    }
+
+    /// Whether `enable-bitcode` is set. Project generation still leaves
+    /// bitcode off for Xcode versions that don't support it.
+    pub fn enable_bitcode(&self) -> bool {
+        self.enable_bitcode
+    }
+
+    /// Files copied into the generated project, which are added to the app's
+    /// resources.
+    pub fn extra_files(&self) -> &[ExtraFile] {
+        &self.extra_files
+    }
+
+    /// A shell command to run for each dSYM after archiving, with
+    /// `<dsym-path>` replaced by the dSYM's shell-quoted path.
+    pub fn dsym_upload_script(&self) -> Option<&str> {
+        self.dsym_upload_script.as_deref()
+    }
+
+    /// The developer dir of `apple.xcode-path`, if it's set, which should be
+    /// passed to Xcode's tools as `DEVELOPER_DIR`.
+    pub fn developer_dir(&self) -> Result<Option<PathBuf>, Error> {
+        self.xcode_path.clone().map(validate_xcode_path).transpose()
+    }
+
     pub fn export_dir(&self) -> PathBuf {
         self.project_dir().join("build")
     }
@@ -437,6 +674,14 @@ impl Config {
             .join(format!("Payload/{}.app", self.app.stylized_name()))
     }
 
+    /// Where `cargo apple build --simulator --export` leaves the simulator
+    /// `.app`, which won't install on physical devices.
+    pub fn simulator_app_path(&self) -> PathBuf {
+        self.export_dir()
+            .join("simulator")
+            .join(format!("{}.app", self.app.stylized_name()))
+    }
+
     pub fn scheme(&self) -> String {
         format!("{}_iOS", self.app.name())
     }
@@ -445,7 +690,105 @@ impl Config {
         &self.bundle_version
     }
 
+    /// The Swift module name, if it's been overridden.
+    pub fn swift_module(&self) -> Option<&str> {
+        self.swift_module.as_deref()
+    }
+
+    pub fn target_env(&self) -> &TargetEnv {
+        &self.target_env
+    }
+
+    pub fn toolchain(&self) -> &TargetToolchain {
+        &self.toolchain
+    }
+
+    /// How many GiB must be free on the disks builds write to before they
+    /// start, unless `--require-free` is passed. Archives need a lot more than
+    /// Android builds do.
+    pub fn require_free(&self) -> Option<f64> {
+        self.require_free
+    }
+
     pub fn development_team(&self) -> Option<&str> {
         self.development_team.as_deref()
     }
+
+    /// Credentials from `apple.api-key`, if it's set. The key file is checked
+    /// here rather than when loading the config, since most commands don't
+    /// need it.
+    ///
+    /// The team ID isn't encoded in the key, so `apple.development-team` is
+    /// still required.
+    pub fn auth_credentials(&self) -> Result<Option<AuthCredentials>, ApiKeyError> {
+        let Some(ApiKey {
+            path,
+            key_id,
+            issuer_id,
+        }) = &self.api_key
+        else {
+            return Ok(None);
+        };
+        if !path.is_file() {
+            return Err(ApiKeyError::Missing(path.clone()));
+        }
+        std::fs::read(path).map_err(|cause| ApiKeyError::Unreadable {
+            path: path.clone(),
+            cause,
+        })?;
+        let key_id = key_id
+            .clone()
+            .or_else(|| {
+                path.file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .and_then(|stem| stem.strip_prefix("AuthKey_"))
+                    .filter(|key_id| !key_id.is_empty())
+                    .map(ToOwned::to_owned)
+            })
+            .ok_or_else(|| ApiKeyError::KeyIdMissing(path.clone()))?;
+        Ok(Some(AuthCredentials {
+            key_path: path.clone(),
+            key_id,
+            key_issuer_id: issuer_id.clone(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn pairs(keys: &[&str]) -> Vec<PListPair> {
+        keys.iter()
+            .map(|key| toml::from_str(&format!("key = {:?}\nvalue = true", key)).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn plist_keys_can_only_be_set_once() {
+        assert!(check_plist_pairs(
+            &pairs(&["NSCameraUsageDescription", "ITSAppUsesNonExemptEncryption"]),
+            false
+        )
+        .is_ok());
+        assert!(matches!(
+            check_plist_pairs(&pairs(&["NSCameraUsageDescription", "NSCameraUsageDescription"]), false),
+            Err(Error::PlistKeyDuplicated(key)) if key == "NSCameraUsageDescription"
+        ));
+        assert!(matches!(
+            check_plist_pairs(&pairs(&["CFBundleVersion"]), false),
+            Err(Error::PlistKeyConflict {
+                set_by: "`apple.bundle-version`",
+                ..
+            })
+        ));
+        assert!(check_plist_pairs(&pairs(&["CFBundleURLTypes"]), false).is_ok());
+        assert!(matches!(
+            check_plist_pairs(&pairs(&["CFBundleURLTypes"]), true),
+            Err(Error::PlistKeyConflict {
+                set_by: "`app.url-schemes`",
+                ..
+            })
+        ));
+    }
 }
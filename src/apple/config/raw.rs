@@ -1,5 +1,6 @@
 use crate::{
     apple::teams,
+    config::{extra_file::ExtraFile, target_env::TargetEnv, toolchain::TargetToolchain},
     util::{cli::TextWrapper, prompt},
 };
 use colored::{Color, Colorize as _};
@@ -111,6 +112,24 @@ pub struct PListPair {
     value: PlistValue,
 }
 
+impl PListPair {
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+}
+
+/// An App Store Connect API key, used to authenticate `xcodebuild` without an
+/// interactive session.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ApiKeyRaw {
+    /// Path to the `.p8` file, relative to the project root.
+    pub path: String,
+    /// Defaults to the `<key-id>` in an `AuthKey_<key-id>.p8` file name.
+    pub key_id: Option<String>,
+    pub issuer_id: String,
+}
+
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct Raw {
@@ -128,6 +147,23 @@ pub struct Raw {
     pub plist_pairs: Option<Vec<PListPair>>,
     pub enable_bitcode: Option<bool>,
     pub export_options_plist_path: Option<String>,
+    pub allow_external_project_dir: Option<bool>,
+    pub strip_release: Option<bool>,
+    pub dsym_upload_script: Option<String>,
+    pub api_key: Option<ApiKeyRaw>,
+    /// An `Xcode.app` to build with instead of the one selected by
+    /// `xcode-select`.
+    pub xcode_path: Option<String>,
+    #[serde(default)]
+    pub extra_files: Vec<ExtraFile>,
+    /// The Swift module name (`PRODUCT_MODULE_NAME`), which Xcode otherwise
+    /// derives from the product name.
+    pub swift_module: Option<String>,
+    #[serde(default, skip_serializing_if = "TargetEnv::is_empty")]
+    pub target_env: TargetEnv,
+    #[serde(default, skip_serializing_if = "TargetToolchain::is_empty")]
+    pub toolchain: TargetToolchain,
+    pub require_free: Option<f64>,
 }
 
 impl Raw {
@@ -151,6 +187,16 @@ impl Raw {
             plist_pairs: None,
             enable_bitcode: None,
             export_options_plist_path: None,
+            allow_external_project_dir: None,
+            strip_release: None,
+            dsym_upload_script: None,
+            api_key: None,
+            xcode_path: None,
+            extra_files: Vec::new(),
+            swift_module: None,
+            target_env: Default::default(),
+            toolchain: Default::default(),
+            require_free: None,
         })
     }
 
@@ -234,6 +280,16 @@ impl Raw {
             plist_pairs: None,
             enable_bitcode: None,
             export_options_plist_path: None,
+            allow_external_project_dir: None,
+            strip_release: None,
+            dsym_upload_script: None,
+            api_key: None,
+            xcode_path: None,
+            extra_files: Vec::new(),
+            swift_module: None,
+            target_env: Default::default(),
+            toolchain: Default::default(),
+            require_free: None,
         })
     }
 }
@@ -15,7 +15,7 @@ use crate::{
     DuctExpressionExt,
 };
 use once_cell_regex::regex;
-use std::collections::hash_set::HashSet;
+use std::{collections::hash_set::HashSet, path::Path};
 use thiserror::Error;
 
 static PACKAGES: &[PackageSpec] = &[
@@ -194,6 +194,7 @@ pub fn install_all(
     non_interactive: bool,
     skip_dev_tools: bool,
     reinstall_deps: bool,
+    developer_dir: Option<&Path>,
 ) -> Result<(), Error> {
     let mut gem_cache = GemCache::new();
     for package in PACKAGES {
@@ -222,8 +223,9 @@ pub fn install_all(
     }
     // we definitely don't want to install this on CI...
     if !skip_dev_tools {
-        let tool_info = DeveloperTools::new()?;
-        let result = xcode_plugin::install(wrapper, reinstall_deps, tool_info.version);
+        let tool_info = DeveloperTools::new(developer_dir)?;
+        let result =
+            xcode_plugin::install(wrapper, reinstall_deps, tool_info.version, developer_dir);
         if let Err(err) = result {
             // philosophy: never be so sturbborn as to prevent use / progress
             Report::action_request(
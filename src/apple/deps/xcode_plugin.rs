@@ -123,11 +123,16 @@ pub struct Context {
 }
 
 impl Context {
-    pub fn new(xcode_version: (u32, u32)) -> Result<Self, Error> {
+    /// `developer_dir` is the one from `apple.xcode-path`, if it's set;
+    /// otherwise, the one `xcode-select` picks is used.
+    pub fn new(xcode_version: (u32, u32), developer_dir: Option<&Path>) -> Result<Self, Error> {
         let repo = Repo::checkouts_dir("rust-xcode-plugin").map_err(Error::NoHomeDir)?;
         let xcode_user_dir = xcode_user_dir()?;
         let xcode_plugins_dir = xcode_user_dir.join("Plug-ins");
-        let xcode_app_dir = xcode_developer_dir().map(|path| xcode_app_dir(&path).to_owned())?;
+        let xcode_app_dir = match developer_dir {
+            Some(developer_dir) => xcode_app_dir(developer_dir).to_owned(),
+            None => xcode_developer_dir().map(|path| xcode_app_dir(&path).to_owned())?,
+        };
         let xcode_lang_res_dir =
             xcode_app_dir.join("SharedFrameworks/SourceModel.framework/Versions/A/Resources");
         let xcode_spec_dir = if xcode_version.0 >= 11 {
@@ -290,8 +295,9 @@ pub fn install(
     wrapper: &TextWrapper,
     reinstall_deps: bool,
     xcode_version: (u32, u32),
+    developer_dir: Option<&Path>,
 ) -> Result<(), Error> {
-    let ctx = Context::new(xcode_version)?;
+    let ctx = Context::new(xcode_version, developer_dir)?;
     if !ctx.check_installation()?.perfect() || reinstall_deps {
         println!("Installing `rust-xcode-plugin`...");
         ctx.update_repo()?;
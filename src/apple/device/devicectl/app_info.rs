@@ -0,0 +1,81 @@
+use crate::{
+    device::AppInfo,
+    env::{Env, ExplicitEnv as _},
+    util::cli::{Report, Reportable},
+    DuctExpressionExt,
+};
+use serde::Deserialize;
+use std::{env::temp_dir, fs::read_to_string};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum AppInfoError {
+    #[error("Failed to request app info from `devicectl`: {0}")]
+    RequestFailed(#[from] std::io::Error),
+    #[error("`devicectl device info apps` returned an invalid JSON: {0}")]
+    InvalidAppList(#[from] serde_json::Error),
+}
+
+impl Reportable for AppInfoError {
+    fn report(&self) -> Report {
+        Report::error("Failed to get installed app info", self)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct App {
+    bundle_identifier: String,
+    version: Option<String>,
+    bundle_version: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct AppListResult {
+    apps: Vec<App>,
+}
+
+#[derive(Deserialize)]
+struct AppListOutput {
+    result: AppListResult,
+}
+
+pub fn app_info(env: &Env, id: &str, bundle_id: &str) -> Result<Option<AppInfo>, AppInfoError> {
+    let json_output_path = temp_dir().join("appinfo.json");
+    let json_output_path_ = json_output_path.clone();
+    std::fs::write(&json_output_path, "")?;
+
+    duct::cmd(
+        "xcrun",
+        [
+            "devicectl",
+            "device",
+            "info",
+            "apps",
+            "--device",
+            id,
+            "--bundle-id",
+            bundle_id,
+            "--json-output",
+        ],
+    )
+    .before_spawn(move |cmd| {
+        cmd.arg(&json_output_path);
+        Ok(())
+    })
+    .stderr_capture()
+    .stdout_capture()
+    .vars(env.explicit_env())
+    .run()?;
+
+    let contents = read_to_string(json_output_path_)?;
+    Ok(serde_json::from_str::<AppListOutput>(&contents)?
+        .result
+        .apps
+        .into_iter()
+        .find(|app| app.bundle_identifier == bundle_id)
+        .map(|app| AppInfo {
+            version: app.version.unwrap_or_default(),
+            build: app.bundle_version.unwrap_or_default(),
+        }))
+}
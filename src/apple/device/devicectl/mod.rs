@@ -1,5 +1,9 @@
+mod app_info;
 mod device_list;
+mod pair;
 mod run;
 
+pub use app_info::{app_info, AppInfoError};
 pub use device_list::device_list;
-pub use run::run;
+pub use pair::{pair, PairError};
+pub use run::{install_argv, json_output_path, launch_argv, run};
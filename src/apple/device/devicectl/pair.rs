@@ -0,0 +1,38 @@
+use crate::{
+    env::{Env, ExplicitEnv as _},
+    util::cli::{Report, Reportable},
+    DuctExpressionExt,
+};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum PairError {
+    #[error("Failed to pair with device: {0}")]
+    PairFailed(std::io::Error),
+}
+
+impl Reportable for PairError {
+    fn report(&self) -> Report {
+        match self {
+            Self::PairFailed(err) => Report::error(
+                "Failed to pair with device",
+                format!(
+                    "{}. Make sure the device is unlocked, accept the \"Trust This Computer?\" prompt on it, and if asked, enable Developer Mode in Settings > Privacy & Security.",
+                    err
+                ),
+            ),
+        }
+    }
+}
+
+pub fn pair(env: &Env, id: &str) -> Result<(), PairError> {
+    println!("Pairing with device...");
+
+    duct::cmd("xcrun", ["devicectl", "manage", "pair", "--device", id])
+        .vars(env.explicit_env())
+        .dup_stdio()
+        .run()
+        .map_err(PairError::PairFailed)?;
+
+    Ok(())
+}
@@ -1,4 +1,9 @@
-use std::{env::temp_dir, fs::read_to_string};
+use std::{
+    env::temp_dir,
+    ffi::OsString,
+    fs::read_to_string,
+    path::{Path, PathBuf},
+};
 
 use crate::{
     apple::config::Config,
@@ -52,46 +57,64 @@ struct InstallOutput {
     result: InstallResult,
 }
 
+pub fn install_argv(id: &str, app_dir: &Path, json_output_path: &Path) -> Vec<OsString> {
+    [
+        "xcrun",
+        "devicectl",
+        "device",
+        "install",
+        "app",
+        "--device",
+        id,
+    ]
+    .into_iter()
+    .map(Into::into)
+    .chain([
+        app_dir.into(),
+        "--json-output".into(),
+        json_output_path.into(),
+    ])
+    .collect()
+}
+
+pub fn launch_argv(id: &str, app_id: &str, launch_args: &[String]) -> Vec<OsString> {
+    [
+        "xcrun",
+        "devicectl",
+        "device",
+        "process",
+        "launch",
+        "--device",
+        id,
+        app_id,
+    ]
+    .into_iter()
+    .map(Into::into)
+    .chain(launch_args.iter().map(Into::into))
+    .collect()
+}
+
+pub fn json_output_path() -> PathBuf {
+    temp_dir().join("deviceinstall.json")
+}
+
 pub fn run(
     config: &Config,
     env: &Env,
     non_interactive: bool,
     id: &str,
-    paired: bool,
     noise_level: NoiseLevel,
+    app_dir: PathBuf,
+    launch_args: Vec<String>,
 ) -> Result<duct::Handle, RunError> {
-    if !paired {
-        println!("Pairing with device...");
-
-        duct::cmd("xcrun", ["devicectl", "manage", "pair", "--device", id])
-            .vars(env.explicit_env())
-            .dup_stdio()
-            .run()
-            .map_err(RunError::DeployFailed)?;
-    }
-
     println!("Deploying app to device...");
 
-    let app_dir = config
-        .export_dir()
-        .join(format!("{}_iOS.xcarchive", config.app().name()))
-        .join("Products/Applications")
-        .join(format!("{}.app", config.app().stylized_name()));
-    let json_output_path = temp_dir().join("deviceinstall.json");
-    let json_output_path_ = json_output_path.clone();
+    let json_output_path = json_output_path();
     std::fs::write(&json_output_path, "").map_err(RunError::DeployFailed)?;
-    let cmd = duct::cmd(
-        "xcrun",
-        ["devicectl", "device", "install", "app", "--device", id],
-    )
-    .vars(env.explicit_env())
-    .before_spawn(move |cmd| {
-        cmd.arg(&app_dir)
-            .arg("--json-output")
-            .arg(&json_output_path_);
-        Ok(())
-    })
-    .dup_stdio();
+    let install = install_argv(id, &app_dir, &json_output_path);
+    let cmd = duct::cmd(&install[0], &install[1..])
+        .vars(env.explicit_env())
+        .dup_stdio();
 
     cmd.run().map_err(RunError::DeployFailed)?;
 
@@ -105,20 +128,10 @@ pub fn run(
         .ok_or(RunError::MissingInstalledApplication)?;
     let app_id = installed_application.bundle_id;
 
-    let launcher_cmd = duct::cmd(
-        "xcrun",
-        [
-            "devicectl",
-            "device",
-            "process",
-            "launch",
-            "--device",
-            id,
-            &app_id,
-        ],
-    )
-    .vars(env.explicit_env())
-    .dup_stdio();
+    let launch = launch_argv(id, &app_id, &launch_args);
+    let launcher_cmd = duct::cmd(&launch[0], &launch[1..])
+        .vars(env.explicit_env())
+        .dup_stdio();
 
     if non_interactive {
         launcher_cmd.start().map_err(RunError::DeployFailed)
@@ -5,6 +5,10 @@ use crate::{
     util::cli::{Report, Reportable},
     DuctExpressionExt,
 };
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -21,30 +25,59 @@ impl Reportable for RunAndDebugError {
     }
 }
 
+pub fn deploy_argv(
+    non_interactive: bool,
+    id: &str,
+    app_path: &Path,
+    launch_args: &[String],
+) -> Vec<OsString> {
+    let mut argv: Vec<OsString> = vec![
+        "ios-deploy".into(),
+        "--debug".into(),
+        "--id".into(),
+        id.into(),
+        "--no-wifi".into(),
+        "--bundle".into(),
+        app_path.into(),
+    ];
+    if !launch_args.is_empty() {
+        argv.push("--args".into());
+        argv.push(launch_args.join(" ").into());
+    }
+    argv.push(if non_interactive {
+        "--noninteractive".into()
+    } else {
+        "--justlaunch".into()
+    });
+    argv
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn run_and_debug(
     config: &Config,
     env: &Env,
     non_interactive: bool,
     id: &str,
     noise_level: NoiseLevel,
+    app_path: PathBuf,
+    launch_args: Vec<String>,
+    wait: bool,
 ) -> Result<duct::Handle, RunAndDebugError> {
     println!("Deploying app to device...");
 
-    let app_path = config.app_path();
-    let deploy_cmd = duct::cmd("ios-deploy", ["--debug", "--id", id, "--no-wifi"])
+    let deploy = deploy_argv(non_interactive || wait, id, &app_path, &launch_args);
+    let deploy_cmd = duct::cmd(&deploy[0], &deploy[1..])
         .vars(env.explicit_env())
-        .before_spawn(move |cmd| {
-            cmd.arg("--bundle").arg(&app_path);
-            if non_interactive {
-                cmd.arg("--noninteractive");
-            } else {
-                cmd.arg("--justlaunch");
-            }
-            Ok(())
-        })
         .dup_stdio();
 
-    if non_interactive {
+    if wait {
+        // `--noninteractive` exits with the app's exit code, which is for the
+        // caller to interpret.
+        deploy_cmd
+            .unchecked()
+            .start()
+            .map_err(RunAndDebugError::DeployFailed)
+    } else if non_interactive {
         Ok(deploy_cmd.start().map_err(RunAndDebugError::DeployFailed)?)
     } else {
         deploy_cmd
@@ -1,19 +1,28 @@
 use super::{
-    config::Config,
+    config::{ApiKeyError, Config, Metadata},
     deps::{GemCache, PackageSpec},
-    target::{ArchiveError, BuildError, ExportError, Target},
+    lipo,
+    target::{ArchiveError, BuildError, ExportError, Target, TestError},
+    xcresult,
 };
 use crate::{
     apple::target::{ArchiveConfig, BuildConfig, ExportConfig},
+    device::{AfterRunError, AppInfo, DryRun, Explanation, Locale},
     env::{Env, ExplicitEnv as _},
     opts,
-    util::cli::{Report, Reportable},
+    target::TargetTrait as _,
+    util::{
+        cli::{Report, Reportable},
+        progress::Phase,
+        prompt,
+    },
     DuctExpressionExt,
 };
 use std::{
     collections::BTreeSet,
     fmt::{self, Display},
-    path::PathBuf,
+    path::{Path, PathBuf},
+    time::Duration,
 };
 use thiserror::Error;
 
@@ -21,10 +30,17 @@ mod devicectl;
 mod ios_deploy;
 mod simctl;
 
-pub use simctl::Device as Simulator;
+pub use devicectl::PairError;
+pub use simctl::{
+    target_key as simulator_target_key, Container as SimulatorContainer,
+    ContainerKind as SimulatorContainerKind, CreateError as CreateSimulatorError,
+    Device as Simulator, DeviceType as SimulatorDeviceType, Runtime as SimulatorRuntime,
+};
 
 #[derive(Debug, Error)]
 pub enum RunError {
+    #[error(transparent)]
+    ApiKeyInvalid(ApiKeyError),
     #[error(transparent)]
     BuildFailed(BuildError),
     #[error(transparent)]
@@ -35,13 +51,24 @@ pub enum RunError {
     IpaMissing { old: PathBuf, new: PathBuf },
     #[error("Failed to unzip archive: {0}")]
     UnzipFailed(std::io::Error),
+    #[error("{0} isn't an `.ipa` or `.app`")]
+    ArtifactInvalid(PathBuf),
+    #[error("No `.app` was found in {0}")]
+    ArtifactAppMissing(PathBuf),
     #[error("{0}")]
     DeployFailed(String),
+    #[error("{name} isn't paired with this Mac")]
+    NotPaired { name: String },
+    #[error(transparent)]
+    PairFailed(PairError),
+    #[error(transparent)]
+    AfterRunFailed(AfterRunError),
 }
 
 impl Reportable for RunError {
     fn report(&self) -> Report {
         match self {
+            Self::ApiKeyInvalid(err) => err.report(),
             Self::BuildFailed(err) => err.report(),
             Self::ArchiveFailed(err) => err.report(),
             Self::ExportFailed(err) => err.report(),
@@ -50,11 +77,222 @@ impl Reportable for RunError {
                 format!("Not found at either {:?} or {:?}", old, new),
             ),
             Self::UnzipFailed(err) => Report::error("Failed to unzip archive", err),
+            Self::ArtifactInvalid(_) | Self::ArtifactAppMissing(_) => {
+                Report::error("Failed to deploy artifact", self)
+            }
             Self::DeployFailed(err) => Report::error("Failed to deploy app", err),
+            Self::AfterRunFailed(err) => err.report(),
+            Self::NotPaired { name } => Report::action_request(
+                format!("{} isn't paired with this Mac", name),
+                "Run `cargo apple pair`, then unlock the device and accept the \"Trust This Computer?\" prompt.",
+            ),
+            Self::PairFailed(err) => err.report(),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum AppInfoError {
+    #[error(transparent)]
+    DeviceCtlFailed(devicectl::AppInfoError),
+    #[error(transparent)]
+    SimctlFailed(simctl::AppInfoError),
+    #[error("Looking up installed apps isn't supported for devices managed by `ios-deploy`")]
+    Unsupported,
+}
+
+impl Reportable for AppInfoError {
+    fn report(&self) -> Report {
+        match self {
+            Self::DeviceCtlFailed(err) => err.report(),
+            Self::SimctlFailed(err) => err.report(),
+            Self::Unsupported => Report::error("Failed to get installed app info", self),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ScreenshotMatrixError {
+    #[error(transparent)]
+    ApiKeyInvalid(ApiKeyError),
+    #[error(transparent)]
+    BuildFailed(BuildError),
+    #[error(transparent)]
+    ArchiveFailed(ArchiveError),
+    #[error("Failed to list simulators: {0}")]
+    SimulatorListFailed(String),
+    #[error("No simulator has the UDID {0:?}")]
+    SimulatorNotFound(String),
+}
+
+impl Reportable for ScreenshotMatrixError {
+    fn report(&self) -> Report {
+        match self {
+            Self::ApiKeyInvalid(err) => err.report(),
+            Self::BuildFailed(err) => err.report(),
+            Self::ArchiveFailed(err) => err.report(),
+            Self::SimulatorListFailed(err) => Report::error("Failed to list simulators", err),
+            Self::SimulatorNotFound(_) => Report::action_request(
+                self.to_string(),
+                "Run `xcrun simctl list devices available` to see the simulators you can use.",
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum SelectSimulatorError {
+    #[error("Failed to list simulators: {0}")]
+    ListFailed(String),
+    #[error("No simulator is named {0:?} or has that UDID")]
+    NotFound(String),
+    #[error("No simulators are available")]
+    NoSimulators,
+    #[error("Failed to prompt for simulator: {0}")]
+    PromptFailed(std::io::Error),
+}
+
+impl Reportable for SelectSimulatorError {
+    fn report(&self) -> Report {
+        match self {
+            Self::ListFailed(err) => Report::error("Failed to list simulators", err),
+            Self::NotFound(_) => Report::action_request(
+                self.to_string(),
+                "Run `xcrun simctl list devices available` to see the simulators you can use.",
+            ),
+            Self::NoSimulators => Report::action_request(
+                self.to_string(),
+                "Create one with `cargo apple create-simulator`.",
+            ),
+            Self::PromptFailed(err) => Report::error("Failed to prompt for simulator", err),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum AppContainerError {
+    #[error(transparent)]
+    SelectFailed(SelectSimulatorError),
+    #[error(transparent)]
+    ContainerFailed(simctl::ContainerError),
+}
+
+impl Reportable for AppContainerError {
+    fn report(&self) -> Report {
+        match self {
+            Self::SelectFailed(err) => err.report(),
+            Self::ContainerFailed(simctl::ContainerError::NotInstalled { .. }) => {
+                Report::action_request(
+                    self.to_string(),
+                    "Run the app on that simulator first, i.e. with `cargo apple run`.",
+                )
+            }
+            Self::ContainerFailed(err) => Report::error("Failed to locate app container", err),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum RunTestsError {
+    #[error(transparent)]
+    ApiKeyInvalid(ApiKeyError),
+    #[error(transparent)]
+    SelectFailed(SelectSimulatorError),
+    #[error(transparent)]
+    TestFailed(TestError),
+    #[error(transparent)]
+    SummaryFailed(xcresult::Error),
+    #[error("`xcodebuild test` failed before any tests could fail")]
+    XcodebuildFailed,
+}
+
+impl Reportable for RunTestsError {
+    fn report(&self) -> Report {
+        match self {
+            Self::ApiKeyInvalid(err) => err.report(),
+            Self::SelectFailed(err) => err.report(),
+            Self::TestFailed(err) => err.report(),
+            Self::SummaryFailed(err) => err.report(),
+            Self::XcodebuildFailed => Report::error(
+                "Failed to run tests",
+                "`xcodebuild test` failed without any test failures; run with `-vv` to see its full output.",
+            ),
         }
     }
 }
 
+#[derive(Debug, Error)]
+pub enum ExportSimulatorAppError {
+    #[error(transparent)]
+    ApiKeyInvalid(ApiKeyError),
+    #[error(transparent)]
+    BuildFailed(BuildError),
+    #[error(transparent)]
+    ArchiveFailed(ArchiveError),
+    #[error("Failed to copy {from} to {to}: {cause}")]
+    CopyFailed {
+        from: PathBuf,
+        to: PathBuf,
+        cause: std::io::Error,
+    },
+    #[error(transparent)]
+    LipoFailed(lipo::Error),
+    #[error("Failed to re-sign the universal app: {0}")]
+    ResignFailed(std::io::Error),
+}
+
+impl Reportable for ExportSimulatorAppError {
+    fn report(&self) -> Report {
+        match self {
+            Self::ApiKeyInvalid(err) => err.report(),
+            Self::BuildFailed(err) => err.report(),
+            Self::ArchiveFailed(err) => err.report(),
+            Self::CopyFailed { .. } | Self::LipoFailed(_) | Self::ResignFailed(_) => {
+                Report::error("Failed to export simulator app", self)
+            }
+        }
+    }
+}
+
+/// The locale and simulator combinations [`screenshot_matrix`] couldn't
+/// capture.
+#[derive(Debug)]
+pub struct ScreenshotFailures {
+    pub total: usize,
+    pub failed: Vec<(Locale, Simulator, simctl::ScreenshotError)>,
+}
+
+impl Display for ScreenshotFailures {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (locale, simulator, err)) in self.failed.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(
+                f,
+                "{} on {} ({}): {}",
+                locale,
+                simulator,
+                simulator.udid(),
+                err
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum RunStep<'a> {
+    Pair,
+    Build,
+    Archive,
+    Export,
+    ExtractIpa,
+    InstallAndLaunch,
+    AfterRun(&'a str),
+    FollowApp,
+}
+
 #[derive(Debug, Clone, Copy, Eq, Ord, PartialEq, PartialOrd)]
 pub enum DeviceKind {
     Simulator,
@@ -105,6 +343,10 @@ impl<'a> Device<'a> {
         self.target
     }
 
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
     pub fn name(&self) -> &str {
         &self.name
     }
@@ -117,90 +359,538 @@ impl<'a> Device<'a> {
         self.kind
     }
 
+    pub fn is_paired(&self) -> bool {
+        self.paired
+    }
+
+    /// Pairs with the device, so that it trusts this Mac.
+    ///
+    /// This only applies to devices managed by `devicectl`; for anything else
+    /// this is a no-op.
+    pub fn pair(&self, env: &Env) -> Result<(), PairError> {
+        if self.kind == DeviceKind::DeviceCtlDevice {
+            devicectl::pair(env, &self.id)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Looks up the version of the `profile` build of the app that's
+    /// installed on this device, if any.
+    pub fn app_info(
+        &self,
+        config: &Config,
+        env: &Env,
+        profile: opts::Profile,
+    ) -> Result<Option<AppInfo>, AppInfoError> {
+        let bundle_id = config.app().identifier_for(profile);
+        match self.kind {
+            DeviceKind::Simulator => {
+                simctl::app_info(env, &self.id, &bundle_id).map_err(AppInfoError::SimctlFailed)
+            }
+            DeviceKind::DeviceCtlDevice => devicectl::app_info(env, &self.id, &bundle_id)
+                .map_err(AppInfoError::DeviceCtlFailed),
+            DeviceKind::IosDeployDevice => Err(AppInfoError::Unsupported),
+        }
+    }
+
+    // What `run` does, in order. `explain` is derived from this too, so that it
+    // can't drift from what actually happens.
+    fn run_steps<'s>(&self, after_run: Option<&'s str>) -> Vec<RunStep<'s>> {
+        let mut steps = Vec::new();
+        // This comes before building, since pairing requires someone to be
+        // around to accept the prompt on the device.
+        if self.kind == DeviceKind::DeviceCtlDevice && !self.paired {
+            steps.push(RunStep::Pair);
+        }
+        // TODO: These steps are run unconditionally, which is slooooooow
+        steps.extend([RunStep::Build, RunStep::Archive]);
+        if self.kind != DeviceKind::Simulator {
+            steps.extend([RunStep::Export, RunStep::ExtractIpa]);
+        }
+        steps.push(RunStep::InstallAndLaunch);
+        steps.push(after_run.map_or(RunStep::FollowApp, RunStep::AfterRun));
+        steps
+    }
+
+    fn installed_app_path(&self, config: &Config) -> PathBuf {
+        match self.kind {
+            DeviceKind::IosDeployDevice => config.app_path(),
+            DeviceKind::Simulator | DeviceKind::DeviceCtlDevice => archived_app_path(config),
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub fn run(
         &self,
         config: &Config,
+        metadata: &Metadata,
         env: &Env,
         noise_level: opts::NoiseLevel,
         non_interactive: bool,
         profile: opts::Profile,
+        locale: Option<&Locale>,
+        after_run: Option<&str>,
+        wait: bool,
     ) -> Result<duct::Handle, RunError> {
-        // TODO: These steps are run unconditionally, which is slooooooow
-        println!("Building app...");
-        self.target
-            .build(
+        let credentials = config.auth_credentials().map_err(RunError::ApiKeyInvalid)?;
+        let bundle_id = config.app().identifier_for(profile);
+        let mut handle = None;
+        for step in self.run_steps(after_run) {
+            match step {
+                RunStep::Pair => self.ensure_paired(env, non_interactive)?,
+                RunStep::Build => {
+                    let mut build_config = BuildConfig::new()
+                        .allow_provisioning_updates()
+                        .valid_archs(metadata.ios().valid_archs());
+                    if let Some(credentials) = &credentials {
+                        build_config = build_config.authentication_credentials(credentials.clone());
+                    }
+                    let phase = Phase::start("Building app...", noise_level);
+                    self.target
+                        .build(config, env, noise_level, profile, build_config)
+                        .map_err(RunError::BuildFailed)?;
+                    phase.finish();
+                }
+                RunStep::Archive => {
+                    let mut archive_config =
+                        ArchiveConfig::new().valid_archs(metadata.ios().valid_archs());
+                    if let Some(credentials) = &credentials {
+                        archive_config =
+                            archive_config.authentication_credentials(credentials.clone());
+                    }
+                    let phase = Phase::start("Archiving app...", noise_level);
+                    self.target
+                        .archive(config, env, noise_level, profile, None, archive_config)
+                        .map_err(RunError::ArchiveFailed)?;
+                    phase.finish();
+                }
+                RunStep::Export => {
+                    let mut export_config = ExportConfig::default().allow_provisioning_updates();
+                    if let Some(credentials) = &credentials {
+                        export_config =
+                            export_config.authentication_credentials(credentials.clone());
+                    }
+                    let phase = Phase::start("Exporting app...", noise_level);
+                    self.target
+                        .export(config, env, noise_level, export_config)
+                        .map_err(RunError::ExportFailed)?;
+                    phase.finish();
+                }
+                RunStep::ExtractIpa => {
+                    println!("Extracting IPA...");
+                    let ipa_path = config
+                        .ipa_path()
+                        .map_err(|(old, new)| RunError::IpaMissing { old, new })?;
+                    unzip(env, noise_level, &ipa_path, &config.export_dir())?;
+                }
+                RunStep::InstallAndLaunch => {
+                    handle = Some(self.install_and_launch(
+                        config,
+                        env,
+                        &bundle_id,
+                        noise_level,
+                        non_interactive,
+                        self.installed_app_path(config),
+                        locale,
+                        wait,
+                    )?);
+                }
+                RunStep::AfterRun(command) => {
+                    if let Err(err) = crate::device::after_run(env, command, &self.id, &bundle_id) {
+                        if let Some(handle) = &handle {
+                            let _ = handle.kill();
+                        }
+                        return Err(RunError::AfterRunFailed(err));
+                    }
+                }
+                // That's left to the caller, which gets the handle.
+                RunStep::FollowApp => (),
+            }
+        }
+        Ok(handle.expect("developer error: `run_steps` didn't include launching"))
+    }
+
+    /// The steps `run` would take with the same arguments.
+    pub fn explain(
+        &self,
+        config: &Config,
+        non_interactive: bool,
+        locale: Option<&Locale>,
+        after_run: Option<&str>,
+        wait: bool,
+    ) -> Explanation {
+        self.explanation(
+            &config.scheme(),
+            self.run_steps(after_run),
+            non_interactive,
+            locale,
+            wait,
+        )
+    }
+
+    fn explanation(
+        &self,
+        scheme: &str,
+        steps: Vec<RunStep<'_>>,
+        non_interactive: bool,
+        locale: Option<&Locale>,
+        wait: bool,
+    ) -> Explanation {
+        // Without logs to follow, launching either stays attached to the app
+        // or returns right away, depending on the transport.
+        let attached = non_interactive || wait;
+        let follows_app = attached && self.kind != DeviceKind::DeviceCtlDevice;
+        let steps = steps
+            .into_iter()
+            .flat_map(|step| match step {
+                RunStep::Pair => vec![
+                    "Pair with the device, which needs someone to accept a prompt on it".to_owned(),
+                ],
+                RunStep::Build => vec![format!(
+                    "Build the `{}` scheme with `xcodebuild build`, which builds the Rust library for `{}` in a build phase",
+                    scheme, self.target.triple
+                )],
+                RunStep::Archive => vec!["Archive the app with `xcodebuild archive`".to_owned()],
+                RunStep::Export => {
+                    vec!["Export an IPA with `xcodebuild -exportArchive`".to_owned()]
+                }
+                RunStep::ExtractIpa => vec!["Extract the app from the IPA".to_owned()],
+                RunStep::InstallAndLaunch => {
+                    let (install, launched_by) = match self.kind {
+                        DeviceKind::Simulator => (
+                            Some("Install the app on the simulator with `simctl install`"),
+                            "`simctl launch`",
+                        ),
+                        DeviceKind::IosDeployDevice => {
+                            (None, "`ios-deploy`, which installs it too")
+                        }
+                        DeviceKind::DeviceCtlDevice => (
+                            Some("Install the app on the device with `devicectl device install app`"),
+                            "`devicectl device process launch`",
+                        ),
+                    };
+                    let launch = match locale {
+                        Some(locale) => format!("Launch the app in {} with {}", locale, launched_by),
+                        None => format!("Launch the app with {}", launched_by),
+                    };
+                    install
+                        .map(ToOwned::to_owned)
+                        .into_iter()
+                        .chain(std::iter::once(if follows_app {
+                            format!("{}, staying attached to its output until it exits", launch)
+                        } else {
+                            launch
+                        }))
+                        .collect()
+                }
+                RunStep::AfterRun(command) => vec![format!("Run `{}`, then stop", command)],
+                RunStep::FollowApp if follows_app && wait => {
+                    vec!["Exit with the app's exit code".to_owned()]
+                }
+                RunStep::FollowApp if attached => Vec::new(),
+                RunStep::FollowApp => vec![format!(
+                    "Follow the app's logs with {} until interrupted",
+                    if self.kind == DeviceKind::Simulator {
+                        "`simctl spawn log stream`"
+                    } else {
+                        "`idevicesyslog`"
+                    }
+                )],
+            })
+            .collect();
+        Explanation {
+            device: self.to_string(),
+            steps,
+        }
+    }
+
+    /// Installs and launches an `.ipa` or `.app` that was built elsewhere,
+    /// without building, archiving, or exporting anything.
+    pub fn deploy(
+        &self,
+        config: &Config,
+        env: &Env,
+        artifact: &Path,
+        noise_level: opts::NoiseLevel,
+        non_interactive: bool,
+    ) -> Result<duct::Handle, RunError> {
+        self.ensure_paired(env, non_interactive)?;
+        let app_path = app_in_artifact(
+            env,
+            noise_level,
+            artifact,
+            &config.export_dir().join("deploy"),
+        )?;
+        let release_id = config.app().identifier_for(opts::Profile::Release);
+        // Artifacts built elsewhere could be from either profile.
+        let bundle_id = match bundle_identifier(env, &app_path) {
+            Ok(bundle_id) if bundle_id == config.app().identifier_for(opts::Profile::Debug) => {
+                bundle_id
+            }
+            Ok(bundle_id) => {
+                if bundle_id != release_id {
+                    log::warn!(
+                        "{:?} has the bundle identifier {:?}, but this project's identifier is {:?}",
+                        artifact,
+                        bundle_id,
+                        release_id
+                    );
+                }
+                release_id
+            }
+            Err(err) => {
+                log::warn!(
+                    "couldn't check the bundle identifier of {:?}: {}",
+                    artifact,
+                    err
+                );
+                release_id
+            }
+        };
+        self.install_and_launch(
+            config,
+            env,
+            &bundle_id,
+            noise_level,
+            non_interactive,
+            app_path,
+            None,
+            false,
+        )
+    }
+
+    /// The artifact and commands `run` would use to install and launch the
+    /// app, without building, archiving, or exporting anything.
+    pub fn dry_run(
+        &self,
+        config: &Config,
+        profile: opts::Profile,
+        non_interactive: bool,
+        locale: Option<&Locale>,
+    ) -> DryRun {
+        self.plan_install_and_launch(
+            &config.app().identifier_for(profile),
+            non_interactive,
+            self.installed_app_path(config),
+            locale,
+        )
+    }
+
+    /// Like [`Device::dry_run`], but for [`Device::deploy`]. Since IPAs aren't
+    /// extracted, the app inside is assumed to be named after the project.
+    pub fn deploy_dry_run(
+        &self,
+        config: &Config,
+        artifact: &Path,
+        non_interactive: bool,
+    ) -> Result<DryRun, RunError> {
+        let app_path = match artifact.extension().and_then(|ext| ext.to_str()) {
+            Some("app") if artifact.is_dir() => artifact.to_owned(),
+            Some("ipa") if artifact.is_file() => config
+                .export_dir()
+                .join("deploy/Payload")
+                .join(format!("{}.app", config.app().stylized_name())),
+            _ => return Err(RunError::ArtifactInvalid(artifact.to_owned())),
+        };
+        let mut dry_run = self.plan_install_and_launch(
+            config.app().identifier(),
+            non_interactive,
+            app_path,
+            None,
+        );
+        dry_run.artifact = artifact.to_owned();
+        Ok(dry_run)
+    }
+
+    fn plan_install_and_launch(
+        &self,
+        bundle_id: &str,
+        non_interactive: bool,
+        app_path: PathBuf,
+        locale: Option<&Locale>,
+    ) -> DryRun {
+        let launch_args = locale.map(locale_launch_args).unwrap_or_default();
+        let commands = match self.kind {
+            DeviceKind::Simulator => vec![
+                simctl::install_argv(&self.id, &app_path),
+                simctl::launch_argv(bundle_id, non_interactive, &self.id, &launch_args),
+            ],
+            DeviceKind::IosDeployDevice => vec![ios_deploy::deploy_argv(
+                non_interactive,
+                &self.id,
+                &app_path,
+                &launch_args,
+            )],
+            DeviceKind::DeviceCtlDevice => vec![
+                devicectl::install_argv(&self.id, &app_path, &devicectl::json_output_path()),
+                // The real run launches whatever bundle ID the install
+                // reports, which should be this one.
+                devicectl::launch_argv(&self.id, bundle_id, &launch_args),
+            ],
+        };
+        DryRun {
+            device: self.to_string(),
+            artifact: app_path,
+            commands,
+        }
+    }
+
+    fn ensure_paired(&self, env: &Env, non_interactive: bool) -> Result<(), RunError> {
+        if self.kind == DeviceKind::DeviceCtlDevice && !self.paired {
+            if non_interactive {
+                return Err(RunError::NotPaired {
+                    name: self.name.clone(),
+                });
+            }
+            self.pair(env).map_err(RunError::PairFailed)?;
+        }
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn install_and_launch(
+        &self,
+        config: &Config,
+        env: &Env,
+        bundle_id: &str,
+        noise_level: opts::NoiseLevel,
+        non_interactive: bool,
+        app_path: PathBuf,
+        locale: Option<&Locale>,
+        wait: bool,
+    ) -> Result<duct::Handle, RunError> {
+        let launch_args = locale.map(locale_launch_args).unwrap_or_default();
+        match self.kind {
+            DeviceKind::Simulator => simctl::run(
                 config,
                 env,
+                bundle_id,
+                non_interactive,
                 noise_level,
-                profile,
-                BuildConfig::new().allow_provisioning_updates(),
+                &self.id,
+                app_path,
+                launch_args,
+                wait,
             )
-            .map_err(RunError::BuildFailed)?;
-        println!("Archiving app...");
-        self.target
-            .archive(
+            .map_err(|e| RunError::DeployFailed(e.to_string())),
+            DeviceKind::IosDeployDevice => ios_deploy::run_and_debug(
                 config,
                 env,
+                non_interactive,
+                &self.id,
                 noise_level,
-                profile,
-                None,
-                ArchiveConfig::new(),
+                app_path,
+                launch_args,
+                wait,
             )
-            .map_err(RunError::ArchiveFailed)?;
-
-        match self.kind {
-            DeviceKind::Simulator => {
-                simctl::run(config, env, non_interactive, noise_level, &self.id)
-                    .map_err(|e| RunError::DeployFailed(e.to_string()))
-            }
-            DeviceKind::IosDeployDevice | DeviceKind::DeviceCtlDevice => {
-                println!("Exporting app...");
-                self.target
-                    .export(
-                        config,
-                        env,
-                        noise_level,
-                        ExportConfig::default().allow_provisioning_updates(),
-                    )
-                    .map_err(RunError::ExportFailed)?;
-                println!("Extracting IPA...");
-
-                let ipa_path = config
-                    .ipa_path()
-                    .map_err(|(old, new)| RunError::IpaMissing { old, new })?;
-                let export_dir = config.export_dir();
-                let cmd = duct::cmd::<&str, [String; 0]>("unzip", [])
-                    .vars(env.explicit_env())
-                    .before_spawn(move |cmd| {
-                        if noise_level.pedantic() {
-                            cmd.arg("-q");
-                        }
-                        cmd.arg("-o").arg(&ipa_path).arg("-d").arg(&export_dir);
-                        Ok(())
-                    })
-                    .dup_stdio();
-
-                cmd.run().map_err(RunError::UnzipFailed)?;
-
-                if self.kind == DeviceKind::IosDeployDevice {
-                    ios_deploy::run_and_debug(config, env, non_interactive, &self.id, noise_level)
-                        .map_err(|e| RunError::DeployFailed(e.to_string()))
-                } else {
-                    devicectl::run(
-                        config,
-                        env,
-                        non_interactive,
-                        &self.id,
-                        self.paired,
-                        noise_level,
-                    )
-                    .map_err(|e| RunError::DeployFailed(e.to_string()))
+            .map_err(|e| RunError::DeployFailed(e.to_string())),
+            DeviceKind::DeviceCtlDevice => {
+                if wait {
+                    log::warn!(
+                        "`devicectl` can't report when the app exits, so not waiting for it"
+                    );
                 }
+                // Launching without streaming logs returns once the app has
+                // launched.
+                devicectl::run(
+                    config,
+                    env,
+                    non_interactive || wait,
+                    &self.id,
+                    noise_level,
+                    app_path,
+                    launch_args,
+                )
+                .map_err(|e| RunError::DeployFailed(e.to_string()))
             }
         }
     }
 }
 
+// These end up in `NSUserDefaults`, overriding the device's settings.
+fn locale_launch_args(locale: &Locale) -> Vec<String> {
+    vec![
+        "-AppleLanguages".to_owned(),
+        format!("({})", locale.tag()),
+        "-AppleLocale".to_owned(),
+        locale.apple_locale(),
+    ]
+}
+
+fn archived_app_path(config: &Config) -> PathBuf {
+    config
+        .export_dir()
+        .join(format!("{}_iOS.xcarchive", config.app().name()))
+        .join("Products/Applications")
+        .join(format!("{}.app", config.app().stylized_name()))
+}
+
+/// The `.app` in an `.ipa` or `.app` artifact. IPAs are extracted into
+/// `dest` first.
+pub(crate) fn app_in_artifact(
+    env: &Env,
+    noise_level: opts::NoiseLevel,
+    artifact: &Path,
+    dest: &Path,
+) -> Result<PathBuf, RunError> {
+    match artifact.extension().and_then(|ext| ext.to_str()) {
+        Some("app") if artifact.is_dir() => Ok(artifact.to_owned()),
+        Some("ipa") if artifact.is_file() => {
+            println!("Extracting IPA...");
+            // Clear out anything left over from a different IPA.
+            if dest.exists() {
+                std::fs::remove_dir_all(dest).map_err(RunError::UnzipFailed)?;
+            }
+            unzip(env, noise_level, artifact, dest)?;
+            std::fs::read_dir(dest.join("Payload"))
+                .map_err(RunError::UnzipFailed)?
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .find(|path| path.extension().is_some_and(|ext| ext == "app"))
+                .ok_or_else(|| RunError::ArtifactAppMissing(artifact.to_owned()))
+        }
+        _ => Err(RunError::ArtifactInvalid(artifact.to_owned())),
+    }
+}
+
+fn unzip(
+    env: &Env,
+    noise_level: opts::NoiseLevel,
+    archive: &Path,
+    dest: &Path,
+) -> Result<(), RunError> {
+    let (archive, dest) = (archive.to_owned(), dest.to_owned());
+    duct::cmd::<&str, [String; 0]>("unzip", [])
+        .vars(env.explicit_env())
+        .before_spawn(move |cmd| {
+            if noise_level.pedantic() {
+                cmd.arg("-q");
+            }
+            cmd.arg("-o").arg(&archive).arg("-d").arg(&dest);
+            Ok(())
+        })
+        .dup_stdio()
+        .run()
+        .map_err(RunError::UnzipFailed)?;
+    Ok(())
+}
+
+fn bundle_identifier(env: &Env, app_path: &Path) -> std::io::Result<String> {
+    let plist = app_path.join("Info.plist");
+    duct::cmd(
+        "plutil",
+        ["-extract", "CFBundleIdentifier", "raw", "-o", "-"],
+    )
+    .before_spawn(move |cmd| {
+        cmd.arg(&plist);
+        Ok(())
+    })
+    .vars(env.explicit_env())
+    .stderr_capture()
+    .read()
+    .map(|bundle_id| bundle_id.trim().to_owned())
+}
+
 pub fn list_devices<'a>(env: &Env) -> Result<BTreeSet<Device<'a>>, String> {
     let mut devices = BTreeSet::default();
     let mut error = None;
@@ -233,3 +923,446 @@ pub fn list_devices<'a>(env: &Env) -> Result<BTreeSet<Device<'a>>, String> {
 pub fn list_simulators(env: &Env) -> Result<BTreeSet<Simulator>, String> {
     simctl::device_list(env).map_err(|e| e.to_string())
 }
+
+// Simulators on different runtimes can share a name, so a UDID match wins.
+fn find_simulator<'a>(available: &'a BTreeSet<Simulator>, query: &str) -> Option<&'a Simulator> {
+    available
+        .iter()
+        .find(|simulator| simulator.udid() == query)
+        .or_else(|| available.iter().find(|simulator| simulator.name() == query))
+}
+
+/// Picks the simulator named by `query` (either its name or UDID), prompting
+/// for one if there's no `query` and more than one is available.
+pub fn select_simulator(env: &Env, query: Option<&str>) -> Result<Simulator, SelectSimulatorError> {
+    let available = simctl::device_list(env)
+        .map_err(|err| SelectSimulatorError::ListFailed(err.to_string()))?;
+    match query {
+        Some(query) => find_simulator(&available, query)
+            .cloned()
+            .ok_or_else(|| SelectSimulatorError::NotFound(query.to_owned())),
+        None => {
+            let index = match available.len() {
+                0 => return Err(SelectSimulatorError::NoSimulators),
+                1 => 0,
+                _ => prompt::list(
+                    "Available simulators",
+                    available.iter(),
+                    "simulator",
+                    None,
+                    "Simulator",
+                )
+                .map_err(SelectSimulatorError::PromptFailed)?,
+            };
+            Ok(available.into_iter().nth(index).unwrap())
+        }
+    }
+}
+
+/// Locates the `kind` container of the app with `bundle_id` on the simulator
+/// picked by [`select_simulator`].
+pub fn app_container(
+    env: &Env,
+    query: Option<&str>,
+    bundle_id: &str,
+    kind: SimulatorContainerKind,
+) -> Result<Vec<SimulatorContainer>, AppContainerError> {
+    let simulator = select_simulator(env, query).map_err(AppContainerError::SelectFailed)?;
+    simctl::container(env, &simulator, bundle_id, kind).map_err(AppContainerError::ContainerFailed)
+}
+
+/// Runs the scheme's tests on the simulator picked by [`select_simulator`],
+/// then summarizes the results.
+pub fn run_tests(
+    config: &Config,
+    metadata: &Metadata,
+    env: &Env,
+    noise_level: opts::NoiseLevel,
+    profile: opts::Profile,
+    query: Option<&str>,
+) -> Result<xcresult::Summary, RunTestsError> {
+    let simulator = select_simulator(env, query).map_err(RunTestsError::SelectFailed)?;
+
+    let mut build_config = BuildConfig::new().valid_archs(metadata.ios().valid_archs());
+    if let Some(credentials) = config
+        .auth_credentials()
+        .map_err(RunTestsError::ApiKeyInvalid)?
+    {
+        build_config = build_config.authentication_credentials(credentials);
+    }
+    log::info!("testing on {}", simulator);
+    let succeeded = simctl::target()
+        .test(
+            config,
+            env,
+            noise_level,
+            profile,
+            simulator.udid(),
+            build_config,
+        )
+        .map_err(RunTestsError::TestFailed)?;
+    let results_path = config.test_results_path();
+    // A build failure can leave no results at all, or results without any
+    // failing tests, neither of which is worth summarizing.
+    if !results_path.exists() {
+        return Err(RunTestsError::XcodebuildFailed);
+    }
+    let summary = xcresult::summarize(env, &results_path).map_err(RunTestsError::SummaryFailed)?;
+    if !succeeded && summary.succeeded() {
+        return Err(RunTestsError::XcodebuildFailed);
+    }
+    Ok(summary)
+}
+
+// Simulators are identified by UDID, since their names needn't be unique.
+fn screenshot_path(out_dir: &Path, locale: &Locale, simulator: &Simulator) -> PathBuf {
+    out_dir
+        .join(locale.tag())
+        .join(format!("{}.png", simulator.udid()))
+}
+
+/// Builds the app once, then captures a screenshot of it on each simulator
+/// (given by UDID) in each locale, saved as `<out_dir>/<locale>/<udid>.png`. A
+/// combination failing doesn't stop the rest from being attempted.
+#[allow(clippy::too_many_arguments)]
+pub fn screenshot_matrix(
+    config: &Config,
+    metadata: &Metadata,
+    env: &Env,
+    noise_level: opts::NoiseLevel,
+    profile: opts::Profile,
+    locales: &[Locale],
+    simulators: &[String],
+    out_dir: &Path,
+    settle: Duration,
+) -> Result<Result<(), ScreenshotFailures>, ScreenshotMatrixError> {
+    let available = simctl::device_list(env)
+        .map_err(|err| ScreenshotMatrixError::SimulatorListFailed(err.to_string()))?;
+    let simulators = simulators
+        .iter()
+        .map(|udid| {
+            available
+                .iter()
+                .find(|simulator| simulator.udid() == udid)
+                .cloned()
+                .ok_or_else(|| ScreenshotMatrixError::SimulatorNotFound(udid.clone()))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut build_config = BuildConfig::new().valid_archs(metadata.ios().valid_archs());
+    let mut archive_config = ArchiveConfig::new().valid_archs(metadata.ios().valid_archs());
+    if let Some(credentials) = config
+        .auth_credentials()
+        .map_err(ScreenshotMatrixError::ApiKeyInvalid)?
+    {
+        build_config = build_config.authentication_credentials(credentials.clone());
+        archive_config = archive_config.authentication_credentials(credentials);
+    }
+    let target = simctl::target();
+    let phase = Phase::start("Building app...", noise_level);
+    target
+        .build(config, env, noise_level, profile, build_config)
+        .map_err(ScreenshotMatrixError::BuildFailed)?;
+    phase.finish();
+    let phase = Phase::start("Archiving app...", noise_level);
+    target
+        .archive(config, env, noise_level, profile, None, archive_config)
+        .map_err(ScreenshotMatrixError::ArchiveFailed)?;
+    phase.finish();
+
+    let app_path = archived_app_path(config);
+    let bundle_id = config.app().identifier_for(profile);
+    let mut failures = ScreenshotFailures {
+        total: locales.len() * simulators.len(),
+        failed: Vec::new(),
+    };
+    for locale in locales {
+        let launch_args = locale_launch_args(locale);
+        for simulator in &simulators {
+            let dest = screenshot_path(out_dir, locale, simulator);
+            println!(
+                "Capturing {} on {} ({})...",
+                locale,
+                simulator,
+                simulator.udid()
+            );
+            match simctl::screenshot(
+                env,
+                simulator,
+                &app_path,
+                &bundle_id,
+                &launch_args,
+                settle,
+                &dest,
+            ) {
+                Ok(()) => println!("Saved {}", dest.display()),
+                Err(err) => {
+                    log::error!(
+                        "failed to capture {} on {} ({}): {}",
+                        locale,
+                        simulator,
+                        simulator.udid(),
+                        err
+                    );
+                    failures
+                        .failed
+                        .push((locale.clone(), simulator.clone(), err));
+                }
+            }
+        }
+    }
+    if failures.failed.is_empty() {
+        Ok(Ok(()))
+    } else {
+        Ok(Err(failures))
+    }
+}
+
+// Builds and archives the app for `target`, then copies the `.app` out of the
+// archive to `to`.
+fn export_simulator_slice(
+    config: &Config,
+    metadata: &Metadata,
+    env: &Env,
+    noise_level: opts::NoiseLevel,
+    profile: opts::Profile,
+    target: &Target,
+    to: &Path,
+) -> Result<(), ExportSimulatorAppError> {
+    let mut build_config = BuildConfig::new().valid_archs(metadata.ios().valid_archs());
+    let mut archive_config = ArchiveConfig::new().valid_archs(metadata.ios().valid_archs());
+    if let Some(credentials) = config
+        .auth_credentials()
+        .map_err(ExportSimulatorAppError::ApiKeyInvalid)?
+    {
+        build_config = build_config.authentication_credentials(credentials.clone());
+        archive_config = archive_config.authentication_credentials(credentials);
+    }
+    target
+        .build(config, env, noise_level, profile, build_config)
+        .map_err(ExportSimulatorAppError::BuildFailed)?;
+    target
+        .archive(config, env, noise_level, profile, None, archive_config)
+        .map_err(ExportSimulatorAppError::ArchiveFailed)?;
+    copy_app(env, &archived_app_path(config), to)
+}
+
+fn copy_app(env: &Env, from: &Path, to: &Path) -> Result<(), ExportSimulatorAppError> {
+    let copy_failed = |cause| ExportSimulatorAppError::CopyFailed {
+        from: from.to_owned(),
+        to: to.to_owned(),
+        cause,
+    };
+    // `ditto` won't clear out files left over from a previous export.
+    if to.exists() {
+        std::fs::remove_dir_all(to).map_err(copy_failed)?;
+    }
+    let (ditto_from, ditto_to) = (from.to_owned(), to.to_owned());
+    duct::cmd::<&str, [String; 0]>("ditto", [])
+        .vars(env.explicit_env())
+        .before_spawn(move |cmd| {
+            cmd.arg(&ditto_from).arg(&ditto_to);
+            Ok(())
+        })
+        .run()
+        .map_err(copy_failed)?;
+    Ok(())
+}
+
+/// Builds and archives the app for the simulator SDK, then copies the `.app`
+/// out of the archive to [`Config::simulator_app_path`], returning that path.
+/// The result only runs on simulators; it can't be installed on a device.
+pub fn export_simulator_app(
+    config: &Config,
+    metadata: &Metadata,
+    env: &Env,
+    noise_level: opts::NoiseLevel,
+    profile: opts::Profile,
+) -> Result<PathBuf, ExportSimulatorAppError> {
+    let to = config.simulator_app_path();
+    export_simulator_slice(
+        config,
+        metadata,
+        env,
+        noise_level,
+        profile,
+        simctl::target(),
+        &to,
+    )?;
+    Ok(to)
+}
+
+/// Like [`export_simulator_app`], but the app runs on simulators on both Intel
+/// and Apple silicon Macs. Each simulator target is exported separately, and
+/// then their executables are combined with `lipo` and the app is re-signed.
+/// Only the main executable is combined, so embedded dynamic frameworks still
+/// need to be universal already.
+pub fn export_universal_simulator_app(
+    config: &Config,
+    metadata: &Metadata,
+    env: &Env,
+    noise_level: opts::NoiseLevel,
+    profile: opts::Profile,
+) -> Result<PathBuf, ExportSimulatorAppError> {
+    let app_name = format!("{}.app", config.app().stylized_name());
+    let slices_dir = config.export_dir().join("simulator-slices");
+    let targets = ["x86_64", "aarch64-sim"].map(|key| &Target::all()[key]);
+    let mut slices = Vec::new();
+    for target in targets {
+        let slice = slices_dir.join(target.arch).join(&app_name);
+        export_simulator_slice(config, metadata, env, noise_level, profile, target, &slice)?;
+        slices.push(slice);
+    }
+
+    let to = config.simulator_app_path();
+    // Everything besides the executable is the same in each slice.
+    copy_app(env, &slices[0], &to)?;
+    let executable = config.app().stylized_name();
+    let executables = slices
+        .iter()
+        .map(|slice| slice.join(executable))
+        .collect::<Vec<_>>();
+    let archs = targets.map(|target| target.xcode_arch());
+    lipo::create(env, &executables, &to.join(executable), &archs)
+        .map_err(ExportSimulatorAppError::LipoFailed)?;
+    // Simulators still require a (possibly ad-hoc) signature, which `lipo`
+    // invalidated.
+    let app = to.clone();
+    duct::cmd(
+        "codesign",
+        ["--force", "--sign", "-", "--preserve-metadata=entitlements"],
+    )
+    .vars(env.explicit_env())
+    .before_spawn(move |cmd| {
+        cmd.arg(&app);
+        Ok(())
+    })
+    .run()
+    .map_err(ExportSimulatorAppError::ResignFailed)?;
+    Ok(to)
+}
+
+pub fn simulator_device_types_and_runtimes(
+    env: &Env,
+) -> Result<(Vec<SimulatorDeviceType>, Vec<SimulatorRuntime>), CreateSimulatorError> {
+    simctl::device_types_and_runtimes(env)
+}
+
+pub fn create_simulator(
+    env: &Env,
+    name: &str,
+    device_type: &str,
+    runtime: &str,
+) -> Result<String, CreateSimulatorError> {
+    simctl::create(env, name, device_type, runtime)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn simulator(name: &str, udid: &str) -> Simulator {
+        serde_json::from_value(serde_json::json!({ "name": name, "udid": udid })).unwrap()
+    }
+
+    #[test]
+    fn simulators_are_found_by_udid_first() {
+        let available = BTreeSet::from([
+            simulator("iPhone 15", "AAAA"),
+            simulator("iPhone 15", "BBBB"),
+            simulator("BBBB", "CCCC"),
+        ]);
+        assert_eq!(
+            find_simulator(&available, "BBBB"),
+            Some(&simulator("iPhone 15", "BBBB"))
+        );
+        assert_eq!(
+            find_simulator(&available, "iPhone 15"),
+            Some(&simulator("iPhone 15", "AAAA"))
+        );
+        assert_eq!(find_simulator(&available, "iPhone 16"), None);
+    }
+
+    fn device(kind: DeviceKind, paired: bool) -> Device<'static> {
+        Device {
+            id: "00008110-000A".to_owned(),
+            name: "iPhone".to_owned(),
+            model: "iPhone 15".to_owned(),
+            target: &Target::all()["aarch64"],
+            kind,
+            paired,
+        }
+    }
+
+    #[test]
+    fn run_steps_depend_on_the_device() {
+        assert_eq!(
+            device(DeviceKind::DeviceCtlDevice, false).run_steps(Some("./check")),
+            [
+                RunStep::Pair,
+                RunStep::Build,
+                RunStep::Archive,
+                RunStep::Export,
+                RunStep::ExtractIpa,
+                RunStep::InstallAndLaunch,
+                RunStep::AfterRun("./check"),
+            ]
+        );
+        assert_eq!(
+            device(DeviceKind::Simulator, true).run_steps(None),
+            [
+                RunStep::Build,
+                RunStep::Archive,
+                RunStep::InstallAndLaunch,
+                RunStep::FollowApp,
+            ]
+        );
+    }
+
+    #[test]
+    fn explanation_narrates_each_run_step() {
+        let device = device(DeviceKind::Simulator, true);
+        let locale = "fr-CA".parse::<Locale>().unwrap();
+        let explain = |wait| {
+            device
+                .explanation(
+                    "app_iOS",
+                    device.run_steps(None),
+                    false,
+                    Some(&locale),
+                    wait,
+                )
+                .steps
+        };
+        assert_eq!(
+            explain(false),
+            [
+                "Build the `app_iOS` scheme with `xcodebuild build`, which builds the Rust library for `aarch64-apple-ios` in a build phase",
+                "Archive the app with `xcodebuild archive`",
+                "Install the app on the simulator with `simctl install`",
+                "Launch the app in fr-CA with `simctl launch`",
+                "Follow the app's logs with `simctl spawn log stream` until interrupted",
+            ]
+        );
+        assert_eq!(
+            explain(true)[3..],
+            [
+                "Launch the app in fr-CA with `simctl launch`, staying attached to its output until it exits",
+                "Exit with the app's exit code",
+            ]
+        );
+    }
+
+    #[test]
+    fn screenshots_are_saved_by_udid() {
+        let locale = "fr-CA".parse::<Locale>().unwrap();
+        assert_eq!(
+            screenshot_path(
+                Path::new("screenshots"),
+                &locale,
+                &simulator("iPhone 15", "BBBB")
+            ),
+            Path::new("screenshots/fr-CA/BBBB.png")
+        );
+    }
+}
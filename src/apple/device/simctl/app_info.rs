@@ -0,0 +1,68 @@
+use crate::{
+    device::AppInfo,
+    env::{Env, ExplicitEnv as _},
+    util::cli::{Report, Reportable},
+    DuctExpressionExt,
+};
+use std::path::Path;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum AppInfoError {
+    #[error("Failed to locate app container with `simctl`: {0}")]
+    ContainerFailed(std::io::Error),
+    #[error("Failed to read `{key}` from {plist}: {cause}")]
+    PlistReadFailed {
+        key: &'static str,
+        plist: String,
+        cause: std::io::Error,
+    },
+}
+
+impl Reportable for AppInfoError {
+    fn report(&self) -> Report {
+        Report::error("Failed to get installed app info", self)
+    }
+}
+
+fn read_plist_key(env: &Env, plist: &Path, key: &'static str) -> Result<String, AppInfoError> {
+    duct::cmd("plutil", ["-extract", key, "raw", "-o", "-"])
+        .before_spawn({
+            let plist = plist.to_owned();
+            move |cmd| {
+                cmd.arg(&plist);
+                Ok(())
+            }
+        })
+        .vars(env.explicit_env())
+        .stderr_capture()
+        .read()
+        .map(|value| value.trim().to_owned())
+        .map_err(|cause| AppInfoError::PlistReadFailed {
+            key,
+            plist: plist.display().to_string(),
+            cause,
+        })
+}
+
+pub fn app_info(env: &Env, udid: &str, bundle_id: &str) -> Result<Option<AppInfo>, AppInfoError> {
+    let output = duct::cmd(
+        "xcrun",
+        ["simctl", "get_app_container", udid, bundle_id, "app"],
+    )
+    .vars(env.explicit_env())
+    .stderr_capture()
+    .stdout_capture()
+    .unchecked()
+    .run()
+    .map_err(AppInfoError::ContainerFailed)?;
+    // `simctl` fails if the app isn't installed.
+    if !output.status.success() {
+        return Ok(None);
+    }
+    let plist = Path::new(String::from_utf8_lossy(&output.stdout).trim()).join("Info.plist");
+    Ok(Some(AppInfo {
+        version: read_plist_key(env, &plist, "CFBundleShortVersionString")?,
+        build: read_plist_key(env, &plist, "CFBundleVersion")?,
+    }))
+}
@@ -0,0 +1,152 @@
+use super::Device;
+use crate::{
+    env::{Env, ExplicitEnv as _},
+    DuctExpressionExt,
+};
+use std::{path::PathBuf, str::FromStr};
+use thiserror::Error;
+
+/// Which of an app's containers to look up.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ContainerKind {
+    /// The installed `.app` bundle.
+    App,
+    /// The sandbox with `Documents`, `Library`, and `tmp`.
+    Data,
+    /// The shared containers of each app group the app belongs to.
+    Groups,
+}
+
+impl ContainerKind {
+    pub const VARIANTS: [&'static str; 3] = ["app", "data", "groups"];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::App => "app",
+            Self::Data => "data",
+            Self::Groups => "groups",
+        }
+    }
+}
+
+impl FromStr for ContainerKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "app" => Ok(Self::App),
+            "data" => Ok(Self::Data),
+            "groups" => Ok(Self::Groups),
+            _ => Err(format!(
+                "{:?} isn't a container type; expected one of {}",
+                s,
+                Self::VARIANTS.join(", ")
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ContainerError {
+    #[error("Failed to locate app container with `simctl`: {0}")]
+    CommandFailed(std::io::Error),
+    #[error("{bundle_id} isn't installed on {simulator}")]
+    NotInstalled {
+        bundle_id: String,
+        simulator: String,
+    },
+}
+
+/// A container on a simulator's filesystem. Only app group containers have a
+/// `group`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Container {
+    pub group: Option<String>,
+    pub path: PathBuf,
+}
+
+// `app` and `data` print a single path, while `groups` prints a
+// "<group>\t<path>" line per app group.
+fn parse_output(kind: ContainerKind, output: &str) -> Vec<Container> {
+    output
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| match line.split_once('\t') {
+            Some((group, path)) if kind == ContainerKind::Groups => Container {
+                group: Some(group.trim().to_owned()),
+                path: path.trim().into(),
+            },
+            _ => Container {
+                group: None,
+                path: line.into(),
+            },
+        })
+        .collect()
+}
+
+/// Locates the `kind` container of the app with `bundle_id` on `device`.
+pub fn container(
+    env: &Env,
+    device: &Device,
+    bundle_id: &str,
+    kind: ContainerKind,
+) -> Result<Vec<Container>, ContainerError> {
+    let output = duct::cmd(
+        "xcrun",
+        [
+            "simctl",
+            "get_app_container",
+            device.udid(),
+            bundle_id,
+            kind.as_str(),
+        ],
+    )
+    .vars(env.explicit_env())
+    .stderr_capture()
+    .stdout_capture()
+    .unchecked()
+    .run()
+    .map_err(ContainerError::CommandFailed)?;
+    // `simctl` fails if the app isn't installed.
+    if !output.status.success() {
+        return Err(ContainerError::NotInstalled {
+            bundle_id: bundle_id.to_owned(),
+            simulator: device.to_string(),
+        });
+    }
+    Ok(parse_output(kind, &String::from_utf8_lossy(&output.stdout)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn groups_are_split_from_their_paths() {
+        let containers = parse_output(
+            ContainerKind::Groups,
+            "group.com.example.shared\t/Users/me/Shared/AppGroup/A1\ngroup.com.example.widgets\t/Users/me/Shared/AppGroup/B2\n",
+        );
+        assert_eq!(
+            containers,
+            [
+                Container {
+                    group: Some("group.com.example.shared".into()),
+                    path: "/Users/me/Shared/AppGroup/A1".into(),
+                },
+                Container {
+                    group: Some("group.com.example.widgets".into()),
+                    path: "/Users/me/Shared/AppGroup/B2".into(),
+                },
+            ]
+        );
+        assert_eq!(
+            parse_output(ContainerKind::Data, "/Users/me/Data/Application/C3\n"),
+            [Container {
+                group: None,
+                path: "/Users/me/Data/Application/C3".into(),
+            }]
+        );
+    }
+}
@@ -0,0 +1,163 @@
+use crate::{
+    env::{Env, ExplicitEnv as _},
+    util::cli::{Report, Reportable},
+    DuctExpressionExt,
+};
+use serde::Deserialize;
+use std::fmt::{self, Display};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum CreateError {
+    #[error("Failed to list device types and runtimes from `simctl`: {0}")]
+    ListFailed(std::io::Error),
+    #[error("`simctl list` returned an invalid JSON: {0}")]
+    InvalidList(#[from] serde_json::Error),
+    #[error(
+        "Device type {device_type:?} isn't available; the possible device types are {possible:?}"
+    )]
+    DeviceTypeInvalid {
+        device_type: String,
+        possible: Vec<String>,
+    },
+    #[error("Runtime {runtime:?} isn't available; the possible runtimes are {possible:?}")]
+    RuntimeInvalid {
+        runtime: String,
+        possible: Vec<String>,
+    },
+    #[error("Failed to create simulator: {0}")]
+    CreateFailed(std::io::Error),
+}
+
+impl Reportable for CreateError {
+    fn report(&self) -> Report {
+        match self {
+            Self::ListFailed(_) | Self::InvalidList(_) => {
+                Report::error("Failed to list simulator device types and runtimes", self)
+            }
+            Self::DeviceTypeInvalid { .. } => {
+                Report::error("Specified simulator device type was invalid", self)
+            }
+            Self::RuntimeInvalid { .. } => {
+                Report::error("Specified simulator runtime was invalid", self)
+            }
+            Self::CreateFailed(err) => Report::error("Failed to create simulator", err),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DeviceType {
+    name: String,
+    identifier: String,
+}
+
+impl Display for DeviceType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.name, self.identifier)
+    }
+}
+
+impl DeviceType {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn identifier(&self) -> &str {
+        &self.identifier
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Runtime {
+    name: String,
+    identifier: String,
+    #[serde(default)]
+    is_available: bool,
+}
+
+impl Display for Runtime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.name, self.identifier)
+    }
+}
+
+impl Runtime {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn identifier(&self) -> &str {
+        &self.identifier
+    }
+}
+
+#[derive(Deserialize)]
+struct ListOutput {
+    devicetypes: Vec<DeviceType>,
+    runtimes: Vec<Runtime>,
+}
+
+/// Lists the device types and (available) runtimes that simulators can be
+/// created with.
+pub fn device_types_and_runtimes(
+    env: &Env,
+) -> Result<(Vec<DeviceType>, Vec<Runtime>), CreateError> {
+    let output = duct::cmd("xcrun", ["simctl", "list", "-j", "devicetypes", "runtimes"])
+        .vars(env.explicit_env())
+        .stdout_capture()
+        .stderr_capture()
+        .run()
+        .map_err(CreateError::ListFailed)?;
+    let list = serde_json::from_slice::<ListOutput>(&output.stdout)?;
+    Ok((
+        list.devicetypes,
+        list.runtimes
+            .into_iter()
+            .filter(|runtime| runtime.is_available)
+            .collect(),
+    ))
+}
+
+/// Creates a new simulator, returning its UDID.
+///
+/// `device_type` and `runtime` can be given either by name (i.e. "iPhone 15"
+/// and "iOS 17.0") or by identifier.
+pub fn create(
+    env: &Env,
+    name: &str,
+    device_type: &str,
+    runtime: &str,
+) -> Result<String, CreateError> {
+    let (device_types, runtimes) = device_types_and_runtimes(env)?;
+    let device_type = device_types
+        .iter()
+        .find(|ty| ty.identifier == device_type || ty.name == device_type)
+        .ok_or_else(|| CreateError::DeviceTypeInvalid {
+            device_type: device_type.to_owned(),
+            possible: device_types.iter().map(|ty| ty.name.clone()).collect(),
+        })?;
+    let runtime = runtimes
+        .iter()
+        .find(|rt| rt.identifier == runtime || rt.name == runtime)
+        .ok_or_else(|| CreateError::RuntimeInvalid {
+            runtime: runtime.to_owned(),
+            possible: runtimes.iter().map(|rt| rt.name.clone()).collect(),
+        })?;
+    let output = duct::cmd(
+        "xcrun",
+        [
+            "simctl",
+            "create",
+            name,
+            &device_type.identifier,
+            &runtime.identifier,
+        ],
+    )
+    .vars(env.explicit_env())
+    .stdout_capture()
+    .run()
+    .map_err(CreateError::CreateFailed)?;
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
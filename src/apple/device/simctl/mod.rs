@@ -2,16 +2,25 @@ use super::super::target::Target;
 use super::DeviceKind;
 use crate::apple::device::Device as AppleDevice;
 use crate::env::{Env, ExplicitEnv};
+use crate::target::TargetTrait as _;
 use crate::DuctExpressionExt;
 use serde::Deserialize;
 
 use std::fmt::Display;
 
+mod app_info;
+mod container;
+mod create;
 mod device_list;
 mod run;
+mod screenshot;
 
+pub use app_info::{app_info, AppInfoError};
+pub use container::{container, Container, ContainerError, ContainerKind};
+pub use create::{create, device_types_and_runtimes, CreateError, DeviceType, Runtime};
 pub use device_list::device_list;
-pub use run::run;
+pub use run::{install_argv, launch_argv, run};
+pub use screenshot::{screenshot, ScreenshotError};
 
 #[derive(Debug, Clone, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Device {
@@ -25,18 +34,27 @@ impl Display for Device {
     }
 }
 
+/// The key of the target to build for simulators on this Mac.
+pub fn target_key() -> &'static str {
+    if cfg!(target_arch = "aarch64") {
+        "aarch64-sim"
+    } else {
+        "x86_64"
+    }
+}
+
+/// The target to build for simulators on this Mac.
+pub fn target<'a>() -> &'a Target<'a> {
+    &Target::all()[target_key()]
+}
+
 impl<'a> From<Device> for AppleDevice<'a> {
     fn from(device: Device) -> AppleDevice<'a> {
         AppleDevice::new(
             device.udid,
             device.name,
             "".into(),
-            Target::for_arch(if cfg!(target_arch = "aarch64") {
-                "arm64-sim"
-            } else {
-                "x86_64"
-            })
-            .unwrap(),
+            target(),
             DeviceKind::Simulator,
         )
     }
@@ -47,6 +65,10 @@ impl Device {
         &self.name
     }
 
+    pub fn udid(&self) -> &str {
+        &self.udid
+    }
+
     fn command(&self, env: &Env) -> duct::Expression {
         duct::cmd(
             "open",
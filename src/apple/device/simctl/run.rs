@@ -5,6 +5,10 @@ use crate::{
     util::cli::{Report, Reportable},
     DuctExpressionExt,
 };
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -21,44 +25,70 @@ impl Reportable for RunError {
     }
 }
 
+pub fn install_argv(id: &str, app_dir: &Path) -> Vec<OsString> {
+    vec![
+        "xcrun".into(),
+        "simctl".into(),
+        "install".into(),
+        id.into(),
+        app_dir.into(),
+    ]
+}
+
+pub fn launch_argv(
+    bundle_id: &str,
+    non_interactive: bool,
+    id: &str,
+    launch_args: &[String],
+) -> Vec<OsString> {
+    let mut argv: Vec<OsString> = vec![
+        "xcrun".into(),
+        "simctl".into(),
+        "launch".into(),
+        id.into(),
+        bundle_id.into(),
+    ];
+    if non_interactive {
+        argv.push("--console".into());
+    }
+    argv.extend(launch_args.iter().map(Into::into));
+    argv
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn run(
     config: &Config,
     env: &Env,
+    bundle_id: &str,
     non_interactive: bool,
     noise_level: NoiseLevel,
     id: &str,
+    app_dir: PathBuf,
+    launch_args: Vec<String>,
+    wait: bool,
 ) -> Result<duct::Handle, RunError> {
     println!("Deploying app to device...");
 
-    let app_dir = config
-        .export_dir()
-        .join(format!("{}_iOS.xcarchive", config.app().name()))
-        .join("Products/Applications")
-        .join(format!("{}.app", config.app().stylized_name()));
-    let cmd = duct::cmd("xcrun", ["simctl", "install", id])
+    let install = install_argv(id, &app_dir);
+    let cmd = duct::cmd(&install[0], &install[1..])
         .vars(env.explicit_env())
-        .before_spawn(move |cmd| {
-            cmd.arg(&app_dir);
-            Ok(())
-        })
         .dup_stdio();
 
     let handle = cmd.start().map_err(RunError::DeployFailed)?;
 
     handle.wait().map_err(RunError::DeployFailed)?;
 
-    let app_id = config.app().identifier();
-    let mut launcher_cmd = duct::cmd("xcrun", ["simctl", "launch", id, app_id])
+    let launch = launch_argv(bundle_id, non_interactive || wait, id, &launch_args);
+    let launcher_cmd = duct::cmd(&launch[0], &launch[1..])
         .vars(env.explicit_env())
         .dup_stdio();
-
-    if non_interactive {
-        launcher_cmd = launcher_cmd.before_spawn(|cmd| {
-            cmd.arg("--console");
-            Ok(())
-        });
-    }
-    if non_interactive {
+    if wait {
+        // The app's exit code is for the caller to interpret.
+        launcher_cmd
+            .unchecked()
+            .start()
+            .map_err(RunError::DeployFailed)
+    } else if non_interactive {
         launcher_cmd.start().map_err(RunError::DeployFailed)
     } else {
         launcher_cmd
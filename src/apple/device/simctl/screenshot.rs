@@ -0,0 +1,140 @@
+use super::Device;
+use crate::{
+    env::{Env, ExplicitEnv as _},
+    DuctExpressionExt,
+};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ScreenshotError {
+    #[error("Failed to boot simulator: {0}")]
+    BootFailed(io::Error),
+    #[error("Failed to install app: {0}")]
+    InstallFailed(io::Error),
+    #[error("Failed to launch app: {0}")]
+    LaunchFailed(io::Error),
+    #[error("Failed to create directory {path}: {cause}")]
+    DirUnwritable { path: PathBuf, cause: io::Error },
+    #[error("Failed to capture screenshot: {0}")]
+    CaptureFailed(io::Error),
+}
+
+impl Device {
+    fn simctl(&self, env: &Env, subcommand: &str) -> duct::Expression {
+        duct::cmd("xcrun", ["simctl", subcommand, &self.udid]).vars(env.explicit_env())
+    }
+
+    /// Boots the simulator and waits for it to finish, returning `false` if it
+    /// was already booted.
+    pub fn boot(&self, env: &Env) -> io::Result<bool> {
+        let output = self
+            .simctl(env, "boot")
+            .stderr_capture()
+            .unchecked()
+            .run()?;
+        let booted = output.status.success();
+        if !booted && !String::from_utf8_lossy(&output.stderr).contains("state: Booted") {
+            return Err(io::Error::other(
+                String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+            ));
+        }
+        self.simctl(env, "bootstatus").stdout_null().run()?;
+        Ok(booted)
+    }
+
+    pub fn shutdown(&self, env: &Env) -> io::Result<()> {
+        self.simctl(env, "shutdown").run().map(|_| ())
+    }
+}
+
+/// Installs and launches the app, waits `settle` for it to draw its first
+/// screen, then saves a screenshot to `dest`. The simulator is shut down
+/// afterwards, unless it was already running.
+pub fn screenshot(
+    env: &Env,
+    device: &Device,
+    app_path: &Path,
+    bundle_id: &str,
+    launch_args: &[String],
+    settle: Duration,
+    dest: &Path,
+) -> Result<(), ScreenshotError> {
+    let booted = device.boot(env).map_err(ScreenshotError::BootFailed)?;
+    let result = capture(env, device, app_path, bundle_id, launch_args, settle, dest);
+    // Terminating fails harmlessly if the app never launched.
+    let _ = duct::cmd("xcrun", ["simctl", "terminate", &device.udid, bundle_id])
+        .vars(env.explicit_env())
+        .stderr_null()
+        .unchecked()
+        .run();
+    if booted {
+        if let Err(err) = device.shutdown(env) {
+            log::warn!("failed to shut down simulator {}: {}", device, err);
+        }
+    }
+    result
+}
+
+fn capture(
+    env: &Env,
+    device: &Device,
+    app_path: &Path,
+    bundle_id: &str,
+    launch_args: &[String],
+    settle: Duration,
+    dest: &Path,
+) -> Result<(), ScreenshotError> {
+    let app_path = app_path.to_owned();
+    device
+        .simctl(env, "install")
+        .before_spawn(move |cmd| {
+            cmd.arg(&app_path);
+            Ok(())
+        })
+        .run()
+        .map_err(ScreenshotError::InstallFailed)?;
+    let launch_args = launch_args.to_vec();
+    duct::cmd(
+        "xcrun",
+        [
+            "simctl",
+            "launch",
+            "--terminate-running-process",
+            &device.udid,
+            bundle_id,
+        ],
+    )
+    .vars(env.explicit_env())
+    .before_spawn(move |cmd| {
+        cmd.args(&launch_args);
+        Ok(())
+    })
+    .stdout_null()
+    .run()
+    .map_err(ScreenshotError::LaunchFailed)?;
+    thread::sleep(settle);
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent).map_err(|cause| ScreenshotError::DirUnwritable {
+            path: parent.to_owned(),
+            cause,
+        })?;
+    }
+    let dest = dest.to_owned();
+    duct::cmd("xcrun", ["simctl", "io", &device.udid, "screenshot"])
+        .vars(env.explicit_env())
+        .before_spawn(move |cmd| {
+            cmd.arg(&dest);
+            Ok(())
+        })
+        .stdout_null()
+        .stderr_capture()
+        .run()
+        .map(|_| ())
+        .map_err(ScreenshotError::CaptureFailed)
+}
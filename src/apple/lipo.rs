@@ -0,0 +1,97 @@
+use crate::{
+    env::{Env, ExplicitEnv as _},
+    DuctExpressionExt,
+};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("`lipo -create` failed: {0}")]
+    CreateFailed(std::io::Error),
+    #[error("`lipo -info` failed: {0}")]
+    InfoFailed(std::io::Error),
+    #[error("{path} should have {} slices, but has {}", expected.join(", "), observed.join(", "))]
+    SlicesMissing {
+        path: PathBuf,
+        expected: Vec<String>,
+        observed: Vec<String>,
+    },
+}
+
+// Handles both "Architectures in the fat file: <path> are: x86_64 arm64" and
+// "Non-fat file: <path> is architecture: arm64".
+fn parse_info(output: &str) -> Vec<String> {
+    output
+        .trim()
+        .rsplit_once(": ")
+        .map(|(_, archs)| archs.split_whitespace().map(ToOwned::to_owned).collect())
+        .unwrap_or_default()
+}
+
+/// The architectures in `binary`, according to `lipo -info`.
+pub fn archs(env: &Env, binary: &Path) -> Result<Vec<String>, Error> {
+    let binary = binary.to_owned();
+    duct::cmd("xcrun", ["lipo", "-info"])
+        .vars(env.explicit_env())
+        .before_spawn(move |cmd| {
+            cmd.arg(&binary);
+            Ok(())
+        })
+        .read()
+        .map(|output| parse_info(&output))
+        .map_err(Error::InfoFailed)
+}
+
+/// Combines single-arch `inputs` into a universal binary at `output`, then
+/// checks that it has each of `expected_archs`. `lipo` is resolved through
+/// `xcrun`, so it comes from the selected Xcode.
+pub fn create(
+    env: &Env,
+    inputs: &[PathBuf],
+    output: &Path,
+    expected_archs: &[&str],
+) -> Result<(), Error> {
+    let (inputs, output_arg) = (inputs.to_owned(), output.to_owned());
+    duct::cmd("xcrun", ["lipo", "-create"])
+        .vars(env.explicit_env())
+        .before_spawn(move |cmd| {
+            cmd.args(&inputs).arg("-output").arg(&output_arg);
+            Ok(())
+        })
+        .dup_stdio()
+        .run()
+        .map_err(Error::CreateFailed)?;
+    let observed = archs(env, output)?;
+    if expected_archs
+        .iter()
+        .all(|arch| observed.iter().any(|observed| observed == arch))
+    {
+        Ok(())
+    } else {
+        Err(Error::SlicesMissing {
+            path: output.to_owned(),
+            expected: expected_archs.iter().map(|&arch| arch.to_owned()).collect(),
+            observed,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(
+        "Architectures in the fat file: /tmp/Example.app/Example are: x86_64 arm64 \n",
+        &["x86_64", "arm64"]
+    )]
+    #[case(
+        "Non-fat file: /tmp/Example.app/Example is architecture: arm64\n",
+        &["arm64"]
+    )]
+    fn test_parse_info(#[case] output: &str, #[case] expected: &[&str]) {
+        assert_eq!(parse_info(output), expected);
+    }
+}
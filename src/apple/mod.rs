@@ -1,13 +1,18 @@
+pub mod clean;
 #[cfg(feature = "cli")]
 pub mod cli;
+pub mod codesign;
 pub mod config;
 pub mod deps;
 pub mod device;
+pub mod lipo;
 pub mod project;
 pub(crate) mod system_profile;
 pub mod target;
 pub mod teams;
 mod version_number;
+pub mod xcode_script;
+pub mod xcresult;
 
 use std::path::PathBuf;
 
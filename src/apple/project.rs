@@ -1,10 +1,16 @@
 use super::{
-    config::{Config, Metadata},
+    config::{Config, Error as ConfigError, Metadata},
     deps, rust_version_check,
+    system_profile::DeveloperTools,
     target::Target,
 };
 use crate::{
-    bicycle,
+    bicycle::{self, JsonMap},
+    config::{
+        app::{self, localization, url_scheme::UrlScheme},
+        extra_file,
+    },
+    env::{Env, ExplicitEnv as _},
     target::TargetTrait as _,
     templating::{self, Pack},
     util::{
@@ -17,21 +23,29 @@ use crate::{
 use std::path::{Path, PathBuf};
 
 pub static TEMPLATE_PACK: &str = "xcode";
+pub static STRINGS_TEMPLATE_PACK: &str = "xcode-strings";
 
 #[derive(Debug)]
 pub enum Error {
     RustupFailed(std::io::Error),
     RustVersionCheckFailed(util::RustVersionError),
+    XcodePathInvalid(ConfigError),
     DepsInstallFailed(deps::Error),
     MissingPack(templating::LookupError),
     TemplateProcessingFailed(bicycle::ProcessingError),
     AssetDirSymlinkFailed(ln::Error),
+    ExtraFileCopyFailed(bicycle::ProcessingError),
+    LocalizationFailed(localization::Error),
     DirectoryCreationFailed {
         path: PathBuf,
         cause: std::io::Error,
     },
     XcodegenFailed(std::io::Error),
     PodInstallFailed(std::io::Error),
+    ProjectDirAbsent {
+        project_dir: PathBuf,
+    },
+    CocoaPodsMissing,
 }
 
 impl Reportable for Error {
@@ -39,6 +53,7 @@ impl Reportable for Error {
         match self {
             Self::RustupFailed(err) => Report::error("Failed to `rustup` Apple toolchains", err),
             Self::RustVersionCheckFailed(err) => err.report(),
+            Self::XcodePathInvalid(err) => Report::error("Failed to select Xcode", err),
             Self::DepsInstallFailed(err) => {
                 Report::error("Failed to install Apple dependencies", err)
             }
@@ -49,16 +64,112 @@ impl Reportable for Error {
             Self::AssetDirSymlinkFailed(err) => {
                 Report::error("Asset dir couldn't be symlinked into Xcode project", err)
             }
+            Self::ExtraFileCopyFailed(err) => {
+                Report::error("Failed to copy `apple.extra-files` into Xcode project", err)
+            }
+            Self::LocalizationFailed(err) => {
+                Report::error("Failed to generate Xcode localized strings", err)
+            }
             Self::DirectoryCreationFailed { path, cause } => Report::error(
                 format!("Failed to create iOS assets directory at {:?}", path),
                 cause,
             ),
             Self::XcodegenFailed(err) => Report::error("Failed to run `xcodegen`", err),
             Self::PodInstallFailed(err) => Report::error("Failed to run `pod install`", err),
+            Self::ProjectDirAbsent { project_dir } => Report::action_request(
+                "Please run `cargo mobile init` and try again!",
+                format!("Xcode project directory {:?} doesn't exist.", project_dir),
+            ),
+            Self::CocoaPodsMissing => Report::action_request(
+                "CocoaPods isn't installed",
+                "Run `cargo mobile init` without `--no-deps` to install it along with the other Apple dependencies, or install it yourself with `brew install cocoapods`.",
+            ),
         }
     }
 }
 
+fn insert_pod_data(map: &mut JsonMap, metadata: &Metadata) {
+    map.insert("ios-supported", metadata.ios().supported());
+    map.insert("macos-supported", metadata.macos().supported());
+    map.insert("ios-pods", metadata.ios().pods().unwrap_or_default());
+    map.insert("macos-pods", metadata.macos().pods().unwrap_or_default());
+    map.insert(
+        "ios-pod-options",
+        metadata.ios().pod_options().unwrap_or_default(),
+    );
+    map.insert(
+        "macos-pod-options",
+        metadata.macos().pod_options().unwrap_or_default(),
+    );
+}
+
+// Web links can only open iOS apps through Universal Links, which need an
+// entitlement rather than an `Info.plist` entry.
+fn ios_url_schemes(url_schemes: &[UrlScheme]) -> Vec<&str> {
+    // Hosts don't matter here, so the same scheme can show up more than once.
+    let mut schemes = Vec::new();
+    for scheme in url_schemes.iter().filter(|scheme| !scheme.is_web()) {
+        if !schemes.contains(&scheme.scheme()) {
+            schemes.push(scheme.scheme());
+        }
+    }
+    schemes
+}
+
+/// `developer_dir` is only needed when there's no `env`, since `env` already
+/// has it.
+fn pod_install(
+    env: Option<&Env>,
+    developer_dir: Option<&Path>,
+    project_dir: &Path,
+) -> Result<(), Error> {
+    let mut cmd = duct::cmd(
+        "pod",
+        [
+            "install",
+            &format!("--project-directory={}", project_dir.display()),
+        ],
+    );
+    if let Some(env) = env {
+        cmd = cmd.vars(env.explicit_env());
+    }
+    if let Some(developer_dir) = developer_dir {
+        cmd = cmd.env("DEVELOPER_DIR", developer_dir);
+    }
+    cmd.dup_stdio().run().map_err(Error::PodInstallFailed)?;
+    Ok(())
+}
+
+/// Re-renders just the Podfile from the current pods config, then runs
+/// `pod install`, which is all a change to the pods needs.
+pub fn install_pods(config: &Config, metadata: &Metadata, env: &Env) -> Result<(), Error> {
+    let dest = config.project_dir();
+    if !config.project_dir_exists() {
+        return Err(Error::ProjectDirAbsent { project_dir: dest });
+    }
+    if !util::command_present("pod").map_err(Error::PodInstallFailed)? {
+        return Err(Error::CocoaPodsMissing);
+    }
+    let src = Pack::lookup_platform(TEMPLATE_PACK)
+        .map_err(Error::MissingPack)?
+        .expect_local();
+    println!("Regenerating Podfile...");
+    templating::init(None)
+        .process_action(
+            &bicycle::Action::WriteTemplate {
+                src: src.join("Podfile.hbs"),
+                dest: dest.join("Podfile"),
+            },
+            |map| {
+                map.insert(app::KEY, config.app());
+                map.insert(super::NAME, config);
+                insert_pod_data(map, metadata);
+            },
+        )
+        .map_err(Error::TemplateProcessingFailed)?;
+    pod_install(Some(env), None, &dest)
+}
+
 // unprefixed app_root seems pretty dangerous!!
 // TODO: figure out what I meant by that
 #[allow(clippy::too_many_arguments)]
@@ -71,6 +182,7 @@ pub fn gen(
     non_interactive: bool,
     skip_dev_tools: bool,
     reinstall_deps: bool,
+    skip_deps: bool,
     filter: &templating::Filter,
     skip_targets_install: bool,
 ) -> Result<(), Error> {
@@ -79,9 +191,18 @@ pub fn gen(
         Target::install_all().map_err(Error::RustupFailed)?;
     }
     rust_version_check(wrapper).map_err(Error::RustVersionCheckFailed)?;
+    let developer_dir = config.developer_dir().map_err(Error::XcodePathInvalid)?;
 
-    deps::install_all(wrapper, non_interactive, skip_dev_tools, reinstall_deps)
+    if !skip_deps {
+        deps::install_all(
+            wrapper,
+            non_interactive,
+            skip_dev_tools,
+            reinstall_deps,
+            developer_dir.as_deref(),
+        )
         .map_err(Error::DepsInstallFailed)?;
+    }
 
     let dest = config.project_dir();
     let rel_prefix = util::relativize_path(config.app().root_dir(), &dest);
@@ -94,22 +215,45 @@ pub fn gen(
         .map_err(Error::MissingPack)?
         .expect_local();
 
+    let enable_bitcode = config.enable_bitcode()
+        && match DeveloperTools::new(developer_dir.as_deref()) {
+            Ok(tools) if !tools.supports_bitcode() => {
+                log::warn!(
+                    "ignoring `enable-bitcode = true`, since bitcode isn't supported by Xcode {}.{}",
+                    tools.version.0,
+                    tools.version.1
+                );
+                false
+            }
+            Ok(_) => true,
+            Err(err) => {
+                log::warn!("couldn't check whether Xcode supports bitcode: {}", err);
+                true
+            }
+        };
+
     let asset_catalogs = metadata.ios().asset_catalogs().unwrap_or_default();
-    let ios_pods = metadata.ios().pods().unwrap_or_default();
-    let macos_pods = metadata.macos().pods().unwrap_or_default();
-    let ios_pod_options = metadata.ios().pod_options().unwrap_or_default();
-    let macos_pod_options = metadata.macos().pod_options().unwrap_or_default();
+    let has_pods = metadata.ios().pods().is_some_and(|pods| !pods.is_empty())
+        || metadata.macos().pods().is_some_and(|pods| !pods.is_empty());
 
     #[cfg(target_arch = "x86_64")]
     let default_archs = [String::from("arm64"), String::from("x86_64")];
     #[cfg(target_arch = "aarch64")]
     let default_archs = [String::from("arm64")];
-    bike.filter_and_process(
+    bike.filter_and_process_changed(
         src,
         &dest,
         |map| {
+            map.insert("project-dir", &dest);
+            map.insert("cargo-target-dir", config.app().cargo_target_dir());
             map.insert("file-groups", &source_dirs);
+            map.insert("enable-bitcode", enable_bitcode);
+            insert_pod_data(map, metadata);
             map.insert("ios-libraries", metadata.ios().libraries());
+            map.insert(
+                "ios-url-schemes",
+                ios_url_schemes(config.app().url_schemes()),
+            );
             map.insert("ios-frameworks", metadata.ios().frameworks());
             map.insert(
                 "ios-valid-archs",
@@ -133,10 +277,6 @@ pub fn gen(
             );
             map.insert("macos-vendor-sdks", metadata.macos().vendor_frameworks());
             map.insert("asset-catalogs", asset_catalogs);
-            map.insert("ios-pods", ios_pods);
-            map.insert("macos-pods", macos_pods);
-            map.insert("ios-pod-options", ios_pod_options);
-            map.insert("macos-pod-options", macos_pod_options);
             map.insert(
                 "ios-additional-targets",
                 metadata.ios().additional_targets(),
@@ -176,12 +316,41 @@ pub fn gen(
             );
         },
         filter.fun(),
+        &templating::snapshot_path(&dest),
     )
     .map_err(Error::TemplateProcessingFailed)?;
 
     ln::force_symlink_relative(config.app().asset_dir(), &dest, ln::TargetStyle::Directory)
         .map_err(Error::AssetDirSymlinkFailed)?;
 
+    extra_file::copy_all(bike, config.app().root_dir(), config.extra_files(), &dest)
+        .map_err(Error::ExtraFileCopyFailed)?;
+
+    // `xcodegen` turns `.lproj` dirs into localized resources on its own.
+    if let Some(localization) = config.app().localization() {
+        let strings = localization
+            .load(config.app().root_dir())
+            .map_err(Error::LocalizationFailed)?;
+        let strings_src = Pack::lookup_platform(STRINGS_TEMPLATE_PACK)
+            .map_err(Error::MissingPack)?
+            .expect_local();
+        for (locale, entries) in strings.iter() {
+            bike.filter_and_process(
+                &strings_src,
+                &dest,
+                |map| {
+                    map.insert("locale", locale);
+                    map.insert(
+                        "strings",
+                        localization::entries(entries, localization::apple_escape),
+                    );
+                },
+                filter.fun(),
+            )
+            .map_err(Error::TemplateProcessingFailed)?;
+        }
+    }
+
     // Create all asset catalog directories if they don't already exist
     for dir in asset_catalogs {
         std::fs::create_dir_all(dir).map_err(|cause| Error::DirectoryCreationFailed {
@@ -203,17 +372,141 @@ pub fn gen(
         .run()
         .map_err(Error::XcodegenFailed)?;
 
-    if !ios_pods.is_empty() || !macos_pods.is_empty() {
-        duct::cmd(
-            "pod",
-            [
-                "install",
-                &format!("--project-directory={}", dest.display()),
-            ],
-        )
-        .dup_stdio()
-        .run()
-        .map_err(Error::PodInstallFailed)?;
+    if has_pods {
+        pod_install(None, developer_dir.as_deref(), &dest)?;
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn podfile_omits_unsupported_platforms() {
+        let bike = templating::init(None);
+        let render = |ios_supported: bool, macos_supported: bool| {
+            bike.render(
+                include_str!("../../templates/platforms/xcode/Podfile.hbs"),
+                |map| {
+                    map.insert("app", serde_json::json!({ "name": "fun-app" }));
+                    map.insert(
+                        "apple",
+                        serde_json::json!({ "ios-version": "13.0", "macos-version": "11.0" }),
+                    );
+                    map.insert("ios-supported", ios_supported);
+                    map.insert("macos-supported", macos_supported);
+                    for key in [
+                        "ios-pods",
+                        "macos-pods",
+                        "ios-pod-options",
+                        "macos-pod-options",
+                    ] {
+                        map.insert(key, Vec::<String>::new());
+                    }
+                },
+            )
+            .unwrap()
+        };
+        let both = render(true, true);
+        assert!(
+            both.starts_with("target 'fun-app_iOS'") && both.contains("target 'fun-app_macOS'")
+        );
+        let ios = render(true, false);
+        assert!(ios.contains("target 'fun-app_iOS'") && !ios.contains("macOS"));
+        let macos = render(false, true);
+        assert!(!macos.contains("iOS") && macos.contains("target 'fun-app_macOS'"));
+    }
+
+    #[test]
+    fn project_yml_omits_unsupported_platforms() {
+        // `prefix-path` needs a whole `Config`, and what it prints doesn't
+        // matter here.
+        let template = include_str!("../../templates/platforms/xcode/project.yml.hbs")
+            .replace("{{prefix-path ", "{{quote ");
+        let bike = templating::init(None);
+        let render = |ios_supported: bool, macos_supported: bool| {
+            bike.render(&template, |map| {
+                map.insert(
+                    "app",
+                    serde_json::json!({
+                        "name": "fun-app",
+                        "stylized-name": "Fun App",
+                        "identifier": "com.example",
+                        "asset-dir": "assets",
+                    }),
+                );
+                map.insert(
+                    "apple",
+                    serde_json::json!({
+                        "ios-version": "13.0",
+                        "macos-version": "11.0",
+                        "bundle-version": "1",
+                        "bundle-version-short": "1.0",
+                        "extra-files": [],
+                    }),
+                );
+                map.insert("cargo-target-dir", "/workspace/target");
+                map.insert("file-groups", Vec::<String>::new());
+                map.insert("ios-valid-archs", ["arm64"]);
+                map.insert("ios-sim-arch", "arm64-sim");
+                map.insert("enable-bitcode", false);
+                for key in [
+                    "ios-command-line-arguments",
+                    "ios-post-build-scripts",
+                    "ios-post-compile-scripts",
+                ] {
+                    map.insert(key, Vec::<String>::new());
+                }
+                map.insert("ios-supported", ios_supported);
+                map.insert("macos-supported", macos_supported);
+            })
+            .unwrap()
+        };
+        let both = render(true, true);
+        assert!(both.contains("  deploymentTarget:\n    iOS: 13.0\n    macOS: 11.0\n"));
+        assert!(both.contains("targets:\n  fun-app_iOS:\n"));
+        assert!(both.contains("/workspace/target\"/arm64-sim/$(CONFIGURATION)"));
+        assert!(both.contains("/workspace/target\"/aarch64-apple-ios/${CONFIGURATION}/deps/"));
+        let ios = render(true, false);
+        assert!(ios.contains("  deploymentTarget:\n    iOS: 13.0\nfileGroups"));
+        let macos = render(false, true);
+        assert!(macos.contains("  deploymentTarget:\n    macOS: 11.0\nfileGroups"));
+        assert!(!macos.contains("targets:"));
+        let neither = render(false, false);
+        assert!(!neither.contains("deploymentTarget:") && !neither.contains("targets:"));
+    }
+
+    #[test]
+    fn custom_url_schemes_are_in_the_plist() {
+        let url_schemes = [
+            ("myapp", None),
+            ("myapp", Some("open")),
+            ("https", Some("example.com")),
+        ]
+        .into_iter()
+        .map(|(scheme, host)| {
+            UrlScheme::from_raw(app::url_scheme::Raw::WithHost {
+                scheme: scheme.to_owned(),
+                host: host.map(ToOwned::to_owned),
+            })
+            .unwrap()
+        })
+        .collect::<Vec<_>>();
+        // The rest of `project.yml` needs far more data than this cares about.
+        let template = include_str!("../../templates/platforms/xcode/project.yml.hbs");
+        let start = template.find("{{#if ios-url-schemes}}").unwrap();
+        let end = start + template[start..].find("{{/if}}").unwrap() + "{{/if}}".len();
+        let rendered = templating::init(None)
+            .render(&template[start..end], |map| {
+                map.insert(
+                    "app",
+                    serde_json::json!({ "identifier": "com.example.app" }),
+                );
+                map.insert("ios-url-schemes", ios_url_schemes(&url_schemes));
+            })
+            .unwrap();
+        assert!(rendered.contains("CFBundleURLName: com.example.app"));
+        assert!(rendered.contains(r#"CFBundleURLSchemes: ["myapp"]"#));
+    }
+}
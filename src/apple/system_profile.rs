@@ -1,5 +1,6 @@
 use crate::util;
-use once_cell_regex::regex;
+use once_cell_regex::{exports::regex::Regex, regex};
+use std::path::Path;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -27,22 +28,40 @@ pub struct DeveloperTools {
 }
 
 impl DeveloperTools {
-    pub fn new() -> Result<Self, Error> {
+    /// `developer_dir` is the one from `apple.xcode-path`, if it's set.
+    pub fn new(developer_dir: Option<&Path>) -> Result<Self, Error> {
+        // `system_profiler` only knows about the Xcode picked by
+        // `xcode-select`, so we ask `xcodebuild` when `apple.xcode-path` picks
+        // a different one.
+        if let Some(developer_dir) = developer_dir {
+            return Self::parse(
+                duct::cmd("xcodebuild", ["-version"])
+                    .env("DEVELOPER_DIR", developer_dir)
+                    .stderr_capture(),
+                regex!(r"\bXcode (?P<major>\d+)\.(?P<minor>\d+)\b"),
+            );
+        }
         // The `-xml` flag can be used to get this info in plist format, but
         // there don't seem to be any high quality plist crates, and parsing
         // XML sucks, we'll be lazy for now.
-        let command = duct::cmd("system_profiler", ["SPDeveloperToolsDataType"]).stderr_capture();
+        Self::parse(
+            duct::cmd("system_profiler", ["SPDeveloperToolsDataType"]).stderr_capture(),
+            regex!(r"\bVersion: (?P<major>\d+)\.(?P<minor>\d+)\b"),
+        )
+    }
+
+    fn parse(command: duct::Expression, version_regex: &Regex) -> Result<Self, Error> {
         let command_string = format!("{command:?}");
         let output = command.read().map_err(util::RunAndSearchError::from)?;
         if output.is_empty() {
             Err(Error::XcodeNotInstalled)
         } else {
-            let caps = regex!(r"\bVersion: (?P<major>\d+)\.(?P<minor>\d+)\b")
-                .captures(&output)
-                .ok_or_else(|| util::RunAndSearchError::SearchFailed {
+            let caps = version_regex.captures(&output).ok_or_else(|| {
+                util::RunAndSearchError::SearchFailed {
                     command: command_string,
                     output: output.to_owned(),
-                })?;
+                }
+            })?;
             let major = {
                 let raw = &caps["major"];
                 raw.parse::<u32>()
@@ -64,4 +83,25 @@ impl DeveloperTools {
             })
         }
     }
+
+    /// Xcode 14 deprecated bitcode, and building with it enabled has only
+    /// produced warnings and errors since.
+    pub fn supports_bitcode(&self) -> bool {
+        self.version < (14, 0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case((12, 5), true)]
+    #[case((13, 4), true)]
+    #[case((14, 0), false)]
+    #[case((15, 2), false)]
+    fn test_supports_bitcode(#[case] version: (u32, u32), #[case] expected: bool) {
+        assert_eq!(DeveloperTools { version }.supports_bitcode(), expected);
+    }
 }
@@ -1,10 +1,11 @@
 use super::{
-    config::{Config, Metadata},
+    config::{Config, Error as ConfigError, Metadata},
     system_profile::{self, DeveloperTools},
     version_number::VersionNumber,
     AuthCredentials,
 };
 use crate::{
+    config::toolchain,
     env::{Env, ExplicitEnv as _},
     opts::{self, NoiseLevel, Profile},
     target::TargetTrait,
@@ -19,7 +20,9 @@ use once_cell_regex::exports::once_cell::sync::OnceCell;
 use std::{
     collections::{BTreeMap, HashMap},
     ffi::{OsStr, OsString},
-    process::Command,
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, ExitStatus},
 };
 use thiserror::Error;
 
@@ -33,6 +36,8 @@ fn verbosity(noise_level: opts::NoiseLevel) -> Option<&'static str> {
 
 #[derive(Debug, Error)]
 pub enum VersionCheckError {
+    #[error(transparent)]
+    XcodePathInvalid(ConfigError),
     #[error("Failed to lookup Xcode version: {0}")]
     LookupFailed(system_profile::Error),
     #[error(
@@ -50,6 +55,7 @@ pub enum VersionCheckError {
 impl Reportable for VersionCheckError {
     fn report(&self) -> Report {
         match self {
+            Self::XcodePathInvalid(err) => Report::error("Failed to select Xcode", err),
             Self::LookupFailed(err) => Report::error("Failed to lookup Xcode version", err),
             Self::TooLow {
                 msg,
@@ -69,6 +75,7 @@ impl Reportable for VersionCheckError {
 #[derive(Debug)]
 pub enum CheckError {
     VersionCheckFailed(VersionCheckError),
+    ToolchainFailed(toolchain::Error),
     CargoCheckFailed(std::io::Error),
 }
 
@@ -76,6 +83,7 @@ impl Reportable for CheckError {
     fn report(&self) -> Report {
         match self {
             Self::VersionCheckFailed(err) => err.report(),
+            Self::ToolchainFailed(err) => err.report(),
             Self::CargoCheckFailed(err) => Report::error("Failed to run `cargo check`", err),
         }
     }
@@ -85,6 +93,8 @@ impl Reportable for CheckError {
 pub enum CompileLibError {
     #[error(transparent)]
     VersionCheckFailed(VersionCheckError),
+    #[error(transparent)]
+    ToolchainFailed(toolchain::Error),
     #[error("Failed to run `cargo build`: {0}")]
     CargoBuildFailed(std::io::Error),
 }
@@ -93,38 +103,133 @@ impl Reportable for CompileLibError {
     fn report(&self) -> Report {
         match self {
             Self::VersionCheckFailed(err) => err.report(),
+            Self::ToolchainFailed(err) => err.report(),
             Self::CargoBuildFailed(err) => Report::error("Failed to run `cargo build`", err),
         }
     }
 }
 
 #[derive(Debug, Error)]
-#[error(transparent)]
-pub struct BuildError(#[from] std::io::Error);
+pub enum CargoPassthroughError {
+    #[error(transparent)]
+    ToolchainFailed(toolchain::Error),
+    #[error("Failed to run `cargo`: {0}")]
+    CargoFailed(std::io::Error),
+}
+
+impl Reportable for CargoPassthroughError {
+    fn report(&self) -> Report {
+        match self {
+            Self::ToolchainFailed(err) => err.report(),
+            Self::CargoFailed(err) => Report::error("Failed to run cargo", err),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+#[error("Arch {arch:?} isn't in `valid-archs` ({})", .valid_archs.join(", "))]
+pub struct ArchNotAllowed {
+    pub arch: String,
+    pub valid_archs: Vec<String>,
+}
+
+impl Reportable for ArchNotAllowed {
+    fn report(&self) -> Report {
+        Report::action_request(
+            format!("Arch {:?} isn't in `valid-archs`", self.arch),
+            format!(
+                "Only {} can be built; add {:?} to `valid-archs` in the `package.metadata.cargo-apple.ios` section of your Cargo.toml, or pick a different target.",
+                self.valid_archs.join(", "),
+                self.arch
+            ),
+        )
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum BuildError {
+    #[error(transparent)]
+    ArchNotAllowed(#[from] ArchNotAllowed),
+    #[error(transparent)]
+    BuildFailed(#[from] std::io::Error),
+}
 
 impl Reportable for BuildError {
     fn report(&self) -> Report {
-        Report::error("Failed to build via `xcodebuild`", &self.0)
+        match self {
+            Self::ArchNotAllowed(err) => err.report(),
+            Self::BuildFailed(err) => Report::error("Failed to build via `xcodebuild`", err),
+        }
     }
 }
 
 #[derive(Debug, Error)]
 pub enum ArchiveError {
+    #[error(transparent)]
+    ArchNotAllowed(#[from] ArchNotAllowed),
     #[error("Failed to set app version number: {0}")]
     SetVersionFailed(WithWorkingDirError<std::io::Error>),
     #[error("Failed to archive via `xcodebuild`: {0}")]
     ArchiveFailed(#[from] std::io::Error),
+    #[error("Failed to look for dSYMs in {path}: {cause}")]
+    DsymLookupFailed {
+        path: PathBuf,
+        cause: std::io::Error,
+    },
 }
 
 impl Reportable for ArchiveError {
     fn report(&self) -> Report {
         match self {
+            Self::ArchNotAllowed(err) => err.report(),
             Self::SetVersionFailed(err) => Report::error("Failed to set app version number", err),
             Self::ArchiveFailed(err) => Report::error("Failed to archive via `xcodebuild`", err),
+            Self::DsymLookupFailed { .. } => Report::error("Failed to look for dSYMs", self),
         }
     }
 }
 
+#[derive(Debug, Error)]
+pub enum TestError {
+    #[error(transparent)]
+    ArchNotAllowed(#[from] ArchNotAllowed),
+    #[error("Failed to remove previous test results at {path:?}: {cause}")]
+    ClearResultsFailed {
+        path: PathBuf,
+        cause: std::io::Error,
+    },
+    #[error("Failed to test via `xcodebuild`: {0}")]
+    TestFailed(#[from] std::io::Error),
+}
+
+impl Reportable for TestError {
+    fn report(&self) -> Report {
+        match self {
+            Self::ArchNotAllowed(err) => err.report(),
+            Self::ClearResultsFailed { .. } => {
+                Report::error("Failed to remove previous test results", self)
+            }
+            Self::TestFailed(err) => Report::error("Failed to test via `xcodebuild`", err),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+#[error("Failed to run dSYM upload script for {path}: {cause}")]
+pub struct DsymUploadError {
+    pub path: PathBuf,
+    pub cause: std::io::Error,
+}
+
+impl Reportable for DsymUploadError {
+    fn report(&self) -> Report {
+        Report::error(
+            format!("Failed to run dSYM upload script for {:?}", self.path),
+            &self.cause,
+        )
+    }
+}
+
 #[derive(Debug, Error)]
 #[error(transparent)]
 pub struct ExportError(#[from] std::io::Error);
@@ -140,6 +245,7 @@ pub struct XcodebuildOptions {
     allow_provisioning_updates: bool,
     skip_codesign: bool,
     authentication_credentials: Option<AuthCredentials>,
+    valid_archs: Option<Vec<String>>,
 }
 
 impl XcodebuildOptions {
@@ -199,6 +305,14 @@ impl BuildConfig {
         Self::default()
     }
 
+    /// Restricts `ARCHS`/`VALID_ARCHS` to these Xcode archs, erroring if the
+    /// target being built isn't one of them. `None` leaves the project's
+    /// settings alone.
+    pub fn valid_archs(mut self, valid_archs: Option<&[String]>) -> Self {
+        self.xcodebuild_options.valid_archs = valid_archs.map(ToOwned::to_owned);
+        self
+    }
+
     pub fn allow_provisioning_updates(mut self) -> Self {
         self.xcodebuild_options.allow_provisioning_updates = true;
         self
@@ -227,6 +341,12 @@ impl ArchiveConfig {
         Self::default()
     }
 
+    /// See [`BuildConfig::valid_archs`].
+    pub fn valid_archs(mut self, valid_archs: Option<&[String]>) -> Self {
+        self.xcodebuild_options.valid_archs = valid_archs.map(ToOwned::to_owned);
+        self
+    }
+
     pub fn allow_provisioning_updates(mut self) -> Self {
         self.xcodebuild_options.allow_provisioning_updates = true;
         self
@@ -335,10 +455,41 @@ impl<'a> Target<'a> {
             .find(|target| target.arch == arch || target.alias == Some(arch))
     }
 
-    fn min_xcode_version_satisfied(&self) -> Result<(), VersionCheckError> {
+    // Xcode doesn't distinguish simulator archs, so `arm64-sim` is just `arm64`.
+    pub(crate) fn xcode_arch(&self) -> &'a str {
+        self.arch.strip_suffix("-sim").unwrap_or(self.arch)
+    }
+
+    /// Build settings enforcing `valid-archs`, which only apply to iOS.
+    fn arch_settings(&self, options: &XcodebuildOptions) -> Result<Vec<String>, ArchNotAllowed> {
+        match &options.valid_archs {
+            Some(valid_archs) if !self.is_macos() => {
+                let arch = self.xcode_arch();
+                if !valid_archs.iter().any(|valid| valid == arch) {
+                    return Err(ArchNotAllowed {
+                        arch: arch.to_owned(),
+                        valid_archs: valid_archs.clone(),
+                    });
+                }
+                // Only the target being built is built, even if others are
+                // allowed.
+                Ok(vec![
+                    format!("ARCHS={}", arch),
+                    format!("VALID_ARCHS={}", valid_archs.join(" ")),
+                ])
+            }
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    fn min_xcode_version_satisfied(&self, config: &Config) -> Result<(), VersionCheckError> {
         self.min_xcode_version
             .map(|(min_version, msg)| {
-                let tool_info = DeveloperTools::new().map_err(VersionCheckError::LookupFailed)?;
+                let developer_dir = config
+                    .developer_dir()
+                    .map_err(VersionCheckError::XcodePathInvalid)?;
+                let tool_info = DeveloperTools::new(developer_dir.as_deref())
+                    .map_err(VersionCheckError::LookupFailed)?;
                 let installed_version = tool_info.version;
                 if installed_version >= min_version {
                     Ok(())
@@ -364,7 +515,7 @@ impl<'a> Target<'a> {
         } else {
             metadata.ios()
         };
-        self.min_xcode_version_satisfied().map(|()| {
+        self.min_xcode_version_satisfied(config).map(|()| {
             CargoCommand::new(subcommand)
                 .with_package(Some(config.app().name()))
                 .with_manifest_path(Some(config.app().manifest_path()))
@@ -375,6 +526,28 @@ impl<'a> Target<'a> {
         })
     }
 
+    /// Runs `cargo` with `args` as given, in the env builds for this target
+    /// use. `args` still needs `--target` to actually build for it.
+    pub fn cargo_passthrough(
+        &self,
+        config: &Config,
+        env: &Env,
+        args: &[String],
+    ) -> Result<ExitStatus, CargoPassthroughError> {
+        util::cargo_passthrough(args, env)
+            .vars(
+                config
+                    .toolchain()
+                    .vars(self.triple)
+                    .map_err(CargoPassthroughError::ToolchainFailed)?,
+            )
+            .vars(config.target_env().vars(self.triple))
+            .unchecked()
+            .run()
+            .map(|output| output.status)
+            .map_err(CargoPassthroughError::CargoFailed)
+    }
+
     pub fn check(
         &self,
         config: &Config,
@@ -386,6 +559,13 @@ impl<'a> Target<'a> {
             .map_err(CheckError::VersionCheckFailed)?
             .with_verbose(noise_level.pedantic())
             .build(env)
+            .vars(
+                config
+                    .toolchain()
+                    .vars(self.triple)
+                    .map_err(CheckError::ToolchainFailed)?,
+            )
+            .vars(config.target_env().vars(self.triple))
             .run()
             .map_err(CheckError::CargoCheckFailed)?;
         Ok(())
@@ -417,6 +597,13 @@ impl<'a> Target<'a> {
                 Ok(())
             })
             .vars(cc_env)
+            .vars(
+                config
+                    .toolchain()
+                    .vars(self.triple)
+                    .map_err(CompileLibError::ToolchainFailed)?,
+            )
+            .vars(config.target_env().vars(self.triple))
             .run()
             .map_err(CompileLibError::CargoBuildFailed)?;
         Ok(())
@@ -439,12 +626,14 @@ impl<'a> Target<'a> {
         } else {
             None
         };
+        let arch_settings = self.arch_settings(&build_config.xcodebuild_options)?;
         let args: Vec<OsString> = vec![];
         duct::cmd("xcodebuild", args)
             .full_env(env.explicit_env())
             .env("FORCE_COLOR", "--force-color")
             .before_spawn(move |cmd| {
                 build_config.xcodebuild_options.args_for(cmd);
+                cmd.args(&arch_settings);
 
                 if let Some(a) = &arch {
                     cmd.args(["-arch", a]);
@@ -472,7 +661,8 @@ impl<'a> Target<'a> {
         profile: opts::Profile,
         build_number: Option<VersionNumber>,
         archive_config: ArchiveConfig,
-    ) -> Result<(), ArchiveError> {
+    ) -> Result<Vec<PathBuf>, ArchiveError> {
+        let arch_settings = self.arch_settings(&archive_config.xcodebuild_options)?;
         if let Some(build_number) = build_number {
             util::with_working_dir(config.project_dir(), || {
                 duct::cmd(
@@ -500,6 +690,7 @@ impl<'a> Target<'a> {
             .full_env(env.explicit_env())
             .before_spawn(move |cmd| {
                 archive_config.xcodebuild_options.args_for(cmd);
+                cmd.args(&arch_settings);
 
                 if let Some(v) = verbosity(noise_level) {
                     cmd.arg(v);
@@ -522,6 +713,84 @@ impl<'a> Target<'a> {
             .start()?
             .wait()?;
 
+        find_dsyms(&config.archive_path().join("dSYMs"))
+    }
+
+    /// Runs the scheme's tests on the simulator or device with `udid`, writing
+    /// the results to `config.test_results_path()`. Returns whether
+    /// `xcodebuild` succeeded; failing tests don't make this return an error,
+    /// since they're meant to be read from the results.
+    pub fn test(
+        &self,
+        config: &Config,
+        env: &Env,
+        noise_level: opts::NoiseLevel,
+        profile: opts::Profile,
+        udid: &str,
+        build_config: BuildConfig,
+    ) -> Result<bool, TestError> {
+        let arch_settings = self.arch_settings(&build_config.xcodebuild_options)?;
+        let results_path = config.test_results_path();
+        // `xcodebuild` refuses to overwrite an existing result bundle.
+        if results_path.exists() {
+            fs::remove_dir_all(&results_path).map_err(|cause| TestError::ClearResultsFailed {
+                path: results_path.clone(),
+                cause,
+            })?;
+        }
+        let configuration = profile.as_str();
+        let scheme = config.scheme();
+        let workspace_path = config.workspace_path();
+        let sdk = self.sdk.to_string();
+        let destination = format!("id={}", udid);
+        let args: Vec<OsString> = vec![];
+        let output = duct::cmd("xcodebuild", args)
+            .full_env(env.explicit_env())
+            .before_spawn(move |cmd| {
+                build_config.xcodebuild_options.args_for(cmd);
+                cmd.args(&arch_settings);
+
+                if let Some(v) = verbosity(noise_level) {
+                    cmd.arg(v);
+                }
+                cmd.args(["-scheme", &scheme])
+                    .arg("-workspace")
+                    .arg(&workspace_path)
+                    .args(["-sdk", &sdk])
+                    .args(["-configuration", configuration])
+                    .args(["-destination", &destination])
+                    .arg("-resultBundlePath")
+                    .arg(&results_path)
+                    .arg("test");
+                Ok(())
+            })
+            .dup_stdio()
+            .unchecked()
+            .run()?;
+        Ok(output.status.success())
+    }
+
+    /// Runs the configured `dsym-upload-script` (if any) once per dSYM.
+    pub fn upload_dsyms(
+        &self,
+        config: &Config,
+        env: &Env,
+        dsyms: &[PathBuf],
+    ) -> Result<(), DsymUploadError> {
+        let Some(script) = config.dsym_upload_script() else {
+            return Ok(());
+        };
+        for dsym in dsyms {
+            duct::cmd("sh", ["-c", &dsym_upload_command(script, dsym)])
+                .vars(env.explicit_env())
+                .env("DSYM_PATH", dsym)
+                .dup_stdio()
+                .run()
+                .map_err(|cause| DsymUploadError {
+                    path: dsym.clone(),
+                    cause,
+                })?;
+        }
         Ok(())
     }
 
@@ -533,9 +802,7 @@ impl<'a> Target<'a> {
         export_config: ExportConfig,
     ) -> Result<(), ExportError> {
         // Super fun discrepancy in expectation of `-archivePath` value
-        let archive_path = config
-            .archive_dir()
-            .join(format!("{}.xcarchive", config.scheme()));
+        let archive_path = config.archive_path();
         let export_dir = config.export_dir();
         let export_plist_path = config.export_plist_path();
 
@@ -565,3 +832,77 @@ impl<'a> Target<'a> {
         Ok(())
     }
 }
+
+// The path is quoted, since it's spliced into a shell command.
+fn dsym_upload_command(script: &str, dsym: &Path) -> String {
+    script.replace("<dsym-path>", &util::shell_quote(&dsym.to_string_lossy()))
+}
+
+// An archive has one dSYM for the app and one for each embedded framework.
+fn find_dsyms(dsyms_dir: &Path) -> Result<Vec<PathBuf>, ArchiveError> {
+    if !dsyms_dir.is_dir() {
+        // Debug builds don't generate dSYMs by default.
+        return Ok(Vec::new());
+    }
+    let mut dsyms = fs::read_dir(dsyms_dir)
+        .and_then(|entries| {
+            entries
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .map_err(|cause| ArchiveError::DsymLookupFailed {
+            path: dsyms_dir.to_owned(),
+            cause,
+        })?;
+    dsyms.retain(|path| path.extension() == Some("dSYM".as_ref()));
+    dsyms.sort();
+    Ok(dsyms)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn dsym_paths_are_quoted() {
+        assert_eq!(
+            dsym_upload_command(
+                "upload <dsym-path>",
+                Path::new("/My Archive/$(reboot);.dSYM")
+            ),
+            "upload '/My Archive/$(reboot);.dSYM'"
+        );
+    }
+
+    #[test]
+    fn valid_archs_become_build_settings() {
+        let valid_archs = vec!["arm64".to_owned(), "x86_64".to_owned()];
+        let options = BuildConfig::new()
+            .valid_archs(Some(&valid_archs))
+            .xcodebuild_options;
+        let target = Target::for_arch("arm64-sim").unwrap();
+        assert_eq!(
+            target.arch_settings(&options).unwrap(),
+            ["ARCHS=arm64", "VALID_ARCHS=arm64 x86_64"]
+        );
+        assert!(Target::for_arch("arm64")
+            .unwrap()
+            .arch_settings(&BuildConfig::new().xcodebuild_options)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn excluded_arch_is_rejected() {
+        let valid_archs = vec!["arm64".to_owned()];
+        let options = ArchiveConfig::new()
+            .valid_archs(Some(&valid_archs))
+            .xcodebuild_options;
+        let err = Target::for_arch("x86_64")
+            .unwrap()
+            .arch_settings(&options)
+            .unwrap_err();
+        assert_eq!(err.arch, "x86_64");
+        assert_eq!(err.valid_archs, valid_archs);
+    }
+}
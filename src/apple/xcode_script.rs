@@ -0,0 +1,93 @@
+use super::config::Config;
+use crate::{
+    env::Env,
+    util::{
+        self,
+        cli::{Report, Reportable},
+    },
+};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("{0} has no file name")]
+    XcodeEnvPathInvalid(PathBuf),
+    #[error("Failed to write {path}: {cause}")]
+    XcodeEnvWriteFailed { path: PathBuf, cause: io::Error },
+}
+
+impl Reportable for Error {
+    fn report(&self) -> Report {
+        Report::error("Failed to generate Xcode script", self)
+    }
+}
+
+// Mirrors the "Build Rust Code" phase in the generated `project.yml`, except
+// that the search paths may legitimately be empty in other projects.
+static INVOCATION: &str = r#"cd "${CARGO_MOBILE_PROJECT_DIR:?}"
+"${CARGO_APPLE:?}" xcode-script -v --platform "${PLATFORM_DISPLAY_NAME:?}" --sdk-root "${SDKROOT:?}" --framework-search-paths "${FRAMEWORK_SEARCH_PATHS}" --header-search-paths "${HEADER_SEARCH_PATHS}" --gcc-preprocessor-definitions "${GCC_PREPROCESSOR_DEFINITIONS}" --configuration "${CONFIGURATION:?}" ${FORCE_COLOR} ${ARCHS:?}
+"#;
+
+// The `PATH` Xcode gives scripts is missing anything added by the user's
+// profile, so the current one is baked in, along with where `cargo-apple` is.
+fn exports(config: &Config, env: &Env) -> String {
+    let cargo_apple = which::which("cargo-apple").unwrap_or_else(|err| {
+        log::warn!(
+            "couldn't find `cargo-apple`, so it'll be looked up on `PATH`: {}",
+            err
+        );
+        "cargo-apple".into()
+    });
+    [
+        ("PATH", env.path().to_string_lossy().into_owned()),
+        ("CARGO_APPLE", cargo_apple.to_string_lossy().into_owned()),
+        (
+            "CARGO_MOBILE_PROJECT_DIR",
+            config.project_dir().to_string_lossy().into_owned(),
+        ),
+    ]
+    .iter()
+    .map(|(key, value)| format!("export {}={}\n", key, util::shell_quote(value)))
+    .collect()
+}
+
+/// The contents of a Run Script build phase that builds the Rust library, for
+/// Xcode projects that cargo-mobile2 didn't generate.
+///
+/// If `xcode_env` is given, the env the script needs is written there instead
+/// of into the script, and the script sources it from the same file name in
+/// `SRCROOT`, like React Native's `.xcode.env`.
+pub fn gen(config: &Config, env: &Env, xcode_env: Option<&Path>) -> Result<String, Error> {
+    let mut script = format!(
+        "# Builds lib{}.a into {} for each of `ARCHS`.\n# Generated by `cargo apple gen-xcode-script`.\n",
+        config.app().lib_name(),
+        config.app().cargo_target_dir().display()
+    );
+    let exports = exports(config, env);
+    match xcode_env {
+        Some(path) => {
+            let file_name = path
+                .file_name()
+                .ok_or_else(|| Error::XcodeEnvPathInvalid(path.to_owned()))?;
+            fs::write(
+                path,
+                format!("# Generated by `cargo apple gen-xcode-script`.\n{exports}"),
+            )
+            .map_err(|cause| Error::XcodeEnvWriteFailed {
+                path: path.to_owned(),
+                cause,
+            })?;
+            script.push_str(&format!(
+                ". \"${{SRCROOT:?}}/{}\"\n",
+                file_name.to_string_lossy()
+            ));
+        }
+        None => script.push_str(&exports),
+    }
+    script.push_str(INVOCATION);
+    Ok(script)
+}
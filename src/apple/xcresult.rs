@@ -0,0 +1,292 @@
+use crate::{
+    env::{Env, ExplicitEnv as _},
+    util::cli::{Report, Reportable},
+    DuctExpressionExt,
+};
+use serde::Serialize;
+use serde_json::Value as Json;
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+use thiserror::Error;
+
+/// How `cargo apple test` prints its summary.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+impl OutputFormat {
+    pub const VARIANTS: [&'static str; 2] = ["text", "json"];
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            _ => Err(format!(
+                "{:?} isn't an output format; expected one of {}",
+                s,
+                Self::VARIANTS.join(", ")
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Failed to read {path:?} with `xcresulttool`: {cause}")]
+    ToolFailed {
+        path: PathBuf,
+        cause: std::io::Error,
+    },
+    #[error("`xcresulttool` output wasn't valid JSON: {0}")]
+    JsonInvalid(#[from] serde_json::Error),
+    #[error(
+        "`xcresulttool` output didn't look like any format we know of; try updating cargo-mobile2"
+    )]
+    FormatUnrecognized,
+}
+
+impl Reportable for Error {
+    fn report(&self) -> Report {
+        Report::error("Failed to summarize test results", self)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct Failure {
+    /// `None` for failures that aren't tied to a test, like build errors.
+    pub test: Option<String>,
+    pub message: String,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct Summary {
+    pub total: u64,
+    pub passed: u64,
+    pub failed: u64,
+    pub skipped: u64,
+    pub failures: Vec<Failure>,
+}
+
+impl Summary {
+    pub fn succeeded(&self) -> bool {
+        self.failed == 0 && self.failures.is_empty()
+    }
+
+    pub fn print(&self, format: OutputFormat) {
+        match format {
+            OutputFormat::Text => println!("{}", self),
+            OutputFormat::Json => println!("{}", serde_json::json!(self)),
+        }
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} passed, {} failed, {} skipped ({} total)",
+            if self.succeeded() {
+                "Tests passed"
+            } else {
+                "Tests failed"
+            },
+            self.passed,
+            self.failed,
+            self.skipped,
+            self.total
+        )?;
+        for failure in &self.failures {
+            match &failure.test {
+                Some(test) => write!(f, "\n  {}: {}", test, failure.message)?,
+                None => write!(f, "\n  {}", failure.message)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+// Xcode 16 added `get test-results summary`, which is much simpler to read.
+fn parse_summary(json: &Json) -> Option<Summary> {
+    let count = |key| json.get(key)?.as_u64();
+    let failures = json
+        .get("testFailures")
+        .and_then(Json::as_array)
+        .into_iter()
+        .flatten()
+        .map(|failure| {
+            let field = |key| failure.get(key).and_then(Json::as_str);
+            Failure {
+                test: field("testName").map(|test| match field("targetName") {
+                    Some(target) => format!("{}.{}", target, test),
+                    None => test.to_owned(),
+                }),
+                message: field("failureText").unwrap_or_default().to_owned(),
+            }
+        })
+        .collect();
+    Some(Summary {
+        total: count("totalTestCount")?,
+        passed: count("passedTests").unwrap_or_default(),
+        failed: count("failedTests").unwrap_or_default(),
+        skipped: count("skippedTests").unwrap_or_default(),
+        failures,
+    })
+}
+
+// Older versions only have the action record, where every value is wrapped
+// like `{"_type": {"_name": "Int"}, "_value": "3"}` and arrays are in
+// `_values`.
+fn parse_legacy(json: &Json) -> Option<Summary> {
+    json.get("_type")?;
+    let value = |json: &Json, path: &[&str]| {
+        path.iter()
+            .try_fold(json, |json, key| json.get(key))?
+            .get("_value")?
+            .as_str()
+            .map(ToOwned::to_owned)
+    };
+    let count = |key| {
+        value(json, &["metrics", key])
+            .and_then(|count| count.parse::<u64>().ok())
+            .unwrap_or_default()
+    };
+    let summaries = |key| {
+        json.pointer(&format!("/issues/{}/_values", key))
+            .and_then(Json::as_array)
+            .cloned()
+            .unwrap_or_default()
+    };
+    let failures = summaries("testFailureSummaries")
+        .iter()
+        .map(|failure| Failure {
+            test: value(failure, &["testCaseName"]),
+            message: value(failure, &["message"]).unwrap_or_default(),
+        })
+        .chain(summaries("errorSummaries").iter().map(|error| Failure {
+            test: None,
+            message: value(error, &["message"]).unwrap_or_default(),
+        }))
+        .collect();
+    let (total, failed, skipped) = (
+        count("testsCount"),
+        count("testsFailedCount"),
+        count("testsSkippedCount"),
+    );
+    Some(Summary {
+        total,
+        passed: total.saturating_sub(failed + skipped),
+        failed,
+        skipped,
+        failures,
+    })
+}
+
+/// Parses the output of either `xcresulttool` format.
+pub fn parse(json: &str) -> Result<Summary, Error> {
+    let json = serde_json::from_str(json)?;
+    parse_summary(&json)
+        .or_else(|| parse_legacy(&json))
+        .ok_or(Error::FormatUnrecognized)
+}
+
+fn xcresulttool(env: &Env, args: &[&str], path: &Path) -> std::io::Result<String> {
+    duct::cmd("xcrun", ["xcresulttool"].iter().chain(args))
+        .vars(env.explicit_env())
+        .before_spawn({
+            let path = path.to_owned();
+            move |cmd| {
+                cmd.arg("--path").arg(&path);
+                Ok(())
+            }
+        })
+        .stderr_capture()
+        .read()
+}
+
+/// Summarizes the test results in the `.xcresult` bundle at `path`.
+pub fn summarize(env: &Env, path: &Path) -> Result<Summary, Error> {
+    let json = xcresulttool(env, &["get", "test-results", "summary", "--compact"], path)
+        .or_else(|err| {
+            log::info!(
+                "`xcresulttool get test-results` failed, so falling back to the legacy format: {}",
+                err
+            );
+            xcresulttool(env, &["get", "--format", "json"], path)
+        })
+        .map_err(|cause| Error::ToolFailed {
+            path: path.to_owned(),
+            cause,
+        })?;
+    parse(&json)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn both_formats_are_summarized() {
+        let summary = parse(
+            r#"{
+                "result": "Failed",
+                "totalTestCount": 3,
+                "passedTests": 1,
+                "failedTests": 1,
+                "skippedTests": 1,
+                "testFailures": [{
+                    "testName": "testExample()",
+                    "targetName": "AppTests",
+                    "failureText": "XCTAssertTrue failed"
+                }]
+            }"#,
+        )
+        .unwrap();
+        let expected = Summary {
+            total: 3,
+            passed: 1,
+            failed: 1,
+            skipped: 1,
+            failures: vec![Failure {
+                test: Some("AppTests.testExample()".into()),
+                message: "XCTAssertTrue failed".into(),
+            }],
+        };
+        assert_eq!(summary, expected);
+        assert!(!summary.succeeded());
+
+        let legacy = parse(
+            r#"{
+                "_type": { "_name": "ActionsInvocationRecord" },
+                "metrics": {
+                    "testsCount": { "_type": { "_name": "Int" }, "_value": "3" },
+                    "testsFailedCount": { "_type": { "_name": "Int" }, "_value": "1" },
+                    "testsSkippedCount": { "_type": { "_name": "Int" }, "_value": "1" }
+                },
+                "issues": {
+                    "testFailureSummaries": {
+                        "_values": [{
+                            "testCaseName": { "_value": "AppTests.testExample()" },
+                            "message": { "_value": "XCTAssertTrue failed" }
+                        }]
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(legacy, expected);
+
+        assert!(matches!(
+            parse(r#"{ "unexpected": true }"#),
+            Err(Error::FormatUnrecognized)
+        ));
+    }
+}
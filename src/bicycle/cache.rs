@@ -0,0 +1,173 @@
+use serde_json::value::{Map, Value as Json};
+use sha2::{Digest as _, Sha256};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+/// The hex SHA-256 of `parts`, which unlike `DefaultHasher` is stable across
+/// Rust releases, so it's safe to persist. Parts are length-prefixed so that
+/// moving text from one part to the next changes the hash.
+pub(super) fn hash(parts: &[&str]) -> String {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update((part.len() as u64).to_le_bytes());
+        hasher.update(part);
+    }
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// An on-disk cache of rendered templates, keyed by the hash of the helpers
+/// and escape fn rendering it, the template contents, and the data it's
+/// rendered with.
+///
+/// This assumes that rendering output depends on nothing else, so helpers
+/// that look outside of the data they're given (i.e. at the clock or the
+/// filesystem) won't be re-run on a cache hit.
+#[derive(Clone, Debug)]
+pub struct RenderCache {
+    dir: PathBuf,
+}
+
+impl RenderCache {
+    /// How long an entry is kept after it was last used.
+    pub const MAX_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Deletes the entries that haven't been used in `max_age`, along with
+    /// any temporary files left behind by interrupted writes.
+    pub fn prune(&self, max_age: Duration) -> io::Result<()> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(err),
+        };
+        let now = SystemTime::now();
+        for entry in entries {
+            let entry = entry?;
+            let modified = entry.metadata()?.modified()?;
+            if now.duration_since(modified).unwrap_or_default() > max_age {
+                log::debug!("pruning render cache entry {:?}", entry.path());
+                fs::remove_file(entry.path())?;
+            }
+        }
+        Ok(())
+    }
+
+    pub(super) fn key(
+        &self,
+        renderer_id: &str,
+        template: &str,
+        data: &Map<String, Json>,
+    ) -> String {
+        let data = serde_json::to_string(data).expect("developer error: data wasn't valid JSON");
+        hash(&[renderer_id, template, &data])
+    }
+
+    pub(super) fn get(&self, key: &str) -> Option<String> {
+        let path = self.dir.join(key);
+        let rendered = fs::read_to_string(&path).ok()?;
+        // Hits count as uses, so that pruning only drops stale entries.
+        if let Err(err) = fs::File::options()
+            .write(true)
+            .open(&path)
+            .and_then(|file| file.set_modified(SystemTime::now()))
+        {
+            log::debug!("failed to touch render cache entry {:?}: {}", path, err);
+        }
+        Some(rendered)
+    }
+
+    pub(super) fn put(&self, key: &str, rendered: &str) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        // Write to a temporary file first so that concurrent runs never see a
+        // partially written entry.
+        let tmp = self.dir.join(format!("{}.{}.tmp", key, std::process::id()));
+        fs::write(&tmp, rendered)?;
+        fs::rename(&tmp, self.dir.join(key))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bicycle::{
+        handlebars::{handlebars_helper, HelperDef},
+        Bicycle, EscapeFn, JsonMap,
+    };
+    use std::{
+        collections::HashMap,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    static RENDERS: AtomicUsize = AtomicUsize::new(0);
+
+    handlebars_helper!(counted: |s: str| {
+        RENDERS.fetch_add(1, Ordering::SeqCst);
+        s.to_owned()
+    });
+
+    // Keys outlive the process, so they can't change between builds.
+    #[test]
+    fn hash_is_stable() {
+        assert_eq!(
+            hash(&["hello"]),
+            "fe745503750fdbf3e6ef676d16d85ee0d63626c594222f7e991908bdffef7ac9"
+        );
+        assert_ne!(hash(&["ab", "c"]), hash(&["a", "bc"]));
+    }
+
+    #[test]
+    fn cache_hit_skips_rendering() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let mut helpers = HashMap::<_, Box<dyn HelperDef + Send + Sync>>::new();
+        helpers.insert("counted", Box::new(counted));
+        let bike = Bicycle::new(EscapeFn::None, helpers, JsonMap::default())
+            .with_cache(RenderCache::new(dir));
+        let render = || {
+            bike.render("Hello {{counted name}}!", |map| map.insert("name", "Asuka"))
+                .unwrap()
+        };
+
+        let miss = render();
+        let hit = render();
+        assert_eq!(miss, "Hello Asuka!");
+        assert_eq!(hit, miss);
+        assert_eq!(RENDERS.load(Ordering::SeqCst), 1);
+
+        // Different data is a different entry.
+        bike.render("Hello {{counted name}}!", |map| map.insert("name", "Rei"))
+            .unwrap();
+        assert_eq!(RENDERS.load(Ordering::SeqCst), 2);
+
+        // So is a different escape fn, even with the same helpers.
+        let mut helpers = HashMap::<_, Box<dyn HelperDef + Send + Sync>>::new();
+        helpers.insert("counted", Box::new(counted));
+        let html = Bicycle::new(EscapeFn::Html, helpers, JsonMap::default())
+            .with_cache(RenderCache::new(dir));
+        html.render("Hello {{counted name}}!", |map| map.insert("name", "Asuka"))
+            .unwrap();
+        assert_eq!(RENDERS.load(Ordering::SeqCst), 3);
+
+        // Entries that were just used survive pruning, but not forever.
+        let cache = RenderCache::new(dir);
+        cache.prune(RenderCache::MAX_AGE).unwrap();
+        assert_eq!(fs::read_dir(dir).unwrap().count(), 3);
+        std::thread::sleep(Duration::from_millis(10));
+        cache.prune(Duration::ZERO).unwrap();
+        assert_eq!(fs::read_dir(dir).unwrap().count(), 0);
+    }
+}
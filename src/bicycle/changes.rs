@@ -0,0 +1,291 @@
+use super::JsonMap;
+use serde::{Deserialize, Serialize};
+use serde_json::value::{Map, Value as Json};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Paths into the template data whose values changed, like
+/// `app.stylized-name`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ChangedKeys(BTreeSet<String>);
+
+impl<S: Into<String>> FromIterator<S> for ChangedKeys {
+    fn from_iter<I: IntoIterator<Item = S>>(iter: I) -> Self {
+        Self(iter.into_iter().map(Into::into).collect())
+    }
+}
+
+fn diff(path: &str, old: Option<&Json>, new: Option<&Json>, changed: &mut BTreeSet<String>) {
+    match (old, new) {
+        (Some(Json::Object(old)), Some(Json::Object(new))) => {
+            for key in old.keys().chain(new.keys()) {
+                diff(
+                    &format!("{}.{}", path, key),
+                    old.get(key),
+                    new.get(key),
+                    changed,
+                );
+            }
+        }
+        (old, new) if old != new => {
+            changed.insert(path.to_owned());
+        }
+        _ => (),
+    }
+}
+
+impl ChangedKeys {
+    /// Compares two sets of data down to the values that aren't objects, so
+    /// changing one field of `app` doesn't mark all of `app` as changed.
+    pub fn between(old: &JsonMap, new: &JsonMap) -> Self {
+        let mut changed = BTreeSet::new();
+        for key in old.0.keys().chain(new.0.keys()) {
+            diff(key, old.0.get(key), new.0.get(key), &mut changed);
+        }
+        Self(changed)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(String::as_str)
+    }
+
+    // A reference depends on a change if either contains the other, e.g.
+    // `{{#with app}}` depends on `app.name`, and `{{app.name}}` depends on
+    // `app` being replaced wholesale.
+    fn affects(&self, reference: &str) -> bool {
+        let within = |outer: &str, inner: &str| {
+            inner
+                .strip_prefix(outer)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+        };
+        self.0
+            .iter()
+            .any(|key| within(key, reference) || within(reference, key))
+    }
+
+    /// Whether rendering `template` could produce something different now.
+    /// This is answered with a lightweight scan for the data paths the
+    /// template mentions, so anything the scan can't account for, like
+    /// partials, `@root`, `../`, or any of `context_helpers` (which read data
+    /// that isn't passed to them), is assumed to be affected.
+    pub fn affect_template<'a>(
+        &self,
+        template: &str,
+        mut context_helpers: impl Iterator<Item = &'a str>,
+    ) -> bool {
+        match references(template) {
+            Some(references) => {
+                context_helpers.any(|helper| references.contains(helper))
+                    || references.iter().any(|reference| self.affects(reference))
+            }
+            None => true,
+        }
+    }
+}
+
+/// What a dir was last generated from, which is saved so that the next run
+/// can tell which templates need to be rendered again.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub(super) struct Snapshot {
+    pub data: Map<String, Json>,
+    /// The hash of each template's contents, since a template that changed
+    /// needs to be rendered again even if its data didn't.
+    pub templates: BTreeMap<PathBuf, String>,
+}
+
+impl Snapshot {
+    /// Anything that keeps this from loading just means rendering everything.
+    pub fn load(path: &Path) -> Option<Self> {
+        let contents = fs::read_to_string(path).ok()?;
+        serde_json::from_str(&contents)
+            .map_err(|err| log::warn!("ignoring invalid snapshot {:?}: {}", path, err))
+            .ok()
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(
+            path,
+            serde_json::to_string(self).expect("developer error: snapshot wasn't valid JSON"),
+        )
+    }
+}
+
+// Splits the inside of a mustache into its words, leaving out string literals
+// and the keys of hash arguments.
+fn words(expr: &str) -> impl Iterator<Item = &str> {
+    let mut words = Vec::new();
+    let mut rest = expr;
+    while let Some(start) = rest.find(|c: char| !c.is_whitespace() && c != '(' && c != ')') {
+        rest = &rest[start..];
+        let end = rest
+            .find(|c: char| c.is_whitespace() || c == '(' || c == ')')
+            .unwrap_or(rest.len());
+        // Hash arguments, like `sep=", "`, only depend on their value.
+        if let Some(eq) = rest[..end].find('=') {
+            rest = &rest[eq + 1..];
+            continue;
+        }
+        if let Some(quote) = rest.chars().next().filter(|c| *c == '"' || *c == '\'') {
+            rest = rest[1..]
+                .find(quote)
+                .map(|end| &rest[end + 2..])
+                .unwrap_or_default();
+            continue;
+        }
+        words.push(&rest[..end]);
+        rest = &rest[end..];
+    }
+    words.into_iter()
+}
+
+/// Finds the data paths mentioned in `template`, with `/` separators
+/// normalized to `.`, or `None` if that can't be done reliably. Helper names
+/// can't be told apart from data paths, so they're included too, which at
+/// worst causes an unnecessary render.
+pub(super) fn references(template: &str) -> Option<BTreeSet<String>> {
+    let mut references = BTreeSet::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        rest = &rest[start + 2..];
+        // Raw blocks can't be scanned the same way, since their contents
+        // aren't expressions.
+        if rest.starts_with("{{") && !rest.starts_with("{{{") {
+            return None;
+        }
+        let expr_start = rest.trim_start_matches('{');
+        // Long comments can contain `}}`.
+        if let Some(comment) = expr_start.trim_start_matches('~').strip_prefix("!--") {
+            let end = comment.find("--")?;
+            let close = end + comment[end..].find("}}")?;
+            rest = &comment[close + 2..];
+            continue;
+        }
+        let end = expr_start.find("}}")?;
+        let expr = expr_start[..end].trim_matches('~').trim();
+        rest = &expr_start[end + 2..];
+        if expr.starts_with('!') {
+            continue;
+        }
+        // Partials and inline partials render templates we can't see.
+        if expr.starts_with('>') || expr.starts_with("#>") || expr.starts_with("#*") {
+            return None;
+        }
+        let expr = expr.trim_start_matches(['#', '/', '^', '&']);
+        for word in words(expr) {
+            if word.starts_with("@root") || word.contains("../") || word.contains('[') {
+                return None;
+            }
+            let relative = word == "this"
+                || word.starts_with("this.")
+                || word.starts_with("this/")
+                || word.starts_with("./")
+                || word.starts_with('@');
+            let literal = word.is_empty()
+                || word.starts_with('|')
+                || word.ends_with('|')
+                || matches!(
+                    word,
+                    "as" | "else" | "true" | "false" | "null" | "undefined"
+                )
+                || word.starts_with(|c: char| c.is_ascii_digit() || c == '-');
+            if !relative && !literal {
+                references.insert(word.replace('/', "."));
+            }
+        }
+    }
+    Some(references)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("Hello {{app.stylized-name}}!", Some(&["app.stylized-name"][..]))]
+    #[case(
+        "{{#each ios-libraries}}{{this}} {{@index}}{{/each}}",
+        Some(&["each", "ios-libraries"][..])
+    )]
+    #[case(
+        "{{~#if has-code}}{{quote (snake-case app.name) sep=\", \" x=app/lib-name}}{{/if}}",
+        Some(&["app.lib-name", "app.name", "has-code", "if", "quote", "snake-case"][..])
+    )]
+    #[case("{{!-- {{secret}} --}}{{{raw}}}", Some(&["raw"][..]))]
+    #[case("{{> header}}", None)]
+    #[case("{{#each list}}{{../outer}}{{/each}}", None)]
+    #[case("{{@root.app}}", None)]
+    #[case("{{{{raw}}}}{{x}}{{{{/raw}}}}", None)]
+    fn test_references(#[case] template: &str, #[case] expected: Option<&[&str]>) {
+        assert_eq!(
+            references(template),
+            expected.map(|expected| expected.iter().map(|s| s.to_string()).collect())
+        );
+    }
+
+    #[test]
+    fn only_templates_mentioning_a_change_are_affected() {
+        let mut old = JsonMap::default();
+        old.insert(
+            "app",
+            serde_json::json!({ "name": "app", "stylized-name": "App" }),
+        );
+        old.insert("targets", ["aarch64"]);
+        let mut new = old.clone();
+        new.insert(
+            "app",
+            serde_json::json!({ "name": "app", "stylized-name": "Cool App" }),
+        );
+        let changed = ChangedKeys::between(&old, &new);
+        assert_eq!(changed, ChangedKeys::from_iter(["app.stylized-name"]));
+
+        let helpers = || ["prefix-path"].into_iter();
+        assert!(changed.affect_template("{{app.stylized-name}}", helpers()));
+        assert!(changed.affect_template("{{#with app}}{{name}}{{/with}}", helpers()));
+        assert!(!changed.affect_template("{{app.name}} {{join targets}}", helpers()));
+        assert!(changed.affect_template("{{prefix-path \"target/\"}}", helpers()));
+        assert!(changed.affect_template("{{> partial}}", helpers()));
+    }
+
+    #[test]
+    fn unaffected_outputs_are_left_alone() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let (src, dest, snapshot) = (dir.join("src"), dir.join("dest"), dir.join("snapshot"));
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(src.join("name.txt.hbs"), "{{name}}").unwrap();
+        std::fs::write(src.join("version.txt.hbs"), "{{version}}").unwrap();
+        let bike = crate::bicycle::Bicycle::default();
+        let insert_data = |name: &'static str| {
+            move |map: &mut JsonMap| {
+                map.insert("name", name);
+                map.insert("version", "1.0");
+            }
+        };
+        let process = |name| {
+            bike.filter_and_process_changed(&src, &dest, insert_data(name), |_| true, &snapshot)
+                .unwrap()
+        };
+        process("old");
+        // Hand edits survive in files whose data didn't change.
+        std::fs::write(dest.join("version.txt"), "edited").unwrap();
+        process("new");
+        let read = |name| std::fs::read_to_string(dest.join(name)).unwrap();
+        assert_eq!(read("name.txt"), "new");
+        assert_eq!(read("version.txt"), "edited");
+        // But not in files whose template changed.
+        std::fs::write(src.join("version.txt.hbs"), "v{{version}}").unwrap();
+        process("new");
+        assert_eq!(read("version.txt"), "v1.0");
+    }
+}
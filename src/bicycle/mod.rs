@@ -3,14 +3,17 @@
 #![forbid(unsafe_code)]
 #![allow(dead_code)]
 
+mod cache;
+mod changes;
 mod json_map;
 mod traverse;
 
-pub use self::{json_map::*, traverse::*};
+pub use self::{cache::*, changes::*, json_map::*, traverse::*};
 pub use handlebars::{self, HelperDef};
 
 use handlebars::Handlebars;
 use std::{
+    collections::BTreeMap,
     fmt::{self, Debug},
     fs,
     io::{self, Read, Write},
@@ -113,6 +116,11 @@ pub enum ProcessingError {
 pub struct Bicycle {
     handlebars: Handlebars<'static>,
     base_data: JsonMap,
+    cache: Option<RenderCache>,
+    // Identifies the helpers and escape fn for the render cache, since the
+    // same template and data can render differently with others.
+    renderer_id: String,
+    context_helpers: Vec<String>,
 }
 
 impl Default for Bicycle {
@@ -172,20 +180,78 @@ impl Bicycle {
     ) -> Self {
         let mut handlebars = Handlebars::new();
         handlebars.set_strict_mode(true);
-        match escape_fn {
-            EscapeFn::Custom(escape_fn) => handlebars.register_escape_fn(escape_fn),
-            EscapeFn::None => handlebars.register_escape_fn(handlebars::no_escape),
-            EscapeFn::Html => handlebars.register_escape_fn(handlebars::html_escape),
-        }
+        // Custom escape fns can only be told apart by address, so their cache
+        // entries don't outlive the process.
+        let escape_id = match escape_fn {
+            EscapeFn::Custom(escape_fn) => {
+                handlebars.register_escape_fn(escape_fn);
+                format!("custom@{:p}", escape_fn)
+            }
+            EscapeFn::None => {
+                handlebars.register_escape_fn(handlebars::no_escape);
+                "none".to_owned()
+            }
+            EscapeFn::Html => {
+                handlebars.register_escape_fn(handlebars::html_escape);
+                "html".to_owned()
+            }
+        };
+        let mut helper_names = Vec::new();
         for (name, helper) in helpers {
             handlebars.register_helper(name, helper);
+            helper_names.push(name.to_owned());
         }
+        helper_names.sort();
+        // Helpers are identified by name, so the version covers changes to
+        // our own helpers' behavior.
+        let renderer_id = format!(
+            "{} {} {}",
+            env!("CARGO_PKG_VERSION"),
+            escape_id,
+            helper_names.join(",")
+        );
         Self {
             handlebars,
             base_data,
+            cache: None,
+            renderer_id,
+            context_helpers: Vec::new(),
         }
     }
 
+    /// Reuses previously rendered output from `cache` whenever the same
+    /// template is rendered with the same data.
+    ///
+    /// This is purely an optimization, and failing to write to the cache
+    /// isn't considered an error.
+    pub fn with_cache(mut self, cache: RenderCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Marks helpers that read template data besides their arguments, so that
+    /// [`Bicycle::filter_and_process_changed`] always renders templates
+    /// using them.
+    pub fn with_context_helpers<'a>(mut self, helpers: impl IntoIterator<Item = &'a str>) -> Self {
+        self.context_helpers
+            .extend(helpers.into_iter().map(ToOwned::to_owned));
+        self
+    }
+
+    /// The data templates are rendered with, i.e. the base data with
+    /// `insert_data` applied. Keep this around to find the [`ChangedKeys`]
+    /// the next time around.
+    pub fn data(&self, insert_data: impl FnOnce(&mut JsonMap)) -> JsonMap {
+        let mut data = self.base_data.clone();
+        insert_data(&mut data);
+        data
+    }
+
+    /// The data every render starts from, before `insert_data` adds to it.
+    pub(crate) fn base_data_mut(&mut self) -> &mut JsonMap {
+        &mut self.base_data
+    }
+
     /// Renders a template.
     ///
     /// Use `insert_data` to define any variables needed for the template.
@@ -207,10 +273,28 @@ impl Bicycle {
     ) -> Result<String, RenderingError> {
         let mut data = self.base_data.clone();
         insert_data(&mut data);
-        self.handlebars
+        let cached = self
+            .cache
+            .as_ref()
+            .map(|cache| (cache, cache.key(&self.renderer_id, template, &data.0)));
+        if let Some(rendered) = cached.as_ref().and_then(|(cache, key)| cache.get(key)) {
+            log::debug!("render cache hit");
+            return Ok(rendered);
+        }
+        let rendered = self
+            .handlebars
             .render_template(template, &data.0)
-            .map_err(Box::new)
-            .map_err(Into::into)
+            .map_err(Box::new)?;
+        if let Some((cache, key)) = cached {
+            if let Err(err) = cache.put(&key, &rendered) {
+                log::warn!(
+                    "failed to write to render cache at {:?}: {}",
+                    cache.dir(),
+                    err
+                );
+            }
+        }
+        Ok(rendered)
     }
 
     /// Executes an [`Action`].
@@ -324,6 +408,62 @@ impl Bicycle {
         })
     }
 
+    /// Does the same work as [`Bicycle::filter_and_process`], but leaves
+    /// existing outputs alone when neither their template nor the data it
+    /// references changed since the last run, rather than re-rendering them.
+    /// What the last run used is kept at `snapshot`, and without it,
+    /// everything is rendered. Templates whose references can't be
+    /// determined are always rendered.
+    pub fn filter_and_process_changed(
+        &self,
+        src: impl AsRef<Path>,
+        dest: impl AsRef<Path>,
+        insert_data: impl Fn(&mut JsonMap),
+        mut filter: impl FnMut(&Action) -> bool,
+        snapshot: &Path,
+    ) -> Result<(), ProcessingError> {
+        let data = self.data(&insert_data);
+        let previous = Snapshot::load(snapshot);
+        let changed = previous
+            .as_ref()
+            .map(|previous| ChangedKeys::between(&JsonMap(previous.data.clone()), &data));
+        let mut templates = BTreeMap::new();
+        self.filter_and_process(src, dest, insert_data, |action| {
+            if !filter(action) {
+                return false;
+            }
+            let Action::WriteTemplate { src, dest } = action else {
+                return true;
+            };
+            // Unreadable templates are left for processing to report.
+            let Ok(template) = fs::read_to_string(src) else {
+                return true;
+            };
+            let hash = cache::hash(&[&template]);
+            templates.insert(src.clone(), hash.clone());
+            let (Some(previous), Some(changed)) = (&previous, &changed) else {
+                return true;
+            };
+            let affected = !dest.is_file()
+                || previous.templates.get(src) != Some(&hash)
+                || changed
+                    .affect_template(&template, self.context_helpers.iter().map(String::as_str));
+            if !affected {
+                log::info!("skipping {:?}, since neither it nor its data changed", src);
+            }
+            affected
+        })?;
+        let snapshot_result = Snapshot {
+            data: data.0,
+            templates,
+        }
+        .save(snapshot);
+        if let Err(err) = snapshot_result {
+            log::warn!("failed to save template snapshot {:?}: {}", snapshot, err);
+        }
+        Ok(())
+    }
+
     /// Renders a path string itself as a template.
     /// Intended to be used as the `transform_path` argument to [`traverse`](traverse()).
     pub fn transform_path(
@@ -12,11 +12,6 @@ fn main() {
 
 #[cfg(not(target_os = "macos"))]
 fn main() {
-    use cargo_mobile2::util::cli::{Exit, Report};
-    Exit::main(|_wrapper| {
-        Err(Exit::Report(Report::error(
-            "`cargo-apple` can only be used on macOS",
-            "Apple doesn't support building for iOS on other platforms, sorry!",
-        )))
-    })
+    use cargo_mobile2::util::cli::{macos_host_required, Exit};
+    Exit::main(|_wrapper| Err(Exit::Report(macos_host_required())))
 }
@@ -2,14 +2,14 @@
 #![forbid(unsafe_code)]
 
 use cargo_mobile2::{
-    doctor, init, update,
+    devcontainer, doctor, init, migrate, store_metadata, update, usage,
     util::{
         self,
         cli::{
             self, Exec, GlobalFlags, Report, Reportable, TextWrapper, VERSION_LONG, VERSION_SHORT,
         },
     },
-    NAME,
+    version, NAME,
 };
 use std::path::PathBuf;
 use structopt::StructOpt;
@@ -42,10 +42,28 @@ pub enum Command {
         skip_targets_install: cli::SkipTargetsInstall,
         #[structopt(flatten)]
         reinstall_deps: cli::ReinstallDeps,
+        #[structopt(flatten)]
+        no_deps: cli::NoDeps,
+        #[structopt(flatten)]
+        project_only: cli::ProjectOnly,
+        #[structopt(flatten)]
+        bare: cli::Bare,
+        #[structopt(flatten)]
+        template_data: cli::TemplateData,
+        #[structopt(flatten)]
+        require_empty: cli::RequireEmpty,
+        #[structopt(flatten)]
+        force_config: cli::ForceConfig,
         #[structopt(long = "open", help = "Open in default code editor")]
         open_in_editor: bool,
         #[structopt(long = "submodule-commit", help = "Template pack commit to checkout")]
         submodule_commit: Option<String>,
+        #[structopt(
+            long = "app",
+            value_name = "name",
+            help = "Initialize this `[workspace]` member, by dir name or `app.name`"
+        )]
+        app: Option<String>,
     },
     #[structopt(name = "new", about = "Creates a new project in a new directory")]
     New {
@@ -55,6 +73,16 @@ pub enum Command {
         skip_targets_install: cli::SkipTargetsInstall,
         #[structopt(flatten)]
         reinstall_deps: cli::ReinstallDeps,
+        #[structopt(flatten)]
+        no_deps: cli::NoDeps,
+        #[structopt(flatten)]
+        project_only: cli::ProjectOnly,
+        #[structopt(flatten)]
+        bare: cli::Bare,
+        #[structopt(flatten)]
+        template_data: cli::TemplateData,
+        #[structopt(flatten)]
+        require_empty: cli::RequireEmpty,
         #[structopt(long = "open", help = "Open in default code editor")]
         open_in_editor: bool,
         #[structopt(long = "submodule-commit", help = "Template pack commit to checkout")]
@@ -73,7 +101,47 @@ pub enum Command {
     Update {
         #[structopt(long = "init", help = "Regenerate project if update succeeds")]
         init: bool,
+        #[structopt(long = "yes", help = "Install the update without asking first")]
+        yes: bool,
+        #[structopt(
+            long = "since",
+            value_name = "rev",
+            help = "List changes since this commit or tag, instead of since the installed version"
+        )]
+        since: Option<String>,
+    },
+    #[structopt(
+        name = "migrate",
+        about = "Moves a cargo-mobile (v1) project's `rust/lib` crate to the project root"
+    )]
+    Migrate,
+    #[structopt(
+        name = "gen-store-metadata",
+        about = "Scaffolds fastlane's App Store and Play Store listing metadata"
+    )]
+    GenStoreMetadata {
+        #[structopt(
+            long = "locale",
+            value_name = "locale",
+            help = "Locale to generate listings for, which can be passed multiple times",
+            default_value = store_metadata::DEFAULT_LOCALE,
+            number_of_values = 1
+        )]
+        locales: Vec<String>,
+    },
+    #[structopt(
+        name = "gen-devcontainer",
+        about = "Generates a devcontainer with the Android SDK, NDK, and Rust targets preinstalled"
+    )]
+    GenDevcontainer {
+        #[structopt(long = "force", help = "Overwrite an existing devcontainer")]
+        force: bool,
     },
+    #[structopt(
+        name = "usage",
+        about = "Shows how much disk space the generated projects and build artifacts use"
+    )]
+    Usage,
     #[cfg_attr(
         target_os = "macos",
         structopt(
@@ -82,17 +150,50 @@ pub enum Command {
         )
     )]
     #[cfg(target_os = "macos")]
-    Apple(cargo_mobile2::apple::cli::Command),
+    Apple {
+        #[structopt(flatten)]
+        config_flags: cli::NestedConfigFlags,
+        #[structopt(subcommand)]
+        command: cargo_mobile2::apple::cli::Command,
+    },
+    // Accepts anything, so that Apple commands fail with an explanation
+    // instead of clap's "unrecognized subcommand".
+    #[cfg(not(target_os = "macos"))]
+    #[structopt(
+        name = "apple",
+        about = "iOS commands (requires a macOS host)",
+        settings = &[
+            structopt::clap::AppSettings::AllowLeadingHyphen,
+            structopt::clap::AppSettings::TrailingVarArg,
+        ]
+    )]
+    Apple {
+        #[structopt(hidden = true)]
+        args: Vec<String>,
+    },
     #[structopt(
         name = "android",
         about = "Android commands (tip: type less by running `cargo android` instead!)"
     )]
-    Android(cargo_mobile2::android::cli::Command),
+    Android {
+        #[structopt(flatten)]
+        config_flags: cli::NestedConfigFlags,
+        #[structopt(subcommand)]
+        command: cargo_mobile2::android::cli::Command,
+    },
     #[structopt(
         name = "doctor",
         about = "Perform a check-up on your installation and environment"
     )]
     Doctor,
+    #[structopt(
+        name = "version",
+        about = "Print the versions of `cargo-mobile2`, its template packs, and this project's template pack"
+    )]
+    Version {
+        #[structopt(long = "json", help = "Print the result as JSON")]
+        json: bool,
+    },
 }
 
 #[derive(Debug)]
@@ -108,10 +209,17 @@ pub enum Error {
     },
     OpenFailed(util::OpenInEditorError),
     UpdateFailed(update::Error),
+    MigrateFailed(migrate::Error),
+    StoreMetadataFailed(store_metadata::Error),
+    DevcontainerFailed(devcontainer::Error),
+    UsageFailed(usage::Error),
     #[cfg(target_os = "macos")]
     AppleFailed(cargo_mobile2::apple::cli::Error),
+    #[cfg(not(target_os = "macos"))]
+    AppleUnsupported,
     AndroidFailed(cargo_mobile2::android::cli::Error),
     DoctorFailed(doctor::Unrecoverable),
+    VersionFailed(version::Error),
 }
 
 impl Reportable for Error {
@@ -129,10 +237,17 @@ impl Reportable for Error {
                 Report::error("Failed to open project in default code editor", err)
             }
             Self::UpdateFailed(err) => Report::error("Failed to update `cargo-mobile2`", err),
+            Self::MigrateFailed(err) => err.report(),
+            Self::StoreMetadataFailed(err) => err.report(),
+            Self::DevcontainerFailed(err) => err.report(),
+            Self::UsageFailed(err) => err.report(),
             #[cfg(target_os = "macos")]
             Self::AppleFailed(err) => err.report(),
+            #[cfg(not(target_os = "macos"))]
+            Self::AppleUnsupported => cli::macos_host_required(),
             Self::AndroidFailed(err) => err.report(),
             Self::DoctorFailed(err) => Report::error("Failed to run doctor", err),
+            Self::VersionFailed(err) => err.report(),
         }
     }
 }
@@ -141,7 +256,7 @@ impl Exec for Input {
     type Report = Error;
 
     fn global_flags(&self) -> GlobalFlags {
-        self.flags
+        self.flags.clone()
     }
 
     fn exec(self, wrapper: &TextWrapper) -> Result<(), Self::Report> {
@@ -157,16 +272,35 @@ impl Exec for Input {
                         skip_targets_install,
                     },
                 reinstall_deps: cli::ReinstallDeps { reinstall_deps },
+                no_deps: cli::NoDeps { no_deps },
+                project_only: cli::ProjectOnly { project_only },
+                bare: cli::Bare { bare },
+                template_data:
+                    cli::TemplateData {
+                        template_data,
+                        strict_template_data,
+                    },
+                require_empty: cli::RequireEmpty { require_empty },
+                force_config: cli::ForceConfig { force_config },
                 open_in_editor,
                 submodule_commit,
+                app,
             } => init::exec(
                 wrapper,
                 non_interactive,
                 skip_dev_tools,
                 skip_targets_install,
                 reinstall_deps,
+                no_deps,
+                project_only,
+                bare,
+                template_data.as_deref(),
+                strict_template_data,
+                require_empty,
+                force_config,
                 open_in_editor,
                 submodule_commit,
+                app.as_deref(),
                 ".",
             )
             .map(|_| ())
@@ -178,10 +312,22 @@ impl Exec for Input {
                         skip_targets_install,
                     },
                 reinstall_deps: cli::ReinstallDeps { reinstall_deps },
+                no_deps: cli::NoDeps { no_deps },
+                project_only: cli::ProjectOnly { project_only },
+                bare: cli::Bare { bare },
+                template_data:
+                    cli::TemplateData {
+                        template_data,
+                        strict_template_data,
+                    },
+                require_empty: cli::RequireEmpty { require_empty },
                 open_in_editor,
                 submodule_commit,
                 directory,
             } => {
+                // Relative to where we were invoked, not the new directory.
+                let template_data =
+                    template_data.map(|path| std::path::absolute(&path).unwrap_or(path));
                 std::fs::create_dir_all(&directory).map_err(|source| Error::DirCreationFailed {
                     path: directory.clone(),
                     source,
@@ -196,16 +342,25 @@ impl Exec for Input {
                     skip_dev_tools,
                     skip_targets_install,
                     reinstall_deps,
+                    no_deps,
+                    project_only,
+                    bare,
+                    template_data.as_deref(),
+                    strict_template_data,
+                    require_empty,
+                    false,
                     open_in_editor,
                     submodule_commit,
+                    None,
                     ".",
                 )
                 .map(|_| ())
                 .map_err(|e| Error::InitFailed(*e))
             }
             Command::Open => util::open_in_editor(".").map_err(Error::OpenFailed),
-            Command::Update { init } => {
-                update::update(wrapper).map_err(Error::UpdateFailed)?;
+            Command::Update { init, yes, since } => {
+                update::update(wrapper, non_interactive, yes, since.as_deref())
+                    .map_err(Error::UpdateFailed)?;
                 if init {
                     init::exec(
                         wrapper,
@@ -215,20 +370,49 @@ impl Exec for Input {
                         Default::default(),
                         Default::default(),
                         Default::default(),
+                        Default::default(),
+                        None,
+                        Default::default(),
+                        Default::default(),
+                        Default::default(),
+                        Default::default(),
+                        Default::default(),
+                        None,
                         ".",
                     )
                     .map_err(|e| Error::InitFailed(*e))?;
                 }
                 Ok(())
             }
+            Command::Migrate => {
+                migrate::exec(wrapper, non_interactive).map_err(Error::MigrateFailed)
+            }
+            Command::GenStoreMetadata { locales } => {
+                store_metadata::exec(wrapper, non_interactive, &locales)
+                    .map_err(Error::StoreMetadataFailed)
+            }
+            Command::GenDevcontainer { force } => {
+                devcontainer::exec(wrapper, non_interactive, force)
+                    .map_err(Error::DevcontainerFailed)
+            }
+            Command::Usage => usage::exec(wrapper, non_interactive).map_err(Error::UsageFailed),
             #[cfg(target_os = "macos")]
-            Command::Apple(command) => cargo_mobile2::apple::cli::Input::new(flags, command)
+            Command::Apple {
+                config_flags,
+                command,
+            } => cargo_mobile2::apple::cli::Input::new(flags, config_flags.0, command)
                 .exec(wrapper)
                 .map_err(Error::AppleFailed),
-            Command::Android(command) => cargo_mobile2::android::cli::Input::new(flags, command)
+            #[cfg(not(target_os = "macos"))]
+            Command::Apple { .. } => Err(Error::AppleUnsupported),
+            Command::Android {
+                config_flags,
+                command,
+            } => cargo_mobile2::android::cli::Input::new(flags, config_flags.0, command)
                 .exec(wrapper)
                 .map_err(Error::AndroidFailed),
             Command::Doctor => doctor::exec(wrapper).map_err(Error::DoctorFailed),
+            Command::Version { json } => version::exec(json).map_err(Error::VersionFailed),
         }
     }
 }
@@ -0,0 +1,168 @@
+use crate::{
+    config::app::App,
+    util::{
+        cli::{Report, Reportable},
+        Git,
+    },
+};
+use std::{
+    fs, io,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use thiserror::Error;
+
+// Files without this first line are left alone, so deleting it is how users
+// take ownership of the module.
+static MARKER: &str = "// @generated by cargo-mobile2";
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Failed to read {path}: {cause}")]
+    ReadFailed { path: PathBuf, cause: io::Error },
+    #[error("Failed to create directory {path}: {cause}")]
+    DirCreationFailed { path: PathBuf, cause: io::Error },
+    #[error("Failed to write {path}: {cause}")]
+    WriteFailed { path: PathBuf, cause: io::Error },
+}
+
+impl Reportable for Error {
+    fn report(&self) -> Report {
+        Report::error("Failed to generate build info", self)
+    }
+}
+
+/// The commit and time a build was made from.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BuildInfo {
+    /// The short SHA of `HEAD`, suffixed with `-dirty` if the working tree
+    /// has uncommitted changes.
+    pub git_sha: String,
+    /// When the build started, as an ISO 8601 UTC timestamp, or
+    /// `SOURCE_DATE_EPOCH` if that's set.
+    pub build_date: String,
+}
+
+impl BuildInfo {
+    pub fn detect(app: &App) -> Self {
+        let git = Git::new(app.root_dir());
+        let git_sha = match git.command_parse("rev-parse --short HEAD").read() {
+            Ok(sha) => {
+                let dirty = git
+                    .command_parse("status --porcelain")
+                    .read()
+                    .map(|status| !status.trim().is_empty())
+                    .unwrap_or_default();
+                format!("{}{}", sha.trim(), if dirty { "-dirty" } else { "" })
+            }
+            Err(err) => {
+                log::warn!("failed to get git commit for build info: {}", err);
+                "unknown".to_owned()
+            }
+        };
+        Self {
+            git_sha,
+            build_date: iso_8601(build_time()),
+        }
+    }
+
+    fn render(&self) -> String {
+        format!(
+            "{MARKER}; delete this line to stop it from being regenerated.
+
+/// The short SHA of the commit this was built from, suffixed with `-dirty` if
+/// there were uncommitted changes.
+pub const GIT_SHA: &str = {:?};
+/// When this was built, as an ISO 8601 UTC timestamp.
+pub const BUILD_DATE: &str = {:?};
+/// The version in `Cargo.toml`.
+pub const VERSION: &str = env!(\"CARGO_PKG_VERSION\");
+",
+            self.git_sha, self.build_date
+        )
+    }
+}
+
+/// Writes the build info module to `app.build-info-path`, unless the existing
+/// file there isn't one we generated.
+pub fn generate(app: &App) -> Result<(), Error> {
+    let path = app.build_info_path();
+    if path.exists() {
+        let existing = fs::read_to_string(&path).map_err(|cause| Error::ReadFailed {
+            path: path.clone(),
+            cause,
+        })?;
+        if !existing.starts_with(MARKER) {
+            log::warn!(
+                "not writing build info to {:?}, since it wasn't generated by cargo-mobile2",
+                path
+            );
+            return Ok(());
+        }
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|cause| Error::DirCreationFailed {
+            path: parent.to_owned(),
+            cause,
+        })?;
+    }
+    let info = BuildInfo::detect(app);
+    log::info!("writing build info {:?} to {:?}", info, path);
+    fs::write(&path, info.render()).map_err(|cause| Error::WriteFailed { path, cause })
+}
+
+// `SOURCE_DATE_EPOCH` pins the build date, so that builds are reproducible.
+fn build_time() -> u64 {
+    if let Ok(epoch) = std::env::var("SOURCE_DATE_EPOCH") {
+        match epoch.trim().parse() {
+            Ok(secs) => return secs,
+            Err(err) => log::warn!(
+                "ignoring `SOURCE_DATE_EPOCH` {:?}, since it isn't a number of seconds: {}",
+                epoch,
+                err
+            ),
+        }
+    }
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|since| since.as_secs())
+        .unwrap_or_default()
+}
+
+// https://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn iso_8601(secs: u64) -> String {
+    let days = (secs / 86400) as i64;
+    let secs_of_day = secs % 86400;
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60,
+        secs_of_day % 60
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(0, "1970-01-01T00:00:00Z")]
+    #[case(951_782_400, "2000-02-29T00:00:00Z")]
+    #[case(1_792_152_245, "2026-10-16T12:04:05Z")]
+    fn test_iso_8601(#[case] secs: u64, #[case] expected: &str) {
+        assert_eq!(iso_8601(secs), expected);
+    }
+}
@@ -0,0 +1,162 @@
+use super::App;
+use crate::{
+    bicycle::{Bicycle, RenderingError},
+    opts::Profile,
+};
+use serde::Deserialize;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("`app.artifact-name-template` couldn't be rendered: {0}")]
+    RenderFailed(#[from] RenderingError),
+    #[error("`app.artifact-name-template` rendered to an empty name")]
+    Empty,
+    #[error("`app.artifact-name-template` rendered to {0:?}, which contains a path separator")]
+    PathSeparator(String),
+    #[error("Failed to copy {src} to {dest}: {cause}")]
+    CopyFailed {
+        src: PathBuf,
+        dest: PathBuf,
+        cause: io::Error,
+    },
+}
+
+/// What an artifact was built for, which the template can refer to along with
+/// `app-name` and `version`.
+#[derive(Clone, Copy, Debug)]
+pub struct Vars<'a> {
+    pub platform: &'a str,
+    pub arch: &'a str,
+    pub profile: Profile,
+}
+
+// `version` is only inserted if the crate has one, so that strict mode points
+// out templates that need it when it's inherited from the workspace.
+fn render(
+    template: &str,
+    app_name: &str,
+    version: Option<&str>,
+    vars: Vars,
+) -> Result<String, Error> {
+    let name = Bicycle::default().render(template, |map| {
+        map.insert("app-name", app_name);
+        if let Some(version) = version {
+            map.insert("version", version);
+        }
+        map.insert("platform", vars.platform);
+        map.insert("arch", vars.arch);
+        map.insert("profile", vars.profile.as_str());
+    })?;
+    let name = name.trim();
+    if name.is_empty() || name == "." || name == ".." {
+        Err(Error::Empty)
+    } else if name.contains(['/', '\\']) {
+        Err(Error::PathSeparator(name.to_owned()))
+    } else {
+        Ok(name.to_owned())
+    }
+}
+
+/// Catches syntax errors, unknown variables, and separators in the template
+/// itself when the config is loaded, rather than after a build.
+pub fn validate(template: &str, app_name: &str) -> Result<(), Error> {
+    let vars = Vars {
+        platform: "android",
+        arch: "aarch64",
+        profile: Profile::Release,
+    };
+    render(template, app_name, Some("0.0.0"), vars).map(|_| ())
+}
+
+fn crate_version(manifest_path: &Path) -> Option<String> {
+    #[derive(Deserialize)]
+    struct Package {
+        version: Option<toml::Value>,
+    }
+
+    #[derive(Deserialize)]
+    struct CargoToml {
+        package: Package,
+    }
+
+    let contents = fs::read_to_string(manifest_path)
+        .inspect_err(|err| log::warn!("failed to read {:?}: {}", manifest_path, err))
+        .ok()?;
+    let cargo_toml = toml::from_str::<CargoToml>(&contents)
+        .inspect_err(|err| log::warn!("failed to parse {:?}: {}", manifest_path, err))
+        .ok()?;
+    match cargo_toml.package.version? {
+        toml::Value::String(version) => Some(version),
+        _ => {
+            log::warn!(
+                "the version in {:?} isn't a string, so it can't be used in artifact names",
+                manifest_path
+            );
+            None
+        }
+    }
+}
+
+/// Copies `artifact` to the name given by `app.artifact-name-template` in the
+/// same directory, returning the new path. The artifact's extension is added
+/// if the template doesn't already end with it. Without a template, this
+/// just returns `artifact`.
+///
+/// The artifact is copied rather than renamed, so that Gradle still finds its
+/// outputs where it left them.
+pub fn apply(app: &App, artifact: &Path, vars: Vars) -> Result<PathBuf, Error> {
+    let Some(template) = app.artifact_name_template() else {
+        return Ok(artifact.to_owned());
+    };
+    let version = crate_version(&app.manifest_path());
+    let mut name = render(template, app.name(), version.as_deref(), vars)?;
+    if let Some(extension) = artifact.extension().and_then(|ext| ext.to_str()) {
+        if !name.ends_with(&format!(".{}", extension)) {
+            name = format!("{}.{}", name, extension);
+        }
+    }
+    let dest = artifact.with_file_name(name);
+    fs::copy(artifact, &dest).map_err(|cause| Error::CopyFailed {
+        src: artifact.to_owned(),
+        dest: dest.clone(),
+        cause,
+    })?;
+    Ok(dest)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(
+        "{{app-name}}-{{version}}-{{platform}}-{{arch}}",
+        Some("1.2.3"),
+        Ok("demo-1.2.3-android-aarch64")
+    )]
+    #[case("{{app-name}}-{{profile}}.apk", None, Ok("demo-release.apk"))]
+    #[case("{{app-name}}-{{version}}", None, Err(()))]
+    #[case("{{platform}}/{{arch}}", Some("1.2.3"), Err(()))]
+    #[case("{{#if false}}x{{/if}}", Some("1.2.3"), Err(()))]
+    fn test_render(
+        #[case] template: &str,
+        #[case] version: Option<&str>,
+        #[case] expected: Result<&str, ()>,
+    ) {
+        let vars = Vars {
+            platform: "android",
+            arch: "aarch64",
+            profile: Profile::Release,
+        };
+        assert_eq!(
+            render(template, "demo", version, vars).map_err(|_| ()),
+            expected.map(ToOwned::to_owned)
+        );
+    }
+}
@@ -0,0 +1,356 @@
+use crate::util;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs, io,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+pub static DEFAULT_BASE_LOCALE: &str = "en";
+
+#[derive(Debug, Error)]
+pub enum Invalid {
+    #[error("`src` {src} couldn't be normalized: {cause}")]
+    SrcNormalizationFailed {
+        src: String,
+        cause: util::NormalizationError,
+    },
+    #[error("`src` {src} is outside of the app root {root_dir}")]
+    SrcOutsideOfAppRoot { src: String, root_dir: PathBuf },
+    #[error(transparent)]
+    BaseLocaleInvalid(LocaleInvalid),
+}
+
+#[derive(Debug, Error)]
+#[error("{0:?} isn't a locale like `en`, `pt-BR`, or `zh-Hans`")]
+pub struct LocaleInvalid(String);
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Failed to read localized strings from {path:?}: {cause}")]
+    ReadFailed { path: PathBuf, cause: io::Error },
+    #[error(
+        "Localized strings at {path:?} aren't a map of locales to maps of keys to strings: {cause}"
+    )]
+    ParseFailed {
+        path: PathBuf,
+        cause: serde_json::Error,
+    },
+    #[error(transparent)]
+    LocaleInvalid(LocaleInvalid),
+    #[error("The base locale {0:?} doesn't have any strings")]
+    BaseLocaleMissing(String),
+    #[error("Key {key:?} for {locale} has to start with a letter and only contain letters, digits, `_`, and `.`, since Android uses it as a resource name")]
+    KeyInvalid { locale: String, key: String },
+}
+
+/// A JSON file of strings for each locale, like
+/// `{ "en": { "greeting": "Hello" }, "fr": { "greeting": "Bonjour" } }`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Raw {
+    /// Relative to the app root.
+    pub src: String,
+    /// The locale other locales fall back to, and that sets which keys there
+    /// are. Defaults to `en`.
+    pub base_locale: Option<String>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Localization {
+    src: String,
+    base_locale: String,
+}
+
+// Splits a locale into its language, script, and region, e.g. `zh-Hans-CN`.
+// Locales end up in paths, so this is strict about what it accepts.
+fn subtags(locale: &str) -> Option<(&str, Option<&str>, Option<&str>)> {
+    let alphabetic = |s: &str, lens: &[usize]| {
+        lens.contains(&s.len()) && s.chars().all(|c| c.is_ascii_alphabetic())
+    };
+    let mut parts = locale.split('-');
+    let language = parts.next().filter(|s| alphabetic(s, &[2, 3]))?;
+    let mut next = parts.next();
+    let script = next.filter(|s| alphabetic(s, &[4]));
+    if script.is_some() {
+        next = parts.next();
+    }
+    let region = next;
+    if let Some(region) = region {
+        let digits = region.len() == 3 && region.chars().all(|c| c.is_ascii_digit());
+        if !alphabetic(region, &[2]) && !digits {
+            return None;
+        }
+    }
+    parts.next().is_none().then_some((language, script, region))
+}
+
+fn check_locale(locale: &str) -> Result<(), LocaleInvalid> {
+    subtags(locale)
+        .map(|_| ())
+        .ok_or_else(|| LocaleInvalid(locale.to_owned()))
+}
+
+/// The Android resource dir for `locale`'s values, e.g. `values-pt-rBR`.
+/// Anything more than a language and region needs the BCP 47 form, like
+/// `values-b+zh+Hans`.
+pub fn android_values_dir(locale: &str) -> String {
+    match subtags(locale) {
+        Some((language, None, None)) => format!("values-{}", language),
+        Some((language, None, Some(region))) if region.chars().all(|c| c.is_ascii_alphabetic()) => {
+            format!("values-{}-r{}", language, region.to_ascii_uppercase())
+        }
+        _ => format!("values-b+{}", locale.replace('-', "+")),
+    }
+}
+
+/// Escapes `value` for a `<string>` in Android's `strings.xml`, which gets
+/// unescaped as XML and then again by `aapt`.
+pub fn android_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    if value.starts_with(['@', '?']) {
+        escaped.push('\\');
+    }
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '\\' => escaped.push_str("\\\\"),
+            '\'' => escaped.push_str("\\'"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Escapes `value` for the inside of a quoted string in Apple's
+/// `Localizable.strings`.
+pub fn apple_escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// A string ready to be rendered into a platform's resource file.
+#[derive(Debug, Serialize)]
+pub struct Entry<'a> {
+    pub key: &'a str,
+    pub value: String,
+}
+
+pub fn entries(
+    strings: &BTreeMap<String, String>,
+    escape: impl Fn(&str) -> String,
+) -> Vec<Entry<'_>> {
+    strings
+        .iter()
+        .map(|(key, value)| Entry {
+            key,
+            value: escape(value),
+        })
+        .collect()
+}
+
+fn key_valid(key: &str) -> bool {
+    key.starts_with(|c: char| c.is_ascii_alphabetic())
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+impl Localization {
+    pub fn from_raw(root_dir: &Path, raw: Raw) -> Result<Self, Invalid> {
+        let under_root = util::under_root(&raw.src, root_dir).map_err(|cause| {
+            Invalid::SrcNormalizationFailed {
+                src: raw.src.clone(),
+                cause,
+            }
+        })?;
+        if !under_root {
+            return Err(Invalid::SrcOutsideOfAppRoot {
+                src: raw.src,
+                root_dir: root_dir.to_owned(),
+            });
+        }
+        let base_locale = raw
+            .base_locale
+            .unwrap_or_else(|| DEFAULT_BASE_LOCALE.to_owned());
+        check_locale(&base_locale).map_err(Invalid::BaseLocaleInvalid)?;
+        Ok(Self {
+            src: raw.src,
+            base_locale,
+        })
+    }
+
+    pub fn src(&self) -> &str {
+        &self.src
+    }
+
+    pub fn base_locale(&self) -> &str {
+        &self.base_locale
+    }
+
+    /// Reads the strings from `src`, relative to `root_dir`.
+    pub fn load(&self, root_dir: &Path) -> Result<Strings, Error> {
+        let path = util::prefix_path(root_dir, &self.src);
+        let json = fs::read_to_string(&path).map_err(|cause| Error::ReadFailed {
+            path: path.clone(),
+            cause,
+        })?;
+        let locales =
+            serde_json::from_str(&json).map_err(|cause| Error::ParseFailed { path, cause })?;
+        Strings::new(&self.base_locale, locales)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Strings {
+    base_locale: String,
+    locales: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl Strings {
+    /// Warns about keys that are missing from, or only in, locales other than
+    /// the base locale, since those show up untranslated or are never used.
+    pub fn new(
+        base_locale: &str,
+        locales: BTreeMap<String, BTreeMap<String, String>>,
+    ) -> Result<Self, Error> {
+        let base_len = locales
+            .get(base_locale)
+            .ok_or_else(|| Error::BaseLocaleMissing(base_locale.to_owned()))?
+            .len();
+        for (locale, strings) in &locales {
+            check_locale(locale).map_err(Error::LocaleInvalid)?;
+            if let Some(key) = strings.keys().find(|key| !key_valid(key)) {
+                return Err(Error::KeyInvalid {
+                    locale: locale.clone(),
+                    key: key.clone(),
+                });
+            }
+        }
+        let strings = Self {
+            base_locale: base_locale.to_owned(),
+            locales,
+        };
+        for (locale, missing, extra) in strings.mismatched_keys() {
+            if !missing.is_empty() {
+                log::warn!(
+                    "{} is missing {} of the {} keys in {}: {}",
+                    locale,
+                    missing.len(),
+                    base_len,
+                    base_locale,
+                    missing.into_iter().collect::<Vec<_>>().join(", ")
+                );
+            }
+            if !extra.is_empty() {
+                log::warn!(
+                    "{} has keys that aren't in {}: {}",
+                    locale,
+                    base_locale,
+                    extra.into_iter().collect::<Vec<_>>().join(", ")
+                );
+            }
+        }
+        Ok(strings)
+    }
+
+    // The keys each locale is missing and has in addition to the base locale.
+    fn mismatched_keys(&self) -> Vec<(&str, BTreeSet<&str>, BTreeSet<&str>)> {
+        fn keys(strings: &BTreeMap<String, String>) -> BTreeSet<&str> {
+            strings.keys().map(String::as_str).collect()
+        }
+        let base = keys(&self.locales[&self.base_locale]);
+        self.locales
+            .iter()
+            .filter(|(locale, _)| **locale != self.base_locale)
+            .map(|(locale, strings)| {
+                let keys = keys(strings);
+                let missing = base.difference(&keys).copied().collect::<BTreeSet<_>>();
+                let extra = keys.difference(&base).copied().collect::<BTreeSet<_>>();
+                (locale.as_str(), missing, extra)
+            })
+            .filter(|(_, missing, extra)| !missing.is_empty() || !extra.is_empty())
+            .collect()
+    }
+
+    pub fn base_locale(&self) -> &str {
+        &self.base_locale
+    }
+
+    pub fn contains_base_key(&self, key: &str) -> bool {
+        self.locales[&self.base_locale].contains_key(key)
+    }
+
+    /// Each locale, along with its strings sorted by key.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &BTreeMap<String, String>)> {
+        self.locales
+            .iter()
+            .map(|(locale, strings)| (locale.as_str(), strings))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("en", Some("values-en"))]
+    #[case("pt-BR", Some("values-pt-rBR"))]
+    #[case("pt-br", Some("values-pt-rBR"))]
+    #[case("zh-Hans", Some("values-b+zh+Hans"))]
+    #[case("zh-Hans-CN", Some("values-b+zh+Hans+CN"))]
+    #[case("es-419", Some("values-b+es+419"))]
+    #[case("", None)]
+    #[case("english", None)]
+    #[case("en-US-x", None)]
+    #[case("../en", None)]
+    fn test_android_values_dir(#[case] locale: &str, #[case] expected: Option<&str>) {
+        assert_eq!(check_locale(locale).is_ok(), expected.is_some());
+        if let Some(expected) = expected {
+            assert_eq!(android_values_dir(locale), expected);
+        }
+    }
+
+    #[test]
+    fn values_are_escaped() {
+        assert_eq!(
+            android_escape("@Tom's \"R&D\" <b>\\\n"),
+            r#"\@Tom\'s \"R&amp;D\" &lt;b&gt;\\\n"#
+        );
+        assert_eq!(apple_escape("Say \"hi\"\\\n"), r#"Say \"hi\"\\\n"#);
+    }
+
+    #[test]
+    fn mismatched_keys_are_found() {
+        let locales = serde_json::from_str(
+            r#"{
+                "en": { "greeting": "Hello", "farewell": "Bye" },
+                "fr": { "greeting": "Bonjour", "farwell": "Salut" },
+                "de": { "greeting": "Hallo", "farewell": "Tschüss" }
+            }"#,
+        )
+        .unwrap();
+        let strings = Strings::new("en", locales).unwrap();
+        assert_eq!(
+            strings.mismatched_keys(),
+            [(
+                "fr",
+                BTreeSet::from(["farewell"]),
+                BTreeSet::from(["farwell"])
+            )]
+        );
+        assert!(matches!(
+            Strings::new("es", strings.locales.clone()),
+            Err(Error::BaseLocaleMissing(_))
+        ));
+    }
+}
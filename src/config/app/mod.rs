@@ -1,27 +1,33 @@
+pub mod artifact_name;
 mod common_email_providers;
 pub mod identifier;
 pub mod lib_name;
+pub mod localization;
 pub mod name;
 mod raw;
+pub mod url_scheme;
 
 pub use self::raw::*;
 
 use crate::{
     opts::Profile,
     templating::{self, Pack},
-    util::{self, cli::Report},
+    util::{self, cli::Report, Git},
 };
 use serde::Serialize;
 use std::{
     fmt::Debug,
+    io,
+    num::NonZeroU32,
     path::{Path, PathBuf},
-    sync::Arc,
+    sync::{Arc, OnceLock},
 };
 use thiserror::Error;
 
 pub static KEY: &str = "app";
 
 pub static DEFAULT_ASSET_DIR: &str = "assets";
+pub static DEFAULT_BUILD_INFO_PATH: &str = "src/build_info.rs";
 pub static IMPLIED_TEMPLATE_PACK: &str = "brainstorm";
 pub static DEFAULT_TEMPLATE_PACK: &str = if cfg!(feature = "brainium") {
     IMPLIED_TEMPLATE_PACK
@@ -50,8 +56,33 @@ pub enum Error {
         asset_dir: PathBuf,
         root_dir: PathBuf,
     },
+    #[error("`app.lib-template` {lib_template} couldn't be normalized: {cause}")]
+    LibTemplateNormalizationFailed {
+        lib_template: PathBuf,
+        cause: util::NormalizationError,
+    },
+    #[error("`app.lib-template` {lib_template} is outside of the app root {root_dir}")]
+    LibTemplateOutsideOfAppRoot {
+        lib_template: PathBuf,
+        root_dir: PathBuf,
+    },
     #[error(transparent)]
     TemplatePackNotFound(templating::LookupError),
+    #[error("`app.cargo-jobs` must be greater than zero")]
+    CargoJobsZero,
+    #[error(transparent)]
+    ArtifactNameTemplateInvalid(artifact_name::Error),
+    #[error("`app.debug-identifier-suffix` {0:?} must start with a `.`")]
+    DebugIdentifierSuffixMissingDot(String),
+    #[error("`app.debug-identifier-suffix` {suffix:?} doesn't make a valid identifier: {cause}")]
+    DebugIdentifierSuffixInvalid {
+        suffix: String,
+        cause: identifier::IdentifierError,
+    },
+    #[error("`app.url-schemes` entry invalid: {0}")]
+    UrlSchemeInvalid(url_scheme::Invalid),
+    #[error("`app.localization` invalid: {0}")]
+    LocalizationInvalid(localization::Invalid),
 }
 
 impl Error {
@@ -67,11 +98,36 @@ pub struct App {
     name: String,
     lib_name: Option<String>,
     stylized_name: String,
+    display_name: String,
     identifier: String,
+    debug_identifier_suffix: Option<String>,
     asset_dir: PathBuf,
+    relative_paths: bool,
+    url_schemes: Vec<url_scheme::UrlScheme>,
+    localization: Option<localization::Localization>,
     #[serde(skip)]
     template_pack: Pack,
     #[serde(skip)]
+    lib_template: Option<PathBuf>,
+    #[serde(skip)]
+    forward_env: Vec<String>,
+    #[serde(skip)]
+    cargo_config_extra: toml::Table,
+    #[serde(skip)]
+    build_info_path: PathBuf,
+    #[serde(skip)]
+    preserve_on_regen: Vec<String>,
+    #[serde(skip)]
+    use_sccache: bool,
+    #[serde(skip)]
+    cargo_jobs: Option<NonZeroU32>,
+    #[serde(skip)]
+    artifact_name_template: Option<String>,
+    #[serde(skip)]
+    target_dir_override: Option<PathBuf>,
+    #[serde(skip)]
+    workspace_target_dir: OnceLock<PathBuf>,
+    #[serde(skip)]
     #[allow(clippy::type_complexity)]
     target_dir_resolver: Option<Arc<Box<dyn Fn(&str, Profile) -> PathBuf>>>,
 }
@@ -82,13 +138,56 @@ impl Debug for App {
             .field("root_dir", &self.root_dir)
             .field("name", &self.name)
             .field("stylized_name", &self.stylized_name)
+            .field("display_name", &self.display_name)
             .field("identifier", &self.identifier)
+            .field("debug_identifier_suffix", &self.debug_identifier_suffix)
             .field("asset_dir", &self.asset_dir)
             .field("template_pack", &self.template_pack)
             .finish()
     }
 }
 
+fn debug_identifier_suffix(identifier: &str, raw: Option<String>) -> Result<Option<String>, Error> {
+    let Some(suffix) = raw else {
+        return Ok(None);
+    };
+    if !suffix.starts_with('.') {
+        return Err(Error::DebugIdentifierSuffixMissingDot(suffix));
+    }
+    identifier::check_identifier_syntax(&format!("{}{}", identifier, suffix)).map_err(|cause| {
+        Error::DebugIdentifierSuffixInvalid {
+            suffix: suffix.clone(),
+            cause,
+        }
+    })?;
+    Ok(Some(suffix))
+}
+
+// Cargo puts build outputs in the `target` dir next to the workspace's
+// manifest, which is only the app root's if the app isn't a workspace member.
+fn locate_target_dir(root_dir: &Path) -> PathBuf {
+    let manifest = duct::cmd(
+        "cargo",
+        ["locate-project", "--workspace", "--message-format", "plain"],
+    )
+    .dir(root_dir)
+    .stderr_capture()
+    .read();
+    match manifest {
+        Ok(manifest) => Path::new(manifest.trim())
+            .parent()
+            .map(|dir| dir.join("target"))
+            .unwrap_or_else(|| root_dir.join("target")),
+        Err(err) => {
+            log::info!(
+                "failed to locate cargo workspace, so assuming the target dir is in the app root: {}",
+                err
+            );
+            root_dir.join("target")
+        }
+    }
+}
+
 impl App {
     pub fn from_raw(root_dir: PathBuf, raw: Raw) -> Result<Self, Error> {
         assert!(root_dir.is_absolute(), "root must be absolute");
@@ -99,6 +198,8 @@ impl App {
 
         let stylized_name = raw.stylized_name.unwrap_or_else(|| name.clone());
 
+        let display_name = raw.display_name.unwrap_or_else(|| stylized_name.clone());
+
         let identifier = {
             let identifier = raw.identifier;
             identifier::check_identifier_syntax(&identifier)
@@ -109,6 +210,9 @@ impl App {
                 .map(|()| identifier)
         }?;
 
+        let debug_identifier_suffix =
+            debug_identifier_suffix(&identifier, raw.debug_identifier_suffix)?;
+
         if raw.asset_dir.as_deref() == Some(DEFAULT_ASSET_DIR) {
             log::warn!(
                 "`{}.asset-dir` is set to the default value; you can remove it from your config",
@@ -131,6 +235,43 @@ impl App {
             });
         }
 
+        let lib_template = raw.lib_template.map(PathBuf::from);
+        if let Some(lib_template) = &lib_template {
+            if !util::under_root(lib_template, &root_dir).map_err(|cause| {
+                Error::LibTemplateNormalizationFailed {
+                    lib_template: lib_template.clone(),
+                    cause,
+                }
+            })? {
+                return Err(Error::LibTemplateOutsideOfAppRoot {
+                    lib_template: lib_template.clone(),
+                    root_dir,
+                });
+            }
+        }
+
+        let cargo_jobs = raw
+            .cargo_jobs
+            .map(|jobs| NonZeroU32::new(jobs).ok_or(Error::CargoJobsZero))
+            .transpose()?;
+
+        if let Some(template) = &raw.artifact_name_template {
+            artifact_name::validate(template, &name).map_err(Error::ArtifactNameTemplateInvalid)?;
+        }
+
+        let url_schemes = raw
+            .url_schemes
+            .into_iter()
+            .map(url_scheme::UrlScheme::from_raw)
+            .collect::<Result<_, _>>()
+            .map_err(Error::UrlSchemeInvalid)?;
+
+        let localization = raw
+            .localization
+            .map(|raw| localization::Localization::from_raw(&root_dir, raw))
+            .transpose()
+            .map_err(Error::LocalizationInvalid)?;
+
         let template_pack = {
             if raw.template_pack.as_deref() == Some(IMPLIED_TEMPLATE_PACK) {
                 log::warn!(
@@ -153,9 +294,27 @@ impl App {
             name,
             lib_name,
             stylized_name,
+            display_name,
             identifier,
+            debug_identifier_suffix,
             asset_dir,
+            relative_paths: raw.relative_paths.unwrap_or_default(),
+            url_schemes,
+            localization,
             template_pack,
+            lib_template,
+            forward_env: raw.forward_env,
+            cargo_config_extra: raw.cargo_config_extra.unwrap_or_default(),
+            build_info_path: raw
+                .build_info_path
+                .map(PathBuf::from)
+                .unwrap_or_else(|| DEFAULT_BUILD_INFO_PATH.into()),
+            preserve_on_regen: raw.preserve_on_regen,
+            use_sccache: raw.use_sccache.unwrap_or_default(),
+            cargo_jobs,
+            artifact_name_template: raw.artifact_name_template,
+            target_dir_override: raw.target_dir.map(PathBuf::from),
+            workspace_target_dir: OnceLock::new(),
             target_dir_resolver: None,
         })
     }
@@ -181,7 +340,19 @@ impl App {
         } else if let Ok(target) = std::env::var("CARGO_BUILD_TARGET_DIR") {
             self.prefix_path(format!("{}/{}/{}", target, triple, profile.as_str()))
         } else {
-            self.prefix_path(format!("target/{}/{}", triple, profile.as_str()))
+            self.cargo_target_dir().join(triple).join(profile.as_str())
+        }
+    }
+
+    /// The `target` dir cargo uses when the environment doesn't override it:
+    /// `app.target-dir` if it's set, and otherwise the workspace's.
+    pub fn cargo_target_dir(&self) -> PathBuf {
+        match &self.target_dir_override {
+            Some(target_dir) => self.prefix_path(target_dir),
+            None => self
+                .workspace_target_dir
+                .get_or_init(|| locate_target_dir(self.root_dir()))
+                .clone(),
         }
     }
 
@@ -210,10 +381,34 @@ impl App {
         &self.stylized_name
     }
 
+    /// The name shown on the home screen, which defaults to the stylized name.
+    pub fn display_name(&self) -> &str {
+        &self.display_name
+    }
+
     pub fn identifier(&self) -> &str {
         &self.identifier
     }
 
+    pub fn debug_identifier_suffix(&self) -> Option<&str> {
+        self.debug_identifier_suffix.as_deref()
+    }
+
+    /// The identifier builds with `profile` are installed under, which has
+    /// `app.debug-identifier-suffix` appended for debug builds.
+    pub fn identifier_for(&self, profile: Profile) -> String {
+        self.suffixed_for(&self.identifier, profile)
+    }
+
+    /// Appends `app.debug-identifier-suffix` to `id` for debug builds, for
+    /// platforms whose IDs can differ from `app.identifier`.
+    pub fn suffixed_for(&self, id: &str, profile: Profile) -> String {
+        match (profile, self.debug_identifier_suffix()) {
+            (Profile::Debug, Some(suffix)) => format!("{}{}", id, suffix),
+            _ => id.to_owned(),
+        }
+    }
+
     pub fn android_identifier_escape_kotlin_keyword(&self) -> String {
         self.identifier
             .split('.')
@@ -236,7 +431,146 @@ impl App {
         self.root_dir().join(&self.asset_dir)
     }
 
+    /// Whether `app.relative-paths` is set, in which case the `prefix-path`
+    /// template helper makes paths relative to the project being generated.
+    pub fn relative_paths(&self) -> bool {
+        self.relative_paths
+    }
+
+    /// URL schemes the app registers to be opened by, on both platforms.
+    pub fn url_schemes(&self) -> &[url_scheme::UrlScheme] {
+        &self.url_schemes
+    }
+
+    pub fn localization(&self) -> Option<&localization::Localization> {
+        self.localization.as_ref()
+    }
+
+    /// The paths that can affect a build: the crate's sources and manifest,
+    /// the asset dir, and the config file.
+    pub fn build_inputs(&self) -> Vec<PathBuf> {
+        let root_dir = self.root_dir();
+        vec![
+            root_dir.join("src"),
+            root_dir.join("build.rs"),
+            self.manifest_path(),
+            root_dir.join("Cargo.lock"),
+            self.asset_dir(),
+            root_dir.join(super::file_name()),
+        ]
+    }
+
+    /// Checks if any of the [`build_inputs`](Self::build_inputs) changed since
+    /// `git_ref`.
+    pub fn changed_since(&self, git_ref: &str) -> io::Result<bool> {
+        Git::new(self.root_dir())
+            .changed_files(git_ref, &self.build_inputs())
+            .map(|files| !files.is_empty())
+    }
+
     pub fn template_pack(&self) -> &Pack {
         &self.template_pack
     }
+
+    pub fn lib_template(&self) -> Option<PathBuf> {
+        self.lib_template
+            .as_ref()
+            .map(|lib_template| self.prefix_path(lib_template))
+    }
+
+    /// Names of additional environment variables to pass through to cargo,
+    /// Gradle, and Xcode.
+    pub fn forward_env(&self) -> &[String] {
+        &self.forward_env
+    }
+
+    /// Extra sections to merge into the generated `.cargo/config.toml`.
+    pub fn cargo_config_extra(&self) -> &toml::Table {
+        &self.cargo_config_extra
+    }
+
+    /// Where `--with-build-info` writes the generated build info module.
+    pub fn build_info_path(&self) -> PathBuf {
+        self.prefix_path(&self.build_info_path)
+    }
+
+    /// Gitignore-style patterns, relative to the app root, for generated files
+    /// that are left alone once they exist.
+    pub fn preserve_on_regen(&self) -> &[String] {
+        &self.preserve_on_regen
+    }
+
+    /// Whether cargo should be run with `RUSTC_WRAPPER=sccache`.
+    pub fn use_sccache(&self) -> bool {
+        self.use_sccache
+    }
+
+    /// How many jobs cargo should run in parallel, if it's been limited.
+    pub fn cargo_jobs(&self) -> Option<NonZeroU32> {
+        self.cargo_jobs
+    }
+
+    /// How built artifacts should be named; see [`artifact_name::apply`].
+    pub fn artifact_name_template(&self) -> Option<&str> {
+        self.artifact_name_template.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(None, true)]
+    #[case(Some(".debug"), true)]
+    #[case(Some(".debug.local"), true)]
+    #[case(Some("debug"), false)]
+    #[case(Some("."), false)]
+    #[case(Some(".2"), false)]
+    #[case(Some(".new"), false)]
+    fn test_debug_identifier_suffix(#[case] raw: Option<&str>, #[case] valid: bool) {
+        assert_eq!(
+            debug_identifier_suffix("com.example.app", raw.map(ToOwned::to_owned)).is_ok(),
+            valid
+        );
+    }
+
+    #[test]
+    fn target_dir_is_the_workspace_one() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        let manifest = |name: &str| {
+            format!("[package]\nname = \"{name}\"\nversion = \"0.1.0\"\nedition = \"2021\"\n")
+        };
+        let write_crate = |dir: &Path, manifest: String| {
+            std::fs::create_dir_all(dir.join("src")).unwrap();
+            std::fs::write(dir.join("Cargo.toml"), manifest).unwrap();
+            std::fs::write(dir.join("src/lib.rs"), "").unwrap();
+        };
+        let workspace = root.join("workspace");
+        std::fs::create_dir_all(&workspace).unwrap();
+        std::fs::write(
+            workspace.join("Cargo.toml"),
+            "[workspace]\nmembers = [\"app\"]\n",
+        )
+        .unwrap();
+        write_crate(&workspace.join("app"), manifest("member-app"));
+        let standalone = root.join("standalone");
+        write_crate(&standalone, manifest("standalone-app"));
+
+        // Temp dirs can be behind symlinks, like `/var` on macOS.
+        let canonical = |target_dir: PathBuf| {
+            let parent = target_dir.parent().unwrap().canonicalize().unwrap();
+            parent.join(target_dir.file_name().unwrap())
+        };
+        assert_eq!(
+            canonical(locate_target_dir(&workspace.join("app"))),
+            workspace.canonicalize().unwrap().join("target")
+        );
+        assert_eq!(
+            canonical(locate_target_dir(&standalone)),
+            standalone.canonicalize().unwrap().join("target")
+        );
+    }
 }
@@ -159,39 +159,109 @@ pub struct Raw {
     pub name: String,
     pub lib_name: Option<String>,
     pub stylized_name: Option<String>,
+    pub display_name: Option<String>,
     pub identifier: String,
     pub asset_dir: Option<String>,
     pub template_pack: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub forward_env: Vec<String>,
+    pub cargo_config_extra: Option<toml::Table>,
+    pub build_info_path: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub preserve_on_regen: Vec<String>,
+    pub use_sccache: Option<bool>,
+    pub cargo_jobs: Option<u32>,
+    pub env_file: Option<String>,
+    pub artifact_name_template: Option<String>,
+    /// Appended to the identifier for debug builds, like `.debug`, so that
+    /// debug and release builds can be installed side by side.
+    pub debug_identifier_suffix: Option<String>,
+    /// Where cargo puts build outputs, relative to the app root, for when it's
+    /// somewhere `cargo locate-project` can't tell, like a `build.target-dir`
+    /// in `.cargo/config.toml`.
+    pub target_dir: Option<String>,
+    /// Whether generated projects refer to files in the app by relative
+    /// paths, so they keep working when the repo is checked out elsewhere.
+    pub relative_paths: Option<bool>,
+    /// Custom URL schemes the app can be opened with, like `myapp`, or
+    /// `https` with a `host` for Android App Links.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub url_schemes: Vec<super::url_scheme::Raw>,
+    /// A JSON file of strings for each locale to generate each platform's
+    /// localized string resources from.
+    pub localization: Option<super::localization::Raw>,
+    /// A template to generate `src/lib.rs` from instead of the template
+    /// pack's, relative to the app root.
+    pub lib_template: Option<String>,
 }
 
 impl Raw {
-    pub fn detect(wrapper: &TextWrapper) -> Result<Self, DetectError> {
+    /// `template_pack` takes the place of the default pack, if given.
+    pub fn detect(wrapper: &TextWrapper, template_pack: Option<&str>) -> Result<Self, DetectError> {
         let defaults = Defaults::new(wrapper).map_err(DetectError::DefaultsFailed)?;
         Ok(Self {
             name: defaults.name.ok_or_else(|| DetectError::NameNotDetected)?,
             lib_name: None,
             stylized_name: Some(defaults.stylized_name),
+            display_name: None,
             identifier: defaults.identifier,
             asset_dir: None,
-            template_pack: Some(super::DEFAULT_TEMPLATE_PACK.to_owned())
-                .filter(|pack| pack != super::IMPLIED_TEMPLATE_PACK),
+            template_pack: Some(
+                template_pack
+                    .unwrap_or(super::DEFAULT_TEMPLATE_PACK)
+                    .to_owned(),
+            )
+            .filter(|pack| pack != super::IMPLIED_TEMPLATE_PACK),
+            forward_env: Vec::new(),
+            cargo_config_extra: None,
+            build_info_path: None,
+            preserve_on_regen: Vec::new(),
+            use_sccache: None,
+            cargo_jobs: None,
+            env_file: None,
+            artifact_name_template: None,
+            debug_identifier_suffix: None,
+            target_dir: None,
+            relative_paths: None,
+            url_schemes: Vec::new(),
+            localization: None,
+            lib_template: None,
         })
     }
 
-    pub fn prompt(wrapper: &TextWrapper) -> Result<Self, PromptError> {
+    /// The template pack is only prompted for if `template_pack` isn't given.
+    pub fn prompt(wrapper: &TextWrapper, template_pack: Option<&str>) -> Result<Self, PromptError> {
         let defaults = Defaults::new(wrapper).map_err(PromptError::DefaultsFailed)?;
         let (name, default_stylized) = Self::prompt_name(&defaults)?;
         let stylized_name = Self::prompt_stylized_name(&name, default_stylized)?;
         let identifier = Self::prompt_identifier(wrapper, &defaults)?;
-        let template_pack = Some(Self::prompt_template_pack(wrapper)?)
-            .filter(|pack| pack != super::IMPLIED_TEMPLATE_PACK);
+        let template_pack = match template_pack {
+            Some(template_pack) => template_pack.to_owned(),
+            None => Self::prompt_template_pack(wrapper)?,
+        };
+        let template_pack = Some(template_pack).filter(|pack| pack != super::IMPLIED_TEMPLATE_PACK);
         Ok(Self {
             name,
             lib_name: None,
             stylized_name: Some(stylized_name),
+            display_name: None,
             identifier,
             asset_dir: None,
             template_pack,
+            forward_env: Vec::new(),
+            cargo_config_extra: None,
+            build_info_path: None,
+            preserve_on_regen: Vec::new(),
+            use_sccache: None,
+            cargo_jobs: None,
+            env_file: None,
+            artifact_name_template: None,
+            debug_identifier_suffix: None,
+            target_dir: None,
+            relative_paths: None,
+            url_schemes: Vec::new(),
+            localization: None,
+            lib_template: None,
         })
     }
 }
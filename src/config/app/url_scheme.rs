@@ -0,0 +1,133 @@
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum Invalid {
+    #[error("{0:?} has to start with a lowercase letter and only contain lowercase letters, digits, `+`, `-`, and `.`")]
+    SchemeInvalid(String),
+    #[error("host {host:?} for {scheme:?} has to be a hostname, like `example.com` or `*.example.com`, with dot-separated labels of letters, digits, and `-`")]
+    HostInvalid { scheme: String, host: String },
+    #[error("{0:?} links need a `host`, since they'd otherwise open every web link")]
+    HostMissing(String),
+}
+
+/// Either just a scheme, like `"myapp"`, or a table with a `host` too, like
+/// `{ scheme = "https", host = "example.com" }`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Raw {
+    Scheme(String),
+    WithHost {
+        scheme: String,
+        host: Option<String>,
+    },
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct UrlScheme {
+    scheme: String,
+    host: Option<String>,
+    /// `http` and `https` links are Android App Links, which are verified
+    /// against the host's `assetlinks.json`.
+    auto_verify: bool,
+}
+
+fn scheme_valid(scheme: &str) -> bool {
+    scheme.starts_with(|c: char| c.is_ascii_lowercase())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '+' | '-' | '.'))
+}
+
+// A DNS hostname, optionally with a leading `*.` to match its subdomains, which
+// is what both App Links and Universal Links accept.
+fn host_valid(host: &str) -> bool {
+    let host = host.strip_prefix("*.").unwrap_or(host);
+    host.len() <= 253
+        && host.split('.').all(|label| {
+            (1..=63).contains(&label.len())
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+}
+
+impl UrlScheme {
+    pub fn from_raw(raw: Raw) -> Result<Self, Invalid> {
+        let (scheme, host) = match raw {
+            Raw::Scheme(scheme) => (scheme, None),
+            Raw::WithHost { scheme, host } => (scheme, host),
+        };
+        if !scheme_valid(&scheme) {
+            return Err(Invalid::SchemeInvalid(scheme));
+        }
+        if let Some(host) = &host {
+            if !host_valid(host) {
+                return Err(Invalid::HostInvalid {
+                    scheme,
+                    host: host.clone(),
+                });
+            }
+        }
+        let auto_verify = scheme == "http" || scheme == "https";
+        if auto_verify && host.is_none() {
+            return Err(Invalid::HostMissing(scheme));
+        }
+        Ok(Self {
+            scheme,
+            host,
+            auto_verify,
+        })
+    }
+
+    pub fn scheme(&self) -> &str {
+        &self.scheme
+    }
+
+    pub fn host(&self) -> Option<&str> {
+        self.host.as_deref()
+    }
+
+    /// Whether this is a web link rather than a custom scheme. iOS can only
+    /// open those through Universal Links, so they're left out of the
+    /// `Info.plist`.
+    pub fn is_web(&self) -> bool {
+        self.auto_verify
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("myapp", None, true)]
+    #[case("my-app+v2.beta", None, true)]
+    #[case("myapp", Some("open"), true)]
+    #[case("https", Some("example.com"), true)]
+    #[case("MyApp", None, false)]
+    #[case("my app", None, false)]
+    #[case("myapp:", None, false)]
+    #[case("2app", None, false)]
+    #[case("", None, false)]
+    #[case("https", None, false)]
+    #[case("https", Some("example.com/path"), false)]
+    #[case("myapp", Some(""), false)]
+    #[case("https", Some("*.example.com"), true)]
+    #[case("https", Some("my-site.example.com"), true)]
+    #[case("https", Some("example.com:8080"), false)]
+    #[case("https", Some("exa mple.com"), false)]
+    #[case("https", Some("example..com"), false)]
+    #[case("https", Some("-example.com"), false)]
+    #[case("https", Some("example.com?q=1"), false)]
+    #[case("https", Some("ex*ample.com"), false)]
+    fn test_from_raw(#[case] scheme: &str, #[case] host: Option<&str>, #[case] valid: bool) {
+        let raw = Raw::WithHost {
+            scheme: scheme.to_owned(),
+            host: host.map(ToOwned::to_owned),
+        };
+        assert_eq!(UrlScheme::from_raw(raw).is_ok(), valid);
+    }
+}
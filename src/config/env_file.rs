@@ -0,0 +1,73 @@
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+#[error("Failed to load env file {path}: {cause}")]
+pub struct Error {
+    path: PathBuf,
+    cause: dotenvy::Error,
+}
+
+/// Reads the variables in the env file at `path`, which are meant to be
+/// passed to everything cargo-mobile2 runs. `${VAR}` in values is expanded
+/// from the process env or from earlier lines. Variables that are already set
+/// in the process env keep that value instead, so that it wins over the file
+/// even for commands that don't inherit the process env, like `xcodebuild`.
+pub fn load(path: &Path) -> Result<HashMap<String, OsString>, Error> {
+    load_with(path, |key| std::env::var_os(key))
+}
+
+fn load_with(
+    path: &Path,
+    lookup: impl Fn(&str) -> Option<OsString>,
+) -> Result<HashMap<String, OsString>, Error> {
+    let error = |cause| Error {
+        path: path.to_owned(),
+        cause,
+    };
+    let mut vars = HashMap::new();
+    for var in dotenvy::from_path_iter(path).map_err(error)? {
+        let (key, value) = var.map_err(error)?;
+        if let Some(set) = lookup(&key) {
+            log::info!(
+                "not loading `{}` from {:?}, since it's already set",
+                key,
+                path
+            );
+            vars.insert(key, set);
+        } else {
+            log::info!("loading `{}` from {:?}", key, path);
+            vars.insert(key, value.into());
+        }
+    }
+    Ok(vars)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn set_vars_win_over_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".env");
+        std::fs::write(
+            &path,
+            "# comment\nexport USER_NAME=me\nGREETING=\"hi ${USER_NAME}\"\nSET=file\n",
+        )
+        .unwrap();
+        let vars = load_with(&path, |key| (key == "SET").then(|| "env".into())).unwrap();
+        assert_eq!(
+            vars,
+            HashMap::from([
+                ("USER_NAME".to_owned(), OsString::from("me")),
+                ("GREETING".to_owned(), OsString::from("hi me")),
+                ("SET".to_owned(), OsString::from("env")),
+            ])
+        );
+    }
+}
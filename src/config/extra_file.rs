@@ -0,0 +1,133 @@
+use crate::{
+    bicycle::{self, Action, ProcessingError},
+    util,
+};
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+use thiserror::Error;
+
+/// A file from the app to copy into the generated project, e.g.
+/// `GoogleService-Info.plist` or `google-services.json`.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ExtraFile {
+    /// Relative to the app root.
+    pub src: String,
+    /// Relative to the generated project's root.
+    pub dest: String,
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("`src` {src} couldn't be normalized: {cause}")]
+    SrcNormalizationFailed {
+        src: String,
+        cause: util::NormalizationError,
+    },
+    #[error("`src` {src} is outside of the app root {root_dir}")]
+    SrcOutsideOfAppRoot { src: String, root_dir: PathBuf },
+    #[error("`dest` {0} must be a relative path inside the generated project")]
+    DestOutsideOfProject(String),
+}
+
+/// Checks that each `src` is under the app root, and that each `dest` stays
+/// inside the generated project.
+pub fn validate(root_dir: &Path, files: Vec<ExtraFile>) -> Result<Vec<ExtraFile>, Error> {
+    for file in &files {
+        let under_root = util::under_root(&file.src, root_dir).map_err(|cause| {
+            Error::SrcNormalizationFailed {
+                src: file.src.clone(),
+                cause,
+            }
+        })?;
+        if !under_root {
+            return Err(Error::SrcOutsideOfAppRoot {
+                src: file.src.clone(),
+                root_dir: root_dir.to_owned(),
+            });
+        }
+        let escapes = Path::new(&file.dest)
+            .components()
+            .any(|component| !matches!(component, Component::Normal(_) | Component::CurDir));
+        if file.dest.is_empty() || escapes {
+            return Err(Error::DestOutsideOfProject(file.dest.clone()));
+        }
+    }
+    Ok(files)
+}
+
+/// Copies `files` from the app at `root_dir` into `project_dir`, overwriting whatever's already there
+/// like the rest of generation does.
+pub fn copy_all(
+    bike: &bicycle::Bicycle,
+    root_dir: &Path,
+    files: &[ExtraFile],
+    project_dir: &Path,
+) -> Result<(), ProcessingError> {
+    for file in files {
+        let dest = project_dir.join(&file.dest);
+        if let Some(parent) = dest.parent() {
+            bike.process_action(
+                &Action::CreateDirectory {
+                    dest: parent.to_owned(),
+                },
+                |_| (),
+            )?;
+        }
+        bike.process_action(
+            &Action::CopyFile {
+                src: util::prefix_path(root_dir, &file.src),
+                dest,
+            },
+            |_| (),
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("GoogleService-Info.plist", "GoogleService-Info.plist", true)]
+    #[case("config/google-services.json", "./app/google-services.json", true)]
+    #[case("../secrets.json", "secrets.json", false)]
+    #[case("secrets.json", "../secrets.json", false)]
+    #[case("secrets.json", "/etc/secrets.json", false)]
+    #[case("secrets.json", "", false)]
+    fn test_validate(#[case] src: &str, #[case] dest: &str, #[case] valid: bool) {
+        let files = vec![ExtraFile {
+            src: src.to_owned(),
+            dest: dest.to_owned(),
+        }];
+        assert_eq!(
+            validate(Path::new("/app"), files.clone()).ok(),
+            valid.then_some(files)
+        );
+    }
+
+    #[test]
+    fn files_are_copied_into_the_project() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        std::fs::create_dir_all(root.join("config")).unwrap();
+        std::fs::write(root.join("config/google-services.json"), "{}").unwrap();
+        let project_dir = root.join("gen/android");
+        copy_all(
+            &bicycle::Bicycle::default(),
+            root,
+            &[ExtraFile {
+                src: "config/google-services.json".to_owned(),
+                dest: "app/google-services.json".to_owned(),
+            }],
+            &project_dir,
+        )
+        .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(project_dir.join("app/google-services.json")).unwrap(),
+            "{}"
+        );
+    }
+}
@@ -1,17 +1,27 @@
 pub mod app;
+pub mod env_file;
+pub mod extra_file;
 pub mod metadata;
+pub mod overrides;
 mod raw;
-pub use raw::Raw;
+pub mod target_env;
+pub mod toolchain;
+pub use raw::{LoadError, Raw};
 
-use self::{app::App, raw::*};
+use self::{app::App, overrides::Override, raw::*};
 #[cfg(target_os = "macos")]
 use crate::apple;
 use crate::{
     android, bicycle, templating,
-    util::cli::{Report, Reportable, TextWrapper},
+    util::{
+        self,
+        cli::{Report, Reportable, TextWrapper},
+    },
 };
 use serde::Serialize;
 use std::{
+    collections::HashMap,
+    ffi::OsString,
     fmt::Debug,
     io,
     path::{Path, PathBuf},
@@ -31,6 +41,19 @@ pub enum FromRawError {
     AppleConfigInvalid(apple::config::Error),
     #[error(transparent)]
     AndroidConfigInvalid(android::config::Error),
+    #[error("`{key}` {path} couldn't be normalized: {cause}")]
+    DirNormalizationFailed {
+        key: &'static str,
+        path: PathBuf,
+        cause: util::NormalizationError,
+    },
+    #[error("`{outer}` {outer_path} and `{inner}` {inner_path} overlap, so generating one would clobber the other")]
+    DirsOverlap {
+        outer: &'static str,
+        outer_path: PathBuf,
+        inner: &'static str,
+        inner_path: PathBuf,
+    },
 }
 
 impl FromRawError {
@@ -40,10 +63,48 @@ impl FromRawError {
             #[cfg(target_os = "macos")]
             Self::AppleConfigInvalid(err) => err.report(msg),
             Self::AndroidConfigInvalid(err) => err.report(msg),
+            Self::DirNormalizationFailed { .. } | Self::DirsOverlap { .. } => {
+                Report::error(msg, self)
+            }
         }
     }
 }
 
+// Generating into a dir that contains another generated dir (or the assets)
+// copies things into themselves.
+fn check_dirs_overlap(dirs: &[(&'static str, PathBuf)]) -> Result<(), FromRawError> {
+    let dirs = dirs
+        .iter()
+        .map(|(key, path)| {
+            util::normalize_path(path)
+                .map(|norm| (*key, dunce::simplified(&norm).to_owned()))
+                .map_err(|cause| FromRawError::DirNormalizationFailed {
+                    key,
+                    path: path.clone(),
+                    cause,
+                })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    for (i, (key, path)) in dirs.iter().enumerate() {
+        for (other_key, other_path) in &dirs[i + 1..] {
+            let (outer, inner) = if other_path.starts_with(path) {
+                ((key, path), (other_key, other_path))
+            } else if path.starts_with(other_path) {
+                ((other_key, other_path), (key, path))
+            } else {
+                continue;
+            };
+            return Err(FromRawError::DirsOverlap {
+                outer: outer.0,
+                outer_path: outer.1.clone(),
+                inner: inner.0,
+                inner_path: inner.1.clone(),
+            });
+        }
+    }
+    Ok(())
+}
+
 #[derive(Debug, Error)]
 pub enum GenError {
     #[error(transparent)]
@@ -56,11 +117,16 @@ pub enum GenError {
     FromRawFailed(FromRawError),
     #[error(transparent)]
     WriteFailed(WriteError),
+    #[error(transparent)]
+    OverrideFailed(overrides::Error),
 }
 
 impl Reportable for GenError {
     fn report(&self) -> Report {
-        Report::error("Failed to generate config", self)
+        match self {
+            Self::WriteFailed(err) => err.report(),
+            _ => Report::error("Failed to generate config", self),
+        }
     }
 }
 
@@ -71,12 +137,19 @@ pub enum LoadOrGenError {
     #[error("Config file at {path} invalid: {cause}")]
     FromRawFailed { path: PathBuf, cause: FromRawError },
     #[error(transparent)]
+    OverrideFailed(overrides::Error),
+    #[error(transparent)]
     GenFailed(GenError),
+    #[error(transparent)]
+    EnvFileFailed(env_file::Error),
 }
 
 impl Reportable for LoadOrGenError {
     fn report(&self) -> Report {
-        Report::error("Config error", self)
+        match self {
+            Self::GenFailed(err) => err.report(),
+            _ => Report::error("Config error", self),
+        }
     }
 }
 
@@ -99,6 +172,8 @@ pub struct Config {
     #[cfg(target_os = "macos")]
     apple: apple::config::Config,
     android: android::config::Config,
+    #[serde(skip)]
+    env_file_vars: HashMap<String, OsString>,
 }
 
 impl Config {
@@ -109,59 +184,143 @@ impl Config {
             .map_err(FromRawError::AppleConfigInvalid)?;
         let android = android::config::Config::from_raw(app.clone(), raw.android)
             .map_err(FromRawError::AndroidConfigInvalid)?;
+        check_dirs_overlap(&[
+            ("app.asset-dir", app.asset_dir()),
+            #[cfg(target_os = "macos")]
+            ("apple.project-dir", apple.project_dir()),
+            ("android.project-dir", android.project_dir()),
+        ])?;
         Ok(Self {
             app,
             #[cfg(target_os = "macos")]
             apple,
             android,
+            env_file_vars: Default::default(),
         })
     }
 
     fn gen(
         cwd: impl AsRef<Path>,
         non_interactive: bool,
+        force_config: bool,
+        overrides: &[Override],
+        template_pack: Option<&str>,
         wrapper: &TextWrapper,
     ) -> Result<Self, GenError> {
         let raw = if !non_interactive {
-            Raw::prompt(wrapper).map_err(GenError::PromptFailed)
+            Raw::prompt(wrapper, template_pack).map_err(GenError::PromptFailed)
         } else {
-            Raw::detect(wrapper).map_err(GenError::DetectFailed)
+            Raw::detect(wrapper, template_pack).map_err(GenError::DetectFailed)
         }?;
         let root_dir = cwd
             .as_ref()
             .canonicalize()
             .map_err(GenError::CanonicalizeFailed)?;
+        // Overrides only apply to this run, so they're kept out of what gets written.
+        let overridden = raw
+            .clone()
+            .with_overrides(overrides)
+            .map_err(GenError::OverrideFailed)?;
         let config =
-            Self::from_raw(root_dir.clone(), raw.clone()).map_err(GenError::FromRawFailed)?;
+            Self::from_raw(root_dir.clone(), overridden).map_err(GenError::FromRawFailed)?;
         log::info!("generated config: {:#?}", config);
-        raw.write(&root_dir).map_err(GenError::WriteFailed)?;
+        raw.write(&root_dir, non_interactive, force_config)
+            .map_err(GenError::WriteFailed)?;
         Ok(config)
     }
 
+    /// `max_search_depth` bounds how many parent dirs are searched for an
+    /// existing config; see [`Raw::discover_root_within`]. If `app` is given,
+    /// the config of that workspace member is loaded instead; see
+    /// [`Raw::load_member`].
+    ///
+    /// `env_file`, or else `app.env-file`, is loaded along with the config;
+    /// see [`env_file::load`] and [`Self::env_file_vars`].
+    ///
+    /// `force_config` lets a config that's already there be overwritten
+    /// without asking; see [`Raw::write`].
+    ///
+    /// `template_pack` is used in place of the default or prompted-for pack
+    /// when a new config is generated, and is otherwise ignored.
+    #[allow(clippy::too_many_arguments)]
     pub fn load_or_gen(
         cwd: impl AsRef<Path>,
         non_interactive: bool,
+        force_config: bool,
+        app: Option<&str>,
+        overrides: &[Override],
+        max_search_depth: Option<usize>,
+        env_file: Option<&Path>,
+        template_pack: Option<&str>,
         wrapper: &TextWrapper,
     ) -> Result<(Self, Origin), LoadOrGenError> {
         let cwd = cwd.as_ref();
-        if let Some((root_dir, raw)) = Raw::load(cwd).map_err(LoadOrGenError::LoadFailed)? {
+        let loaded = match app {
+            Some(app) => Raw::load_member(cwd, max_search_depth, app).map(Some),
+            None => Raw::load_within(cwd, max_search_depth),
+        }
+        .map_err(LoadOrGenError::LoadFailed)?;
+        if let Some((root_dir, raw)) = loaded {
+            let raw = raw
+                .with_overrides(overrides)
+                .map_err(LoadOrGenError::OverrideFailed)?;
+            let env_file = env_file.map(ToOwned::to_owned).or_else(|| {
+                raw.app
+                    .env_file
+                    .as_ref()
+                    .map(|env_file| root_dir.join(env_file))
+            });
+            let env_file_vars = env_file
+                .map(|env_file| env_file::load(&env_file))
+                .transpose()
+                .map_err(LoadOrGenError::EnvFileFailed)?
+                .unwrap_or_default();
             Self::from_raw(root_dir.clone(), raw)
-                .map(|config| (config, Origin::Loaded))
+                .map(|config| (config.with_env_file_vars(env_file_vars), Origin::Loaded))
                 .map_err(|cause| LoadOrGenError::FromRawFailed {
                     path: root_dir,
                     cause,
                 })
         } else {
-            Self::gen(cwd, non_interactive, wrapper)
-                .map(|config| (config, Origin::FreshlyMinted))
-                .map_err(LoadOrGenError::GenFailed)
+            let env_file_vars = env_file
+                .map(env_file::load)
+                .transpose()
+                .map_err(LoadOrGenError::EnvFileFailed)?
+                .unwrap_or_default();
+            Self::gen(
+                cwd,
+                non_interactive,
+                force_config,
+                overrides,
+                template_pack,
+                wrapper,
+            )
+            .map(|config| {
+                (
+                    config.with_env_file_vars(env_file_vars),
+                    Origin::FreshlyMinted,
+                )
+            })
+            .map_err(LoadOrGenError::GenFailed)
         }
     }
 
+    fn with_env_file_vars(mut self, env_file_vars: HashMap<String, OsString>) -> Self {
+        self.env_file_vars = env_file_vars;
+        self
+    }
+
     pub fn path(&self) -> PathBuf {
         self.app().root_dir().join(file_name())
     }
 
+    /// The vars from the env file, if one was loaded, which go in the env of
+    /// everything cargo-mobile2 runs. Anything that sets vars more
+    /// specifically, like `env-vars` in `Cargo.toml`, wins over these.
+    pub fn env_file_vars(&self) -> &HashMap<String, OsString> {
+        &self.env_file_vars
+    }
+
     pub fn app(&self) -> &App {
         &self.app
     }
@@ -179,3 +338,43 @@ impl Config {
         templating::init(Some(self))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn nested_dirs_overlap() {
+        let result = check_dirs_overlap(&[
+            ("app.asset-dir", "/fun-app/gen/apple/assets".into()),
+            ("apple.project-dir", "/fun-app/gen/apple".into()),
+            ("android.project-dir", "/fun-app/gen/android".into()),
+        ]);
+        assert!(matches!(
+            result,
+            Err(FromRawError::DirsOverlap {
+                outer: "apple.project-dir",
+                inner: "app.asset-dir",
+                ..
+            })
+        ));
+        assert!(matches!(
+            check_dirs_overlap(&[
+                ("apple.project-dir", "/fun-app/gen".into()),
+                ("android.project-dir", "/fun-app/gen/../gen".into()),
+            ]),
+            Err(FromRawError::DirsOverlap { .. })
+        ));
+    }
+
+    #[test]
+    fn sibling_dirs_dont_overlap() {
+        check_dirs_overlap(&[
+            ("app.asset-dir", "/fun-app/assets".into()),
+            ("apple.project-dir", "/fun-app/gen/apple".into()),
+            // Sharing a prefix isn't the same as being nested.
+            ("android.project-dir", "/fun-app/gen/apple-android".into()),
+        ])
+        .unwrap();
+    }
+}
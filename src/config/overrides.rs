@@ -0,0 +1,469 @@
+use super::raw::Raw;
+use crate::util::cli::{Report, Reportable};
+use serde::{
+    de::{self, DeserializeSeed, IntoDeserializer as _, MapAccess, Visitor},
+    Deserialize, Deserializer,
+};
+use std::{cell::Cell, fmt, str::FromStr};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ParseError {
+    #[error("Config override {0:?} isn't of the form `key=value`")]
+    MissingEquals(String),
+    #[error("Config override {0:?} has an empty key")]
+    EmptyKey(String),
+}
+
+/// A `key=value` pair from `--config`, where `key` is a dotted path into
+/// `mobile.toml`, e.g. `apple.enable-bitcode=true`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Override {
+    key: String,
+    value: String,
+}
+
+impl FromStr for Override {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (key, value) = s
+            .split_once('=')
+            .ok_or_else(|| ParseError::MissingEquals(s.to_owned()))?;
+        let key = key.trim();
+        if key.is_empty() {
+            return Err(ParseError::EmptyKey(s.to_owned()));
+        }
+        Ok(Self {
+            key: key.to_owned(),
+            value: value.trim().to_owned(),
+        })
+    }
+}
+
+impl fmt::Display for Override {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", self.key, self.value)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Unknown config key `{key}`{}", .suggestion.as_ref().map(|s| format!("; did you mean `{}`?", s)).unwrap_or_default())]
+    UnknownKey {
+        key: String,
+        suggestion: Option<String>,
+    },
+    #[error("Config key `{key}` can't be overridden, since `{parent}` isn't a table")]
+    NotATable { key: String, parent: String },
+    #[error("Invalid value for config key `{key}`: {cause}")]
+    ValueInvalid { key: String, cause: toml::de::Error },
+    #[error("Failed to serialize config: {0}")]
+    SerializeFailed(toml::ser::Error),
+}
+
+impl Reportable for Error {
+    fn report(&self) -> Report {
+        Report::error("Failed to apply `--config` override", self)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Outcome {
+    Found,
+    // The part at `depth` isn't one of `fields`, or is missing, if the path
+    // ends at a table.
+    Unknown {
+        depth: usize,
+        fields: &'static [&'static str],
+    },
+    // The part before `depth` isn't a table.
+    NotATable {
+        depth: usize,
+    },
+}
+
+// Follows a key path through the types `Raw` is made of, by having them
+// deserialize themselves from a deserializer that only ever hands out the
+// next part of the path, and records where that ends.
+struct KeyProbe<'a> {
+    path: &'a [&'a str],
+    depth: usize,
+    outcome: &'a Cell<Option<Outcome>>,
+}
+
+impl KeyProbe<'_> {
+    fn stop<T>(&self, outcome: Outcome) -> Result<T, de::value::Error> {
+        self.outcome.set(Some(outcome));
+        Err(de::Error::custom("only probing for a key"))
+    }
+
+    fn leaf<T>(&self) -> Result<T, de::value::Error> {
+        if self.depth == self.path.len() {
+            self.stop(Outcome::Found)
+        } else {
+            self.stop(Outcome::NotATable { depth: self.depth })
+        }
+    }
+}
+
+macro_rules! leaves {
+    ($($method:ident)*) => {
+        $(fn $method<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+            self.leaf()
+        })*
+    };
+}
+
+impl<'de> Deserializer<'de> for KeyProbe<'_> {
+    type Error = de::value::Error;
+
+    // Free-form values, like `toml::Value` and untagged enums, could have
+    // any keys, so whether they fit is left to deserializing the result.
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+        self.stop(Outcome::Found)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        if self.depth == self.path.len() {
+            self.stop(Outcome::Found)
+        } else {
+            visitor.visit_map(NextPart(Some(self)))
+        }
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self.path.get(self.depth) {
+            Some(part) if fields.contains(part) => visitor.visit_map(NextPart(Some(self))),
+            _ => self.stop(Outcome::Unknown {
+                depth: self.depth,
+                fields,
+            }),
+        }
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.leaf()
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.leaf()
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.leaf()
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.leaf()
+    }
+
+    leaves! {
+        deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32
+        deserialize_i64 deserialize_i128 deserialize_u8 deserialize_u16
+        deserialize_u32 deserialize_u64 deserialize_u128 deserialize_f32
+        deserialize_f64 deserialize_char deserialize_str deserialize_string
+        deserialize_bytes deserialize_byte_buf deserialize_unit deserialize_seq
+        deserialize_identifier deserialize_ignored_any
+    }
+}
+
+// A map with just the next part of the path as its key.
+struct NextPart<'a>(Option<KeyProbe<'a>>);
+
+impl<'de> MapAccess<'de> for NextPart<'_> {
+    type Error = de::value::Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        self.0
+            .as_ref()
+            .map(|probe| seed.deserialize(probe.path[probe.depth].into_deserializer()))
+            .transpose()
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Self::Error> {
+        let probe = self
+            .0
+            .take()
+            .expect("developer error: value requested before key");
+        seed.deserialize(KeyProbe {
+            depth: probe.depth + 1,
+            ..probe
+        })
+    }
+}
+
+fn distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut prev = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut cur = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = prev[j] + usize::from(ca != *cb);
+            cur.push(substitution.min(prev[j + 1] + 1).min(cur[j] + 1));
+        }
+        prev = cur;
+    }
+    prev[b.len()]
+}
+
+fn closest<'a>(key: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    candidates
+        .into_iter()
+        .map(|candidate| (distance(key, candidate), candidate))
+        .filter(|(distance, _)| *distance <= 3)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+fn check_key(key: &str) -> Result<Vec<&str>, Error> {
+    let path = key.split('.').collect::<Vec<_>>();
+    let outcome = Cell::new(None);
+    let _ = Raw::deserialize(KeyProbe {
+        path: &path,
+        depth: 0,
+        outcome: &outcome,
+    });
+    match outcome
+        .get()
+        .expect("developer error: key probe didn't reach an outcome")
+    {
+        Outcome::Found => Ok(path),
+        Outcome::Unknown { depth, fields } => Err(Error::UnknownKey {
+            key: key.to_owned(),
+            suggestion: path.get(depth).and_then(|part| {
+                closest(part, fields.iter().copied())
+                    .map(|field| [&path[..depth], &[field]].concat().join("."))
+            }),
+        }),
+        Outcome::NotATable { depth } => Err(Error::NotATable {
+            key: key.to_owned(),
+            parent: path[..depth].join("."),
+        }),
+    }
+}
+
+// Values are parsed as TOML literals when possible, so `true` and `21` come
+// through as a bool and an integer. Whether that's what the field actually
+// wants is left to deserialization, falling back to a plain string.
+fn candidates(value: &str) -> Vec<toml::Value> {
+    let literal = format!("v = {}", value)
+        .parse::<toml::Table>()
+        .ok()
+        .and_then(|mut table| table.remove("v"));
+    literal
+        .into_iter()
+        .chain(std::iter::once(toml::Value::String(value.to_owned())))
+        .collect()
+}
+
+fn set(root: &mut toml::Table, key: &str, path: &[&str], value: toml::Value) -> Result<(), Error> {
+    let (leaf, parents) = path.split_last().expect("developer error: empty key path");
+    let mut table = root;
+    for (i, part) in parents.iter().enumerate() {
+        table = table
+            .entry(*part)
+            .or_insert_with(|| toml::Value::Table(Default::default()))
+            .as_table_mut()
+            .ok_or_else(|| Error::NotATable {
+                key: key.to_owned(),
+                parent: path[..=i].join("."),
+            })?;
+    }
+    table.insert((*leaf).to_owned(), value);
+    Ok(())
+}
+
+fn get<'a>(root: &'a toml::Table, path: &[&str]) -> Option<&'a toml::Value> {
+    let (leaf, parents) = path.split_last()?;
+    parents
+        .iter()
+        .try_fold(root, |table, part| table.get(*part)?.as_table())?
+        .get(*leaf)
+}
+
+impl Raw {
+    /// Patches in `--config` overrides, prior to validation by
+    /// [`Config::from_raw`](super::Config::from_raw).
+    /// Each override wins over the config file and any earlier override of
+    /// the same key, which is logged so it's clear where a value came from.
+    pub fn with_overrides(self, overrides: &[Override]) -> Result<Self, Error> {
+        let mut overridden = Vec::new();
+        overrides
+            .iter()
+            .try_fold(self, |raw, Override { key, value }| {
+                let path = check_key(key)?;
+                let root = toml::Table::try_from(&raw).map_err(Error::SerializeFailed)?;
+                // Overriding the config file is the point, but overriding an
+                // earlier `--config` is more likely a mistake.
+                match get(&root, &path) {
+                    Some(old) if overridden.contains(key) => log::warn!(
+                        "`--config {}={}` wins over {} from an earlier `--config`",
+                        key,
+                        value,
+                        old
+                    ),
+                    Some(old) => log::info!(
+                        "`--config {}={}` wins over {} from the config file",
+                        key,
+                        value,
+                        old
+                    ),
+                    None => (),
+                }
+                overridden.push(key.clone());
+                let mut last_err = None;
+                for candidate in candidates(value) {
+                    let mut root = root.clone();
+                    set(&mut root, key, &path, candidate)?;
+                    match toml::Value::Table(root).try_into::<Raw>() {
+                        Ok(raw) => {
+                            log::info!("overriding config key `{}` with {:?}", key, value);
+                            return Ok(raw);
+                        }
+                        Err(err) => last_err = Some(err),
+                    }
+                }
+                Err(Error::ValueInvalid {
+                    key: key.to_owned(),
+                    cause: last_err.expect("developer error: no candidate values were tried"),
+                })
+            })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn raw() -> Raw {
+        toml::from_str(
+            r#"
+            [app]
+            name = "fun-app"
+            identifier = "com.example.fun-app"
+            "#,
+        )
+        .unwrap()
+    }
+
+    fn apply(overrides: &[&str]) -> Result<Raw, Error> {
+        raw().with_overrides(
+            &overrides
+                .iter()
+                .map(|o| o.parse().unwrap())
+                .collect::<Vec<Override>>(),
+        )
+    }
+
+    #[test]
+    fn values_are_coerced_to_field_types() {
+        let raw = apply(&[
+            "android.min-sdk-version=30",
+            "android.no-default-features=true",
+            "android.features=[\"a\", \"b\"]",
+            "app.stylized-name=Fun App",
+            "app.name=1.0",
+        ])
+        .unwrap();
+        let android = raw.android.unwrap();
+        assert_eq!(android.min_sdk_version, Some(30));
+        assert_eq!(android.no_default_features, Some(true));
+        assert_eq!(android.features, Some(vec!["a".into(), "b".into()]));
+        assert_eq!(raw.app.stylized_name.as_deref(), Some("Fun App"));
+        assert_eq!(raw.app.name, "1.0");
+    }
+
+    #[test]
+    fn unknown_keys_suggest_the_closest_match() {
+        match apply(&["android.min-sdk-verison=30"]) {
+            Err(Error::UnknownKey { suggestion, .. }) => {
+                assert_eq!(suggestion.as_deref(), Some("android.min-sdk-version"))
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(matches!(
+            apply(&["andriod.features=[]"]),
+            Err(Error::UnknownKey { suggestion: Some(s), .. }) if s == "android"
+        ));
+    }
+
+    #[test]
+    fn nested_keys_are_checked_all_the_way_down() {
+        let raw = apply(&[
+            "app.localization.src=strings",
+            "android.target-env.aarch64-linux-android.RUST_LOG=debug",
+            "app.cargo-config-extra.build.rustflags=[\"-Dwarnings\"]",
+        ])
+        .unwrap();
+        assert_eq!(raw.app.localization.unwrap().src, "strings");
+        assert!(!raw.android.unwrap().target_env.is_empty());
+        assert!(raw.app.cargo_config_extra.unwrap()["build"]
+            .get("rustflags")
+            .is_some());
+        assert!(matches!(
+            apply(&["app.localization.base-locle=fr"]),
+            Err(Error::UnknownKey { suggestion: Some(s), .. }) if s == "app.localization.base-locale"
+        ));
+        assert!(matches!(
+            apply(&["app.name.first=fun"]),
+            Err(Error::NotATable { parent, .. }) if parent == "app.name"
+        ));
+        assert!(matches!(
+            apply(&["android=true"]),
+            Err(Error::UnknownKey {
+                suggestion: None,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn mistyped_values_are_rejected() {
+        assert!(matches!(
+            apply(&["android.min-sdk-version=lots"]),
+            Err(Error::ValueInvalid { .. })
+        ));
+    }
+}
@@ -3,7 +3,10 @@ use super::app;
 use crate::apple;
 use crate::{
     android,
-    util::cli::{Report, Reportable, TextWrapper},
+    util::{
+        cli::{Report, Reportable, TextWrapper},
+        prompt,
+    },
 };
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
@@ -13,6 +16,10 @@ use std::{
     path::{Path, PathBuf},
 };
 
+/// The newest `manifest-version` this build understands. Bump this whenever a
+/// config key is added that older versions would silently ignore.
+pub const MANIFEST_VERSION: u32 = 1;
+
 #[derive(Debug, Error)]
 pub enum PromptError {
     #[error("Failed to prompt for `app` config: {0}")]
@@ -54,6 +61,18 @@ pub enum LoadError {
         path: PathBuf,
         cause: toml::de::Error,
     },
+    #[error("Config file at {path} has `manifest-version = {version}`, but this version of cargo-mobile2 only supports up to {MANIFEST_VERSION}; please update cargo-mobile2")]
+    TooNew { path: PathBuf, version: u32 },
+    #[error("Config file at {path} is a workspace, so `--app` is needed to pick one of its members: {members}")]
+    AppUnspecified { path: PathBuf, members: String },
+    #[error("`--app {0}` was given, but no config file with a `[workspace]` table was found")]
+    WorkspaceNotFound(String),
+    #[error("The workspace at {path} has no member named {app:?}; its members are: {members}")]
+    MemberNotFound {
+        path: PathBuf,
+        app: String,
+        members: String,
+    },
 }
 
 #[derive(Debug, Error)]
@@ -62,29 +81,140 @@ pub enum WriteError {
     Serialize(toml::ser::Error),
     #[error("Failed to write config: {0}")]
     Write(io::Error),
+    #[error("A config already exists at {0}")]
+    WouldOverwrite(PathBuf),
+    #[error("Failed to prompt for overwriting config: {0}")]
+    PromptFailed(io::Error),
+    #[error("Kept the existing config at {0}")]
+    OverwriteDeclined(PathBuf),
+    #[error("Failed to back up config to {path}: {cause}")]
+    BackupFailed { path: PathBuf, cause: io::Error },
 }
 
 impl Reportable for WriteError {
     fn report(&self) -> Report {
-        Report::error("Failed to write config", self)
+        match self {
+            Self::WouldOverwrite(_) => Report::action_request(
+                self.to_string(),
+                "Pass `--force-config` to overwrite it; it'll be backed up first.",
+            ),
+            _ => Report::error("Failed to write config", self),
+        }
     }
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct Raw {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub manifest_version: Option<u32>,
     pub app: app::Raw,
     #[cfg(target_os = "macos")]
     pub apple: Option<apple::config::Raw>,
     pub android: Option<android::config::Raw>,
 }
 
+/// Lists dirs, relative to the config file declaring it, that each have their
+/// own config file. Commands pick one with `--app`.
+#[derive(Debug, Deserialize)]
+struct Workspace {
+    members: Vec<PathBuf>,
+}
+
+// Just enough of the config to decide how to load the rest of it.
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct Probe {
+    manifest_version: Option<u32>,
+    app: Option<toml::Value>,
+    workspace: Option<Workspace>,
+}
+
+impl Probe {
+    fn parse(path: &Path, toml_str: &str) -> Result<Self, LoadError> {
+        toml::from_str(toml_str).map_err(|cause| LoadError::Parse {
+            path: path.to_owned(),
+            cause,
+        })
+    }
+}
+
+// This runs before the full parse, since keys from a newer version could make
+// that fail in ways that hide the real problem.
+fn check_manifest_version(path: &Path, toml_str: &str) -> Result<(), LoadError> {
+    match Probe::parse(path, toml_str)?.manifest_version {
+        Some(version) if version > MANIFEST_VERSION => Err(LoadError::TooNew {
+            path: path.to_owned(),
+            version,
+        }),
+        _ => Ok(()),
+    }
+}
+
+fn read(path: &Path) -> Result<String, LoadError> {
+    fs::read_to_string(path).map_err(|cause| LoadError::Read {
+        path: path.to_owned(),
+        cause,
+    })
+}
+
+fn discover(
+    cwd: &Path,
+    max_depth: Option<usize>,
+    accept: impl Fn(&Path) -> bool,
+) -> io::Result<Option<PathBuf>> {
+    let file_name = super::file_name();
+    let cwd = cwd.canonicalize()?;
+    for (depth, dir) in cwd.ancestors().enumerate() {
+        let path = dir.join(&file_name);
+        log::info!("looking for config file at {:?}", path);
+        if accept(&path) {
+            log::info!("found config file at {:?}", path);
+            return Ok(Some(dir.to_owned()));
+        }
+        if dir.join(".git").exists() {
+            log::info!("stopping config file search at git repo root {:?}", dir);
+            break;
+        }
+        if max_depth.is_some_and(|max_depth| depth >= max_depth) {
+            log::info!("stopping config file search after {} parent dirs", depth);
+            break;
+        }
+    }
+    log::info!("no config file was ever found");
+    Ok(None)
+}
+
+// Finds a backup path that isn't taken yet, like `mobile.toml.bak`, then
+// `mobile.toml.bak.1`, and so on.
+fn backup_path(path: &Path) -> PathBuf {
+    let with_suffix = |suffix: String| {
+        let mut backup = path.as_os_str().to_owned();
+        backup.push(suffix);
+        PathBuf::from(backup)
+    };
+    std::iter::once(".bak".to_owned())
+        .chain((1..).map(|n| format!(".bak.{}", n)))
+        .map(with_suffix)
+        .find(|backup| !backup.exists())
+        .expect("developer error: ran out of backup paths")
+}
+
+fn member_names(members: &[PathBuf]) -> String {
+    members
+        .iter()
+        .map(|member| member.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 impl Raw {
-    pub fn prompt(wrapper: &TextWrapper) -> Result<Self, PromptError> {
-        let app = app::Raw::prompt(wrapper).map_err(PromptError::AppFailed)?;
+    pub fn prompt(wrapper: &TextWrapper, template_pack: Option<&str>) -> Result<Self, PromptError> {
+        let app = app::Raw::prompt(wrapper, template_pack).map_err(PromptError::AppFailed)?;
         #[cfg(target_os = "macos")]
         let apple = apple::config::Raw::prompt(wrapper).map_err(PromptError::AppleFailed)?;
         Ok(Self {
+            manifest_version: Some(MANIFEST_VERSION),
             app,
             #[cfg(target_os = "macos")]
             apple: Some(apple),
@@ -92,11 +222,12 @@ impl Raw {
         })
     }
 
-    pub fn detect(wrapper: &TextWrapper) -> Result<Self, DetectError> {
-        let app = app::Raw::detect(wrapper).map_err(DetectError::AppFailed)?;
+    pub fn detect(wrapper: &TextWrapper, template_pack: Option<&str>) -> Result<Self, DetectError> {
+        let app = app::Raw::detect(wrapper, template_pack).map_err(DetectError::AppFailed)?;
         #[cfg(target_os = "macos")]
         let apple = apple::config::Raw::detect().map_err(DetectError::AppleFailed)?;
         Ok(Self {
+            manifest_version: Some(MANIFEST_VERSION),
             app,
             #[cfg(target_os = "macos")]
             apple: Some(apple),
@@ -105,46 +236,331 @@ impl Raw {
     }
 
     pub fn discover_root(cwd: impl AsRef<Path>) -> io::Result<Option<PathBuf>> {
-        let file_name = super::file_name();
-        let mut path = cwd.as_ref().canonicalize()?.join(&file_name);
-        log::info!("looking for config file at {:?}", path);
-        while !path.exists() {
-            if let Some(parent) = path.parent().and_then(Path::parent) {
-                path = parent.join(&file_name);
-                log::info!("looking for config file at {:?}", path);
-            } else {
-                log::info!("no config file was ever found");
-                return Ok(None);
-            }
-        }
-        log::info!("found config file at {:?}", path);
-        path.pop();
-        Ok(Some(path))
+        Self::discover_root_within(cwd, None)
+    }
+
+    /// Walks up from `cwd` looking for the config file, checking at most
+    /// `max_depth` parent dirs. The search also stops at the root of the
+    /// enclosing git repo, so that an unrelated project further up isn't found.
+    pub fn discover_root_within(
+        cwd: impl AsRef<Path>,
+        max_depth: Option<usize>,
+    ) -> io::Result<Option<PathBuf>> {
+        discover(cwd.as_ref(), max_depth, |path| path.exists())
+    }
+
+    /// Like [`Raw::discover_root_within`], but skips config files that don't
+    /// have a `[workspace]` table.
+    pub fn discover_workspace_root_within(
+        cwd: impl AsRef<Path>,
+        max_depth: Option<usize>,
+    ) -> io::Result<Option<PathBuf>> {
+        // Config files that don't parse are accepted too, so that loading
+        // reports what's wrong with them instead of not finding a workspace.
+        discover(cwd.as_ref(), max_depth, |path| {
+            fs::read_to_string(path)
+                .map(|toml_str| {
+                    Probe::parse(path, &toml_str).map_or(true, |probe| probe.workspace.is_some())
+                })
+                .unwrap_or_default()
+        })
     }
 
     pub fn load(cwd: impl AsRef<Path>) -> Result<Option<(PathBuf, Self)>, LoadError> {
-        Self::discover_root(cwd)
+        Self::load_within(cwd, None)
+    }
+
+    /// Like [`Raw::load`], with discovery bounded as in
+    /// [`Raw::discover_root_within`].
+    pub fn load_within(
+        cwd: impl AsRef<Path>,
+        max_depth: Option<usize>,
+    ) -> Result<Option<(PathBuf, Self)>, LoadError> {
+        Self::discover_root_within(cwd, max_depth)
             .map_err(LoadError::Discover)?
             .map(|root_dir| {
                 let path = root_dir.join(super::file_name());
-                let toml_str = fs::read_to_string(&path).map_err(|cause| LoadError::Read {
-                    path: path.clone(),
-                    cause,
-                })?;
-                toml::from_str::<Self>(&toml_str)
-                    .map(|raw| (root_dir, raw))
-                    .map_err(|cause| LoadError::Parse {
-                        path: path.clone(),
-                        cause,
-                    })
+                let toml_str = read(&path)?;
+                match Probe::parse(&path, &toml_str)? {
+                    Probe {
+                        app: None,
+                        workspace: Some(workspace),
+                        ..
+                    } => Err(LoadError::AppUnspecified {
+                        path,
+                        members: member_names(&workspace.members),
+                    }),
+                    _ => Self::parse(&path, &toml_str).map(|raw| (root_dir, raw)),
+                }
             })
             .transpose()
     }
 
-    pub fn write(&self, root_dir: &Path) -> Result<(), WriteError> {
+    /// Loads the config of the workspace member selected by `--app`, which
+    /// matches either the member's dir name or its `app.name`.
+    pub fn load_member(
+        cwd: impl AsRef<Path>,
+        max_depth: Option<usize>,
+        app: &str,
+    ) -> Result<(PathBuf, Self), LoadError> {
+        let root_dir = Self::discover_workspace_root_within(cwd, max_depth)
+            .map_err(LoadError::Discover)?
+            .ok_or_else(|| LoadError::WorkspaceNotFound(app.to_owned()))?;
+        let path = root_dir.join(super::file_name());
+        let members = Probe::parse(&path, &read(&path)?)?
+            .workspace
+            .map(|workspace| workspace.members)
+            .unwrap_or_default();
+        let member_path = |member: &Path| {
+            let member_dir = root_dir.join(member);
+            let member_path = member_dir.join(super::file_name());
+            if member_path.exists() {
+                Some((member_dir, member_path))
+            } else {
+                log::warn!("workspace member {:?} has no config file", member_dir);
+                None
+            }
+        };
+        // Dir names are checked first, so that selecting a member doesn't
+        // take parsing the others, which might not even be valid.
+        if let Some((member_dir, member_path)) = members
+            .iter()
+            .filter(|member| member.file_name().is_some_and(|name| name == app))
+            .find_map(|member| member_path(member))
+        {
+            log::info!("using workspace member {:?}", member_dir);
+            let raw = Self::parse(&member_path, &read(&member_path)?)?;
+            return Ok((member_dir, raw));
+        }
+        let mut first_err = None;
+        for (member_dir, member_path) in members.iter().filter_map(|member| member_path(member)) {
+            match read(&member_path).and_then(|toml_str| Self::parse(&member_path, &toml_str)) {
+                Ok(raw) if raw.app.name == app => {
+                    log::info!("using workspace member {:?}", member_dir);
+                    return Ok((member_dir, raw));
+                }
+                Ok(_) => (),
+                Err(err) => {
+                    log::warn!("skipping workspace member {:?}: {}", member_dir, err);
+                    first_err.get_or_insert(err);
+                }
+            }
+        }
+        // The app might well be the member that failed to load.
+        if let Some(err) = first_err {
+            return Err(err);
+        }
+        Err(LoadError::MemberNotFound {
+            path,
+            app: app.to_owned(),
+            members: member_names(&members),
+        })
+    }
+
+    fn parse(path: &Path, toml_str: &str) -> Result<Self, LoadError> {
+        check_manifest_version(path, toml_str)?;
+        toml::from_str(toml_str).map_err(|cause| LoadError::Parse {
+            path: path.to_owned(),
+            cause,
+        })
+    }
+
+    /// An existing config is only overwritten if `force` is set, or if the
+    /// user agrees to when interactive, and is backed up first either way.
+    pub fn write(
+        &self,
+        root_dir: &Path,
+        non_interactive: bool,
+        force: bool,
+    ) -> Result<(), WriteError> {
         let toml_str = toml::to_string(self).map_err(WriteError::Serialize)?;
         let path = root_dir.join(super::file_name());
+        if path.exists() {
+            if !force {
+                if non_interactive {
+                    return Err(WriteError::WouldOverwrite(path));
+                }
+                let overwrite = loop {
+                    if let Some(overwrite) = prompt::yes_no(
+                        format!("Overwrite the existing config at {:?}?", path),
+                        Some(false),
+                    )
+                    .map_err(WriteError::PromptFailed)?
+                    {
+                        break overwrite;
+                    }
+                };
+                if !overwrite {
+                    return Err(WriteError::OverwriteDeclined(path));
+                }
+            }
+            let backup = backup_path(&path);
+            fs::copy(&path, &backup).map_err(|cause| WriteError::BackupFailed {
+                path: backup.clone(),
+                cause,
+            })?;
+            log::warn!("backed up the existing config to {:?}", backup);
+        }
         log::info!("writing config to {:?}", path);
         fs::write(path, toml_str).map_err(WriteError::Write)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn discovery_stops_at_repo_root() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        let repo = root.join("repo");
+        let nested = repo.join("crates/app/src");
+        fs::create_dir_all(&nested).unwrap();
+        fs::create_dir_all(repo.join(".git")).unwrap();
+        // An unrelated project above the repo shouldn't be picked up...
+        fs::write(root.join(super::super::file_name()), "").unwrap();
+        assert_eq!(Raw::discover_root(&nested).unwrap(), None);
+
+        // ...but one inside the repo should be, unless it's too far up.
+        let app = repo.join("crates/app");
+        fs::write(app.join(super::super::file_name()), "").unwrap();
+        assert_eq!(
+            Raw::discover_root(&nested).unwrap(),
+            Some(app.canonicalize().unwrap())
+        );
+        assert_eq!(
+            Raw::discover_root_within(&nested, Some(1)).unwrap(),
+            Some(app.canonicalize().unwrap())
+        );
+        assert_eq!(Raw::discover_root_within(&nested, Some(0)).unwrap(), None);
+    }
+
+    #[test]
+    fn workspace_members_are_selected_by_app() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::write(
+            root.join(super::super::file_name()),
+            "[workspace]\nmembers = [\"apps/first\", \"apps/second\"]\n",
+        )
+        .unwrap();
+        for (dir, name) in [("apps/first", "fun-app"), ("apps/second", "fun-tool")] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+            fs::write(
+                root.join(dir).join(super::super::file_name()),
+                format!(
+                    "[app]\nname = \"{}\"\nidentifier = \"com.example.{}\"\n",
+                    name, name
+                ),
+            )
+            .unwrap();
+        }
+
+        assert!(matches!(
+            Raw::load(root),
+            Err(LoadError::AppUnspecified { .. })
+        ));
+        let (dir, raw) = Raw::load_member(root, None, "second").unwrap();
+        assert_eq!(
+            dir.canonicalize().unwrap(),
+            root.join("apps/second").canonicalize().unwrap()
+        );
+        assert_eq!(raw.app.name, "fun-tool");
+        // Members can be found from inside other members, too.
+        let (_, raw) = Raw::load_member(root.join("apps/second"), None, "fun-app").unwrap();
+        assert_eq!(raw.app.name, "fun-app");
+        assert!(matches!(
+            Raw::load_member(root, None, "third"),
+            Err(LoadError::MemberNotFound { .. })
+        ));
+
+        // A broken member doesn't get in the way of selecting the others...
+        fs::write(
+            root.join(super::super::file_name()),
+            "[workspace]\nmembers = [\"apps/broken\", \"apps/first\", \"apps/second\"]\n",
+        )
+        .unwrap();
+        fs::create_dir_all(root.join("apps/broken")).unwrap();
+        fs::write(
+            root.join("apps/broken").join(super::super::file_name()),
+            "[app",
+        )
+        .unwrap();
+        let (_, raw) = Raw::load_member(root, None, "second").unwrap();
+        assert_eq!(raw.app.name, "fun-tool");
+        let (_, raw) = Raw::load_member(root, None, "fun-app").unwrap();
+        assert_eq!(raw.app.name, "fun-app");
+        assert!(matches!(
+            Raw::load_member(root, None, "broken"),
+            Err(LoadError::Parse { .. })
+        ));
+
+        // ...but a broken workspace config is reported as such.
+        fs::write(root.join(super::super::file_name()), "[workspace").unwrap();
+        assert!(matches!(
+            Raw::load_member(root.join("apps/second"), None, "second"),
+            Err(LoadError::Parse { .. })
+        ));
+    }
+
+    #[test]
+    fn overwritten_configs_are_backed_up() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        let path = root.join(super::super::file_name());
+        let raw = |name: &str| {
+            Raw::parse(
+                &path,
+                &format!(
+                    "[app]\nname = \"{}\"\nidentifier = \"com.example.{}\"\n",
+                    name, name
+                ),
+            )
+            .unwrap()
+        };
+        let read = |path: &Path| fs::read_to_string(path).unwrap();
+
+        raw("first").write(root, true, false).unwrap();
+        let first = read(&path);
+        assert!(matches!(
+            raw("second").write(root, true, false),
+            Err(WriteError::WouldOverwrite(_))
+        ));
+        assert_eq!(read(&path), first);
+
+        raw("second").write(root, true, true).unwrap();
+        let second = read(&path);
+        assert_ne!(second, first);
+        let backup = |suffix: &str| {
+            let mut backup = path.clone().into_os_string();
+            backup.push(suffix);
+            PathBuf::from(backup)
+        };
+        assert_eq!(read(&backup(".bak")), first);
+        // Earlier backups are left alone.
+        raw("third").write(root, true, true).unwrap();
+        assert_eq!(read(&backup(".bak")), first);
+        assert_eq!(read(&backup(".bak.1")), second);
+    }
+
+    #[test]
+    fn newer_manifest_versions_are_rejected() {
+        let path = Path::new("mobile.toml");
+        let app = "[app]\nname = \"fun-app\"\nidentifier = \"com.example.fun-app\"\n";
+        assert!(check_manifest_version(path, app).is_ok());
+        assert!(check_manifest_version(
+            path,
+            &format!("manifest-version = {}\n{}", MANIFEST_VERSION, app)
+        )
+        .is_ok());
+        assert!(matches!(
+            check_manifest_version(
+                path,
+                &format!("manifest-version = {}\n{}", MANIFEST_VERSION + 1, app)
+            ),
+            Err(LoadError::TooNew { version, .. }) if version == MANIFEST_VERSION + 1
+        ));
+    }
+}
@@ -0,0 +1,88 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+/// Env vars for the cargo invocations of particular targets, keyed by target
+/// triple, e.g. `[android.target-env.aarch64-linux-android]`.
+///
+/// These are applied last, so they win over the vars cargo-mobile2 derives for
+/// the target itself (like the NDK's `TARGET_CC` and `TARGET_AR`), as well as
+/// over anything in the ambient environment.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct TargetEnv(BTreeMap<String, BTreeMap<String, String>>);
+
+#[derive(Debug, Error)]
+#[error("{triple:?} isn't a target triple cargo-mobile2 builds for; expected one of {}", .known.join(", "))]
+pub struct UnknownTriple {
+    pub triple: String,
+    pub known: Vec<String>,
+}
+
+/// Checks that every one of `triples` is `known`.
+pub fn check_triples<'a, 'b>(
+    mut triples: impl Iterator<Item = &'a String>,
+    known: impl IntoIterator<Item = &'b str>,
+) -> Result<(), UnknownTriple> {
+    let known = known.into_iter().collect::<Vec<_>>();
+    match triples.find(|triple| !known.contains(&triple.as_str())) {
+        Some(triple) => Err(UnknownTriple {
+            triple: triple.clone(),
+            known: known.into_iter().map(ToOwned::to_owned).collect(),
+        }),
+        None => Ok(()),
+    }
+}
+
+impl TargetEnv {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Catches triples that would otherwise silently never match.
+    pub fn validate<'a>(
+        self,
+        known: impl IntoIterator<Item = &'a str>,
+    ) -> Result<Self, UnknownTriple> {
+        check_triples(self.0.keys(), known).map(|()| self)
+    }
+
+    /// The vars to set when building for `triple`.
+    pub fn vars(&self, triple: &str) -> impl Iterator<Item = (&String, &String)> {
+        self.0.get(triple).into_iter().flatten()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn vars_only_apply_to_their_triple() {
+        let target_env = toml::from_str::<TargetEnv>(
+            r#"
+            aarch64-linux-android = { CC = "/opt/toolchain/bin/clang", AR = "/opt/toolchain/bin/ar" }
+            x86_64-linux-android = { CC = "/usr/bin/clang" }
+            "#,
+        )
+        .unwrap()
+        .validate(["aarch64-linux-android", "x86_64-linux-android"])
+        .unwrap();
+        let vars = |triple| {
+            target_env
+                .vars(triple)
+                .map(|(key, value)| (key.as_str(), value.as_str()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            vars("aarch64-linux-android"),
+            [
+                ("AR", "/opt/toolchain/bin/ar"),
+                ("CC", "/opt/toolchain/bin/clang")
+            ]
+        );
+        assert_eq!(vars("x86_64-linux-android"), [("CC", "/usr/bin/clang")]);
+        assert!(vars("i686-linux-android").is_empty());
+        assert!(target_env.validate(["x86_64-linux-android"]).is_err());
+    }
+}
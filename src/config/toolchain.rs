@@ -0,0 +1,115 @@
+use super::target_env::{check_triples, UnknownTriple};
+use crate::util::{
+    self,
+    cli::{Report, Reportable},
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+/// Rust toolchains for the cargo invocations of particular targets, keyed by
+/// target triple, e.g. `aarch64-linux-android = "nightly"` in
+/// `[android.toolchain]`. Targets without one use whatever toolchain cargo
+/// would normally pick, like the one in `rust-toolchain.toml`.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct TargetToolchain(BTreeMap<String, String>);
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Failed to list installed toolchains with `rustup`: {0}")]
+    ListFailed(std::io::Error),
+    #[error("The {toolchain:?} toolchain for {triple} isn't installed")]
+    NotInstalled { triple: String, toolchain: String },
+}
+
+impl Reportable for Error {
+    fn report(&self) -> Report {
+        match self {
+            Self::ListFailed(_) => Report::error("Failed to check Rust toolchain", self),
+            Self::NotInstalled { triple, toolchain } => Report::action_request(
+                self.to_string(),
+                format!(
+                    "Run `rustup toolchain install {} --target {}` and try again.",
+                    toolchain, triple
+                ),
+            ),
+        }
+    }
+}
+
+// `rustup toolchain list` gives full names, like `nightly-aarch64-apple-darwin`,
+// which the short names people write in configs leave the host off of.
+fn installed(list: &str, toolchain: &str, host: impl FnOnce() -> Option<String>) -> bool {
+    let names = list
+        .lines()
+        .filter_map(|line| line.split_whitespace().next())
+        .collect::<Vec<_>>();
+    names.contains(&toolchain)
+        || host().is_some_and(|host| names.contains(&format!("{}-{}", toolchain, host).as_str()))
+}
+
+impl TargetToolchain {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Catches triples that would otherwise silently never match.
+    pub fn validate<'a>(
+        self,
+        known: impl IntoIterator<Item = &'a str>,
+    ) -> Result<Self, UnknownTriple> {
+        check_triples(self.0.keys(), known).map(|()| self)
+    }
+
+    pub fn get(&self, triple: &str) -> Option<&str> {
+        self.0.get(triple).map(String::as_str)
+    }
+
+    /// Checks that `triple`'s toolchain, if it has one, is installed, and
+    /// returns the vars that make cargo use it.
+    pub fn vars(&self, triple: &str) -> Result<Option<(&'static str, &str)>, Error> {
+        let Some(toolchain) = self.get(triple) else {
+            return Ok(None);
+        };
+        let list = duct::cmd("rustup", ["toolchain", "list"])
+            .stderr_capture()
+            .read()
+            .map_err(Error::ListFailed)?;
+        let host = || util::host_target_triple().ok();
+        if !installed(&list, toolchain, host) {
+            return Err(Error::NotInstalled {
+                triple: triple.to_owned(),
+                toolchain: toolchain.to_owned(),
+            });
+        }
+        log::info!("using the {:?} toolchain for {}", toolchain, triple);
+        Ok(Some(("RUSTUP_TOOLCHAIN", toolchain)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("nightly", true)]
+    #[case("nightly-x86_64-unknown-linux-gnu", true)]
+    #[case("1.80.0", true)]
+    #[case("my-linked", true)]
+    #[case("beta", false)]
+    #[case("1.80", false)]
+    fn test_installed(#[case] toolchain: &str, #[case] expected: bool) {
+        let list = "stable-x86_64-unknown-linux-gnu (default)\n\
+                    nightly-x86_64-unknown-linux-gnu\n\
+                    1.80.0-x86_64-unknown-linux-gnu\n\
+                    my-linked\n";
+        assert_eq!(
+            installed(list, toolchain, || Some(
+                "x86_64-unknown-linux-gnu".to_owned()
+            )),
+            expected
+        );
+    }
+}
@@ -0,0 +1,112 @@
+use crate::{
+    android::{self, target::Target},
+    bicycle::{JsonMap, ProcessingError},
+    config::{Config, LoadOrGenError},
+    target::TargetTrait as _,
+    templating::{LookupError, Pack},
+    util::cli::{Report, Reportable, TextWrapper},
+};
+use std::path::PathBuf;
+use thiserror::Error;
+
+pub static TEMPLATE_PACK: &str = "devcontainer";
+// Used when no NDK is installed to match.
+static DEFAULT_NDK_VERSION: &str = "26.1.10909125";
+// Keep in sync with `compileSdk` in the Android Studio template.
+const PLATFORM_VERSION: u32 = 34;
+static BUILD_TOOLS_VERSION: &str = "34.0.0";
+static CMDLINE_TOOLS_VERSION: &str = "11076708";
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error(transparent)]
+    ConfigFailed(Box<LoadOrGenError>),
+    #[error(transparent)]
+    MissingPack(LookupError),
+    #[error("A devcontainer already exists at {}", .0.iter().map(|path| path.display().to_string()).collect::<Vec<_>>().join(", "))]
+    AlreadyExists(Vec<PathBuf>),
+    #[error(transparent)]
+    TemplateProcessingFailed(Box<ProcessingError>),
+}
+
+impl Reportable for Error {
+    fn report(&self) -> Report {
+        match self {
+            Self::ConfigFailed(err) => err.report(),
+            Self::AlreadyExists(_) => {
+                Report::action_request(self.to_string(), "Pass `--force` to overwrite it.")
+            }
+            _ => Report::error("Failed to generate devcontainer", self),
+        }
+    }
+}
+
+fn ndk_version() -> String {
+    match android::ndk::Env::new() {
+        Ok(ndk) => ndk.version().to_string(),
+        Err(err) => {
+            log::info!(
+                "using NDK {} in devcontainer, since no NDK was found: {}",
+                DEFAULT_NDK_VERSION,
+                err
+            );
+            DEFAULT_NDK_VERSION.to_owned()
+        }
+    }
+}
+
+/// Generates `.devcontainer/devcontainer.json` and a `Dockerfile` with the
+/// Android SDK, the installed NDK's version (if any), and every Android Rust
+/// target. iOS builds need macOS, so they're out of scope. Existing files are
+/// only overwritten if `force` is set.
+pub fn exec(wrapper: &TextWrapper, non_interactive: bool, force: bool) -> Result<(), Error> {
+    let (config, _origin) = Config::load_or_gen(
+        ".",
+        non_interactive,
+        false,
+        None,
+        &[],
+        None,
+        None,
+        None,
+        wrapper,
+    )
+    .map_err(|err| Error::ConfigFailed(Box::new(err)))?;
+    let src = Pack::lookup_platform(TEMPLATE_PACK)
+        .map_err(Error::MissingPack)?
+        .expect_local();
+    let root_dir = config.app().root_dir();
+    let bike = config.build_a_bike();
+    let ndk_version = ndk_version();
+    let rust_targets = Target::all()
+        .values()
+        .map(|target| target.triple)
+        .collect::<Vec<_>>();
+    let insert_data = |map: &mut JsonMap| {
+        map.insert("ndk-version", &ndk_version);
+        map.insert("platform-version", PLATFORM_VERSION);
+        map.insert("build-tools-version", BUILD_TOOLS_VERSION);
+        map.insert("cmdline-tools-version", CMDLINE_TOOLS_VERSION);
+        map.insert("rust-targets", &rust_targets);
+    };
+    if !force {
+        // Checked up front, so nothing is written unless everything can be.
+        let mut existing = Vec::new();
+        bike.filter_and_process(&src, root_dir, insert_data, |action| {
+            if !action.is_create_directory() && action.dest().exists() {
+                existing.push(action.dest().to_owned());
+            }
+            false
+        })
+        .map_err(|err| Error::TemplateProcessingFailed(Box::new(err)))?;
+        if !existing.is_empty() {
+            return Err(Error::AlreadyExists(existing));
+        }
+    }
+    println!(
+        "Generating devcontainer with NDK {} and Android platform {}...",
+        ndk_version, PLATFORM_VERSION
+    );
+    bike.filter_and_process(&src, root_dir, insert_data, |_| true)
+        .map_err(|err| Error::TemplateProcessingFailed(Box::new(err)))
+}
@@ -1,8 +1,21 @@
-use crate::util::cli::{Report, Reportable};
+use crate::{
+    env::ExplicitEnv,
+    util::{
+        self,
+        cli::{Report, Reportable},
+    },
+    DuctExpressionExt,
+};
+use once_cell_regex::regex;
+use serde::Serialize;
+use sha2::{Digest as _, Sha256};
 use std::{
     error::Error,
+    ffi::OsString,
     fmt::{self, Debug, Display},
-    io,
+    fs, io,
+    path::{Path, PathBuf},
+    str::FromStr,
 };
 
 #[derive(Debug, thiserror::Error)]
@@ -74,27 +87,228 @@ impl<T: Debug + Display> PromptError<T> {
     }
 }
 
+/// The version of an app that's installed on a device.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct AppInfo {
+    /// The user-facing version, e.g. `1.2.3`.
+    pub version: String,
+    /// The build number, e.g. `45`.
+    pub build: String,
+}
+
+impl Display for AppInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.version, self.build)
+    }
+}
+
+/// Prints the result of an installed app lookup, either for humans or as a
+/// JSON object with `device`, `identifier`, and `app` (which is `null` if the
+/// app isn't installed).
+pub fn print_app_info(device: &str, identifier: &str, info: Option<&AppInfo>, json: bool) {
+    if json {
+        println!(
+            "{}",
+            serde_json::json!({
+                "device": device,
+                "identifier": identifier,
+                "app": info,
+            })
+        );
+    } else if let Some(info) = info {
+        println!("{} has {} {} installed", device, identifier, info);
+    } else {
+        println!("{} doesn't have {} installed", device, identifier);
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("{0:?} isn't a BCP-47 language tag (e.g. `en-US`, `ar`, or `en-XA`)")]
+pub struct LocaleInvalid(String);
+
+/// A BCP-47 language tag to launch an app with, e.g. `en-US`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Locale(String);
+
+impl FromStr for Locale {
+    type Err = LocaleInvalid;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // language[-script][-region][-variant...]
+        regex!(r"^[a-zA-Z]{2,3}(-[a-zA-Z]{4})?(-([a-zA-Z]{2}|[0-9]{3}))?(-([a-zA-Z0-9]{5,8}|[0-9][a-zA-Z0-9]{3}))*$")
+            .is_match(s)
+            .then(|| Self(s.to_owned()))
+            .ok_or_else(|| LocaleInvalid(s.to_owned()))
+    }
+}
+
+impl Display for Locale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Locale {
+    pub fn tag(&self) -> &str {
+        &self.0
+    }
+
+    /// The tag in the form `NSLocale` expects, e.g. `en_US`.
+    pub fn apple_locale(&self) -> String {
+        self.0.replace('-', "_")
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("After-run command {command:?} failed: {cause}")]
+pub struct AfterRunError {
+    command: String,
+    cause: io::Error,
+}
+
+impl Reportable for AfterRunError {
+    fn report(&self) -> Report {
+        Report::error(
+            format!("After-run command {:?} failed", self.command),
+            &self.cause,
+        )
+    }
+}
+
+/// Runs `--after-run`'s command in a shell once the app is up, with
+/// `CARGO_MOBILE_DEVICE_ID` and `CARGO_MOBILE_APP_IDENTIFIER` set so that
+/// test runners know what to talk to.
+pub fn after_run(
+    env: &impl ExplicitEnv,
+    command: &str,
+    device_id: &str,
+    app_identifier: &str,
+) -> Result<(), AfterRunError> {
+    println!("Running after-run command {:?}...", command);
+    let shell = if cfg!(windows) {
+        duct::cmd("cmd", ["/C", command])
+    } else {
+        duct::cmd("sh", ["-c", command])
+    };
+    shell
+        .vars(env.explicit_env())
+        .env("CARGO_MOBILE_DEVICE_ID", device_id)
+        .env("CARGO_MOBILE_APP_IDENTIFIER", app_identifier)
+        .dup_stdio()
+        .run()
+        .map(|_| ())
+        .map_err(|cause| AfterRunError {
+            command: command.to_owned(),
+            cause,
+        })
+}
+
+// Devices are remembered per project, keyed by a hash of its root dir, so
+// that picking a device in one project doesn't pick it in another.
+fn last_device_path(project: &Path, platform: &str) -> PathBuf {
+    let hash = Sha256::digest(project.to_string_lossy().as_bytes());
+    let project = hash[..8]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<String>();
+    util::temp_dir()
+        .join("last-device")
+        .join(project)
+        .join(platform)
+}
+
+/// The ID of the device most recently picked for `platform` in the project
+/// at `project`, if any.
+pub fn last_device(project: &Path, platform: &str) -> Option<String> {
+    fs::read_to_string(last_device_path(project, platform))
+        .ok()
+        .map(|id| id.trim().to_owned())
+}
+
+pub fn remember_device(project: &Path, platform: &str, id: &str) {
+    let path = last_device_path(project, platform);
+    let result = path
+        .parent()
+        .map(fs::create_dir_all)
+        .transpose()
+        .and_then(|_| fs::write(&path, id));
+    if let Err(err) = result {
+        log::warn!("failed to remember last device at {:?}: {}", path, err);
+    }
+}
+
+/// Defines `device_prompt`, `device_prompt_for`, and `device_prompt_with`.
+/// When `$id` names a method returning a stable device ID, the device picked
+/// for a project is remembered, so that `device_prompt_with(env, Some(root),
+/// true, _)` can pick it again without prompting.
 #[macro_export]
 macro_rules! define_device_prompt {
-    ($func:path, $e:ty, $name:ident) => {
+    (@last $project:ident, $device_list:ident, $name:ident) => {
+        (false, None)
+    };
+    (@last $project:ident, $device_list:ident, $name:ident, $id:ident) => {{
+        let remembered = $project
+            .and_then(|project| $crate::device::last_device(project, stringify!($name)));
+        let index = remembered.as_ref().and_then(|id| {
+            $device_list
+                .iter()
+                .position(|device| device.$id() == id.as_str())
+        });
+        (remembered.is_some(), index)
+    }};
+    (@remember $project:ident, $device:ident, $name:ident) => {};
+    (@remember $project:ident, $device:ident, $name:ident, $id:ident) => {
+        $crate::device::remember_device($project, stringify!($name), $device.$id())
+    };
+    ($func:path, $e:ty, $name:ident $(, $id:ident)?) => {
+        // For when there's no project to remember the device for.
         fn device_prompt<'a>(env: &'_ Env) -> Result<Device<'a>, $crate::device::PromptError<$e>> {
+            device_prompt_with(env, None, false, false)
+        }
+
+        #[allow(dead_code)]
+        fn device_prompt_for<'a>(
+            env: &'_ Env,
+            project: &std::path::Path,
+        ) -> Result<Device<'a>, $crate::device::PromptError<$e>> {
+            device_prompt_with(env, Some(project), false, true)
+        }
+
+        // With `last_device`, the device used last time in `project` is
+        // picked without prompting, as long as it's still connected. Without
+        // `remember`, the device that's picked isn't saved for next time.
+        #[allow(dead_code)]
+        fn device_prompt_with<'a>(
+            env: &'_ Env,
+            project: Option<&std::path::Path>,
+            last_device: bool,
+            remember: bool,
+        ) -> Result<Device<'a>, $crate::device::PromptError<$e>> {
             let device_list = $func(env).map_err(|cause| {
                 $crate::device::PromptError::detection_failed(stringify!($name), cause)
             })?;
             if device_list.len() > 0 {
-                let index = if device_list.len() > 1 {
-                    prompt::list(
-                        concat!("Detected ", stringify!($name), " devices"),
-                        device_list.iter(),
-                        "device",
-                        None,
-                        "Device",
-                    )
-                    .map_err(|cause| {
-                        $crate::device::PromptError::prompt_failed(stringify!($name), cause)
-                    })?
-                } else {
-                    0
+                let (remembered, last_index): (bool, Option<usize>) =
+                    $crate::define_device_prompt!(@last project, device_list, $name $(, $id)?);
+                let index = match last_index {
+                    Some(index) if last_device => index,
+                    _ if device_list.len() > 1 => {
+                        if last_device && remembered {
+                            println!("The last device used isn't connected, so you'll need to pick one.");
+                        }
+                        prompt::list_with_default(
+                            concat!("Detected ", stringify!($name), " devices"),
+                            device_list.iter(),
+                            "device",
+                            None,
+                            "Device",
+                            last_index,
+                        )
+                        .map_err(|cause| {
+                            $crate::device::PromptError::prompt_failed(stringify!($name), cause)
+                        })?
+                    }
+                    _ => 0,
                 };
                 let device = device_list.into_iter().nth(index).unwrap();
                 println!(
@@ -102,6 +316,9 @@ macro_rules! define_device_prompt {
                     device,
                     device.target().triple,
                 );
+                if let Some(project) = project.filter(|_| remember) {
+                    $crate::define_device_prompt!(@remember project, device, $name $(, $id)?);
+                }
                 Ok(device)
             } else {
                 Err($crate::device::PromptError::none_detected(stringify!(
@@ -111,3 +328,80 @@ macro_rules! define_device_prompt {
         }
     };
 }
+
+/// What `run` or `deploy` would do, for `--dry-run`.
+#[derive(Clone, Debug)]
+pub struct DryRun {
+    pub device: String,
+    pub artifact: PathBuf,
+    /// The install and launch commands, in the order they'd run.
+    pub commands: Vec<Vec<OsString>>,
+}
+
+impl DryRun {
+    pub fn print(&self) {
+        println!(
+            "Would deploy {:?} to {} by running:",
+            self.artifact, self.device
+        );
+        for command in &self.commands {
+            println!("  {}", format_command(command));
+        }
+    }
+}
+
+/// The steps `run` would take, in order, for `--explain`. Unlike [`DryRun`],
+/// this narrates the whole pipeline instead of listing exact commands.
+#[derive(Clone, Debug)]
+pub struct Explanation {
+    pub device: String,
+    pub steps: Vec<String>,
+}
+
+impl Explanation {
+    pub fn print(&self) {
+        println!("Running on {} would:", self.device);
+        for (index, step) in self.steps.iter().enumerate() {
+            println!("  {}. {}", index + 1, step);
+        }
+    }
+}
+
+// Quotes args so that the printed command can be pasted into a shell.
+fn format_command(command: &[OsString]) -> String {
+    command
+        .iter()
+        .map(|arg| util::shell_quote(&arg.to_string_lossy()))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("en", true)]
+    #[case("en-US", true)]
+    #[case("zh-Hant-TW", true)]
+    #[case("es-419", true)]
+    #[case("en-XA", true)]
+    #[case("de-CH-1996", true)]
+    #[case("english", false)]
+    #[case("en_US", false)]
+    #[case("en-", false)]
+    #[case("", false)]
+    fn test_locale_validation(#[case] tag: &str, #[case] valid: bool) {
+        assert_eq!(tag.parse::<Locale>().is_ok(), valid);
+    }
+
+    #[rstest]
+    #[case(&["adb", "-s", "emulator-5554"], "adb -s emulator-5554")]
+    #[case(&["ios-deploy", "--bundle", "/tmp/Fun App.app"], "ios-deploy --bundle '/tmp/Fun App.app'")]
+    #[case(&["echo", "", "it's"], r"echo '' 'it'\''s'")]
+    fn test_format_command(#[case] command: &[&str], #[case] expected: &str) {
+        let command = command.iter().map(OsString::from).collect::<Vec<_>>();
+        assert_eq!(format_command(&command), expected);
+    }
+}
@@ -21,11 +21,51 @@ pub enum Unrecoverable {
     ContractHomeFailed(#[from] util::ContractHomeError),
 }
 
+// Doctor doesn't need a config, but if there is one, then it should report on
+// the Xcode it picks, and check it against the deployment targets.
+#[cfg(target_os = "macos")]
+fn apple_config() -> Option<(std::path::PathBuf, crate::apple::config::Raw)> {
+    match crate::config::Raw::load(".") {
+        Ok(loaded) => loaded.and_then(|(root_dir, raw)| Some((root_dir, raw.apple?))),
+        Err(err) => {
+            log::info!(
+                "not checking `apple` config, since config failed to load: {}",
+                err
+            );
+            None
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn developer_dir(
+    root_dir: &std::path::Path,
+    raw: &crate::apple::config::Raw,
+) -> Option<std::path::PathBuf> {
+    let xcode_path = raw.xcode_path.as_ref()?;
+    crate::apple::config::developer_dir(root_dir, xcode_path)
+        .map_err(|err| log::warn!("{}", err))
+        .ok()
+}
+
 pub fn exec(wrapper: &TextWrapper) -> Result<(), Unrecoverable> {
     let env = Env::new()?;
     section::cargo_mobile::check()?.print(wrapper);
+    if let Some(section) = section::template_pack::check() {
+        section.print(wrapper);
+    }
     #[cfg(target_os = "macos")]
-    section::apple::check().print(wrapper);
+    {
+        let config = apple_config();
+        let developer_dir = config
+            .as_ref()
+            .and_then(|(root_dir, raw)| developer_dir(root_dir, raw));
+        section::apple::check(
+            config.as_ref().map(|(_, raw)| raw),
+            developer_dir.as_deref(),
+        )
+        .print(wrapper);
+    }
     section::android::check(&env)?.print(wrapper);
     section::device_list::check(&env).print(wrapper);
     Ok(())
@@ -15,14 +15,11 @@ pub fn check(env: &Env) -> Result<Section, Unrecoverable> {
                 )),
                 Err(err) => Err(format!("Failed to get SDK version: {}", err)),
             })
-            .with_item(match android_env.ndk.version() {
-                Ok(ndk_version) => Ok(format!(
-                    "NDK v{} installed at {:?}",
-                    ndk_version,
-                    util::contract_home(android_env.ndk.home())?,
-                )),
-                Err(err) => Err(format!("Failed to get NDK version: {}", err)),
-            }),
+            .with_victory(format!(
+                "NDK v{} installed at {:?}",
+                android_env.ndk.version(),
+                util::contract_home(android_env.ndk.home())?,
+            )),
         Err(err) => section.with_failure(err),
     })
 }
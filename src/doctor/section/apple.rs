@@ -1,18 +1,42 @@
 use super::{Item, Section};
 use crate::{
-    apple::{deps::xcode_plugin, system_profile::DeveloperTools, teams},
-    util::prompt,
+    apple::{
+        config::{Raw, DEFAULT_IOS_VERSION, DEFAULT_MACOS_VERSION},
+        deps::xcode_plugin,
+        system_profile::DeveloperTools,
+        teams,
+    },
+    util::{prompt, VersionDouble},
     DuctExpressionExt,
 };
-use std::path::Path;
+use std::{path::Path, str::FromStr};
 
-fn validate_developer_dir() -> Result<String, String> {
-    static FORBIDDEN: &str = "/Library/Developer/CommandLineTools";
+// The Command Line Tools can live anywhere `xcode-select` is pointed, but a
+// full Xcode's developer dir is always inside its `.app`.
+fn is_command_line_tools(developer_dir: &Path) -> bool {
+    !developer_dir
+        .components()
+        .any(|component| component.as_os_str().to_string_lossy().ends_with(".app"))
+}
+
+fn validate_developer_dir(developer_dir: Option<&Path>) -> Result<String, String> {
+    if let Some(developer_dir) = developer_dir {
+        return Ok(format!(
+            "Developer dir from `apple.xcode-path`: {:?}",
+            developer_dir
+        ));
+    }
     static SUGGESTED: &str = "/Applications/Xcode.app/Contents/Developer";
     let xcode_developer_dir = xcode_plugin::xcode_developer_dir()
         .map_err(|err| format!("Failed to get active Xcode developer dir: {}", err))?;
+    if is_command_line_tools(&xcode_developer_dir) && !Path::new(SUGGESTED).is_dir() {
+        return Err(format!(
+            "Only the Command Line Tools are selected ({:?}), which can't build for iOS; install Xcode from the App Store, then run `sudo xcode-select -s {}`",
+            xcode_developer_dir, SUGGESTED
+        ));
+    }
     let xcode_developer_dir = {
-        if xcode_developer_dir == Path::new(FORBIDDEN) {
+        if is_command_line_tools(&xcode_developer_dir) {
             println!(
                 "Your active toolchain appears to be the Apple command-line tools: {:?}",
                 xcode_developer_dir
@@ -39,7 +63,10 @@ fn validate_developer_dir() -> Result<String, String> {
                     .map_err(|err| format!("Failed to update Xcode developer dir: {}", err))?;
                 Path::new(SUGGESTED)
             } else {
-                &xcode_developer_dir
+                return Err(format!(
+                    "Only the Command Line Tools are selected ({:?}), which can't build for iOS; run `sudo xcode-select -s {}` to use Xcode",
+                    xcode_developer_dir, SUGGESTED
+                ));
             }
         } else {
             &xcode_developer_dir
@@ -48,8 +75,75 @@ fn validate_developer_dir() -> Result<String, String> {
     Ok(format!("Active developer dir: {:?}", xcode_developer_dir))
 }
 
-fn validate_xcode_plugin(xcode_version: (u32, u32), section: Section) -> Section {
-    match xcode_plugin::Context::new(xcode_version) {
+// The oldest Xcode major version whose SDK covers `target`. Xcode 11 through
+// 16 shipped iOS 13 through 18 and macOS 10.15 through 15, and from Xcode 26
+// on, the versions match. Point releases can need a point release of Xcode
+// too, which this doesn't account for.
+fn min_xcode_major(ios: bool, target: VersionDouble) -> u32 {
+    let major = target.major;
+    match (ios, major) {
+        (true, 0..=13) => 11,
+        (true, 14..=18) => major - 2,
+        (false, 0..=10) => 11,
+        (false, 11..=15) => major + 1,
+        _ => major.max(26),
+    }
+}
+
+fn validate_deployment_targets(xcode_version: (u32, u32), raw: Option<&Raw>) -> Item {
+    let target = |key: &str, raw: Option<&String>, default| {
+        raw.and_then(|version| {
+            VersionDouble::from_str(version)
+                .inspect_err(|err| log::warn!("`apple.{}` is invalid: {}", key, err))
+                .ok()
+        })
+        .unwrap_or(default)
+    };
+    let ios = target(
+        "ios-version",
+        raw.and_then(|raw| raw.ios_version.as_ref()),
+        DEFAULT_IOS_VERSION,
+    );
+    let macos = target(
+        "macos-version",
+        raw.and_then(|raw| raw.macos_version.as_ref()),
+        DEFAULT_MACOS_VERSION,
+    );
+    let too_old = [
+        ("iOS", "ios-version", ios, true),
+        ("macOS", "macos-version", macos, false),
+    ]
+    .into_iter()
+    .map(|(name, key, target, ios)| (name, key, target, min_xcode_major(ios, target)))
+    .filter(|(.., min_major)| xcode_version.0 < *min_major)
+    .map(|(name, key, target, min_major)| {
+        format!(
+            "the {} {} deployment target (`apple.{}`) needs Xcode {} or newer",
+            name, target, key, min_major
+        )
+    })
+    .collect::<Vec<_>>();
+    if too_old.is_empty() {
+        Item::victory(format!(
+            "Xcode v{}.{} supports the iOS {} and macOS {} deployment targets",
+            xcode_version.0, xcode_version.1, ios, macos
+        ))
+    } else {
+        Item::failure(format!(
+            "Xcode v{}.{} is too old: {}",
+            xcode_version.0,
+            xcode_version.1,
+            too_old.join(", and ")
+        ))
+    }
+}
+
+fn validate_xcode_plugin(
+    xcode_version: (u32, u32),
+    developer_dir: Option<&Path>,
+    section: Section,
+) -> Section {
+    match xcode_plugin::Context::new(xcode_version, developer_dir) {
         Ok(ctx) => match ctx.check_installation() {
             Ok(status) => section
                 .with_item(if status.plugin_present {
@@ -102,8 +196,9 @@ fn validate_xcode_plugin(xcode_version: (u32, u32), section: Section) -> Section
     }
 }
 
-pub fn check() -> Section {
-    let xcode_version = DeveloperTools::new().map(|dev_tools| dev_tools.version);
+/// `developer_dir` is the one from `apple.xcode-path`, if it's set.
+pub fn check(raw: Option<&Raw>, developer_dir: Option<&Path>) -> Section {
+    let xcode_version = DeveloperTools::new(developer_dir).map(|dev_tools| dev_tools.version);
     let section = Section::new("Apple developer tools")
         .with_item(
             xcode_version
@@ -111,7 +206,7 @@ pub fn check() -> Section {
                 .map(|(major, minor)| format!("Xcode v{}.{}", major, minor))
                 .map_err(|err| format!("Failed to check Xcode version: {}", err)),
         )
-        .with_item(validate_developer_dir())
+        .with_item(validate_developer_dir(developer_dir))
         .with_item(
             duct::cmd("ios-deploy", ["--version"])
                 .stderr_capture()
@@ -127,7 +222,11 @@ pub fn check() -> Section {
                 .map_err(|err| format!("Failed to check ios-deploy version: {}", err)),
         );
     let section = if let Ok(version) = xcode_version {
-        validate_xcode_plugin(version, section)
+        validate_xcode_plugin(
+            version,
+            developer_dir,
+            section.with_item(validate_deployment_targets(version, raw)),
+        )
     } else {
         section
     };
@@ -142,3 +241,24 @@ pub fn check() -> Section {
         Err(err) => section.with_failure(format!("Failed to find development teams: {}", err)),
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(true, VersionDouble::new(13, 0), 11)]
+    #[case(true, VersionDouble::new(17, 2), 15)]
+    #[case(true, VersionDouble::new(26, 0), 26)]
+    #[case(false, VersionDouble::new(10, 15), 11)]
+    #[case(false, VersionDouble::new(14, 0), 15)]
+    #[case(false, VersionDouble::new(26, 1), 26)]
+    fn test_min_xcode_major(
+        #[case] ios: bool,
+        #[case] target: VersionDouble,
+        #[case] expected: u32,
+    ) {
+        assert_eq!(min_xcode_major(ios, target), expected);
+    }
+}
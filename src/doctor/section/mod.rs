@@ -3,6 +3,7 @@ pub mod android;
 pub mod apple;
 pub mod cargo_mobile;
 pub mod device_list;
+pub mod template_pack;
 
 use crate::util::{
     self,
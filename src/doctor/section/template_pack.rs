@@ -0,0 +1,47 @@
+use super::{Item, Section};
+use crate::{
+    config::{self, app::IMPLIED_TEMPLATE_PACK},
+    templating::Pack,
+    util,
+};
+
+/// Checks for the tools the current project's template pack requires, if
+/// there's a project and its pack declares any.
+pub fn check() -> Option<Section> {
+    let name = match config::Raw::load(".") {
+        Ok(loaded) => loaded?
+            .1
+            .app
+            .template_pack
+            .unwrap_or_else(|| IMPLIED_TEMPLATE_PACK.to_owned()),
+        Err(err) => {
+            log::info!(
+                "not checking template pack, since config failed to load: {}",
+                err
+            );
+            return None;
+        }
+    };
+    let section = Section::new(format!("Template pack {:?}", name));
+    let pack = match Pack::lookup_app(&name) {
+        Ok(pack) => pack,
+        Err(err) => return Some(section.with_failure(err)),
+    };
+    let tools = pack.required_tools();
+    if tools.is_empty() {
+        return None;
+    }
+    Some(
+        section.with_items(
+            tools
+                .into_iter()
+                .map(|tool| match util::command_present(&tool.name) {
+                    Ok(true) => Item::victory(format!("`{}` is installed", tool.name)),
+                    Ok(false) => Item::failure(format!("{} isn't installed", tool)),
+                    Err(err) => {
+                        Item::failure(format!("Failed to check for `{}`: {}", tool.name, err))
+                    }
+                }),
+        ),
+    )
+}
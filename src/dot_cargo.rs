@@ -2,8 +2,12 @@ use crate::{
     config::app::App,
     util::cli::{Report, Reportable},
 };
-use serde::{Deserialize, Serialize};
-use std::{collections::BTreeMap, fs, io, path::PathBuf};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
 use thiserror::Error;
 use toml::Value;
 
@@ -48,15 +52,18 @@ impl Reportable for WriteError {
     }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Default, Deserialize, Serialize)]
 pub struct DotCargoBuild {
-    target: String,
+    target: Option<String>,
+    #[serde(flatten)]
+    extra: toml::Table,
 }
 
 impl DotCargoBuild {
     pub fn new(target: impl Into<String>) -> Self {
         Self {
-            target: target.into(),
+            target: Some(target.into()),
+            extra: Default::default(),
         }
     }
 }
@@ -66,6 +73,8 @@ pub struct DotCargoTarget {
     pub linker: Option<String>,
     #[serde(default)]
     pub rustflags: Vec<String>,
+    #[serde(flatten)]
+    pub extra: toml::Table,
 }
 
 impl DotCargoTarget {
@@ -79,7 +88,73 @@ pub struct DotCargo {
     build: Option<DotCargoBuild>,
     target: BTreeMap<String, DotCargoTarget>,
     #[serde(flatten)]
-    extra: BTreeMap<String, Value>,
+    extra: toml::Table,
+    // What `merge_extra` merged in last time, so that whatever's been removed
+    // from `cargo-config-extra` since can be removed here too.
+    #[serde(skip)]
+    previous_extra: toml::Table,
+}
+
+// Tables are merged recursively, while anything else in `src` replaces what's
+// already there.
+fn merge_value(dst: &mut Value, src: &Value) {
+    match (dst, src) {
+        (Value::Table(dst), Value::Table(src)) => {
+            for (key, value) in src {
+                match dst.get_mut(key) {
+                    Some(existing) => merge_value(existing, value),
+                    None => {
+                        dst.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        (dst, src) => *dst = src.clone(),
+    }
+}
+
+fn merge_entry(map: &mut toml::Table, key: &str, value: &Value) {
+    match map.get_mut(key) {
+        Some(existing) => merge_value(existing, value),
+        None => {
+            map.insert(key.to_owned(), value.clone());
+        }
+    }
+}
+
+// Removes what merging `previous` added at `key`, other than what `current`
+// still has. Anything added by hand is left alone, and tables that end up
+// empty are removed.
+fn remove_stale_entry(map: &mut toml::Table, key: &str, previous: &Value, current: Option<&Value>) {
+    match (previous, current) {
+        (Value::Table(previous), None | Some(Value::Table(_))) => {
+            if let Some(Value::Table(table)) = map.get_mut(key) {
+                for (key, previous) in previous {
+                    remove_stale_entry(
+                        table,
+                        key,
+                        previous,
+                        current.and_then(|current| current.get(key)),
+                    );
+                }
+                if table.is_empty() {
+                    map.remove(key);
+                }
+            }
+        }
+        (_, None) => {
+            map.remove(key);
+        }
+        // Merging replaces it anyway.
+        _ => {}
+    }
+}
+
+fn warn_managed(key: &str) {
+    log::warn!(
+        "`cargo-config-extra` can't override `{}`, since it's managed by cargo-mobile2",
+        key
+    );
 }
 
 impl DotCargo {
@@ -90,6 +165,23 @@ impl DotCargo {
             .map_err(|cause| (dir, cause))
     }
 
+    // Cargo only reads `config.toml`, so this can sit next to it.
+    fn previous_extra_path(path: &Path) -> PathBuf {
+        path.with_file_name("cargo-config-extra.toml")
+    }
+
+    fn read<T: DeserializeOwned + Default>(path: PathBuf) -> Result<T, LoadError> {
+        if path.is_file() {
+            let toml_str = fs::read_to_string(&path).map_err(|cause| LoadError::ReadFailed {
+                path: path.clone(),
+                cause,
+            })?;
+            toml::from_str(&toml_str).map_err(|cause| LoadError::DeserializeFailed { path, cause })
+        } else {
+            Ok(T::default())
+        }
+    }
+
     pub fn load(app: &App) -> Result<Self, LoadError> {
         let path = Self::create_dir_and_get_path(app)
             .map_err(|(path, cause)| LoadError::DirCreationFailed { path, cause })?;
@@ -105,15 +197,11 @@ impl DotCargo {
                 cause,
             })?;
         }
-        if path.is_file() {
-            let toml_str = fs::read_to_string(&path).map_err(|cause| LoadError::ReadFailed {
-                path: path.clone(),
-                cause,
-            })?;
-            toml::from_str(&toml_str).map_err(|cause| LoadError::DeserializeFailed { path, cause })
-        } else {
-            Ok(Self::default())
-        }
+        let previous_extra = Self::read(Self::previous_extra_path(&path))?;
+        Self::read(path).map(|this| Self {
+            previous_extra,
+            ..this
+        })
     }
 
     pub fn set_default_target(&mut self, target: impl Into<String>) {
@@ -122,8 +210,98 @@ impl DotCargo {
 
     pub fn insert_target(&mut self, name: impl Into<String>, target: DotCargoTarget) {
         if !target.is_empty() {
-            // merging could be nice, but is also very painful...
-            self.target.insert(name.into(), target);
+            let name = name.into();
+            // Keep anything the user added to this target by hand.
+            let extra = self
+                .target
+                .remove(&name)
+                .map(|old| old.extra)
+                .unwrap_or_default();
+            self.target.insert(name, DotCargoTarget { extra, ..target });
+        }
+    }
+
+    fn remove_stale_extra(&mut self, previous: &toml::Table, current: &toml::Table) {
+        for (key, previous) in previous {
+            let current = current.get(key);
+            match (key.as_str(), previous) {
+                ("build", Value::Table(previous)) => {
+                    if let Some(build) = &mut self.build {
+                        for (key, previous) in previous {
+                            let current = current.and_then(|current| current.get(key));
+                            remove_stale_entry(&mut build.extra, key, previous, current);
+                        }
+                        if build.target.is_none() && build.extra.is_empty() {
+                            self.build = None;
+                        }
+                    }
+                }
+                ("target", Value::Table(previous)) => {
+                    for (name, previous) in previous {
+                        let (Value::Table(previous), Some(target)) =
+                            (previous, self.target.get_mut(name))
+                        else {
+                            continue;
+                        };
+                        for (key, previous) in previous {
+                            let current = current
+                                .and_then(|current| current.get(name))
+                                .and_then(|current| current.get(key));
+                            remove_stale_entry(&mut target.extra, key, previous, current);
+                        }
+                        if target.is_empty() && target.extra.is_empty() {
+                            self.target.remove(name);
+                        }
+                    }
+                }
+                _ => remove_stale_entry(&mut self.extra, key, previous, current),
+            }
+        }
+    }
+
+    /// Merges user-provided sections (`app.cargo-config-extra`) into the
+    /// config, first removing whatever was merged in last time that's since
+    /// been removed from them. `build.target` and each target's `linker` and
+    /// `rustflags` are generated, so attempts to override them are ignored.
+    pub fn merge_extra(&mut self, extra: &toml::Table) {
+        let previous = std::mem::replace(&mut self.previous_extra, extra.clone());
+        self.remove_stale_extra(&previous, extra);
+        for (key, value) in extra {
+            match (key.as_str(), value) {
+                ("build", Value::Table(build)) => {
+                    let dst = self.build.get_or_insert_with(Default::default);
+                    for (key, value) in build {
+                        if key == "target" {
+                            warn_managed("build.target");
+                        } else {
+                            merge_entry(&mut dst.extra, key, value);
+                        }
+                    }
+                }
+                ("target", Value::Table(targets)) => {
+                    for (name, value) in targets {
+                        let Value::Table(target) = value else {
+                            log::warn!("ignoring `target.{}` in `cargo-config-extra`, since it isn't a table", name);
+                            continue;
+                        };
+                        let dst = self.target.entry(name.clone()).or_default();
+                        for (key, value) in target {
+                            if key == "linker" || key == "rustflags" {
+                                warn_managed(&format!("target.{}.{}", name, key));
+                            } else {
+                                merge_entry(&mut dst.extra, key, value);
+                            }
+                        }
+                    }
+                }
+                ("build" | "target", _) => {
+                    log::warn!(
+                        "ignoring `{}` in `cargo-config-extra`, since it isn't a table",
+                        key
+                    );
+                }
+                _ => merge_entry(&mut self.extra, key, value),
+            }
         }
     }
 
@@ -131,6 +309,127 @@ impl DotCargo {
         let path = Self::create_dir_and_get_path(app)
             .map_err(|(path, cause)| WriteError::DirCreationFailed { path, cause })?;
         let ser = toml::to_string_pretty(&self).map_err(WriteError::SerializeFailed)?;
+        fs::write(&path, ser).map_err(|cause| WriteError::WriteFailed {
+            path: path.clone(),
+            cause,
+        })?;
+        let path = Self::previous_extra_path(&path);
+        let ser =
+            toml::to_string_pretty(&self.previous_extra).map_err(WriteError::SerializeFailed)?;
         fs::write(&path, ser).map_err(|cause| WriteError::WriteFailed { path, cause })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn user_sections_survive_regeneration() {
+        let existing = r#"
+[build]
+target = "aarch64-linux-android"
+
+[target.aarch64-linux-android]
+linker = "/old/ndk/clang"
+rustflags = ["-Clink-arg=-landroid"]
+runner = "my-runner"
+
+[source.crates-io]
+replace-with = "mirror"
+
+[source.mirror]
+registry = "sparse+https://mirror.example.com/index/"
+"#;
+        let mut dot_cargo: DotCargo = toml::from_str(existing).unwrap();
+        dot_cargo.insert_target(
+            "aarch64-linux-android",
+            DotCargoTarget {
+                linker: Some("/new/ndk/clang".to_owned()),
+                rustflags: vec!["-Clink-arg=-landroid".to_owned()],
+                ..Default::default()
+            },
+        );
+        dot_cargo.merge_extra(
+            &toml::from_str(
+                r#"
+env = { FOO = "bar" }
+target.aarch64-linux-android = { linker = "ignored", runner = "other-runner" }
+"#,
+            )
+            .unwrap(),
+        );
+
+        let regenerated: toml::Table =
+            toml::from_str(&toml::to_string_pretty(&dot_cargo).unwrap()).unwrap();
+        let expected: toml::Table = toml::from_str(
+            r#"
+[build]
+target = "aarch64-linux-android"
+
+[target.aarch64-linux-android]
+linker = "/new/ndk/clang"
+rustflags = ["-Clink-arg=-landroid"]
+runner = "other-runner"
+
+[source.crates-io]
+replace-with = "mirror"
+
+[source.mirror]
+registry = "sparse+https://mirror.example.com/index/"
+
+[env]
+FOO = "bar"
+"#,
+        )
+        .unwrap();
+        assert_eq!(regenerated, expected);
+    }
+
+    #[test]
+    fn removed_user_sections_are_removed_on_regeneration() {
+        let mut dot_cargo: DotCargo = toml::from_str(
+            r#"
+[target.aarch64-linux-android]
+linker = "/ndk/clang"
+
+[env]
+HAND = "written"
+"#,
+        )
+        .unwrap();
+        dot_cargo.merge_extra(
+            &toml::from_str(
+                r#"
+env = { FOO = "bar", BAZ = "qux" }
+build.jobs = 2
+target.aarch64-linux-android.runner = "my-runner"
+source.mirror.registry = "sparse+https://mirror.example.com/index/"
+"#,
+            )
+            .unwrap(),
+        );
+        // As if `load` had read it back in.
+        let mut dot_cargo = DotCargo {
+            previous_extra: dot_cargo.previous_extra.clone(),
+            ..toml::from_str(&toml::to_string_pretty(&dot_cargo).unwrap()).unwrap()
+        };
+        dot_cargo.merge_extra(&toml::from_str(r#"env = { BAZ = "quux" }"#).unwrap());
+
+        let regenerated: toml::Table =
+            toml::from_str(&toml::to_string_pretty(&dot_cargo).unwrap()).unwrap();
+        let expected: toml::Table = toml::from_str(
+            r#"
+[target.aarch64-linux-android]
+linker = "/ndk/clang"
+rustflags = []
+
+[env]
+HAND = "written"
+BAZ = "quux"
+"#,
+        )
+        .unwrap();
+        assert_eq!(regenerated, expected);
+    }
+}
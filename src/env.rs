@@ -1,5 +1,14 @@
-use crate::util::cli::{Report, Reportable};
-use std::{collections::HashMap, ffi::OsString, fmt::Debug, path::Path};
+use crate::util::{
+    cli::{Report, Reportable},
+    CargoLock, CARGO_LOCK_VAR, DENY_WARNINGS_VAR,
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    ffi::OsString,
+    fmt::Debug,
+    num::NonZeroU32,
+    path::Path,
+};
 use thiserror::Error;
 
 pub trait ExplicitEnv: Debug {
@@ -35,6 +44,28 @@ impl Env {
         if let Some(ssh_auth_sock) = std::env::var_os("SSH_AUTH_SOCK") {
             vars.insert("SSH_AUTH_SOCK".into(), ssh_auth_sock);
         }
+        // Lets cargo, Gradle, and Xcode pin the timestamps they embed:
+        // https://reproducible-builds.org/specs/source-date-epoch/
+        if let Some(source_date_epoch) = std::env::var_os("SOURCE_DATE_EPOCH") {
+            vars.insert("SOURCE_DATE_EPOCH".into(), source_date_epoch);
+        }
+
+        for key in [CARGO_LOCK_VAR, DENY_WARNINGS_VAR] {
+            if let Some(value) = std::env::var_os(key) {
+                vars.insert(key.into(), value);
+            }
+        }
+
+        // Compiler caches like sccache are configured through the environment,
+        // and don't touch the linker, so the NDK linker settings still apply.
+        if let Some(rustc_wrapper) = std::env::var_os("RUSTC_WRAPPER") {
+            vars.insert("RUSTC_WRAPPER".into(), rustc_wrapper);
+        }
+        vars.extend(
+            std::env::vars_os()
+                .filter_map(|(key, value)| Some((key.into_string().ok()?, value)))
+                .filter(|(key, _)| key.starts_with("SCCACHE_")),
+        );
 
         vars.insert("HOME".into(), home);
         vars.insert("PATH".into(), path);
@@ -62,6 +93,68 @@ impl Env {
         self.vars.extend(vars);
         self
     }
+
+    /// Copies the named variables from the ambient environment. Variables
+    /// that aren't set are skipped.
+    pub fn forward_vars(mut self, names: &[String]) -> Self {
+        for name in names {
+            if let Some(value) = std::env::var_os(name) {
+                self.vars.insert(name.clone(), value);
+            } else {
+                log::debug!("not forwarding `{}`, since it isn't set", name);
+            }
+        }
+        self
+    }
+
+    /// Limits how many jobs cargo runs in parallel. This goes through
+    /// `CARGO_BUILD_JOBS` rather than `-j`, so that it also reaches the cargo
+    /// invoked by Xcode and Gradle.
+    pub fn with_cargo_jobs(mut self, jobs: Option<NonZeroU32>) -> Self {
+        if let Some(jobs) = jobs {
+            self.vars
+                .insert("CARGO_BUILD_JOBS".into(), jobs.to_string().into());
+        }
+        self
+    }
+
+    /// Makes cargo respect `Cargo.lock` the way `--locked` or `--frozen` would.
+    /// Cargo has no env var for these, so this sets our own, which survives
+    /// the trip through Xcode and Gradle.
+    pub fn with_cargo_lock(mut self, lock: Option<CargoLock>) -> Self {
+        if let Some(lock) = lock {
+            self.vars
+                .insert(CARGO_LOCK_VAR.into(), lock.as_str().into());
+        }
+        self
+    }
+
+    /// Makes the cargo invocations cargo-mobile2 drives treat warnings as
+    /// errors, without affecting host builds; see [`DENY_WARNINGS_VAR`].
+    pub fn with_deny_warnings(mut self, deny_warnings: bool) -> Self {
+        if deny_warnings {
+            self.vars.insert(DENY_WARNINGS_VAR.into(), "1".into());
+        }
+        self
+    }
+
+    /// Sets `RUSTC_WRAPPER` to `sccache`, unless it isn't installed or another
+    /// wrapper is already set.
+    pub fn with_sccache(mut self) -> Self {
+        if let Some(rustc_wrapper) = self.vars.get("RUSTC_WRAPPER") {
+            log::info!(
+                "not using sccache, since `RUSTC_WRAPPER` is already set to {:?}",
+                rustc_wrapper
+            );
+        } else if crate::util::command_present("sccache").unwrap_or_default() {
+            self.vars.insert("RUSTC_WRAPPER".into(), "sccache".into());
+        } else {
+            log::warn!(
+                "`app.use-sccache` is set, but `sccache` isn't installed; building without it"
+            );
+        }
+        self
+    }
 }
 
 impl ExplicitEnv for Env {
@@ -69,3 +162,116 @@ impl ExplicitEnv for Env {
         self.vars.clone()
     }
 }
+
+/// The shell syntax for [`export`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ExportSyntax {
+    /// `export KEY="VALUE"`, for `eval` in a POSIX shell.
+    Sh,
+    /// `set "KEY=VALUE"`, for a batch file that's then `call`ed.
+    Cmd,
+}
+
+impl ExportSyntax {
+    pub fn host() -> Self {
+        if cfg!(windows) {
+            Self::Cmd
+        } else {
+            Self::Sh
+        }
+    }
+
+    // Everything but these is taken literally inside double quotes.
+    fn quote_sh(value: &str) -> String {
+        let mut quoted = String::with_capacity(value.len() + 2);
+        quoted.push('"');
+        for c in value.chars() {
+            if matches!(c, '"' | '\\' | '$' | '`') {
+                quoted.push('\\');
+            }
+            quoted.push(c);
+        }
+        quoted.push('"');
+        quoted
+    }
+
+    // Inside `set "..."`, cmd only expands `%`, and everything up to the last
+    // quote is the value, so quotes within it don't need escaping.
+    fn quote_cmd(key: &str, value: &str) -> Option<String> {
+        if value.contains(['\n', '\r']) {
+            None
+        } else {
+            Some(format!("set \"{}={}\"", key, value.replace('%', "%%")))
+        }
+    }
+
+    fn line(self, key: &str, value: &str) -> Option<String> {
+        match self {
+            Self::Sh => Some(format!("export {}={}", key, Self::quote_sh(value))),
+            Self::Cmd => Self::quote_cmd(key, value),
+        }
+    }
+}
+
+/// Formats `env`'s vars as lines a shell can source to reproduce it, sorted by
+/// name. Values that can't be expressed in `syntax` are skipped with a warning.
+pub fn export(env: &impl ExplicitEnv, syntax: ExportSyntax) -> Vec<String> {
+    env.explicit_env()
+        .into_iter()
+        .collect::<BTreeMap<_, _>>()
+        .into_iter()
+        .filter_map(|(key, value)| {
+            let line = syntax.line(&key, &value.to_string_lossy());
+            if line.is_none() {
+                log::warn!("not exporting `{}`, since its value has a newline", key);
+            }
+            line
+        })
+        .collect()
+}
+
+/// Prints `env`'s vars as `KEY=VALUE`, or with `export_syntax`, in a form a
+/// shell can source.
+pub fn print(env: &impl ExplicitEnv, export_syntax: Option<ExportSyntax>) {
+    if let Some(syntax) = export_syntax {
+        for line in export(env, syntax) {
+            println!("{}", line);
+        }
+    } else {
+        for (key, value) in env.explicit_env().into_iter().collect::<BTreeMap<_, _>>() {
+            println!("{}={}", key, value.to_string_lossy());
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(ExportSyntax::Sh, "plain", Some(r#"export KEY="plain""#))]
+    #[case(ExportSyntax::Sh, "two words", Some(r#"export KEY="two words""#))]
+    #[case(ExportSyntax::Sh, r#"say "hi""#, Some(r#"export KEY="say \"hi\"""#))]
+    #[case(ExportSyntax::Sh, "$HOME`id`", Some(r#"export KEY="\$HOME\`id\`""#))]
+    #[case(ExportSyntax::Sh, r"C:\dir\", Some(r#"export KEY="C:\\dir\\""#))]
+    #[case(
+        ExportSyntax::Sh,
+        "it's\nmultiline",
+        Some("export KEY=\"it's\nmultiline\"")
+    )]
+    #[case(ExportSyntax::Cmd, "two words", Some(r#"set "KEY=two words""#))]
+    #[case(
+        ExportSyntax::Cmd,
+        r#"say "hi" & 100%"#,
+        Some(r#"set "KEY=say "hi" & 100%%""#)
+    )]
+    #[case(ExportSyntax::Cmd, "multi\nline", None)]
+    fn test_line(
+        #[case] syntax: ExportSyntax,
+        #[case] value: &str,
+        #[case] expected: Option<&str>,
+    ) {
+        assert_eq!(syntax.line("KEY", value).as_deref(), expected);
+    }
+}
@@ -17,6 +17,7 @@ use crate::{
 };
 use std::{
     fs, io,
+    ops::{BitOr, BitOrAssign},
     path::{Path, PathBuf},
 };
 
@@ -37,14 +38,66 @@ again (the contents don't matter) and run `cargo mobile init`. Just, if you do
 that, any generated files you modified will be overwritten!
 "#;
 
+// What `--require-empty` tolerates, since a crate is often started with
+// `cargo init` or `git init` before cargo-mobile2 is pulled in.
+static REQUIRE_EMPTY_ALLOWLIST: &[&str] = &[".git", "Cargo.toml", "src"];
+
+// The parts of init that can be skipped.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+struct Steps(u8);
+
+impl Steps {
+    // The base project from the template pack.
+    const HELLO_WORLD: Self = Self(1 << 0);
+    // `.cargo/config.toml`.
+    const CARGO: Self = Self(1 << 1);
+    // Rustup targets.
+    const TOOLCHAINS: Self = Self(1 << 2);
+    // Editor extensions and other dev tools.
+    const DEV_TOOLS: Self = Self(1 << 3);
+    // The iOS dependencies installed through package managers.
+    const PACKAGES: Self = Self(1 << 4);
+    // Everything that touches a package manager.
+    const DEPS: Self = Self(Self::TOOLCHAINS.0 | Self::DEV_TOOLS.0 | Self::PACKAGES.0);
+
+    fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for Steps {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for Steps {
+    fn bitor_assign(&mut self, rhs: Self) {
+        *self = *self | rhs;
+    }
+}
+
 #[derive(Debug)]
 pub enum Error {
+    DirReadFailed {
+        dir: PathBuf,
+        cause: io::Error,
+    },
+    DirNotEmpty {
+        dir: PathBuf,
+        unexpected: Vec<String>,
+    },
     ConfigLoadOrGenFailed(config::LoadOrGenError),
+    RequiredToolsCheckFailed(io::Error),
+    RequiredToolsMissing(Vec<templating::RequiredTool>),
     DotFirstInitWriteFailed {
         path: PathBuf,
         cause: io::Error,
     },
     FilterConfigureFailed(templating::FilterError),
+    TemplateDataFailed(templating::TemplateDataError),
     ProjectInitFailed(project::Error),
     AssetDirCreationFailed {
         asset_dir: PathBuf,
@@ -70,9 +123,14 @@ pub enum Error {
 impl Reportable for Error {
     fn report(&self) -> Report {
         match self {
+            Self::DirReadFailed { dir, cause } => Report::error(format!("Failed to read dir {:?}", dir), cause),
+            Self::DirNotEmpty { dir, unexpected } => Report::action_request(format!("{:?} isn't empty, so nothing was generated", dir), format!("Found unexpected files: {}. Move them out of the way, or drop `--require-empty`.", unexpected.join(", "))),
             Self::ConfigLoadOrGenFailed(err) => err.report(),
+            Self::RequiredToolsCheckFailed(err) => Report::error("Failed to check for tools required by template pack", err),
+            Self::RequiredToolsMissing(missing) => Report::action_request("Template pack requires tools that aren't installed, so nothing was generated", format!("Install {}, then run this again.", missing.iter().map(ToString::to_string).collect::<Vec<_>>().join(", "))),
             Self::DotFirstInitWriteFailed { path, cause } => Report::error(format!("Failed to write first init dot file {:?}", path), cause),
             Self::FilterConfigureFailed(err) => Report::error("Failed to configure template filter", err),
+            Self::TemplateDataFailed(err) => Report::error("Failed to load template data", err),
             Self::ProjectInitFailed(err) => err.report(),
             Self::AssetDirCreationFailed { asset_dir, cause } => Report::error(format!("Failed to create asset dir {:?}", asset_dir), cause),
             Self::CodeCommandPresentFailed(err) => Report::error("Failed to check for presence of `code` command", err),
@@ -91,6 +149,20 @@ impl Reportable for Error {
     }
 }
 
+// Anything in `dir` besides what's in `REQUIRE_EMPTY_ALLOWLIST`.
+fn unexpected_files(dir: &Path) -> io::Result<Vec<String>> {
+    let mut unexpected = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let name = entry?.file_name();
+        let name = name.to_string_lossy();
+        if !REQUIRE_EMPTY_ALLOWLIST.contains(&name.as_ref()) {
+            unexpected.push(name.into_owned());
+        }
+    }
+    unexpected.sort();
+    Ok(unexpected)
+}
+
 #[allow(clippy::too_many_arguments)]
 pub fn exec(
     wrapper: &TextWrapper,
@@ -98,13 +170,82 @@ pub fn exec(
     skip_dev_tools: bool,
     skip_targets_install: bool,
     #[cfg_attr(not(target_os = "macos"), allow(unused))] reinstall_deps: bool,
+    no_deps: bool,
+    project_only: bool,
+    bare: bool,
+    template_data: Option<&Path>,
+    strict_template_data: bool,
+    require_empty: bool,
+    force_config: bool,
     open_in_editor: bool,
     submodule_commit: Option<String>,
+    app: Option<&str>,
     cwd: impl AsRef<Path>,
 ) -> Result<Config, Box<Error>> {
     let cwd = cwd.as_ref();
-    let (config, config_origin) =
-        Config::load_or_gen(cwd, non_interactive, wrapper).map_err(Error::ConfigLoadOrGenFailed)?;
+    // Checked before the config is loaded, since generating one writes to disk.
+    if require_empty {
+        let unexpected = unexpected_files(cwd).map_err(|cause| Error::DirReadFailed {
+            dir: cwd.to_owned(),
+            cause,
+        })?;
+        if !unexpected.is_empty() {
+            return Err(Box::new(Error::DirNotEmpty {
+                dir: cwd.to_owned(),
+                unexpected,
+            }));
+        }
+    }
+    let mut skip = Steps::default();
+    if skip_dev_tools {
+        skip |= Steps::DEV_TOOLS;
+    }
+    if skip_targets_install {
+        skip |= Steps::TOOLCHAINS;
+    }
+    if no_deps {
+        skip |= Steps::DEPS;
+    }
+    // For adding mobile support to an existing crate, without touching any of
+    // the crate's own files.
+    if project_only {
+        skip |= Steps::HELLO_WORLD | Steps::CARGO | Steps::TOOLCHAINS | Steps::DEV_TOOLS;
+    }
+    // Loaded up front, so that a bad file fails before anything's generated.
+    let template_data = template_data
+        .map(templating::load_template_data)
+        .transpose()
+        .map_err(Error::TemplateDataFailed)?;
+    let template_pack = bare.then_some(templating::BARE_PACK_NAME);
+    let (config, config_origin) = Config::load_or_gen(
+        cwd,
+        non_interactive,
+        force_config,
+        app,
+        &[],
+        None,
+        None,
+        template_pack,
+        wrapper,
+    )
+    .map_err(Error::ConfigLoadOrGenFailed)?;
+    if bare && !matches!(config.app().template_pack(), templating::Pack::Builtin(_)) {
+        log::warn!(
+            "`--bare` only applies when generating a new config, so the template pack in {:?} will be used",
+            config.path()
+        );
+    }
+    // The pack's own tools aren't needed if the base project isn't generated.
+    if !skip.contains(Steps::HELLO_WORLD) {
+        let missing = config
+            .app()
+            .template_pack()
+            .missing_tools()
+            .map_err(Error::RequiredToolsCheckFailed)?;
+        if !missing.is_empty() {
+            return Err(Box::new(Error::RequiredToolsMissing(missing)));
+        }
+    }
     let dot_first_init_path = config.app().root_dir().join(DOT_FIRST_INIT_FILE_NAME);
     let dot_first_init_exists = {
         let dot_first_init_exists = dot_first_init_path.exists();
@@ -123,19 +264,30 @@ pub fn exec(
             dot_first_init_exists
         }
     };
-    let bike = config.build_a_bike();
+    let mut bike = config.build_a_bike();
+    if let Some(data) = template_data {
+        templating::merge_template_data(&mut bike, data, strict_template_data)
+            .map_err(Error::TemplateDataFailed)?;
+    }
     let filter = templating::Filter::new(&config, config_origin, dot_first_init_exists)
         .map_err(Error::FilterConfigureFailed)?;
 
     // Generate the base project
-    project::gen(&config, &bike, &filter, submodule_commit).map_err(Error::ProjectInitFailed)?;
+    if skip.contains(Steps::HELLO_WORLD) {
+        println!("Skipping base project generation, since `--project-only` was passed");
+    } else {
+        project::gen(&config, &bike, &filter, submodule_commit)
+            .map_err(Error::ProjectInitFailed)?;
+    }
 
     let asset_dir = config.app().asset_dir();
     if !asset_dir.is_dir() {
         fs::create_dir_all(&asset_dir)
             .map_err(|cause| Error::AssetDirCreationFailed { asset_dir, cause })?;
     }
-    if !skip_dev_tools && util::command_present("code").map_err(Error::CodeCommandPresentFailed)? {
+    if !skip.contains(Steps::DEV_TOOLS)
+        && util::command_present("code").map_err(Error::CodeCommandPresentFailed)?
+    {
         code_command()
             .before_spawn(move |cmd| {
                 cmd.args(["--install-extension", "vadimcn.vscode-lldb"]);
@@ -162,10 +314,11 @@ pub fn exec(
             &bike,
             wrapper,
             non_interactive,
-            skip_dev_tools,
+            skip.contains(Steps::DEV_TOOLS),
             reinstall_deps,
+            skip.contains(Steps::PACKAGES),
             &filter,
-            skip_targets_install,
+            skip.contains(Steps::TOOLCHAINS),
         )
         .map_err(Error::AppleInitFailed)?;
     } else {
@@ -175,17 +328,20 @@ pub fn exec(
     // Generate Android Studio project
     if metadata.android().supported() {
         match android::env::Env::new() {
-            Ok(env) => android::project::gen(
-                config.android(),
-                metadata.android(),
-                &env,
-                &bike,
-                wrapper,
-                &filter,
-                &mut dot_cargo,
-                skip_targets_install,
-            )
-            .map_err(Error::AndroidInitFailed)?,
+            Ok(mut env) => {
+                env.base = env.base.explicit_env_vars(config.env_file_vars().clone());
+                android::project::gen(
+                    config.android(),
+                    metadata.android(),
+                    &env,
+                    &bike,
+                    wrapper,
+                    &filter,
+                    &mut dot_cargo,
+                    skip.contains(Steps::TOOLCHAINS),
+                )
+                .map_err(Error::AndroidInitFailed)?
+            }
             Err(err) => {
                 if err.sdk_or_ndk_issue() {
                     Report::action_request(
@@ -204,9 +360,12 @@ pub fn exec(
         );
     }
 
-    dot_cargo
-        .write(config.app())
-        .map_err(Error::DotCargoWriteFailed)?;
+    if !skip.contains(Steps::CARGO) {
+        dot_cargo.merge_extra(config.app().cargo_config_extra());
+        dot_cargo
+            .write(config.app())
+            .map_err(Error::DotCargoWriteFailed)?;
+    }
     if dot_first_init_exists {
         log::info!("deleting first init dot file at {:?}", dot_first_init_path);
         fs::remove_file(&dot_first_init_path).map_err(|cause| Error::DotFirstInitDeleteFailed {
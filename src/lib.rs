@@ -4,29 +4,40 @@ pub mod android;
 #[cfg(target_os = "macos")]
 pub mod apple;
 pub mod bicycle;
+pub mod build_info;
 pub mod config;
+pub mod devcontainer;
 pub mod device;
 pub mod doctor;
 pub mod dot_cargo;
 pub mod env;
 pub mod init;
+pub mod migrate;
 pub mod opts;
 pub mod os;
 mod project;
 pub mod reserved_names;
+pub mod store_metadata;
 pub mod target;
 mod templating;
 pub mod update;
+pub mod usage;
 pub mod util;
+pub mod version;
 use std::ffi::OsStr;
 
 pub use duct::Handle as ChildHandle;
+pub use templating::{enumerate_packs, ListError, PackCategory, PackInfo, PackKind};
 
 pub static NAME: &str = "mobile";
 
 trait DuctExpressionExt {
     fn vars(self, vars: impl IntoIterator<Item = (impl AsRef<OsStr>, impl AsRef<OsStr>)>) -> Self;
     fn run_and_detach(self) -> Result<(), std::io::Error>;
+    // Puts the spawned process in a new process group, without detaching it.
+    // The group's id is the process' pid, as given by `Handle::pids`, so the
+    // whole group can be signalled later on.
+    fn new_process_group(&self) -> Self;
     // Sets the stdin, stdout and stderr to properly
     // show the command output in a Node.js wrapper (napi-rs).
     fn dup_stdio(&self) -> Self;
@@ -88,9 +99,39 @@ impl DuctExpressionExt for duct::Expression {
         Ok(())
     }
 
+    fn new_process_group(&self) -> Self {
+        self.before_spawn(|cmd| {
+            #[cfg(unix)]
+            {
+                use std::os::unix::process::CommandExt as _;
+                cmd.process_group(0);
+            }
+            #[cfg(windows)]
+            {
+                use std::os::windows::process::CommandExt;
+                const CREATE_NEW_PROCESS_GROUP: u32 = 0x00000200;
+                cmd.creation_flags(CREATE_NEW_PROCESS_GROUP);
+            }
+            Ok(())
+        })
+    }
+
     fn dup_stdio(&self) -> Self {
+        // Output has to go around a spinner instead of straight to the
+        // terminal, or the two would write over each other.
+        if let Some((stdout, stderr)) = util::progress::relay_stdio() {
+            return self
+                .stdin_file(os_pipe::dup_stdin().unwrap())
+                .stdout_file(stdout)
+                .stderr_file(stderr);
+        }
+        let stdout = if util::cli::stdout_reserved() {
+            os_pipe::dup_stderr()
+        } else {
+            os_pipe::dup_stdout()
+        };
         self.stdin_file(os_pipe::dup_stdin().unwrap())
-            .stdout_file(os_pipe::dup_stdout().unwrap())
+            .stdout_file(stdout.unwrap())
             .stderr_file(os_pipe::dup_stderr().unwrap())
     }
 }
@@ -0,0 +1,237 @@
+use crate::util::{
+    cli::{Report, Reportable, TextWrapper},
+    prompt, Git,
+};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+// The original cargo-mobile kept the app's crate in `rust/lib`, rather than at
+// the project root.
+static LEGACY_LIB_DIR: &str = "rust/lib";
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Failed to get current working directory: {0}")]
+    CurrentDirFailed(io::Error),
+    #[error("No cargo-mobile (v1) project was found in {0}")]
+    NotDetected(PathBuf),
+    #[error("Failed to read directory {path}: {cause}")]
+    ReadDirFailed { path: PathBuf, cause: io::Error },
+    #[error("{0} already exists, so it can't be moved into place")]
+    Conflict(PathBuf),
+    #[error("Failed to prompt for confirmation: {0}")]
+    PromptFailed(io::Error),
+    #[error("Migration was cancelled")]
+    Cancelled,
+    #[error("Failed to move {from} to {to}: {cause}")]
+    MoveFailed {
+        from: PathBuf,
+        to: PathBuf,
+        cause: io::Error,
+    },
+    #[error("{cause}; moving the rest back failed too, so these are still at the project root: {}", .stranded.iter().map(|path| format!("{:?}", path)).collect::<Vec<_>>().join(", "))]
+    RollbackFailed {
+        cause: Box<Error>,
+        stranded: Vec<PathBuf>,
+    },
+}
+
+impl Reportable for Error {
+    fn report(&self) -> Report {
+        match self {
+            Self::Cancelled => Report::action_request(
+                "Migration cancelled",
+                "Back up or commit your project, then run `cargo mobile migrate` again.",
+            ),
+            _ => Report::error("Failed to migrate project", self),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct LegacyProject {
+    root: PathBuf,
+    lib_dir: PathBuf,
+}
+
+impl LegacyProject {
+    /// Looks for the `rust/lib` crate used by cargo-mobile (v1). Projects that
+    /// already have a `Cargo.toml` at the root are assumed to be migrated.
+    pub fn heuristic_detect(root: impl Into<PathBuf>) -> Option<Self> {
+        let root = root.into();
+        let lib_dir = root.join(LEGACY_LIB_DIR);
+        (lib_dir.join("Cargo.toml").is_file() && !root.join("Cargo.toml").exists())
+            .then_some(Self { root, lib_dir })
+    }
+
+    fn entries(&self) -> Result<Vec<PathBuf>, Error> {
+        let mut entries = fs::read_dir(&self.lib_dir)
+            .and_then(|entries| {
+                entries
+                    .map(|entry| entry.map(|entry| entry.path()))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .map_err(|cause| Error::ReadDirFailed {
+                path: self.lib_dir.clone(),
+                cause,
+            })?;
+        entries.sort();
+        Ok(entries)
+    }
+
+    fn move_entry(&self, from: &Path, to: &Path) -> Result<(), Error> {
+        let result = if self.root.join(".git").exists() {
+            let (from, to) = (from.to_owned(), to.to_owned());
+            Git::new(&self.root)
+                .command()
+                .before_spawn(move |cmd| {
+                    cmd.arg("mv").arg(&from).arg(&to);
+                    Ok(())
+                })
+                .run()
+                .map(|_| ())
+        } else {
+            fs::rename(from, to)
+        };
+        result.map_err(|cause| Error::MoveFailed {
+            from: from.to_owned(),
+            to: to.to_owned(),
+            cause,
+        })
+    }
+
+    // Moves back what was already moved, so that a failed migration leaves
+    // the project as it was.
+    fn roll_back(&self, cause: Error, moved: Vec<(PathBuf, PathBuf)>) -> Error {
+        let stranded = moved
+            .into_iter()
+            .rev()
+            .filter(|(from, to)| {
+                log::info!("moving {:?} back to {:?}", to, from);
+                match self.move_entry(to, from) {
+                    Ok(()) => false,
+                    Err(err) => {
+                        log::error!("{}", err);
+                        true
+                    }
+                }
+            })
+            .map(|(_, to)| to)
+            .collect::<Vec<_>>();
+        if stranded.is_empty() {
+            cause
+        } else {
+            Error::RollbackFailed {
+                cause: Box::new(cause),
+                stranded,
+            }
+        }
+    }
+
+    /// Moves everything in `rust/lib` up to the project root, using `git mv`
+    /// when the project is a git repo so that history is preserved. If any
+    /// move fails, the ones before it are undone.
+    pub fn migrate(&self) -> Result<(), Error> {
+        let moves = self
+            .entries()?
+            .into_iter()
+            .map(|from| {
+                let to = self.root.join(
+                    from.file_name()
+                        .expect("developer error: dir entry had no file name"),
+                );
+                if to.exists() {
+                    Err(Error::Conflict(to))
+                } else {
+                    Ok((from, to))
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut moved = Vec::new();
+        for (from, to) in moves {
+            log::info!("moving {:?} to {:?}", from, to);
+            if let Err(err) = self.move_entry(&from, &to) {
+                return Err(self.roll_back(err, moved));
+            }
+            moved.push((from, to));
+        }
+        // These only get removed if they're empty, since the user may have
+        // put other things in `rust`.
+        for dir in [&self.lib_dir, &self.root.join("rust")] {
+            if let Err(err) = fs::remove_dir(dir) {
+                log::info!("not removing {:?}: {}", dir, err);
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn exec(wrapper: &TextWrapper, non_interactive: bool) -> Result<(), Error> {
+    let root = std::env::current_dir().map_err(Error::CurrentDirFailed)?;
+    let project = LegacyProject::heuristic_detect(&root).ok_or(Error::NotDetected(root))?;
+    if !non_interactive {
+        println!(
+            "{}",
+            wrapper.fill(&format!(
+                "This will move the contents of {:?} to the project root. Make sure you've committed or backed up your project first!",
+                project.lib_dir
+            ))
+        );
+        let proceed = loop {
+            if let Some(proceed) =
+                prompt::yes_no("Continue?", Some(false)).map_err(Error::PromptFailed)?
+            {
+                break proceed;
+            }
+        };
+        if !proceed {
+            return Err(Error::Cancelled);
+        }
+    }
+    project.migrate()?;
+    Report::victory(
+        "Project migrated",
+        "Check any relative paths in your `Cargo.toml`, then run `cargo mobile init` to regenerate your Android Studio and Xcode projects.",
+    )
+    .print(wrapper);
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn failed_moves_are_rolled_back() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        let lib_dir = root.join(LEGACY_LIB_DIR);
+        fs::create_dir_all(lib_dir.join("src")).unwrap();
+        fs::write(lib_dir.join("Cargo.toml"), "[package]").unwrap();
+        fs::write(lib_dir.join("src/lib.rs"), "").unwrap();
+        let git = |args: &'static [&'static str]| {
+            Git::new(root)
+                .command()
+                .before_spawn(move |cmd| {
+                    cmd.args(args);
+                    Ok(())
+                })
+                .run()
+                .unwrap();
+        };
+        git(&["init", "-q"]);
+        git(&["add", "."]);
+        // `git mv` refuses to move untracked files, and this sorts between
+        // the other two.
+        fs::write(lib_dir.join("notes.txt"), "").unwrap();
+
+        let project = LegacyProject::heuristic_detect(root).unwrap();
+        assert!(matches!(project.migrate(), Err(Error::MoveFailed { .. })));
+        assert!(lib_dir.join("Cargo.toml").is_file());
+        assert!(lib_dir.join("src/lib.rs").is_file());
+        assert!(!root.join("Cargo.toml").exists());
+    }
+}
@@ -118,3 +118,57 @@ impl FilterLevel {
         }
     }
 }
+
+/// When to print colors
+#[cfg(not(feature = "cli"))]
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub enum ColorChoice {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+#[cfg(feature = "cli")]
+arg_enum! {
+    /// When to print colors
+    #[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+    pub enum ColorChoice {
+        Auto,
+        Always,
+        Never,
+    }
+}
+
+/// How reports are printed
+#[cfg(not(feature = "cli"))]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum OutputFormat {
+    Pretty,
+    Plain,
+}
+
+#[cfg(feature = "cli")]
+arg_enum! {
+    /// How reports are printed
+    #[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+    pub enum OutputFormat {
+        Pretty,
+        Plain,
+    }
+}
+
+impl OutputFormat {
+    /// Picks plain output if `NO_COLOR` is set, and pretty output otherwise.
+    pub fn detect() -> Self {
+        if std::env::var_os("NO_COLOR").is_some_and(|var| !var.is_empty()) {
+            Self::Plain
+        } else {
+            Self::Pretty
+        }
+    }
+
+    pub fn plain(self) -> bool {
+        matches!(self, Self::Plain)
+    }
+}
@@ -164,7 +164,9 @@ pub fn open_file_with(
 // Linux does not require a binary "command" in path, so this seems the way to go.
 #[cfg(target_os = "linux")]
 pub fn command_path(name: &str) -> std::io::Result<std::process::Output> {
-    duct::cmd("sh", ["-c", format!("command -v {name}").as_str()]).run()
+    duct::cmd("sh", ["-c", format!("command -v {name}").as_str()])
+        .stdout_capture()
+        .run()
 }
 
 pub fn code_command() -> duct::Expression {
@@ -181,5 +183,6 @@ pub mod consts {
     pub const AR: &str = "ar";
     pub const LD: &str = "ld";
     pub const READELF: &str = "readelf";
+    pub const STRIP: &str = "strip";
     pub const NDK_STACK: &str = "ndk-stack";
 }
@@ -102,7 +102,7 @@ pub fn open_file_with(
 
 #[cfg(target_os = "macos")]
 pub fn command_path(name: &str) -> std::io::Result<std::process::Output> {
-    duct::cmd("command", ["-v", name]).dup_stdio().run()
+    duct::cmd("command", ["-v", name]).stdout_capture().run()
 }
 
 pub fn code_command() -> duct::Expression {
@@ -126,5 +126,6 @@ pub mod consts {
     pub const AR: &str = "ar";
     pub const LD: &str = "ld";
     pub const READELF: &str = "readelf";
+    pub const STRIP: &str = "strip";
     pub const NDK_STACK: &str = "ndk-stack";
 }
@@ -279,5 +279,6 @@ pub mod consts {
     pub const LD: &str = "ld.exe";
     pub const AR: &str = "ar.exe";
     pub const READELF: &str = "readelf.exe";
+    pub const STRIP: &str = "strip.exe";
     pub const NDK_STACK: &str = "ndk-stack.cmd";
 }
@@ -1,5 +1,5 @@
 use crate::{
-    bicycle,
+    bicycle::{self, Action},
     config::Config,
     templating::{self, FancyPackResolveError},
     util::{
@@ -46,20 +46,63 @@ pub fn gen(
     let root = config.app().root_dir();
     let git = Git::new(root);
     git.init().map_err(Error::GitInit)?;
-    let pack_chain = config
-        .app()
-        .template_pack()
-        .resolve(git, submodule_commit.as_deref())
-        .map_err(Error::TemplatePackResolve)?;
-    log::info!("template pack chain: {:#?}", pack_chain);
-    for pack in pack_chain {
-        log::info!("traversing template pack {:#?}", pack);
-        bike.filter_and_process(pack, root, |_| (), filter.fun())
+    // `app.lib-template` only replaces the pack's `src/lib.rs`.
+    let lib_rs = root.join("src/lib.rs");
+    let lib_template = config.app().lib_template().filter(|lib_template| {
+        let exists = lib_template.is_file();
+        if !exists {
+            log::warn!(
+                "`app.lib-template` {:?} doesn't exist, so using the template pack's `src/lib.rs`",
+                lib_template
+            );
+        }
+        exists
+    });
+    let mut pack_filter = {
+        let mut filter = filter.fun();
+        let replaced = lib_template.is_some();
+        move |action: &Action| !(replaced && action.dest() == lib_rs) && filter(action)
+    };
+    let template_pack = config.app().template_pack();
+    if let templating::Pack::Builtin(pack) = template_pack {
+        log::info!("processing builtin template pack {:?}", pack.name());
+        pack.filter_and_process(bike, root, &mut pack_filter)
             .map_err(|cause| Error::Processing {
-                src: pack.to_owned(),
+                src: pack.name().into(),
                 dest: root.to_owned(),
                 cause,
             })?;
+    } else {
+        let pack_chain = template_pack
+            .resolve(git, submodule_commit.as_deref())
+            .map_err(Error::TemplatePackResolve)?;
+        log::info!("template pack chain: {:#?}", pack_chain);
+        for pack in pack_chain {
+            log::info!("traversing template pack {:#?}", pack);
+            bike.filter_and_process(pack, root, |_| (), &mut pack_filter)
+                .map_err(|cause| Error::Processing {
+                    src: pack.to_owned(),
+                    dest: root.to_owned(),
+                    cause,
+                })?;
+        }
+    }
+    if let Some(lib_template) = lib_template {
+        let write = Action::WriteTemplate {
+            src: lib_template.clone(),
+            dest: root.join("src/lib.rs"),
+        };
+        if filter.fun()(&write) {
+            let src_dir = Action::CreateDirectory {
+                dest: root.join("src"),
+            };
+            bike.process_actions([src_dir, write].iter(), |_| ())
+                .map_err(|cause| Error::Processing {
+                    src: lib_template,
+                    dest: root.to_owned(),
+                    cause,
+                })?;
+        }
     }
     Ok(())
 }
@@ -0,0 +1,107 @@
+use crate::{
+    bicycle::{Action, JsonMap, ProcessingError},
+    config::{Config, LoadOrGenError},
+    templating::{LookupError, Pack},
+    util::cli::{Report, Reportable, TextWrapper},
+};
+use thiserror::Error;
+
+pub static TEMPLATE_PACK: &str = "store-metadata";
+pub static DEFAULT_LOCALE: &str = "en-US";
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error(transparent)]
+    ConfigFailed(Box<LoadOrGenError>),
+    #[error("Locale {0:?} invalid: Locales can only contain ASCII letters, numbers, and hyphens, like `en-US`")]
+    LocaleInvalid(String),
+    #[error(transparent)]
+    MissingPack(LookupError),
+    #[error(transparent)]
+    TemplateProcessingFailed(Box<ProcessingError>),
+}
+
+impl Reportable for Error {
+    fn report(&self) -> Report {
+        match self {
+            Self::ConfigFailed(err) => err.report(),
+            _ => Report::error("Failed to generate store metadata", self),
+        }
+    }
+}
+
+// Locales end up in paths, so anything that could escape the metadata dirs is
+// rejected.
+fn locale_valid(locale: &str) -> bool {
+    !locale.is_empty()
+        && locale
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+/// Scaffolds the metadata dirs used by fastlane's `deliver` (in
+/// `fastlane/metadata`) and `supply` (in `fastlane/android/metadata`) for each
+/// of `locales`, along with their screenshot dirs. Files that already exist are
+/// left alone, so this is safe to re-run when adding locales.
+pub fn exec(wrapper: &TextWrapper, non_interactive: bool, locales: &[String]) -> Result<(), Error> {
+    if let Some(locale) = locales.iter().find(|locale| !locale_valid(locale)) {
+        return Err(Error::LocaleInvalid(locale.clone()));
+    }
+    let (config, _origin) = Config::load_or_gen(
+        ".",
+        non_interactive,
+        false,
+        None,
+        &[],
+        None,
+        None,
+        None,
+        wrapper,
+    )
+    .map_err(|err| Error::ConfigFailed(Box::new(err)))?;
+    let src = Pack::lookup_platform(TEMPLATE_PACK)
+        .map_err(Error::MissingPack)?
+        .expect_local();
+    let root_dir = config.app().root_dir();
+    let bike = config.build_a_bike();
+    for locale in locales {
+        println!("Generating store metadata for {}...", locale);
+        let insert_data = |map: &mut JsonMap| map.insert("locale", locale);
+        bike.filter_and_process(&src, root_dir, insert_data, |action| {
+            let exists = action.dest().exists();
+            if exists && !action.is_create_directory() {
+                log::info!("not overwriting existing metadata {:?}", action.dest());
+            }
+            !exists
+        })
+        .map_err(|err| Error::TemplateProcessingFailed(Box::new(err)))?;
+        let screenshot_dirs = [
+            root_dir.join("fastlane/screenshots").join(locale),
+            root_dir
+                .join("fastlane/android/metadata")
+                .join(locale)
+                .join("images/phoneScreenshots"),
+        ];
+        for dest in screenshot_dirs {
+            bike.process_action(&Action::CreateDirectory { dest }, |_| ())
+                .map_err(|err| Error::TemplateProcessingFailed(Box::new(err)))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("en-US", true)]
+    #[case("zh-Hans", true)]
+    #[case("", false)]
+    #[case("../en-US", false)]
+    #[case("en US", false)]
+    fn test_locale_valid(#[case] locale: &str, #[case] valid: bool) {
+        assert_eq!(locale_valid(locale), valid);
+    }
+}
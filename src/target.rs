@@ -113,6 +113,82 @@ where
     })
 }
 
+/// The outcome of running something for several targets without bailing on
+/// the first failure.
+#[derive(Debug)]
+pub struct TargetFailures<E> {
+    pub passed: Vec<String>,
+    pub failed: Vec<(String, E)>,
+}
+
+impl<E> Display for TargetFailures<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut lines = Vec::new();
+        if !self.passed.is_empty() {
+            lines.push(format!("passed: {}", self.passed.join(", ")));
+        }
+        if !self.failed.is_empty() {
+            let failed = self
+                .failed
+                .iter()
+                .map(|(triple, _)| triple.as_str())
+                .collect::<Vec<_>>();
+            lines.push(format!("failed: {}", failed.join(", ")));
+        }
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+/// Like [`call_for_targets_with_fallback`], but with `keep_going`, continues
+/// through the remaining targets after a failure. Each failure is reported as
+/// it happens, and which targets passed is printed at the end, or if any
+/// failed, passed to `failed` to become the error that's returned.
+pub fn call_for_targets_with_fallback_keep_going<'a, Iter, I, T, U, E, F>(
+    targets: Iter,
+    fallback: &'a dyn Fn(U) -> Option<&'a T>,
+    arg: U,
+    keep_going: bool,
+    wrapper: &util::cli::TextWrapper,
+    failed: impl FnOnce(TargetFailures<E>) -> E,
+    mut f: F,
+) -> Result<Result<(), E>, TargetInvalid>
+where
+    Iter: ExactSizeIterator<Item = &'a I>,
+    I: AsRef<str> + 'a,
+    T: TargetTrait<'a>,
+    E: util::cli::Reportable,
+    F: FnMut(&T) -> Result<(), E>,
+{
+    if !keep_going {
+        return call_for_targets_with_fallback(targets, fallback, arg, f);
+    }
+    get_targets(targets, Some((fallback, arg))).map(|targets| {
+        let mut failures = TargetFailures {
+            passed: Vec::new(),
+            failed: Vec::new(),
+        };
+        for target in targets {
+            match f(target) {
+                Ok(()) => failures.passed.push(target.triple().to_owned()),
+                Err(err) => {
+                    err.report().print(wrapper);
+                    failures.failed.push((target.triple().to_owned(), err));
+                }
+            }
+        }
+        if failures.failed.is_empty() {
+            util::cli::Report::victory(
+                format!("All {} targets passed", failures.passed.len()),
+                &failures,
+            )
+            .print(wrapper);
+            Ok(())
+        } else {
+            Err(failed(failures))
+        }
+    })
+}
+
 pub fn call_for_targets<'a, Iter, I, T, E, F>(
     targets: Iter,
     f: F,
@@ -0,0 +1,122 @@
+use crate::bicycle::{Action, Bicycle, ProcessingError};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// The name `app.template-pack` uses to refer to [`BARE`].
+pub const BARE_PACK_NAME: &str = "bare";
+
+/// A template pack compiled into cargo-mobile2, so using it never touches the
+/// disk or network before rendering.
+#[derive(Debug)]
+pub struct BuiltinPack {
+    name: &'static str,
+    description: &'static str,
+    // Paths are relative to the app root, and every file is a template.
+    files: &'static [(&'static str, &'static str)],
+}
+
+/// A hello-world with no dependencies, for bringing your own windowing.
+pub static BARE: BuiltinPack = BuiltinPack {
+    name: BARE_PACK_NAME,
+    description: "Dependency-free hello-world built into cargo-mobile2",
+    files: &[
+        (
+            ".gitignore.hbs",
+            include_str!("../../templates/builtin/bare/.gitignore.hbs"),
+        ),
+        (
+            "Cargo.toml.hbs",
+            include_str!("../../templates/builtin/bare/Cargo.toml.hbs"),
+        ),
+        (
+            "src/lib.rs.hbs",
+            include_str!("../../templates/builtin/bare/src/lib.rs.hbs"),
+        ),
+        (
+            "gen/bin/desktop.rs.hbs",
+            include_str!("../../templates/builtin/bare/gen/bin/desktop.rs.hbs"),
+        ),
+    ],
+};
+
+impl BuiltinPack {
+    pub fn lookup(name: &str) -> Option<&'static Self> {
+        [&BARE].into_iter().find(|pack| pack.name == name)
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn description(&self) -> &'static str {
+        self.description
+    }
+
+    /// The builtin counterpart to [`Bicycle::filter_and_process`]. `src` in the
+    /// actions given to `filter` is the file's path within the pack.
+    pub fn filter_and_process(
+        &self,
+        bike: &Bicycle,
+        dest: &Path,
+        mut filter: impl FnMut(&Action) -> bool,
+    ) -> Result<(), ProcessingError> {
+        for (path, template) in self.files {
+            let src = PathBuf::from(path);
+            let dest = dest.join(path.strip_suffix(".hbs").unwrap_or(path));
+            let action = Action::WriteTemplate {
+                src: src.clone(),
+                dest: dest.clone(),
+            };
+            if !filter(&action) {
+                continue;
+            }
+            log::info!("{:#?}", action);
+            if let Some(parent) = dest.parent() {
+                fs::create_dir_all(parent).map_err(|cause| ProcessingError::DirectoryCreation {
+                    dest: parent.to_owned(),
+                    cause,
+                })?;
+            }
+            let rendered =
+                bike.render(template, |_| ())
+                    .map_err(|cause| ProcessingError::TemplateRender {
+                        src: src.clone(),
+                        cause,
+                    })?;
+            fs::write(&dest, rendered).map_err(|cause| ProcessingError::TemplateWrite {
+                src,
+                dest,
+                cause,
+            })?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn bare_pack_renders() {
+        let bike = crate::templating::init(None);
+        let rendered = BARE
+            .files
+            .iter()
+            .map(|(path, template)| {
+                let rendered = bike
+                    .render(template, |map| {
+                        map.insert("app", serde_json::json!({ "name": "fun-app" }));
+                        map.insert("author", "Fun Author <fun@example.com>");
+                    })
+                    .unwrap_or_else(|err| panic!("failed to render {}: {}", path, err));
+                (*path, rendered)
+            })
+            .collect::<std::collections::HashMap<_, _>>();
+        assert!(rendered["Cargo.toml.hbs"].contains("name = \"fun-app-desktop\""));
+        assert!(rendered["src/lib.rs.hbs"].contains("Hello from fun-app!"));
+        assert!(rendered["gen/bin/desktop.rs.hbs"].contains("fun_app::start_app();"));
+    }
+}
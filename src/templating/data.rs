@@ -0,0 +1,134 @@
+use crate::bicycle::Bicycle;
+use serde_json::{Map, Value as Json};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+// Values can hold a table of scalars or a list of those, but nothing deeper,
+// since templates shouldn't need to dig through whole documents.
+const MAX_DEPTH: usize = 2;
+
+#[derive(Debug, Error)]
+pub enum TemplateDataError {
+    #[error("Failed to read template data {path}: {cause}")]
+    ReadFailed { path: PathBuf, cause: io::Error },
+    #[error("Template data {path} should be a `.json` or `.toml` file")]
+    FormatUnknown { path: PathBuf },
+    #[error("Failed to parse template data {path}: {cause}")]
+    JsonParseFailed {
+        path: PathBuf,
+        cause: serde_json::Error,
+    },
+    #[error("Failed to parse template data {path}: {cause}")]
+    TomlParseFailed {
+        path: PathBuf,
+        cause: toml::de::Error,
+    },
+    #[error("Template data {path} should be an object of key/value pairs")]
+    NotAnObject { path: PathBuf },
+    #[error("Template data key `{key}` is nested more than {MAX_DEPTH} levels deep")]
+    TooDeep { key: String },
+    #[error("Template data key `{key}` collides with a config-derived key")]
+    Collision { key: String },
+}
+
+fn depth(value: &Json) -> usize {
+    match value {
+        Json::Array(values) => 1 + values.iter().map(depth).max().unwrap_or_default(),
+        Json::Object(map) => 1 + map.values().map(depth).max().unwrap_or_default(),
+        _ => 0,
+    }
+}
+
+fn parse(path: &Path, contents: &str) -> Result<Map<String, Json>, TemplateDataError> {
+    let value = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("json") => serde_json::from_str::<Json>(contents).map_err(|cause| {
+            TemplateDataError::JsonParseFailed {
+                path: path.to_owned(),
+                cause,
+            }
+        })?,
+        Some("toml") => toml::from_str::<toml::Table>(contents)
+            .map(|table| {
+                serde_json::to_value(table).expect("developer error: TOML isn't valid JSON")
+            })
+            .map_err(|cause| TemplateDataError::TomlParseFailed {
+                path: path.to_owned(),
+                cause,
+            })?,
+        _ => {
+            return Err(TemplateDataError::FormatUnknown {
+                path: path.to_owned(),
+            })
+        }
+    };
+    let Json::Object(map) = value else {
+        return Err(TemplateDataError::NotAnObject {
+            path: path.to_owned(),
+        });
+    };
+    if let Some((key, _)) = map.iter().find(|(_, value)| depth(value) > MAX_DEPTH) {
+        return Err(TemplateDataError::TooDeep { key: key.clone() });
+    }
+    Ok(map)
+}
+
+/// Reads extra template data from a JSON or TOML file, which must be an
+/// object whose values are at most [`MAX_DEPTH`] levels deep.
+pub fn load_template_data(path: &Path) -> Result<Map<String, Json>, TemplateDataError> {
+    let contents = fs::read_to_string(path).map_err(|cause| TemplateDataError::ReadFailed {
+        path: path.to_owned(),
+        cause,
+    })?;
+    parse(path, &contents)
+}
+
+/// Makes `data` available to every template `bike` renders. Config-derived
+/// keys take precedence, unless `strict`, in which case collisions are errors.
+pub fn merge_template_data(
+    bike: &mut Bicycle,
+    data: Map<String, Json>,
+    strict: bool,
+) -> Result<(), TemplateDataError> {
+    let base = &mut bike.base_data_mut().0;
+    for (key, value) in data {
+        if base.contains_key(&key) {
+            if strict {
+                return Err(TemplateDataError::Collision { key });
+            }
+            log::warn!(
+                "template data key `{}` is shadowed by the config-derived value",
+                key
+            );
+        } else {
+            base.insert(key, value);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(
+        "data.json",
+        r#"{"company": "Acme", "links": {"support": "https://acme.test"}}"#,
+        true
+    )]
+    #[case(
+        "data.toml",
+        "company = \"Acme\"\n[links]\nsupport = \"https://acme.test\"",
+        true
+    )]
+    #[case("data.json", r#"{"a": {"b": {"c": {"d": 1}}}}"#, false)]
+    #[case("data.json", r#"["company"]"#, false)]
+    #[case("data.yaml", "company: Acme", false)]
+    fn test_parse(#[case] path: &str, #[case] contents: &str, #[case] ok: bool) {
+        assert_eq!(parse(Path::new(path), contents).is_ok(), ok);
+    }
+}
@@ -24,6 +24,8 @@ pub enum FancyPackParseError {
     NoHomeDir(util::NoHomeDir),
     #[error("Failed to lookup base template pack: {0}")]
     BaseFailed(Box<LookupError>),
+    #[error("Required tool {name:?} in {path} isn't a valid command name")]
+    ToolNameInvalid { path: PathBuf, name: String },
 }
 
 #[derive(Debug, Error)]
@@ -34,11 +36,66 @@ pub enum FancyPackResolveError {
     PackNotFound(PathBuf),
 }
 
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawRequiredTool {
+    Name(String),
+    #[serde(rename_all = "kebab-case")]
+    Detailed {
+        name: String,
+        install_hint: Option<String>,
+    },
+}
+
+/// A command a template pack's generated project can't do without, given in
+/// `required-tools` as either a name or a `{ name, install-hint }` table.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[serde(from = "RawRequiredTool")]
+pub struct RequiredTool {
+    pub name: String,
+    pub install_hint: Option<String>,
+}
+
+impl From<RawRequiredTool> for RequiredTool {
+    fn from(raw: RawRequiredTool) -> Self {
+        match raw {
+            RawRequiredTool::Name(name) => Self {
+                name,
+                install_hint: None,
+            },
+            RawRequiredTool::Detailed { name, install_hint } => Self { name, install_hint },
+        }
+    }
+}
+
+impl std::fmt::Display for RequiredTool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "`{}`", self.name)?;
+        if let Some(install_hint) = &self.install_hint {
+            write!(f, " ({})", install_hint)?;
+        }
+        Ok(())
+    }
+}
+
+impl RequiredTool {
+    // The name ends up in a shell command when checking for it.
+    fn name_valid(&self) -> bool {
+        !self.name.is_empty()
+            && self
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "._+-".contains(c))
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct FancyPack {
     path: PathBuf,
     base: Option<Box<Pack>>,
     submodule: Option<Submodule>,
+    description: Option<String>,
+    required_tools: Vec<RequiredTool>,
 }
 
 impl FancyPack {
@@ -48,6 +105,9 @@ impl FancyPack {
             path: PathBuf,
             base: Option<String>,
             submodule: Option<Submodule>,
+            description: Option<String>,
+            #[serde(default, rename = "required-tools")]
+            required_tools: Vec<RequiredTool>,
         }
 
         let path = path.as_ref();
@@ -63,6 +123,13 @@ impl FancyPack {
             })?
         };
 
+        if let Some(tool) = raw.required_tools.iter().find(|tool| !tool.name_valid()) {
+            return Err(FancyPackParseError::ToolNameInvalid {
+                path: path.to_owned(),
+                name: tool.name.clone(),
+            });
+        }
+
         let raw_path = path
             .parent()
             .map(|p| p.join(&raw.path))
@@ -85,6 +152,8 @@ impl FancyPack {
                 .map_err(FancyPackParseError::BaseFailed)?
                 .map(Box::new),
             submodule: raw.submodule,
+            description: raw.description,
+            required_tools: raw.required_tools,
         };
         log::info!("template pack {:#?}", this);
         Ok(this)
@@ -94,6 +163,31 @@ impl FancyPack {
         self.submodule.as_ref().map(|submodule| submodule.path())
     }
 
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    /// The tools this pack and its bases require, bases first.
+    pub fn required_tools(&self) -> Vec<&RequiredTool> {
+        let mut tools = self
+            .base
+            .as_ref()
+            .map(|base| base.required_tools())
+            .unwrap_or_default();
+        tools.extend(&self.required_tools);
+        tools
+    }
+
+    /// Whether resolving this pack or any of its bases initializes a
+    /// submodule.
+    pub fn requires_submodule(&self) -> bool {
+        self.submodule.is_some()
+            || self
+                .base
+                .as_ref()
+                .is_some_and(|base| base.requires_submodule())
+    }
+
     pub fn resolve(
         &self,
         git: Git<'_>,
@@ -123,3 +217,58 @@ impl FancyPack {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn pack(required_tools: Vec<RequiredTool>) -> Pack {
+        Pack::Fancy(FancyPack {
+            path: Default::default(),
+            base: None,
+            submodule: None,
+            description: None,
+            required_tools,
+        })
+    }
+
+    #[test]
+    fn required_tools_parse_as_names_or_tables() {
+        #[derive(Deserialize)]
+        struct Spec {
+            #[serde(rename = "required-tools")]
+            required_tools: Vec<RequiredTool>,
+        }
+        let spec = toml::from_str::<Spec>(
+            r#"required-tools = ["node", { name = "protoc", install-hint = "brew install protobuf" }]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            spec.required_tools,
+            [
+                RequiredTool {
+                    name: "node".into(),
+                    install_hint: None,
+                },
+                RequiredTool {
+                    name: "protoc".into(),
+                    install_hint: Some("brew install protobuf".into()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn only_absent_tools_are_missing() {
+        let absent = RequiredTool {
+            name: "cargo-mobile2-nonexistent-tool".into(),
+            install_hint: Some("it doesn't exist".into()),
+        };
+        let present = RequiredTool {
+            name: "cargo".into(),
+            install_hint: None,
+        };
+        let pack = pack(vec![present, absent.clone()]);
+        assert_eq!(pack.missing_tools().unwrap(), [absent]);
+    }
+}
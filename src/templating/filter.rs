@@ -2,16 +2,23 @@ use crate::{
     bicycle::Action,
     config::{Config, Origin},
 };
-use ignore::gitignore::Gitignore;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
 use std::{
     fmt::{self, Display},
     io,
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
 #[derive(Debug)]
 pub enum FilterError {
-    ReadDirFailed { path: PathBuf, cause: io::Error },
+    ReadDirFailed {
+        path: PathBuf,
+        cause: io::Error,
+    },
+    PreservePatternInvalid {
+        pattern: String,
+        cause: ignore::Error,
+    },
 }
 
 impl Display for FilterError {
@@ -22,28 +29,59 @@ impl Display for FilterError {
                 "App root directory {:?} couldn't be checked for emptiness: {}",
                 path, cause
             ),
+            Self::PreservePatternInvalid { pattern, cause } => write!(
+                f,
+                "`app.preserve-on-regen` pattern {:?} is invalid: {}",
+                pattern, cause
+            ),
         }
     }
 }
 
 #[derive(Debug)]
-pub enum Filter {
+enum Strategy {
     WildWest,
     Protected { unprotected: Gitignore },
 }
 
+#[derive(Debug)]
+pub struct Filter {
+    strategy: Strategy,
+    // Files matching `app.preserve-on-regen` are only generated if they
+    // don't exist yet, since they tend to hold local values (e.g. `.env`).
+    preserved: Gitignore,
+}
+
+fn preserve_matcher(root_dir: &Path, patterns: &[String]) -> Result<Gitignore, FilterError> {
+    let mut builder = GitignoreBuilder::new(root_dir);
+    for pattern in patterns {
+        builder
+            .add_line(None, pattern)
+            .map_err(|cause| FilterError::PreservePatternInvalid {
+                pattern: pattern.clone(),
+                cause,
+            })?;
+    }
+    builder
+        .build()
+        .map_err(|cause| FilterError::PreservePatternInvalid {
+            pattern: patterns.join(", "),
+            cause,
+        })
+}
+
 impl Filter {
     pub fn new(
         config: &Config,
         config_origin: Origin,
         dot_first_init_exists: bool,
     ) -> Result<Self, FilterError> {
-        if config_origin.freshly_minted() {
+        let strategy = if config_origin.freshly_minted() {
             log::info!("config freshly minted, so we're assuming a brand new project; using `WildWest` filtering strategy");
-            Ok(Self::WildWest)
+            Strategy::WildWest
         } else if dot_first_init_exists {
             log::info!("`{}` exists, so we're assuming a brand new project; using `WildWest` filtering strategy", crate::init::DOT_FIRST_INIT_FILE_NAME);
-            Ok(Self::WildWest)
+            Strategy::WildWest
         } else {
             log::info!("existing config loaded, so we're assuming an existing project; using `Protected` filtering strategy");
             let gitignore_path = config.app().root_dir().join(".gitignore");
@@ -60,20 +98,50 @@ impl Filter {
                     gitignore_path
                 );
             }
-            Ok(Self::Protected { unprotected })
-        }
+            Strategy::Protected { unprotected }
+        };
+        Ok(Self {
+            strategy,
+            preserved: preserve_matcher(config.app().root_dir(), config.app().preserve_on_regen())?,
+        })
+    }
+
+    fn is_preserved(&self, dest: &Path, is_dir: bool) -> bool {
+        // Paths outside of the app root (e.g. an external project dir) can't
+        // be matched against patterns relative to it.
+        dest.starts_with(self.preserved.path())
+            && self
+                .preserved
+                .matched_path_or_any_parents(dest, is_dir)
+                .is_ignore()
     }
 
     pub fn fun(&self) -> impl FnMut(&Action) -> bool + '_ {
-        move |action| match self {
-            Self::WildWest => {
+        move |action| {
+            if !action.is_create_directory()
+                && action.dest().exists()
+                && self.is_preserved(action.dest(), false)
+            {
+                log::info!(
+                    "{:?} matches `app.preserve-on-regen` and already exists, so it won't be overwritten",
+                    action.dest()
+                );
+                return false;
+            }
+            self.strategy_allows(action)
+        }
+    }
+
+    fn strategy_allows(&self, action: &Action) -> bool {
+        match &self.strategy {
+            Strategy::WildWest => {
                 log::debug!(
                     "filtering strategy is `WildWest`, so action will be processed: {:#?}",
                     action
                 );
                 true
             }
-            Self::Protected { unprotected } => {
+            Strategy::Protected { unprotected } => {
                 // If we're protecting the user's files, then we only allow
                 // actions that apply to paths excluded from version control.
                 let ignored = unprotected
@@ -95,3 +163,23 @@ impl Filter {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn preserved_paths_match_patterns_under_root() {
+        let root = Path::new("/app");
+        let filter = Filter {
+            strategy: Strategy::WildWest,
+            preserved: preserve_matcher(root, &[".env".into(), "gen/android/*.properties".into()])
+                .unwrap(),
+        };
+        assert!(filter.is_preserved(&root.join(".env"), false));
+        assert!(filter.is_preserved(&root.join("gen/.env"), false));
+        assert!(filter.is_preserved(&root.join("gen/android/keystore.properties"), false));
+        assert!(!filter.is_preserved(&root.join("gen/android/build.gradle.kts"), false));
+        assert!(!filter.is_preserved(Path::new("/elsewhere/.env"), false));
+    }
+}
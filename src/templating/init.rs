@@ -3,13 +3,17 @@ use handlebars::RenderErrorReason;
 use crate::{
     bicycle::{
         handlebars::{self, Context, Handlebars, Helper, HelperResult, Output, RenderContext},
-        Bicycle, EscapeFn, HelperDef, JsonMap,
+        Bicycle, EscapeFn, HelperDef, JsonMap, RenderCache,
     },
     config::{app, Config},
     reserved_names::KOTLIN_ONLY_KEYWORDS,
     util::{self, Git},
 };
-use std::collections::HashMap;
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+};
 
 fn get_str<'a>(helper: &'a Helper) -> &'a str {
     helper
@@ -37,6 +41,18 @@ fn html_escape(
         .map_err(Into::into)
 }
 
+fn quote(
+    helper: &Helper,
+    _: &Handlebars,
+    _: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    // JSON strings are also valid YAML and plist strings.
+    out.write(&serde_json::Value::from(get_str(helper)).to_string())
+        .map_err(Into::into)
+}
+
 fn join(
     helper: &Helper,
     _: &Handlebars,
@@ -100,6 +116,67 @@ fn snake_case(
         .map_err(Into::into)
 }
 
+fn kebab_case(
+    helper: &Helper,
+    _: &Handlebars,
+    _: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    use heck::ToKebabCase as _;
+    out.write(&get_str(helper).to_kebab_case())
+        .map_err(Into::into)
+}
+
+fn title_case(
+    helper: &Helper,
+    _: &Handlebars,
+    _: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    use heck::ToTitleCase as _;
+    out.write(&get_str(helper).to_title_case())
+        .map_err(Into::into)
+}
+
+fn uppercase(
+    helper: &Helper,
+    _: &Handlebars,
+    _: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    out.write(&get_str(helper).to_uppercase())
+        .map_err(Into::into)
+}
+
+fn lowercase(
+    helper: &Helper,
+    _: &Handlebars,
+    _: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    out.write(&get_str(helper).to_lowercase())
+        .map_err(Into::into)
+}
+
+fn reverse_domain(
+    helper: &Helper,
+    _: &Handlebars,
+    _: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let reversed = get_str(helper)
+        .split('.')
+        .rev()
+        .collect::<Vec<_>>()
+        .join(".");
+    out.write(&reversed).map_err(Into::into)
+}
+
 fn ident_last_part(
     helper: &Helper,
     _: &Handlebars,
@@ -159,6 +236,29 @@ fn app_root(ctx: &Context) -> Result<&str, RenderErrorReason> {
     })
 }
 
+// With `app.relative-paths` set, paths are made relative to the
+// `project-dir` of the project being generated, if there is one. The trailing
+// separator is kept, since templates append to it.
+fn prefixed_path(ctx: &Context, path: &str) -> Result<PathBuf, RenderErrorReason> {
+    let prefixed = util::prefix_path(app_root(ctx)?, path);
+    let data = ctx.data();
+    let relative = data
+        .get(app::KEY)
+        .and_then(|app| app.get("relative-paths"))
+        .and_then(|relative| relative.as_bool())
+        .unwrap_or_default();
+    match data.get("project-dir").and_then(|dir| dir.as_str()) {
+        Some(project_dir) if relative => {
+            let mut relative = util::relativize_path(prefixed, project_dir).into_os_string();
+            if path.ends_with('/') {
+                relative.push("/");
+            }
+            Ok(relative.into())
+        }
+        _ => Ok(prefixed),
+    }
+}
+
 fn prefix_path(
     helper: &Helper,
     _: &Handlebars,
@@ -167,7 +267,7 @@ fn prefix_path(
     out: &mut dyn Output,
 ) -> HelperResult {
     out.write(
-        util::prefix_path(app_root(ctx)?, get_str(helper))
+        prefixed_path(ctx, get_str(helper))?
             .to_str()
             .ok_or_else(|| {
                 RenderErrorReason::Other(
@@ -224,19 +324,58 @@ fn detect_author() -> String {
     format!("{} <{}>", name.trim(), email.trim())
 }
 
+/// Where what `dest` was last generated from is kept, for
+/// [`Bicycle::filter_and_process_changed`].
+pub fn snapshot_path(dest: &Path) -> PathBuf {
+    let mut hasher = DefaultHasher::new();
+    dest.hash(&mut hasher);
+    util::temp_dir()
+        .join("template-snapshots")
+        .join(format!("{:016x}.json", hasher.finish()))
+}
+
+/// Creates the [`Bicycle`] used to render template packs.
+///
+/// Besides the config data, templates can use these helpers:
+///
+/// | helper | example |
+/// | ------ | ------- |
+/// | `snake-case` | `{{snake-case "My App"}}` → `my_app` |
+/// | `kebab-case` | `{{kebab-case "My App"}}` → `my-app` |
+/// | `title-case` | `{{title-case "my-app"}}` → `My App` |
+/// | `uppercase` | `{{uppercase "my-app"}}` → `MY-APP` |
+/// | `lowercase` | `{{lowercase "My App"}}` → `my app` |
+/// | `reverse-domain` | `{{reverse-domain "example.com"}}` → `com.example` |
+/// | `html-escape` | `{{html-escape "A & B"}}` → `A &amp; B` |
+/// | `quote` | `{{quote "My \"App\""}}` → `"My \"App\""` |
+/// | `join` | `{{join list}}` → `a, b` |
+/// | `dot-to-slash` | `{{dot-to-slash "com.example"}}` → `com/example` |
+/// | `ident-last-part` | `{{ident-last-part "com.example.app"}}` → `app` |
+/// | `ident-no-last-part` | `{{ident-no-last-part "com.example.app"}}` → `com.example` |
+///
+/// When a config is provided, `prefix-path` and `unprefix-path` are also
+/// available for converting between paths relative to the app root and the
+/// output dir. If `app.relative-paths` is set and the data has a
+/// `project-dir`, `prefix-path` gives paths relative to it instead.
 pub fn init(config: Option<&Config>) -> Bicycle {
-    Bicycle::new(
+    let bike = Bicycle::new(
         EscapeFn::None,
         {
             let mut helpers = HashMap::<_, Box<dyn HelperDef + Send + Sync>>::new();
             helpers.insert("html-escape", Box::new(html_escape));
             helpers.insert("join", Box::new(join));
+            helpers.insert("quote", Box::new(quote));
             helpers.insert("quote-and-join", Box::new(quote_and_join));
             helpers.insert(
                 "quote-and-join-colon-prefix",
                 Box::new(quote_and_join_colon_prefix),
             );
             helpers.insert("snake-case", Box::new(snake_case));
+            helpers.insert("kebab-case", Box::new(kebab_case));
+            helpers.insert("title-case", Box::new(title_case));
+            helpers.insert("uppercase", Box::new(uppercase));
+            helpers.insert("lowercase", Box::new(lowercase));
+            helpers.insert("reverse-domain", Box::new(reverse_domain));
             helpers.insert("ident-no-last-part", Box::new(ident_no_last_part));
             helpers.insert("ident-last-part", Box::new(ident_last_part));
             helpers.insert("escape-kotlin-keyword", Box::new(escape_kotlin_keyword));
@@ -259,5 +398,113 @@ pub fn init(config: Option<&Config>) -> Bicycle {
             }
             map
         },
-    )
+    );
+    // These read `app.root-dir` and friends from the data directly.
+    let bike = bike.with_context_helpers(["prefix-path", "unprefix-path"]);
+    // Opt-in, since custom template packs could conceivably render
+    // differently without their inputs changing.
+    if std::env::var_os("CARGO_MOBILE_RENDER_CACHE").is_some() {
+        let cache = RenderCache::new(util::temp_dir().join("render-cache"));
+        if let Err(err) = cache.prune(RenderCache::MAX_AGE) {
+            log::warn!("failed to prune render cache at {:?}: {}", cache.dir(), err);
+        }
+        bike.with_cache(cache)
+    } else {
+        bike
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+    use std::path::Path;
+
+    fn render_template_line(path: &str, needle: &str, display_name: &str) -> String {
+        let template = std::fs::read_to_string(
+            Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("templates/platforms")
+                .join(path),
+        )
+        .unwrap();
+        let line = template.lines().find(|line| line.contains(needle)).unwrap();
+        init(None)
+            .render(line, |map| {
+                map.insert(
+                    app::KEY,
+                    serde_json::json!({ "display-name": display_name }),
+                )
+            })
+            .unwrap()
+    }
+
+    #[rstest]
+    #[case("{{snake-case \"My App\"}}", "my_app")]
+    #[case("{{kebab-case \"My App\"}}", "my-app")]
+    #[case("{{title-case \"my-app\"}}", "My App")]
+    #[case("{{uppercase \"my-app\"}}", "MY-APP")]
+    #[case("{{lowercase \"My App\"}}", "my app")]
+    #[case("{{reverse-domain \"app.example.com\"}}", "com.example.app")]
+    fn test_case_helpers(#[case] template: &str, #[case] expected: &str) {
+        assert_eq!(init(None).render(template, |_| ()).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case("My App", "        CFBundleDisplayName: \"My App\"")]
+    #[case(
+        "Cool \"App\" 🚀",
+        "        CFBundleDisplayName: \"Cool \\\"App\\\" 🚀\""
+    )]
+    fn test_plist_display_name(#[case] display_name: &str, #[case] expected: &str) {
+        assert_eq!(
+            render_template_line("xcode/project.yml.hbs", "CFBundleDisplayName", display_name),
+            expected
+        );
+    }
+
+    #[rstest]
+    #[case("My App", "    <string name=\"app_name\">My App</string>")]
+    #[case(
+        "Apps & Stuff 🚀",
+        "    <string name=\"app_name\">Apps &amp; Stuff 🚀</string>"
+    )]
+    fn test_manifest_label(#[case] display_name: &str, #[case] expected: &str) {
+        assert_eq!(
+            render_template_line(
+                "android-studio/app/src/main/res/values/strings.xml.hbs",
+                "app_name",
+                display_name
+            ),
+            expected
+        );
+    }
+
+    #[cfg(not(windows))]
+    #[rstest]
+    #[case(false, "/repo/app/target/")]
+    #[case(true, "../../target/")]
+    fn test_prefix_path(#[case] relative_paths: bool, #[case] expected: &str) {
+        let bike = Bicycle::new(
+            EscapeFn::None,
+            {
+                let mut helpers = HashMap::<_, Box<dyn HelperDef + Send + Sync>>::new();
+                helpers.insert("prefix-path", Box::new(prefix_path));
+                helpers
+            },
+            JsonMap::default(),
+        );
+        let rendered = bike
+            .render("{{prefix-path \"target/\"}}", |map| {
+                map.insert(
+                    app::KEY,
+                    serde_json::json!({
+                        "root-dir": "/repo/app",
+                        "relative-paths": relative_paths,
+                    }),
+                );
+                map.insert("project-dir", "/repo/app/gen/apple");
+            })
+            .unwrap();
+        assert_eq!(rendered, expected);
+    }
 }
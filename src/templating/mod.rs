@@ -1,10 +1,13 @@
+mod builtin;
+mod data;
 mod fancy;
 mod filter;
 mod init;
 
-pub use self::{fancy::*, filter::*, init::*};
+pub use self::{builtin::*, data::*, fancy::*, filter::*, init::*};
 
 use crate::util::{self, Git};
+use serde::Serialize;
 use std::{
     fmt::{self, Display},
     fs, io,
@@ -42,6 +45,7 @@ pub enum LookupError {
 pub enum Pack {
     Simple(PathBuf),
     Fancy(FancyPack),
+    Builtin(&'static BuiltinPack),
 }
 
 impl Pack {
@@ -87,16 +91,30 @@ impl Pack {
     }
 
     pub fn lookup_app(name: &str) -> Result<Self, LookupError> {
-        app_pack_dir()
-            .map_err(LookupError::NoHomeDir)
-            .and_then(|dir| Self::lookup(dir, name))
+        match app_pack_dir() {
+            Ok(dir) => Self::lookup_app_in(&dir, name),
+            Err(err) => BuiltinPack::lookup(name)
+                .map(Self::Builtin)
+                .ok_or(LookupError::NoHomeDir(err)),
+        }
+    }
+
+    // Installed packs win over builtin ones with the same name, so that
+    // adding a builtin pack never changes what an existing app generates.
+    fn lookup_app_in(dir: &Path, name: &str) -> Result<Self, LookupError> {
+        match Self::lookup(dir, name) {
+            Err(err @ LookupError::MissingPack { .. }) => {
+                BuiltinPack::lookup(name).map(Self::Builtin).ok_or(err)
+            }
+            result => result,
+        }
     }
 
     pub fn expect_local(self) -> PathBuf {
         if let Self::Simple(path) = self {
             path
         } else {
-            panic!("developer error: called `expect_local` on a `Pack::Fancy`")
+            panic!("developer error: called `expect_local` on a non-`Pack::Simple`")
         }
     }
 
@@ -108,6 +126,31 @@ impl Pack {
         }
     }
 
+    pub fn required_tools(&self) -> Vec<&RequiredTool> {
+        match self {
+            Self::Simple(_) | Self::Builtin(_) => Vec::new(),
+            Self::Fancy(pack) => pack.required_tools(),
+        }
+    }
+
+    /// The [`Pack::required_tools`] that aren't on the `PATH`.
+    pub fn missing_tools(&self) -> io::Result<Vec<RequiredTool>> {
+        let mut missing = Vec::new();
+        for tool in self.required_tools() {
+            if !util::command_present(&tool.name)? && !missing.contains(tool) {
+                missing.push(tool.clone());
+            }
+        }
+        Ok(missing)
+    }
+
+    pub fn requires_submodule(&self) -> bool {
+        match self {
+            Self::Simple(_) | Self::Builtin(_) => false,
+            Self::Fancy(pack) => pack.requires_submodule(),
+        }
+    }
+
     pub fn resolve(
         &self,
         git: Git<'_>,
@@ -123,6 +166,16 @@ impl Pack {
                 Ok(vec![path])
             }
             Self::Fancy(pack) => pack.resolve(git, submodule_commit),
+            // Builtin packs aren't on disk; see `BuiltinPack::filter_and_process`.
+            Self::Builtin(pack) => {
+                if submodule_commit.is_some() {
+                    log::warn!(
+                        "specified a submodule commit, but the template pack {:?} is builtin",
+                        pack.name()
+                    );
+                }
+                Ok(Vec::new())
+            }
         }
     }
 }
@@ -148,8 +201,7 @@ impl Display for ListError {
     }
 }
 
-pub fn list_app_packs() -> Result<Vec<String>, ListError> {
-    let dir = app_pack_dir().map_err(ListError::NoHomeDir)?;
+fn list_packs(dir: PathBuf) -> Result<Vec<String>, ListError> {
     let mut packs = Vec::new();
     for entry in fs::read_dir(&dir).map_err(|cause| ListError::DirReadFailed {
         dir: dir.clone(),
@@ -160,14 +212,22 @@ pub fn list_app_packs() -> Result<Vec<String>, ListError> {
             cause,
         })?;
         if let Some(name) = entry.path().file_stem() {
-            let name = name.to_string_lossy();
-            if !BRAINIUM.contains(&name.as_ref()) {
-                packs.push(name.into_owned());
-            }
+            packs.push(name.to_string_lossy().into_owned());
         }
     }
     packs.sort_unstable();
     packs.dedup();
+    Ok(packs)
+}
+
+pub fn list_app_packs() -> Result<Vec<String>, ListError> {
+    list_app_packs_in(app_pack_dir().map_err(ListError::NoHomeDir)?)
+}
+
+fn list_app_packs_in(dir: PathBuf) -> Result<Vec<String>, ListError> {
+    let packs = list_packs(dir)?
+        .into_iter()
+        .filter(|name| !BRAINIUM.contains(&name.as_str()));
     Ok(if cfg!(feature = "brainium") {
         // This solution is slightly devious...
         BRAINIUM
@@ -176,6 +236,186 @@ pub fn list_app_packs() -> Result<Vec<String>, ListError> {
             .chain(packs)
             .collect()
     } else {
-        packs
+        packs.collect()
     })
 }
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PackCategory {
+    App,
+    Platform,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PackKind {
+    /// A plain directory of templates.
+    Simple,
+    /// A `.toml` spec pointing at templates, possibly in a submodule.
+    Fancy,
+    /// Compiled into cargo-mobile2 itself.
+    Builtin,
+}
+
+/// What a front-end needs to know to offer a template pack to the user.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct PackInfo {
+    pub name: String,
+    pub category: PackCategory,
+    pub kind: PackKind,
+    pub description: Option<String>,
+    pub requires_submodule: bool,
+}
+
+impl PackInfo {
+    fn new(name: String, category: PackCategory, pack: &Pack) -> Self {
+        let (kind, description) = match pack {
+            Pack::Simple(_) => (PackKind::Simple, None),
+            Pack::Fancy(pack) => (PackKind::Fancy, pack.description().map(ToOwned::to_owned)),
+            Pack::Builtin(pack) => (PackKind::Builtin, Some(pack.description().to_owned())),
+        };
+        Self {
+            name,
+            category,
+            kind,
+            description,
+            requires_submodule: pack.requires_submodule(),
+        }
+    }
+}
+
+/// Lists the installed app packs in the order [`list_app_packs`] gives them,
+/// then the builtin app packs that aren't shadowed by one of those, then the
+/// installed platform packs in alphabetical order. Packs that fail to load
+/// are skipped with a warning, so one bad spec doesn't hide the rest.
+pub fn enumerate_packs() -> Result<Vec<PackInfo>, ListError> {
+    enumerate_packs_in(
+        &app_pack_dir().map_err(ListError::NoHomeDir)?,
+        &platform_pack_dir().map_err(ListError::NoHomeDir)?,
+    )
+}
+
+fn enumerate_packs_in(app_dir: &Path, platform_dir: &Path) -> Result<Vec<PackInfo>, ListError> {
+    let mut apps = list_app_packs_in(app_dir.to_owned())?;
+    if !apps.iter().any(|name| name == BARE.name()) {
+        apps.push(BARE.name().to_owned());
+    }
+    let apps = apps.into_iter().map(|name| (name, PackCategory::App));
+    let platforms = list_packs(platform_dir.to_owned())?
+        .into_iter()
+        .map(|name| (name, PackCategory::Platform));
+    Ok(apps
+        .chain(platforms)
+        .filter_map(|(name, category)| {
+            let pack = match category {
+                PackCategory::App => Pack::lookup_app_in(app_dir, &name),
+                PackCategory::Platform => Pack::lookup(platform_dir, &name),
+            };
+            match pack {
+                Ok(pack) => Some(PackInfo::new(name, category, &pack)),
+                Err(err) => {
+                    log::warn!("skipping template pack {:?}: {}", name, err);
+                    None
+                }
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn enumerate_packs_skips_broken_packs() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let (app_dir, platform_dir) = (dir.join("apps"), dir.join("platforms"));
+        fs::create_dir_all(app_dir.join("simple")).unwrap();
+        fs::create_dir_all(platform_dir.join("xcode")).unwrap();
+        fs::write(
+            app_dir.join("fancy.toml"),
+            r#"
+path = "simple"
+description = "Fancy"
+[submodule]
+path = "sub"
+remote = "https://example.com/sub.git"
+"#,
+        )
+        .unwrap();
+        fs::write(app_dir.join("broken.toml"), "nope").unwrap();
+        let packs = enumerate_packs_in(&app_dir, &platform_dir);
+        let packs = packs
+            .unwrap()
+            .into_iter()
+            .map(|pack| {
+                (
+                    pack.name,
+                    pack.category,
+                    pack.kind,
+                    pack.description,
+                    pack.requires_submodule,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            packs,
+            [
+                (
+                    "fancy".to_owned(),
+                    PackCategory::App,
+                    PackKind::Fancy,
+                    Some("Fancy".to_owned()),
+                    true
+                ),
+                (
+                    "simple".to_owned(),
+                    PackCategory::App,
+                    PackKind::Simple,
+                    None,
+                    false
+                ),
+                (
+                    BARE.name().to_owned(),
+                    PackCategory::App,
+                    PackKind::Builtin,
+                    Some(BARE.description().to_owned()),
+                    false
+                ),
+                (
+                    "xcode".to_owned(),
+                    PackCategory::Platform,
+                    PackKind::Simple,
+                    None,
+                    false
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn installed_packs_win_over_builtin_ones() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let (app_dir, platform_dir) = (dir.join("apps"), dir.join("platforms"));
+        fs::create_dir_all(&app_dir).unwrap();
+        fs::create_dir_all(&platform_dir).unwrap();
+        let builtin = Pack::lookup_app_in(&app_dir, BARE.name());
+        fs::create_dir_all(app_dir.join(BARE.name())).unwrap();
+        let installed = Pack::lookup_app_in(&app_dir, BARE.name());
+        let packs = enumerate_packs_in(&app_dir, &platform_dir);
+        assert!(matches!(builtin, Ok(Pack::Builtin(pack)) if pack.name() == BARE.name()));
+        assert!(matches!(installed, Ok(Pack::Simple(path)) if path == app_dir.join(BARE.name())));
+        assert_eq!(
+            packs
+                .unwrap()
+                .into_iter()
+                .map(|pack| (pack.name, pack.kind))
+                .collect::<Vec<_>>(),
+            [(BARE.name().to_owned(), PackKind::Simple)]
+        );
+    }
+}
@@ -2,6 +2,7 @@ use crate::{
     util::{
         self,
         cli::{Report, TextWrapper},
+        prompt,
         repo::{self, Repo},
     },
     DuctExpressionExt,
@@ -23,6 +24,8 @@ static ENABLED_FEATURES: &[&str] = &[
 pub enum Error {
     NoHomeDir(util::NoHomeDir),
     StatusFailed(repo::Error),
+    PromptFailed(io::Error),
+    Cancelled,
     MarkerCreateFailed { path: PathBuf, cause: io::Error },
     UpdateFailed(repo::Error),
     InstallFailed(std::io::Error),
@@ -36,6 +39,8 @@ impl Display for Error {
             Self::StatusFailed(err) => {
                 write!(f, "Failed to check status of `cargo-mobile2` repo: {}", err)
             }
+            Self::PromptFailed(err) => write!(f, "Failed to prompt for confirmation: {}", err),
+            Self::Cancelled => write!(f, "Update cancelled"),
             Self::MarkerCreateFailed { path, cause } => {
                 write!(f, "Failed to create marker file at {:?}: {}", path, cause)
             }
@@ -65,7 +70,49 @@ pub(crate) fn updating_marker_path(repo: &Repo) -> PathBuf {
         .join(".updating")
 }
 
-pub fn update(wrapper: &TextWrapper) -> Result<(), Error> {
+// The checkout is left at whatever was last installed from it, so its `HEAD`
+// is a good stand-in for the installed version.
+fn print_changes(repo: &Repo, since: Option<&str>) {
+    if !repo.path().is_dir() {
+        println!("Not showing what's changed, since `cargo-mobile2` wasn't installed by `cargo mobile update`");
+        return;
+    }
+    let since = since.unwrap_or("HEAD");
+    match repo.upstream_log(since) {
+        Ok(log) if log.trim().is_empty() => println!("No changes since {}", since),
+        Ok(log) => {
+            println!("Changes since {}:", since);
+            for line in log.lines() {
+                println!("  {}", line);
+            }
+        }
+        Err(err) => log::warn!("failed to get changes since {}: {}", since, err),
+    }
+}
+
+fn confirm() -> Result<(), Error> {
+    let proceed = loop {
+        if let Some(proceed) =
+            prompt::yes_no("Install this update?", Some(true)).map_err(Error::PromptFailed)?
+        {
+            break proceed;
+        }
+    };
+    if proceed {
+        Ok(())
+    } else {
+        Err(Error::Cancelled)
+    }
+}
+
+/// Updates `cargo-mobile2`, first listing the new commits since `since` (or
+/// the installed version) and asking to proceed, unless `yes` is set.
+pub fn update(
+    wrapper: &TextWrapper,
+    non_interactive: bool,
+    yes: bool,
+    since: Option<&str>,
+) -> Result<(), Error> {
     let repo = cargo_mobile_repo().map_err(Error::NoHomeDir)?;
     let marker = updating_marker_path(&repo);
     let marker_exists = marker.is_file();
@@ -75,6 +122,13 @@ pub fn update(wrapper: &TextWrapper) -> Result<(), Error> {
         log::info!("no marker file present at {:?}", marker);
     }
     let msg = if marker_exists || repo.status().map_err(Error::StatusFailed)?.stale() {
+        // An interrupted update was already confirmed.
+        if !marker_exists {
+            print_changes(&repo, since);
+            if !(yes || non_interactive) {
+                confirm()?;
+            }
+        }
         File::create(&marker).map_err(|cause| Error::MarkerCreateFailed {
             path: marker.to_owned(),
             cause,
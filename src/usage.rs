@@ -0,0 +1,175 @@
+use crate::{
+    android,
+    config::{Config, LoadOrGenError},
+    opts::Profile,
+    target::TargetTrait as _,
+    util::{
+        cli::{Report, Reportable, TextWrapper},
+        disk::format_size,
+    },
+};
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error(transparent)]
+    ConfigFailed(Box<LoadOrGenError>),
+}
+
+impl Reportable for Error {
+    fn report(&self) -> Report {
+        match self {
+            Self::ConfigFailed(err) => err.report(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Entry {
+    description: String,
+    path: PathBuf,
+    bytes: u64,
+}
+
+// Sums the apparent size of every file under `path`, without following
+// symlinks or descending into `exclude`. Anything unreadable is skipped, since
+// this is only an estimate.
+fn dir_size(path: &Path, exclude: &[PathBuf]) -> u64 {
+    let entries = match fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(err) => {
+            log::info!("skipping unreadable dir {:?}: {}", path, err);
+            return 0;
+        }
+    };
+    entries
+        .filter_map(|entry| {
+            entry
+                .and_then(|entry| Ok((entry.path(), entry.metadata()?)))
+                .ok()
+        })
+        .map(|(path, metadata)| {
+            if metadata.is_dir() {
+                if exclude.contains(&path) {
+                    0
+                } else {
+                    dir_size(&path, exclude)
+                }
+            } else {
+                metadata.len()
+            }
+        })
+        .sum()
+}
+
+fn entries(config: &Config) -> Vec<Entry> {
+    let mut entries = Vec::new();
+    let mut push = |description: String, path: PathBuf, exclude: &[PathBuf]| {
+        if path.is_dir() {
+            let bytes = dir_size(&path, exclude);
+            entries.push(Entry {
+                description,
+                path,
+                bytes,
+            });
+        }
+    };
+
+    push(
+        "Android Studio project".into(),
+        config.android().project_dir(),
+        &[],
+    );
+    #[cfg(target_os = "macos")]
+    {
+        let apple = config.apple();
+        // Archives and exports both go in the project's `build` dir, which is
+        // reported separately so the Xcode project's size isn't inflated.
+        let build_dir = apple.archive_dir();
+        push(
+            "Xcode project".into(),
+            apple.project_dir(),
+            std::slice::from_ref(&build_dir),
+        );
+        push("Xcode archives and exports".into(), build_dir, &[]);
+        // Finding DerivedData means asking `xcodebuild`, which is best-effort.
+        match crate::env::Env::new()
+            .map_err(|err| err.to_string())
+            .and_then(|env| {
+                crate::apple::clean::derived_data_dir(apple, &env).map_err(|err| err.to_string())
+            }) {
+            Ok(Some(dir)) => push("Xcode DerivedData".into(), dir, &[]),
+            Ok(None) => log::info!("couldn't find this project's DerivedData"),
+            Err(err) => log::warn!("failed to find this project's DerivedData: {}", err),
+        }
+    }
+
+    // Each target's dir holds both profiles, so it's reported as a whole.
+    let triples = android::target::Target::all()
+        .values()
+        .map(|target| target.triple);
+    #[cfg(target_os = "macos")]
+    let triples = triples.chain(
+        crate::apple::target::Target::all()
+            .values()
+            .map(|target| target.triple),
+    );
+    let target_dirs = triples
+        .filter_map(|triple| {
+            let dir = config.app().target_dir(triple, Profile::Debug);
+            dir.parent().map(|dir| (dir.to_owned(), triple))
+        })
+        .collect::<BTreeSet<_>>();
+    for (dir, triple) in target_dirs {
+        push(format!("Rust build for `{}`", triple), dir, &[]);
+    }
+
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.bytes));
+    entries
+}
+
+/// Prints how much disk space the generated projects and build artifacts take
+/// up, largest first. Nothing is deleted.
+pub fn exec(wrapper: &TextWrapper, non_interactive: bool) -> Result<(), Error> {
+    let (config, _origin) = Config::load_or_gen(
+        ".",
+        non_interactive,
+        false,
+        None,
+        &[],
+        None,
+        None,
+        None,
+        wrapper,
+    )
+    .map_err(|err| Error::ConfigFailed(Box::new(err)))?;
+    let entries = entries(&config);
+    if entries.is_empty() {
+        println!("No generated projects or build artifacts found.");
+        return Ok(());
+    }
+    let root_dir = config.app().root_dir();
+    let total = format_size(entries.iter().map(|entry| entry.bytes).sum());
+    let width = entries
+        .iter()
+        .map(|entry| format_size(entry.bytes).len())
+        .chain([total.len()])
+        .max()
+        .unwrap_or_default();
+    for entry in &entries {
+        let path = entry.path.strip_prefix(root_dir).unwrap_or(&entry.path);
+        println!(
+            "{:>width$}  {} ({})",
+            format_size(entry.bytes),
+            path.display(),
+            entry.description,
+        );
+    }
+    println!("{:>width$}  total", total);
+    Ok(())
+}
@@ -1,7 +1,78 @@
-use std::{collections::HashMap, ffi::OsString, path::PathBuf};
+use std::{collections::HashMap, ffi::OsString, path::PathBuf, str::FromStr};
 
 use crate::{env::ExplicitEnv, DuctExpressionExt};
 
+/// Carries `--locked`/`--frozen` to every cargo invocation, including the ones
+/// Xcode and Gradle make through `xcode-script` and `android-studio-script`.
+pub const CARGO_LOCK_VAR: &str = "CARGO_MOBILE_CARGO_LOCK";
+
+/// How strictly cargo treats `Cargo.lock`, mirroring cargo's own flags.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CargoLock {
+    /// `--locked`: fail instead of updating `Cargo.lock`.
+    Locked,
+    /// `--frozen`: `--locked`, plus no network access.
+    Frozen,
+}
+
+impl CargoLock {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Locked => "locked",
+            Self::Frozen => "frozen",
+        }
+    }
+}
+
+impl FromStr for CargoLock {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "locked" => Ok(Self::Locked),
+            "frozen" => Ok(Self::Frozen),
+            _ => Err(format!("{s:?} isn't `locked` or `frozen`")),
+        }
+    }
+}
+
+/// Set to make every cargo invocation treat warnings as errors, which reaches
+/// the ones made through `xcode-script` and `android-studio-script` too.
+pub const DENY_WARNINGS_VAR: &str = "CARGO_MOBILE_DENY_WARNINGS";
+
+// Cargo ignores `rustflags` from `.cargo/config.toml` (like the NDK linker
+// flags) whenever `RUSTFLAGS` is set, so `-Dwarnings` only goes there if it's
+// already set. Otherwise, it goes in the env var for the target's rustflags,
+// which cargo merges with the config, and which leaves host builds alone.
+fn deny_warnings_var(
+    target: Option<&str>,
+    lookup: impl Fn(&str) -> Option<OsString>,
+) -> (String, OsString) {
+    let append = |key: String, sep: &str| {
+        let mut flags = lookup(&key).unwrap_or_default();
+        if !flags.is_empty() {
+            flags.push(sep);
+        }
+        flags.push("-Dwarnings");
+        (key, flags)
+    };
+    if lookup("CARGO_ENCODED_RUSTFLAGS").is_some() {
+        append("CARGO_ENCODED_RUSTFLAGS".into(), "\x1f")
+    } else if lookup("RUSTFLAGS").is_some() {
+        append("RUSTFLAGS".into(), " ")
+    } else if let Some(target) = target {
+        append(
+            format!(
+                "CARGO_TARGET_{}_RUSTFLAGS",
+                target.replace(['-', '.'], "_").to_uppercase()
+            ),
+            " ",
+        )
+    } else {
+        append("RUSTFLAGS".into(), " ")
+    }
+}
+
 #[derive(Debug)]
 pub struct CargoCommand<'a> {
     subcommand: &'a str,
@@ -73,6 +144,21 @@ impl<'a> CargoCommand<'a> {
     }
 
     pub fn build(self, env: &impl ExplicitEnv) -> duct::Expression {
+        let (args, vars) = self.resolve(env);
+        duct::cmd("cargo", args).vars(vars).dup_stdio()
+    }
+
+    /// The args to pass to `cargo` and the vars to run it with, which is
+    /// what [`Self::build`] runs. Like with duct, the vars set closest to the
+    /// command win, so callers layering more on top shouldn't overwrite these.
+    pub fn resolve(self, env: &impl ExplicitEnv) -> (Vec<String>, HashMap<String, OsString>) {
+        let mut vars = env.explicit_env();
+        if vars.contains_key(DENY_WARNINGS_VAR) {
+            let (key, flags) = deny_warnings_var(self.target, |key| {
+                vars.get(key).cloned().or_else(|| std::env::var_os(key))
+            });
+            vars.insert(key, flags);
+        }
         let mut args = vec![self.subcommand.to_owned()];
         if self.verbose {
             args.push("-vv".into());
@@ -113,14 +199,28 @@ impl<'a> CargoCommand<'a> {
         if self.release {
             args.push("--release".into());
         }
-
-        duct::cmd("cargo", args)
-            .vars(env.explicit_env())
-            .vars(explicit_cargo_env())
-            .dup_stdio()
+        if let Some(lock) = vars.get(CARGO_LOCK_VAR) {
+            match lock.to_str().map(CargoLock::from_str) {
+                Some(Ok(lock)) => args.push(format!("--{}", lock.as_str())),
+                _ => log::warn!("ignoring invalid `{}`: {:?}", CARGO_LOCK_VAR, lock),
+            }
+        }
+        for (key, value) in explicit_cargo_env() {
+            vars.entry(key).or_insert(value);
+        }
+        (args, vars)
     }
 }
 
+/// Runs `cargo` with `args` as given, with the same env as [`CargoCommand`],
+/// for subcommands we don't wrap, like `clippy`.
+pub fn cargo_passthrough(args: &[String], env: &impl ExplicitEnv) -> duct::Expression {
+    duct::cmd("cargo", args)
+        .vars(env.explicit_env())
+        .vars(explicit_cargo_env())
+        .dup_stdio()
+}
+
 fn explicit_cargo_env() -> HashMap<String, OsString> {
     let mut vars = HashMap::new();
     if let Some(target_dir) = std::env::var_os("CARGO_TARGET_DIR") {
@@ -131,3 +231,36 @@ fn explicit_cargo_env() -> HashMap<String, OsString> {
     }
     vars
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(&[], Some("aarch64-linux-android"), "CARGO_TARGET_AARCH64_LINUX_ANDROID_RUSTFLAGS", "-Dwarnings")]
+    #[case(
+        &[("CARGO_TARGET_AARCH64_APPLE_IOS_RUSTFLAGS", "-Cdebuginfo=1")],
+        Some("aarch64-apple-ios"),
+        "CARGO_TARGET_AARCH64_APPLE_IOS_RUSTFLAGS",
+        "-Cdebuginfo=1 -Dwarnings"
+    )]
+    #[case(&[("RUSTFLAGS", "-Copt-level=3")], Some("x86_64-apple-ios"), "RUSTFLAGS", "-Copt-level=3 -Dwarnings")]
+    #[case(&[("CARGO_ENCODED_RUSTFLAGS", "")], None, "CARGO_ENCODED_RUSTFLAGS", "-Dwarnings")]
+    fn test_deny_warnings_var(
+        #[case] set: &[(&str, &str)],
+        #[case] target: Option<&str>,
+        #[case] key: &str,
+        #[case] flags: &str,
+    ) {
+        let lookup = |key: &str| {
+            set.iter()
+                .find(|(set_key, _)| *set_key == key)
+                .map(|(_, value)| OsString::from(value))
+        };
+        assert_eq!(
+            deny_warnings_var(target, lookup),
+            (key.to_owned(), OsString::from(flags))
+        );
+    }
+}
@@ -1,10 +1,101 @@
+use crate::{
+    config::app::App,
+    opts::{ColorChoice, OutputFormat},
+};
 use colored::Colorize as _;
-use std::fmt::{Debug, Display};
+use std::{
+    fmt::{Debug, Display},
+    sync::atomic::{AtomicBool, Ordering},
+};
 
 pub use interface::*;
 
 pub static VERSION_SHORT: &str = concat!("v", env!("CARGO_PKG_VERSION"));
 
+static PLAIN: AtomicBool = AtomicBool::new(false);
+
+/// Sets how reports are printed for the rest of the process.
+///
+/// Plain output has no colors and no wrapping, which is friendlier to logs.
+pub fn set_output_format(format: OutputFormat) {
+    PLAIN.store(format.plain(), Ordering::Relaxed);
+    if format.plain() {
+        colored::control::set_override(false);
+    }
+}
+
+/// Forces colors on or off for the rest of the process. With
+/// [`ColorChoice::Auto`], `colored` decides based on whether stdout is a
+/// terminal, and on `NO_COLOR`, `CLICOLOR`, and `CLICOLOR_FORCE`.
+pub fn set_color(choice: ColorChoice) {
+    match choice {
+        ColorChoice::Auto => (),
+        ColorChoice::Always => colored::control::set_override(true),
+        ColorChoice::Never => colored::control::set_override(false),
+    }
+}
+
+pub fn output_format() -> OutputFormat {
+    if PLAIN.load(Ordering::Relaxed) {
+        OutputFormat::Plain
+    } else {
+        OutputFormat::Pretty
+    }
+}
+
+static JSON_ERRORS: AtomicBool = AtomicBool::new(false);
+
+/// Makes the report a command exits with get printed to stderr as a single
+/// line of JSON for the rest of the process, unless it's a victory.
+pub fn set_json_errors(json_errors: bool) {
+    JSON_ERRORS.store(json_errors, Ordering::Relaxed);
+}
+
+pub fn json_errors() -> bool {
+    JSON_ERRORS.load(Ordering::Relaxed)
+}
+
+static STDOUT_RESERVED: AtomicBool = AtomicBool::new(false);
+
+/// Reserves stdout for machine-readable output (i.e. artifact paths) for the
+/// rest of the process, sending subprocess output and reports to stderr.
+pub fn reserve_stdout() {
+    STDOUT_RESERVED.store(true, Ordering::Relaxed);
+}
+
+pub fn stdout_reserved() -> bool {
+    STDOUT_RESERVED.load(Ordering::Relaxed)
+}
+
+/// How commands find and load `mobile.toml`.
+#[derive(Clone, Copy, Debug)]
+pub struct LoadConfig<'a> {
+    pub non_interactive: bool,
+    pub flags: &'a ConfigFlags,
+}
+
+/// Checks whether a build can be skipped, since nothing relevant changed
+/// since the `--if-changed` ref (if one was passed), saying so if it can.
+pub fn unchanged(app: &App, if_changed: Option<&str>) -> std::io::Result<bool> {
+    let Some(git_ref) = if_changed else {
+        return Ok(false);
+    };
+    let changed = app.changed_since(git_ref)?;
+    if !changed {
+        let msg = format!(
+            "Skipping build, since nothing relevant changed since {}",
+            git_ref
+        );
+        // Like reports, this stays out of the way of `--print-artifact`.
+        if stdout_reserved() {
+            eprintln!("{}", msg)
+        } else {
+            println!("{}", msg)
+        }
+    }
+    Ok(!changed)
+}
+
 #[derive(Clone)]
 pub struct TextWrapper(pub textwrap::Options<'static>);
 
@@ -19,7 +110,23 @@ impl Default for TextWrapper {
 
 impl TextWrapper {
     pub fn fill(&self, text: &str) -> String {
-        textwrap::fill(text, &self.0)
+        if output_format().plain() {
+            // Keep the indentation, but don't wrap.
+            text.lines()
+                .enumerate()
+                .map(|(index, line)| {
+                    let indent = if index == 0 {
+                        self.0.initial_indent
+                    } else {
+                        self.0.subsequent_indent
+                    };
+                    format!("{}{}", indent, line)
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        } else {
+            textwrap::fill(text, &self.0)
+        }
     }
 }
 
@@ -69,6 +176,7 @@ pub struct Report {
     label: Label,
     msg: String,
     details: String,
+    exit_code: Option<i32>,
 }
 
 impl Report {
@@ -77,6 +185,7 @@ impl Report {
             label,
             msg: format!("{}", msg),
             details: format!("{}", details),
+            exit_code: None,
         }
     }
 
@@ -92,8 +201,29 @@ impl Report {
         Self::new(Label::Victory, msg, details)
     }
 
-    pub fn exit_code(&self) -> i8 {
-        self.label.exit_code()
+    /// Exits with `code` instead of the label's, i.e. to pass on the exit code
+    /// of an app that was waited on.
+    pub fn with_exit_code(mut self, code: i32) -> Self {
+        self.exit_code = Some(code);
+        self
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        self.exit_code
+            .unwrap_or_else(|| self.label.exit_code().into())
+    }
+
+    /// Serializes this as `{"error": {"label", "message", "details", "exit_code"}}`,
+    /// with no colors or wrapping.
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "error": {
+                "label": self.label.as_str(),
+                "message": self.msg,
+                "details": self.details,
+                "exit_code": self.exit_code(),
+            }
+        })
     }
 
     fn format(&self, wrapper: &TextWrapper) -> String {
@@ -121,7 +251,7 @@ impl Report {
 
     pub fn print(&self, wrapper: &TextWrapper) {
         let s = self.format(wrapper);
-        if matches!(self.label, Label::Error) {
+        if matches!(self.label, Label::Error) || stdout_reserved() {
             eprint!("{}", s)
         } else {
             print!("{}", s)
@@ -138,7 +268,15 @@ mod interface {}
 
 #[cfg(feature = "cli")]
 mod interface {
-    use std::fmt::Debug;
+    use std::{
+        fmt::Debug,
+        fs::{File, OpenOptions},
+        io::{self, Write as _},
+        num::NonZeroU32,
+        path::{Path, PathBuf},
+        sync::Mutex,
+        time::Instant,
+    };
 
     use crate::{opts, util};
     use once_cell_regex::exports::once_cell::sync::Lazy;
@@ -161,6 +299,14 @@ mod interface {
         format!("cargo {}", name)
     }
 
+    /// What Apple commands report on other platforms, before doing anything.
+    pub fn macos_host_required() -> Report {
+        Report::error(
+            "iOS/macOS builds require a macOS host",
+            "Apple only supports Xcode and `xcodebuild` on macOS, so Apple commands can't be used here. Android commands still work!",
+        )
+    }
+
     pub static VERSION_LONG: Lazy<String> = Lazy::new(|| match util::installed_commit_msg() {
         Ok(Some(msg)) => format!("{}\n{}", VERSION_SHORT, util::format_commit_msg(msg)),
         Ok(None) => VERSION_SHORT.to_owned(),
@@ -170,7 +316,7 @@ mod interface {
         }
     });
 
-    #[derive(Clone, Copy, Debug, StructOpt)]
+    #[derive(Clone, Debug, StructOpt)]
     pub struct GlobalFlags {
         #[structopt(
         short = "v",
@@ -188,6 +334,44 @@ mod interface {
             global = true
         )]
         pub non_interactive: bool,
+        #[structopt(
+            long = "format",
+            help = "How to print reports (defaults to plain if `NO_COLOR` is set)",
+            global = true,
+            possible_values = &opts::OutputFormat::variants(),
+            case_insensitive = true,
+        )]
+        pub format: Option<opts::OutputFormat>,
+        #[structopt(
+            long = "color",
+            help = "When to print colors",
+            global = true,
+            default_value = "auto",
+            possible_values = &opts::ColorChoice::variants(),
+            case_insensitive = true,
+        )]
+        pub color: opts::ColorChoice,
+        #[structopt(
+            long = "log-file",
+            value_name = "path",
+            help = "Also write every log record to this file, regardless of `-v`",
+            global = true,
+            parse(from_os_str)
+        )]
+        pub log_file: Option<PathBuf>,
+        #[structopt(
+            long = "log-append",
+            help = "Append to the `--log-file` instead of truncating it",
+            global = true,
+            requires = "log-file"
+        )]
+        pub log_append: bool,
+        #[structopt(
+            long = "json-errors",
+            help = "Print the error a command fails with as a single line of JSON",
+            global = true
+        )]
+        pub json_errors: bool,
     }
 
     #[derive(Clone, Copy, Debug, StructOpt)]
@@ -214,6 +398,341 @@ mod interface {
         pub reinstall_deps: bool,
     }
 
+    #[derive(Clone, Copy, Debug, StructOpt)]
+    pub struct NoDeps {
+        #[structopt(
+            long = "no-deps",
+            help = "Don't install or update any dependencies or rustup targets; run `cargo mobile doctor` to check them instead",
+            conflicts_with = "reinstall-deps"
+        )]
+        pub no_deps: bool,
+    }
+
+    #[derive(Clone, Copy, Debug, StructOpt)]
+    pub struct ProjectOnly {
+        #[structopt(
+            long = "project-only",
+            help = "Only generate the platform projects, leaving `src`, `.cargo/config.toml`, rustup targets, and editor tools alone"
+        )]
+        pub project_only: bool,
+    }
+
+    #[derive(Clone, Copy, Debug, StructOpt)]
+    pub struct Bare {
+        #[structopt(
+            long = "bare",
+            help = "Use the builtin dependency-free hello-world template instead of a template pack, without needing submodules or network access",
+            conflicts_with = "project-only"
+        )]
+        pub bare: bool,
+    }
+
+    #[derive(Clone, Copy, Debug, StructOpt)]
+    pub struct RequireEmpty {
+        #[structopt(
+            long = "require-empty",
+            help = "Abort without generating anything if the directory has files other than `.git`, `Cargo.toml`, and `src`"
+        )]
+        pub require_empty: bool,
+    }
+
+    #[derive(Clone, Copy, Debug, StructOpt)]
+    pub struct ForceConfig {
+        #[structopt(
+            long = "force-config",
+            help = "Overwrite an existing `mobile.toml` without asking (it's backed up first either way)"
+        )]
+        pub force_config: bool,
+    }
+
+    #[derive(Clone, Debug, StructOpt)]
+    pub struct TemplateData {
+        #[structopt(
+            long = "template-data",
+            value_name = "file",
+            help = "JSON or TOML file of extra key/value pairs to make available to templates",
+            parse(from_os_str)
+        )]
+        pub template_data: Option<PathBuf>,
+        #[structopt(
+            long = "strict-template-data",
+            help = "Fail if `--template-data` has a key that's already derived from the config, instead of ignoring it",
+            requires = "template-data"
+        )]
+        pub strict_template_data: bool,
+    }
+
+    #[derive(Clone, Copy, Debug, StructOpt)]
+    pub struct LastDevice {
+        #[structopt(
+            long = "last-device",
+            help = "Use the device used last time without prompting, if it's still connected"
+        )]
+        pub last_device: bool,
+    }
+
+    #[derive(Clone, Debug, StructOpt)]
+    pub struct AfterRun {
+        #[structopt(
+            long = "after-run",
+            help = "Command to run once the app has launched, e.g. a test runner; `CARGO_MOBILE_DEVICE_ID` and `CARGO_MOBILE_APP_IDENTIFIER` are set for it"
+        )]
+        pub after_run: Option<String>,
+    }
+
+    #[derive(Clone, Copy, Debug, StructOpt)]
+    pub struct CargoJobs {
+        #[structopt(
+            long = "cargo-jobs",
+            value_name = "N",
+            help = "Number of jobs cargo runs in parallel; defaults to `app.cargo-jobs`, or the number of CPUs"
+        )]
+        pub cargo_jobs: Option<NonZeroU32>,
+    }
+
+    #[derive(Clone, Copy, Debug, StructOpt)]
+    pub struct RequireFree {
+        #[structopt(
+            long = "require-free",
+            value_name = "GiB",
+            parse(try_from_str = util::disk::parse_gib),
+            help = "Fail before building unless the target and project dirs' disks have this much free space; defaults to `require-free` in the platform's config"
+        )]
+        pub require_free: Option<f64>,
+    }
+
+    #[derive(Clone, Copy, Debug, StructOpt)]
+    pub struct Lockfile {
+        #[structopt(
+            long = "locked",
+            help = "Pass `--locked` to cargo, so it fails instead of updating `Cargo.lock`"
+        )]
+        pub locked: bool,
+        #[structopt(
+            long = "frozen",
+            help = "Pass `--frozen` to cargo, which is `--locked` plus `--offline`"
+        )]
+        pub frozen: bool,
+    }
+
+    impl Lockfile {
+        /// `--frozen` implies `--locked`, like it does for cargo.
+        pub fn lock(self) -> Option<crate::util::CargoLock> {
+            use crate::util::CargoLock;
+            if self.frozen {
+                Some(CargoLock::Frozen)
+            } else if self.locked {
+                Some(CargoLock::Locked)
+            } else {
+                None
+            }
+        }
+    }
+
+    #[derive(Clone, Copy, Debug, StructOpt)]
+    pub struct DenyWarnings {
+        #[structopt(
+            long = "deny-warnings",
+            help = "Build the Rust code for mobile targets with `-D warnings`, leaving host builds alone"
+        )]
+        pub deny_warnings: bool,
+    }
+
+    #[derive(Clone, Copy, Debug, StructOpt)]
+    pub struct DryRun {
+        #[structopt(
+            long = "dry-run",
+            help = "Print the device, artifact, and commands that would be used to deploy, without building or deploying anything"
+        )]
+        pub dry_run: bool,
+    }
+
+    #[derive(Clone, Copy, Debug, StructOpt)]
+    pub struct SkipSigning {
+        #[structopt(
+            long = "skip-signing",
+            help = "Don't code sign, for builds that are only inspected, like in CI without signing set up; they can't be installed on normal devices"
+        )]
+        pub skip_signing: bool,
+    }
+
+    #[derive(Clone, Copy, Debug, StructOpt)]
+    pub struct Explain {
+        #[structopt(
+            long = "explain",
+            help = "Print the steps that would be taken to build, deploy, and launch the app, without doing any of them",
+            conflicts_with = "dry-run"
+        )]
+        pub explain: bool,
+    }
+
+    #[derive(Clone, Copy, Debug, StructOpt)]
+    pub struct AllTargets {
+        #[structopt(
+            long = "all-targets",
+            help = "Use every target, instead of `targets` or the one matching a connected device"
+        )]
+        pub all_targets: bool,
+    }
+
+    impl AllTargets {
+        /// Every target name if `--all-targets` was passed, otherwise `targets`.
+        pub fn expand<'a, T: crate::target::TargetTrait<'a>>(
+            self,
+            targets: Vec<String>,
+        ) -> Vec<String> {
+            if self.all_targets {
+                T::name_list().into_iter().map(ToOwned::to_owned).collect()
+            } else {
+                targets
+            }
+        }
+    }
+
+    #[derive(Clone, Copy, Debug, StructOpt)]
+    pub struct KeepGoing {
+        #[structopt(
+            long = "keep-going",
+            help = "Continue with the remaining targets after a failure, then summarize"
+        )]
+        pub keep_going: bool,
+    }
+
+    #[derive(Clone, Copy, Debug, StructOpt)]
+    pub struct PrintArtifact {
+        #[structopt(
+            long = "print-artifact",
+            help = "Print only the path(s) of the built artifact(s) to stdout, sending everything else to stderr"
+        )]
+        pub print_artifact: bool,
+    }
+
+    #[derive(Clone, Debug, StructOpt)]
+    pub struct Locale {
+        #[structopt(
+            long = "locale",
+            value_name = "tag",
+            help = "Launch the app with this locale (a BCP-47 tag like `en-US`, or a pseudolocale like `en-XA`)"
+        )]
+        pub locale: Option<crate::device::Locale>,
+    }
+
+    #[derive(Clone, Debug, StructOpt)]
+    pub struct Install {
+        #[structopt(
+            long = "downgrade",
+            help = "Allow installing over a newer version of the app"
+        )]
+        pub downgrade: bool,
+        #[structopt(
+            long = "grant-permissions",
+            help = "Grant all of the app's runtime permissions when installing it"
+        )]
+        pub grant_permissions: bool,
+        #[structopt(
+            long = "install-arg",
+            value_name = "arg",
+            help = "Pass an extra argument to `adb install`, or `bundletool install-apks` for apps with asset packs; may be given more than once",
+            number_of_values = 1,
+            allow_hyphen_values = true
+        )]
+        pub install_args: Vec<String>,
+    }
+
+    #[derive(Clone, Debug, Default, StructOpt)]
+    pub struct ConfigFlags {
+        #[structopt(
+            long = "config",
+            value_name = "key=value",
+            help = "Override a `mobile.toml` value for this run, e.g. `apple.enable-bitcode=true`",
+            global = true,
+            number_of_values = 1
+        )]
+        pub config_overrides: Vec<crate::config::overrides::Override>,
+        #[structopt(
+            long = "max-search-depth",
+            value_name = "dirs",
+            help = "Search at most this many parent dirs for `mobile.toml` (the search always stops at the git repo root)",
+            global = true
+        )]
+        pub max_search_depth: Option<usize>,
+        #[structopt(
+            long = "app",
+            value_name = "name",
+            help = "Use the config of this `[workspace]` member, by dir name or `app.name`",
+            global = true
+        )]
+        pub app: Option<String>,
+        #[structopt(
+            long = "env-file",
+            value_name = "path",
+            help = "Load environment variables that aren't already set from this file, instead of from `app.env-file`",
+            global = true,
+            parse(from_os_str)
+        )]
+        pub env_file: Option<std::path::PathBuf>,
+        #[structopt(
+            long = "force-config",
+            help = "Overwrite an existing `mobile.toml` without asking (it's backed up first either way)",
+            global = true
+        )]
+        pub force_config: bool,
+    }
+
+    /// [`ConfigFlags`] for `cargo mobile android` and `cargo mobile apple`.
+    ///
+    /// clap only hands the values of global args to every subcommand when
+    /// they're defined at the top level, so these are gathered from each of
+    /// the subcommands they could've been passed after.
+    #[derive(Clone, Debug, Default)]
+    pub struct NestedConfigFlags(pub ConfigFlags);
+
+    impl StructOpt for NestedConfigFlags {
+        fn clap<'a, 'b>() -> clap::App<'a, 'b> {
+            ConfigFlags::clap()
+        }
+
+        fn from_clap(matches: &clap::ArgMatches<'_>) -> Self {
+            let mut flags = ConfigFlags::from_clap(matches);
+            let mut matches = matches;
+            while let (_, Some(sub_matches)) = matches.subcommand() {
+                let nested = ConfigFlags::from_clap(sub_matches);
+                flags.config_overrides.extend(nested.config_overrides);
+                flags.max_search_depth = nested.max_search_depth.or(flags.max_search_depth);
+                flags.app = nested.app.or(flags.app);
+                flags.env_file = nested.env_file.or(flags.env_file);
+                flags.force_config |= nested.force_config;
+                matches = sub_matches;
+            }
+            Self(flags)
+        }
+    }
+
+    impl structopt::StructOptInternal for NestedConfigFlags {
+        fn augment_clap<'a, 'b>(app: clap::App<'a, 'b>) -> clap::App<'a, 'b> {
+            ConfigFlags::augment_clap(app)
+        }
+    }
+
+    #[derive(Clone, Copy, Debug, StructOpt)]
+    pub struct BuildInfo {
+        #[structopt(
+            long = "with-build-info",
+            help = "Write the git commit and build date to a Rust module at `app.build-info-path` (you'll probably want to gitignore it)"
+        )]
+        pub with_build_info: bool,
+    }
+
+    #[derive(Clone, Debug, StructOpt)]
+    pub struct IfChanged {
+        #[structopt(
+            long = "if-changed",
+            value_name = "ref",
+            help = "Skip the build if no sources, assets, or config changed since this git ref"
+        )]
+        pub if_changed: Option<String>,
+    }
+
     #[derive(Clone, Copy, Debug, StructOpt)]
     pub struct Profile {
         #[structopt(
@@ -254,7 +773,61 @@ mod interface {
         args
     }
 
-    fn init_logging(noise_level: opts::NoiseLevel) {
+    // Sends records to `env_logger` as usual, and additionally writes all of
+    // them to the `--log-file`, if there is one.
+    struct Logger {
+        stderr: env_logger::Logger,
+        file: Option<Mutex<File>>,
+        start: Instant,
+    }
+
+    impl log::Log for Logger {
+        fn enabled(&self, metadata: &log::Metadata<'_>) -> bool {
+            self.file.is_some() || self.stderr.enabled(metadata)
+        }
+
+        fn log(&self, record: &log::Record<'_>) {
+            if self.stderr.matches(record) {
+                self.stderr.log(record);
+            }
+            if let Some(file) = &self.file {
+                let mut file = file.lock().unwrap_or_else(|err| err.into_inner());
+                // There's nowhere left to report a failed log write.
+                let _ = writeln!(
+                    file,
+                    "[{:>10.3}s {:<5} {}] {}",
+                    self.start.elapsed().as_secs_f64(),
+                    record.level(),
+                    record.target(),
+                    record.args()
+                );
+            }
+        }
+
+        fn flush(&self) {
+            self.stderr.flush();
+            if let Some(file) = &self.file {
+                let _ = file.lock().unwrap_or_else(|err| err.into_inner()).flush();
+            }
+        }
+    }
+
+    fn open_log_file(path: &Path, append: bool) -> io::Result<File> {
+        let mut options = OpenOptions::new();
+        options.create(true);
+        if append {
+            options.append(true);
+        } else {
+            options.write(true).truncate(true);
+        }
+        options.open(path)
+    }
+
+    fn init_logging(
+        noise_level: opts::NoiseLevel,
+        log_file: Option<&Path>,
+        log_append: bool,
+    ) -> Result<(), Report> {
         use env_logger::{Builder, Env};
         let default_level = match noise_level {
             opts::NoiseLevel::Polite => "warn",
@@ -266,7 +839,26 @@ mod interface {
             }
         };
         let env = Env::default().default_filter_or(default_level);
-        Builder::from_env(env).init();
+        let stderr = Builder::from_env(env).build();
+        let file = log_file
+            .map(|path| {
+                open_log_file(path, log_append).map_err(|err| {
+                    Report::error(format!("Failed to open log file {:?}", path), err)
+                })
+            })
+            .transpose()?;
+        log::set_max_level(if file.is_some() {
+            log::LevelFilter::Trace
+        } else {
+            stderr.filter()
+        });
+        let logger = Logger {
+            stderr,
+            file: file.map(Mutex::new),
+            start: Instant::now(),
+        };
+        log::set_boxed_logger(Box::new(logger)).expect("developer error: logger already set");
+        Ok(())
     }
 
     #[derive(Debug)]
@@ -284,8 +876,12 @@ mod interface {
         fn do_the_thing(self, wrapper: TextWrapper) -> ! {
             match self {
                 Self::Report(report) => {
-                    report.print(&wrapper);
-                    std::process::exit(report.label.exit_code().into())
+                    if json_errors() && !matches!(report.label, Label::Victory) {
+                        eprintln!("{}", report.to_json());
+                    } else {
+                        report.print(&wrapper);
+                    }
+                    std::process::exit(report.exit_code())
                 }
                 Self::Clap(err) => err.exit(),
             }
@@ -303,9 +899,47 @@ mod interface {
         Exit::main(|wrapper| {
             let args = get_args(name);
             let input = E::from_iter_safe(&args).map_err(Exit::Clap)?;
-            init_logging(input.global_flags().noise_level);
+            set_output_format(
+                input
+                    .global_flags()
+                    .format
+                    .unwrap_or_else(opts::OutputFormat::detect),
+            );
+            // This comes after the output format, so that `--color always`
+            // wins over plain output's lack of colors.
+            set_color(input.global_flags().color);
+            set_json_errors(input.global_flags().json_errors);
+            let flags = input.global_flags();
+            init_logging(
+                flags.noise_level,
+                flags.log_file.as_deref(),
+                flags.log_append,
+            )
+            .map_err(Exit::Report)?;
             log::debug!("raw args: {:#?}", args);
             input.exec(wrapper).map_err(Exit::report)
         })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn json_report_is_one_valid_line() {
+        let report = Report::error(
+            "Failed to open \"mobile.toml\"",
+            "line one\nline \"two\"\t\\",
+        );
+        let json = report.to_json().to_string();
+        assert!(!json.contains('\n'));
+        let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed["error"]["message"], "Failed to open \"mobile.toml\"");
+        assert_eq!(parsed["error"]["details"], "line one\nline \"two\"\t\\");
+        assert_eq!(parsed["error"]["label"], "error");
+        assert_eq!(parsed["error"]["exit_code"], 1);
+        let report = report.with_exit_code(3);
+        assert_eq!(report.to_json()["error"]["exit_code"], 3);
+    }
+}
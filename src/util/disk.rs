@@ -0,0 +1,126 @@
+use crate::util::cli::{Report, Reportable};
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+const GIB: f64 = 1024.0 * 1024.0 * 1024.0;
+
+/// Formats `bytes` with binary units, e.g. `1.5 KiB`.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64;
+    let mut unit = "B";
+    for next in UNITS {
+        if size < 1024.0 {
+            break;
+        }
+        size /= 1024.0;
+        unit = next;
+    }
+    format!("{:.1} {}", size, unit)
+}
+
+/// Whether `gib` makes sense as an amount of free space to require.
+pub fn gib_valid(gib: f64) -> bool {
+    gib.is_finite() && gib > 0.0
+}
+
+pub fn parse_gib(s: &str) -> Result<f64, String> {
+    s.parse()
+        .ok()
+        .filter(|&gib| gib_valid(gib))
+        .ok_or_else(|| format!("{:?} isn't a positive number of GiB", s))
+}
+
+#[derive(Debug, Error)]
+pub enum FreeSpaceError {
+    #[error("Failed to check free disk space at {path:?}: {source}")]
+    CheckFailed { path: PathBuf, source: io::Error },
+    #[error("Only {} free on the disk containing {path:?}, but {} is required", format_size(*.available), format_size(*.required))]
+    NotEnough {
+        path: PathBuf,
+        available: u64,
+        required: u64,
+    },
+}
+
+impl Reportable for FreeSpaceError {
+    fn report(&self) -> Report {
+        match self {
+            Self::CheckFailed { .. } => Report::error("Failed to check free disk space", self),
+            Self::NotEnough { .. } => Report::action_request(
+                self.to_string(),
+                "Free up some space and try again; `cargo mobile usage` shows what the build artifacts take up.",
+            ),
+        }
+    }
+}
+
+// Build dirs might not exist yet, in which case the closest dir that does is
+// on the same disk.
+fn existing_ancestor(path: &Path) -> &Path {
+    path.ancestors()
+        .find(|path| path.exists())
+        .unwrap_or(Path::new("."))
+}
+
+/// Ensures the disks containing each of `paths` have at least `required_gib`
+/// free, so a build can fail before it starts instead of when the disk fills
+/// up halfway through.
+pub fn ensure_free_space<'a>(
+    paths: impl IntoIterator<Item = &'a Path>,
+    required_gib: f64,
+) -> Result<(), FreeSpaceError> {
+    let required = (required_gib * GIB) as u64;
+    for path in paths {
+        let available = fs4::available_space(existing_ancestor(path)).map_err(|source| {
+            FreeSpaceError::CheckFailed {
+                path: path.to_owned(),
+                source,
+            }
+        })?;
+        log::info!(
+            "{} free on the disk containing {:?}",
+            format_size(available),
+            path
+        );
+        if available < required {
+            return Err(FreeSpaceError::NotEnough {
+                path: path.to_owned(),
+                available,
+                required,
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(0, "0 B")]
+    #[case(1023, "1023 B")]
+    #[case(1536, "1.5 KiB")]
+    #[case(5 * 1024 * 1024 * 1024, "5.0 GiB")]
+    fn test_format_size(#[case] bytes: u64, #[case] formatted: &str) {
+        assert_eq!(format_size(bytes), formatted);
+    }
+
+    #[test]
+    fn missing_dirs_are_checked_on_their_disk() {
+        let dir = std::env::temp_dir();
+        assert!(ensure_free_space([dir.join("not/built/yet").as_path()], 0.0).is_ok());
+        assert!(matches!(
+            ensure_free_space([dir.as_path()], 1e12),
+            Err(FreeSpaceError::NotEnough { .. })
+        ));
+    }
+}
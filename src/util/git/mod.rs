@@ -1,8 +1,12 @@
 pub mod lfs;
+pub mod network;
 pub mod repo;
 pub mod submodule;
 
-use std::{fs, io, path::Path};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
 
 #[derive(Clone, Copy, Debug)]
 pub struct Git<'a> {
@@ -80,4 +84,82 @@ impl<'a> Git<'a> {
             })
             .read()
     }
+
+    /// Lists the files under `paths` that differ between `git_ref` and the
+    /// working tree, including untracked files that aren't ignored.
+    pub fn changed_files(&self, git_ref: &str, paths: &[PathBuf]) -> io::Result<Vec<String>> {
+        let git_ref = git_ref.to_owned();
+        let diff_paths = paths.to_vec();
+        let mut files = self
+            .command()
+            .before_spawn(move |cmd| {
+                cmd.args(["diff", "--name-only"])
+                    .arg(&git_ref)
+                    .arg("--")
+                    .args(&diff_paths);
+                Ok(())
+            })
+            .read()?;
+        let paths = paths.to_vec();
+        files.push('\n');
+        files.push_str(
+            &self
+                .command()
+                .before_spawn(move |cmd| {
+                    cmd.args(["ls-files", "--others", "--exclude-standard", "--"])
+                        .args(&paths);
+                    Ok(())
+                })
+                .read()?,
+        );
+        Ok(files
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(ToOwned::to_owned)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn untracked_files_count_as_changed() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("src/lib.rs"), "").unwrap();
+        fs::write(root.join(".gitignore"), "*.log\n").unwrap();
+        let git = Git::new(root);
+        let run = |args: &'static [&'static str]| {
+            git.command()
+                .before_spawn(move |cmd| {
+                    cmd.args(args);
+                    Ok(())
+                })
+                .run()
+                .unwrap();
+        };
+        run(&["init", "-q"]);
+        run(&["add", "."]);
+        run(&[
+            "-c",
+            "user.name=test",
+            "-c",
+            "user.email=test@example.com",
+            "commit",
+            "-qm",
+            "init",
+        ]);
+        let src = [PathBuf::from("src")];
+        assert!(git.changed_files("HEAD", &src).unwrap().is_empty());
+
+        fs::write(root.join("src/build.log"), "").unwrap();
+        fs::write(root.join("src/new.rs"), "").unwrap();
+        fs::write(root.join("src/lib.rs"), "mod new;").unwrap();
+        let mut changed = git.changed_files("HEAD", &src).unwrap();
+        changed.sort();
+        assert_eq!(changed, ["src/lib.rs", "src/new.rs"]);
+    }
 }
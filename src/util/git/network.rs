@@ -0,0 +1,149 @@
+use std::{
+    ffi::OsString,
+    io, thread,
+    time::{Duration, Instant},
+};
+
+/// Seconds each attempt at a clone, fetch, or submodule update gets before
+/// it's killed.
+pub const TIMEOUT_VAR: &str = "CARGO_MOBILE_GIT_TIMEOUT";
+/// How many times a failed clone, fetch, or submodule update is retried.
+pub const RETRIES_VAR: &str = "CARGO_MOBILE_GIT_RETRIES";
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(300);
+const DEFAULT_RETRIES: u32 = 2;
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How long git operations that hit the network may take, and how many times
+/// they're retried, so a flaky connection can't hang setup forever.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Network {
+    timeout: Duration,
+    retries: u32,
+}
+
+impl Default for Network {
+    fn default() -> Self {
+        Self {
+            timeout: DEFAULT_TIMEOUT,
+            retries: DEFAULT_RETRIES,
+        }
+    }
+}
+
+fn parse_var<T: std::str::FromStr>(
+    key: &str,
+    lookup: &impl Fn(&str) -> Option<OsString>,
+) -> Option<T> {
+    let value = lookup(key)?;
+    let parsed = value.to_str().and_then(|value| value.trim().parse().ok());
+    if parsed.is_none() {
+        log::warn!("ignoring {}={:?}, which isn't a whole number", key, value);
+    }
+    parsed
+}
+
+impl Network {
+    /// Reads [`TIMEOUT_VAR`] and [`RETRIES_VAR`], falling back to the
+    /// defaults for anything unset or invalid. A timeout of `0` is ignored.
+    pub fn from_env() -> Self {
+        Self::from_lookup(|key| std::env::var_os(key))
+    }
+
+    fn from_lookup(lookup: impl Fn(&str) -> Option<OsString>) -> Self {
+        let default = Self::default();
+        Self {
+            timeout: parse_var(TIMEOUT_VAR, &lookup)
+                .filter(|&secs| secs > 0)
+                .map(Duration::from_secs)
+                .unwrap_or(default.timeout),
+            retries: parse_var(RETRIES_VAR, &lookup).unwrap_or(default.retries),
+        }
+    }
+
+    /// For operations that can't safely be retried, since a failed attempt
+    /// leaves state behind.
+    pub fn without_retries(self) -> Self {
+        Self { retries: 0, ..self }
+    }
+
+    fn run_once(&self, description: &str, expr: &duct::Expression) -> io::Result<()> {
+        let handle = expr.start()?;
+        let deadline = Instant::now() + self.timeout;
+        loop {
+            if handle.try_wait()?.is_some() {
+                return Ok(());
+            }
+            if Instant::now() >= deadline {
+                handle.kill()?;
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!(
+                        "`{}` timed out after {}s; set `{}` to allow longer",
+                        description,
+                        self.timeout.as_secs(),
+                        TIMEOUT_VAR,
+                    ),
+                ));
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    /// Runs the expression `attempt` returns until it succeeds or the retries
+    /// run out, killing any attempt that outlives the timeout. `attempt` is
+    /// called again for each retry, so it can clean up after a killed attempt.
+    pub fn run(
+        &self,
+        description: &str,
+        mut attempt: impl FnMut() -> duct::Expression,
+    ) -> io::Result<()> {
+        let mut tries = 0;
+        loop {
+            match self.run_once(description, &attempt()) {
+                Ok(()) => return Ok(()),
+                Err(err) if tries < self.retries => {
+                    tries += 1;
+                    let backoff = Duration::from_secs(2u64.pow(tries.min(5)));
+                    log::warn!(
+                        "`{}` failed: {}; retrying in {}s ({} of {})",
+                        description,
+                        err,
+                        backoff.as_secs(),
+                        tries,
+                        self.retries,
+                    );
+                    thread::sleep(backoff);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(None, None, Network::default())]
+    #[case(
+        Some("60"),
+        Some("0"),
+        Network { timeout: Duration::from_secs(60), retries: 0 }
+    )]
+    #[case(Some("0"), Some("five"), Network::default())]
+    fn test_from_lookup(
+        #[case] timeout: Option<&str>,
+        #[case] retries: Option<&str>,
+        #[case] expected: Network,
+    ) {
+        let network = Network::from_lookup(|key| match key {
+            TIMEOUT_VAR => timeout.map(Into::into),
+            RETRIES_VAR => retries.map(Into::into),
+            _ => None,
+        });
+        assert_eq!(network, expected);
+    }
+}
@@ -1,3 +1,4 @@
+use super::network::Network;
 use crate::util::{self, Git};
 use std::{
     ffi::OsStr,
@@ -84,8 +85,8 @@ impl Repo {
             Status::Stale
         } else {
             let git = self.git();
-            git.command_parse("fetch origin")
-                .run()
+            Network::from_env()
+                .run("git fetch origin", || git.command_parse("fetch origin"))
                 .map_err(Error::FetchFailed)?;
             let local = git
                 .command_parse("rev-parse HEAD")
@@ -124,6 +125,22 @@ impl Repo {
             .map_err(Error::LogFailed)
     }
 
+    /// One line per upstream commit since `since`, which needs to have been
+    /// fetched by [`Repo::status`] first.
+    pub fn upstream_log(&self, since: &str) -> Result<String, Error> {
+        let range = format!("{since}..@{{u}}");
+        self.git()
+            .command()
+            .before_spawn(move |cmd| {
+                cmd.args(["log", "--no-merges", "--pretty=format:%h%x20%s"])
+                    .arg(&range);
+                Ok(())
+            })
+            .stderr_capture()
+            .read()
+            .map_err(Error::LogFailed)
+    }
+
     pub fn update(&self, url: impl AsRef<OsStr>, branch: &str) -> Result<(), Error> {
         let path = self.path();
         if !path.is_dir() {
@@ -139,13 +156,22 @@ impl Repo {
                     }
                 })?;
             }
-            Git::new(parent)
-                .command_parse(format!(
-                    "clone --depth 1 --single-branch {} {}",
-                    url.as_ref().to_string_lossy(),
-                    path.to_string_lossy()
-                ))
-                .run()
+            let args = format!(
+                "clone --depth 1 --single-branch {} {}",
+                url.as_ref().to_string_lossy(),
+                path.to_string_lossy()
+            );
+            Network::from_env()
+                .run("git clone", || {
+                    // A clone that was killed partway leaves its dir behind,
+                    // which would make the retry fail.
+                    if path.is_dir() {
+                        if let Err(err) = std::fs::remove_dir_all(path) {
+                            log::warn!("failed to remove partial clone {:?}: {}", path, err);
+                        }
+                    }
+                    Git::new(parent).command_parse(&args)
+                })
                 .map_err(Error::CloneFailed)?;
         } else {
             println!(
@@ -157,9 +183,8 @@ impl Repo {
                 )
                 .display()
             );
-            self.git()
-                .command_parse("fetch --depth 1")
-                .run()
+            Network::from_env()
+                .run("git fetch", || self.git().command_parse("fetch --depth 1"))
                 .map_err(Error::FetchFailed)?;
             self.git()
                 .command_parse(format!("reset --hard origin/{branch}"))
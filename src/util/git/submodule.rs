@@ -1,4 +1,4 @@
-use super::{lfs, Git};
+use super::{lfs, network::Network, Git};
 use once_cell_regex::regex;
 use serde::{Deserialize, Serialize};
 use std::{
@@ -153,12 +153,17 @@ impl Submodule {
             log::info!("adding submodule: {:#?}", self);
             let remote = self.remote.clone();
             let name = name.to_owned();
-            git.command()
-                .before_spawn(move |cmd| {
-                    cmd.args(["submodule", "add", "--name", &name, &remote, &path_str]);
-                    Ok(())
+            // A failed add leaves the submodule half-registered, so it's only
+            // bounded, not retried.
+            Network::from_env()
+                .without_retries()
+                .run("git submodule add", || {
+                    let (name, remote, path_str) = (name.clone(), remote.clone(), path_str.clone());
+                    git.command().before_spawn(move |cmd| {
+                        cmd.args(["submodule", "add", "--name", &name, &remote, &path_str]);
+                        Ok(())
+                    })
                 })
-                .run()
                 .map_err(|cause| Error {
                     submodule: self.clone(),
                     cause: Box::new(Cause::AddFailed(cause)),
@@ -173,12 +178,13 @@ impl Submodule {
         };
         if !initialized {
             log::info!("initializing submodule: {:#?}", self);
-            git.command()
-                .before_spawn(|cmd| {
-                    cmd.args(["submodule", "update", "--init", "--recursive"]);
-                    Ok(())
+            Network::from_env()
+                .run("git submodule update", || {
+                    git.command().before_spawn(|cmd| {
+                        cmd.args(["submodule", "update", "--init", "--recursive"]);
+                        Ok(())
+                    })
                 })
-                .run()
                 .map_err(|cause| Error {
                     submodule: self.clone(),
                     cause: Box::new(Cause::InitFailed(cause)),
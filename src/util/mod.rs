@@ -1,8 +1,10 @@
 mod cargo;
 pub mod cli;
+pub mod disk;
 mod git;
 pub mod ln;
 mod path;
+pub mod progress;
 pub mod prompt;
 
 pub use self::{cargo::*, git::*, path::*};
@@ -18,7 +20,7 @@ use path_abs::PathOps;
 use serde::{ser::Serializer, Deserialize, Serialize};
 use std::{
     error::Error as StdError,
-    ffi::OsStr,
+    ffi::{OsStr, OsString},
     fmt::{self, Debug, Display},
     io,
     path::{Path, PathBuf},
@@ -47,6 +49,19 @@ pub fn list_display(list: &[impl Display]) -> String {
     }
 }
 
+/// Quotes `arg` for POSIX shells, unless it's plain enough not to need it.
+pub fn shell_quote(arg: &str) -> String {
+    if !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=@%+,".contains(c))
+    {
+        arg.to_owned()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
 pub fn reverse_domain(domain: &str) -> String {
     domain.split('.').rev().collect::<Vec<_>>().join(".")
 }
@@ -671,25 +686,24 @@ pub fn gradlew(
 
     let project_dir = dunce::simplified(&project_dir);
     let gradlew_p = project_dir.join(gradlew);
-    if gradlew_p.exists() {
-        duct::cmd(
-            gradlew_p,
-            [OsStr::new("--project-dir"), project_dir.as_ref()],
-        )
-        .vars(env.explicit_env())
-        .dup_stdio()
+    let program = if gradlew_p.exists() {
+        gradlew_p.into_os_string()
     } else if duct::cmd(gradlew, ["-v"])
         .dup_stdio()
         .run()
         .map(|o| o.status.success())
         .unwrap_or(false)
     {
-        duct::cmd(gradlew, [OsStr::new("--project-dir"), project_dir.as_ref()])
-            .vars(env.explicit_env())
-            .dup_stdio()
+        gradlew.into()
     } else {
-        duct::cmd(gradle, [OsStr::new("--project-dir"), project_dir.as_ref()])
-            .vars(env.explicit_env())
-            .dup_stdio()
-    }
+        gradle.into()
+    };
+    let args = [OsStr::new("--project-dir"), project_dir.as_os_str()]
+        .into_iter()
+        .map(ToOwned::to_owned)
+        .chain(config.gradle_args().iter().map(Into::into))
+        .collect::<Vec<OsString>>();
+    duct::cmd(program, args)
+        .vars(env.explicit_env())
+        .dup_stdio()
 }
@@ -59,6 +59,10 @@ pub fn install_dir() -> Result<PathBuf, NoHomeDir> {
         .or_else(|_| home_dir().map(|home| home.join(".cargo").join(dir_name)))
 }
 
+pub fn temp_dir() -> PathBuf {
+    std::env::temp_dir().join(env!("CARGO_PKG_NAME"))
+}
+
 pub fn checkouts_dir() -> Result<PathBuf, NoHomeDir> {
     install_dir().map(|install_dir| install_dir.join("checkouts"))
 }
@@ -0,0 +1,169 @@
+use crate::{opts::NoiseLevel, util::cli};
+use os_pipe::PipeWriter;
+use std::{
+    io::{self, BufRead as _, BufReader, IsTerminal as _, Write as _},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+static FRAMES: &[&str] = &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+const TICK: Duration = Duration::from_millis(100);
+const CLEAR_LINE: &str = "\r\x1b[2K";
+// How long finishing waits for relays to print what finished subprocesses
+// left behind. Subprocesses that outlive the phase could keep their relays
+// going indefinitely.
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(1);
+
+// The spinner that's currently animating, if any, which subprocess output is
+// relayed around.
+static ACTIVE: Mutex<Option<Arc<Shared>>> = Mutex::new(None);
+
+// Only animate when both streams are attached to a terminal; if stdout is
+// being captured, we'd rather keep emitting the plain status lines callers
+// are already parsing.
+fn interactive() -> bool {
+    io::stdout().is_terminal() && io::stderr().is_terminal()
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    stop: AtomicBool,
+    // Held while writing to the terminal, so that the spinner and relayed
+    // output never write over each other.
+    draw: Mutex<()>,
+    relays: Mutex<Vec<JoinHandle<()>>>,
+}
+
+#[derive(Debug)]
+struct Spinner {
+    shared: Arc<Shared>,
+    handle: JoinHandle<()>,
+}
+
+// Copies lines written to the returned pipe to stdout or stderr, clearing the
+// spinner first so that it's redrawn below them on the next tick.
+fn relay(shared: Arc<Shared>, to_stdout: bool) -> io::Result<PipeWriter> {
+    let (reader, writer) = os_pipe::pipe()?;
+    let relays = Arc::clone(&shared);
+    let handle = thread::spawn(move || {
+        let mut reader = BufReader::new(reader);
+        let mut line = Vec::new();
+        while matches!(reader.read_until(b'\n', &mut line), Ok(len) if len > 0) {
+            let _guard = shared.draw.lock().unwrap();
+            let mut stderr = io::stderr();
+            let _ = write!(stderr, "{}", CLEAR_LINE);
+            let _ = stderr.flush();
+            if to_stdout {
+                let mut stdout = io::stdout();
+                let _ = stdout.write_all(&line);
+                let _ = stdout.flush();
+            } else {
+                let _ = stderr.write_all(&line);
+                let _ = stderr.flush();
+            }
+            line.clear();
+        }
+    });
+    relays.relays.lock().unwrap().push(handle);
+    Ok(writer)
+}
+
+/// Pipes for a subprocess' stdout and stderr that print around the active
+/// spinner, or `None` if there isn't one, in which case the subprocess can
+/// just inherit our stdio.
+pub(crate) fn relay_stdio() -> Option<(PipeWriter, PipeWriter)> {
+    let shared = ACTIVE.lock().unwrap().clone()?;
+    let pipes = relay(Arc::clone(&shared), !cli::stdout_reserved())
+        .and_then(|stdout| relay(shared, false).map(|stderr| (stdout, stderr)));
+    pipes
+        .map_err(|err| log::warn!("failed to relay subprocess output: {}", err))
+        .ok()
+}
+
+/// A status indicator for a long-running phase, such as an `xcodebuild`
+/// invocation.
+///
+/// When running interactively at the default noise level, this animates a
+/// spinner on stderr until finished, with the output of subprocesses started
+/// in the meantime printed above it. Otherwise (in CI, when output is piped,
+/// or when the subprocess is going to be chatty anyway) it falls back to
+/// printing the message as a plain line, exactly once.
+#[derive(Debug)]
+pub struct Phase {
+    msg: String,
+    spinner: Option<Spinner>,
+}
+
+impl Phase {
+    pub fn start(msg: impl Into<String>, noise_level: NoiseLevel) -> Self {
+        let msg = msg.into();
+        let spinner = if noise_level.polite() && interactive() {
+            let shared = Arc::new(Shared::default());
+            let handle = {
+                let shared = Arc::clone(&shared);
+                let msg = msg.clone();
+                thread::spawn(move || {
+                    for frame in FRAMES.iter().cycle() {
+                        if shared.stop.load(Ordering::Relaxed) {
+                            break;
+                        }
+                        {
+                            let _guard = shared.draw.lock().unwrap();
+                            let mut stderr = io::stderr();
+                            let _ = write!(stderr, "\r{} {}", frame, msg);
+                            let _ = stderr.flush();
+                        }
+                        thread::sleep(TICK);
+                    }
+                    let _guard = shared.draw.lock().unwrap();
+                    let mut stderr = io::stderr();
+                    let _ = write!(stderr, "{}", CLEAR_LINE);
+                    let _ = stderr.flush();
+                })
+            };
+            *ACTIVE.lock().unwrap() = Some(Arc::clone(&shared));
+            Some(Spinner { shared, handle })
+        } else {
+            println!("{}", msg);
+            None
+        };
+        Self { msg, spinner }
+    }
+
+    /// Stops the spinner (if any) and clears its line, leaving the message
+    /// behind as a plain line.
+    pub fn finish(mut self) {
+        self.stop();
+    }
+
+    fn stop(&mut self) {
+        if let Some(Spinner { shared, handle }) = self.spinner.take() {
+            *ACTIVE.lock().unwrap() = None;
+            let deadline = Instant::now() + DRAIN_TIMEOUT;
+            while Instant::now() < deadline
+                && !shared
+                    .relays
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .all(JoinHandle::is_finished)
+            {
+                thread::sleep(TICK / 10);
+            }
+            shared.stop.store(true, Ordering::Relaxed);
+            let _ = handle.join();
+            println!("{}", self.msg);
+        }
+    }
+}
+
+// Phases that end early, like when a build fails, still need cleaning up.
+impl Drop for Phase {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
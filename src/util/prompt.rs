@@ -74,6 +74,18 @@ pub fn list(
     noun: impl Display,
     alternative: Option<&str>,
     msg: impl Display,
+) -> io::Result<usize> {
+    list_with_default(header, choices, noun, alternative, msg, None)
+}
+
+/// Like [`list`], but entering nothing picks `default_index`.
+pub fn list_with_default(
+    header: impl Display,
+    choices: impl ExactSizeIterator<Item = impl Display>,
+    noun: impl Display,
+    alternative: Option<&str>,
+    msg: impl Display,
+    default_index: Option<usize>,
 ) -> io::Result<usize> {
     println!("{}:", header);
     let choice_count = choices.len();
@@ -89,11 +101,10 @@ pub fn list(
         println!("  Enter an {} for a {} above.", "index".green(), noun);
     }
     loop {
-        let response = default(
-            &msg,
-            if choice_count == 1 { Some("0") } else { None },
-            Some(Color::Green),
-        )?;
+        let default_index = default_index
+            .or((choice_count == 1).then_some(0))
+            .map(|index| index.to_string());
+        let response = default(&msg, default_index.as_deref(), Some(Color::Green))?;
         if !response.is_empty() {
             if let Ok(index) = response.parse::<usize>() {
                 if index < choice_count {
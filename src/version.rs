@@ -0,0 +1,166 @@
+use crate::{
+    config::{self, app::IMPLIED_TEMPLATE_PACK},
+    templating::{self, Pack, PackCategory, PackInfo},
+    util::{
+        self,
+        cli::{Report, Reportable, VERSION_SHORT},
+        Git,
+    },
+};
+use serde::Serialize;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Failed to list template packs: {0}")]
+    PacksListFailed(templating::ListError),
+    #[error("Failed to load config: {0}")]
+    ConfigLoadFailed(Box<config::LoadError>),
+    #[error("Failed to look up template pack {name:?}: {cause}")]
+    PackLookupFailed {
+        name: String,
+        cause: Box<templating::LookupError>,
+    },
+}
+
+impl Reportable for Error {
+    fn report(&self) -> Report {
+        Report::error("Failed to get version info", self)
+    }
+}
+
+/// An installed or builtin template pack, and the cargo-mobile2 it came with.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct PackVersion {
+    #[serde(flatten)]
+    pub info: PackInfo,
+    /// The version of cargo-mobile2 that installed the pack, or that it's
+    /// built into.
+    pub version: &'static str,
+    /// The last commit included in that cargo-mobile2, if it was installed
+    /// from source. Packs that require a submodule get their templates from
+    /// whichever commit the project checks out instead.
+    pub installed_commit: Option<String>,
+}
+
+/// The template pack the project in the current directory was generated from.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ProjectVersion {
+    pub template_pack: String,
+    /// The commit checked out in the pack's submodule, if it's submodule-based.
+    pub submodule_commit: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct VersionInfo {
+    pub version: &'static str,
+    /// The last commit included in the installed build, if it was installed
+    /// from source.
+    pub installed_commit: Option<String>,
+    pub template_packs: Vec<PackVersion>,
+    pub project: Option<ProjectVersion>,
+}
+
+impl VersionInfo {
+    pub fn detect() -> Result<Self, Error> {
+        let installed_commit = util::installed_commit_msg()
+            .unwrap_or_else(|err| {
+                log::error!("failed to get current commit msg: {}", err);
+                None
+            })
+            .filter(|msg| !msg.trim().is_empty());
+        let template_packs = templating::enumerate_packs()
+            .map_err(Error::PacksListFailed)?
+            .into_iter()
+            .map(|info| PackVersion {
+                info,
+                // The templates are installed by the same build script that
+                // records the commit.
+                version: VERSION_SHORT,
+                installed_commit: installed_commit.clone(),
+            })
+            .collect();
+        Ok(Self {
+            version: VERSION_SHORT,
+            installed_commit,
+            template_packs,
+            project: detect_project()?,
+        })
+    }
+
+    fn print(&self) {
+        println!("cargo-mobile2 {}", self.version);
+        if let Some(msg) = &self.installed_commit {
+            println!("{}", util::format_commit_msg(msg.clone()));
+        }
+        println!("Template packs:");
+        for pack in &self.template_packs {
+            let category = match pack.info.category {
+                PackCategory::App => "app",
+                PackCategory::Platform => "platform",
+            };
+            print!("  {} ({}) {}", pack.info.name, category, pack.version);
+            if let Some(msg) = &pack.installed_commit {
+                print!(", contains commits up to {:?}", msg.trim());
+            }
+            if pack.info.requires_submodule {
+                print!(", templates from a submodule");
+            }
+            println!();
+        }
+        if let Some(project) = &self.project {
+            match &project.submodule_commit {
+                Some(commit) => println!(
+                    "Project template pack: {} (submodule at {})",
+                    project.template_pack, commit
+                ),
+                None => println!("Project template pack: {}", project.template_pack),
+            }
+        }
+    }
+}
+
+fn detect_project() -> Result<Option<ProjectVersion>, Error> {
+    let Some((root_dir, raw)) =
+        config::Raw::load(".").map_err(|err| Error::ConfigLoadFailed(Box::new(err)))?
+    else {
+        return Ok(None);
+    };
+    let name = raw
+        .app
+        .template_pack
+        .unwrap_or_else(|| IMPLIED_TEMPLATE_PACK.to_owned());
+    let pack = Pack::lookup_app(&name).map_err(|cause| Error::PackLookupFailed {
+        name: name.clone(),
+        cause: Box::new(cause),
+    })?;
+    let submodule_commit = pack.submodule_path().and_then(|path| {
+        Git::new(&root_dir.join(path))
+            .command_parse("rev-parse HEAD")
+            .stderr_capture()
+            .read()
+            .map(|commit| commit.trim().to_owned())
+            .inspect_err(|err| log::warn!("failed to get submodule commit at {:?}: {}", path, err))
+            .ok()
+    });
+    Ok(Some(ProjectVersion {
+        template_pack: name,
+        submodule_commit,
+    }))
+}
+
+pub fn exec(json: bool) -> Result<(), Error> {
+    let info = VersionInfo::detect()?;
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string(&info).expect("developer error: failed to serialize version")
+        );
+    } else {
+        info.print();
+    }
+    Ok(())
+}